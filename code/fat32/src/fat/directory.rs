@@ -1,5 +1,6 @@
 use defmt::Format;
 
+use super::LongNameEntry;
 use crate::{ClusterID, BLOCK_SIZE};
 
 const ENTRY_TYPE_LONG_NAME_MASK: u8 = 0b00001111;
@@ -30,7 +31,7 @@ pub enum DirectoryEntry {
     Directory(Directory),
     File(File),
     VolumeID,
-    LFN,
+    LFN(LongNameEntry),
 }
 
 #[derive(Debug, PartialEq)]
@@ -58,6 +59,23 @@ impl Name {
         // TODO Support extensions and stuff ... we probably need a dedicated Path struct for handling this mess
         core::str::from_utf8(&self.0[8..])
     }
+
+    /// Checksum over the raw name, which long filename entries use to reference it
+    pub fn checksum(&self) -> u8 {
+        self.0
+            .iter()
+            .fold(0u8, |sum, byte| sum.rotate_right(1).wrapping_add(*byte))
+    }
+
+    /// Compares the name to one in `NAME.EXT` notation, ignoring the case like FAT does
+    pub(crate) fn matches(&self, name: &str) -> bool {
+        let (stem, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+
+        self.name().is_ok_and(|own| own.eq_ignore_ascii_case(stem))
+            && self
+                .extension()
+                .is_ok_and(|own| own.trim_end().eq_ignore_ascii_case(extension))
+    }
 }
 
 impl File {
@@ -114,8 +132,8 @@ impl From<&[u8]> for DirectoryIndexEntry {
 
             let size = u32::from_le_bytes([data[0x1C], data[0x1D], data[0x1E], data[0x1F]]);
 
-            let entry = if attributes & ENTRY_TYPE_LONG_NAME_MASK > 1 {
-                DirectoryEntry::LFN
+            let entry = if attributes & ENTRY_TYPE_LONG_NAME_MASK == ENTRY_TYPE_LONG_NAME_MASK {
+                DirectoryEntry::LFN(LongNameEntry::from(data))
             } else if attributes & ENTRY_TYPE_VOLUME_ID_MASK > 1 {
                 DirectoryEntry::VolumeID
             } else if attributes & ENTRY_TYPE_DIRECTORY_MASK > 1 {
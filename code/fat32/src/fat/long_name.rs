@@ -0,0 +1,135 @@
+use defmt::Format;
+
+use super::Name;
+
+/// Number of UCS-2 characters stored in a single long filename entry
+const CHARACTERS_PER_ENTRY: usize = 13;
+/// Long filenames are limited to 255 characters, which take up to 20 entries
+const ENTRY_COUNT_LIMIT: u8 = 20;
+/// Set in the order byte of the entry holding the end of the name, which is the first one on disk
+const LAST_ENTRY_FLAG: u8 = 0x40;
+const ORDER_MASK: u8 = 0x1F;
+
+/// Entry of a VFAT long filename, holding a part of the name of the short entry following it.
+///
+/// A name spans multiple entries which are stored in reverse order directly in front of the short entry.
+#[derive(Format, Debug, PartialEq)]
+pub struct LongNameEntry {
+    order: u8,
+    checksum: u8,
+    characters: [u16; CHARACTERS_PER_ENTRY],
+}
+
+impl LongNameEntry {
+    /// Position of the entry within the name, starting at one
+    pub fn sequence_number(&self) -> u8 {
+        self.order & ORDER_MASK
+    }
+
+    /// Whether the entry holds the end of the name and thus is the first of the sequence
+    pub fn is_last(&self) -> bool {
+        self.order & LAST_ENTRY_FLAG != 0
+    }
+
+    /// Checksum of the short name this entry belongs to
+    pub fn checksum(&self) -> u8 {
+        self.checksum
+    }
+}
+
+impl From<&[u8]> for LongNameEntry {
+    fn from(data: &[u8]) -> Self {
+        let mut characters = [0u16; CHARACTERS_PER_ENTRY];
+
+        // The characters are scattered across three ranges around the attribute, checksum and cluster fields
+        let ranges = [0x01..0x0B, 0x0E..0x1A, 0x1C..0x20];
+        let bytes = ranges
+            .into_iter()
+            .flat_map(|range| data[range].chunks_exact(2));
+
+        for (character, bytes) in characters.iter_mut().zip(bytes) {
+            *character = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+
+        Self {
+            order: data[0],
+            checksum: data[0x0D],
+            characters,
+        }
+    }
+}
+
+/// Reassembles long filenames from the entries preceding a short entry
+pub(crate) struct LongNameBuilder {
+    characters: [u16; CHARACTERS_PER_ENTRY * ENTRY_COUNT_LIMIT as usize],
+    length: usize,
+    checksum: u8,
+    /// Sequence number of the entry expected next, zero if the name is complete or no name is being assembled
+    expected: u8,
+}
+
+impl LongNameBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            characters: [0; CHARACTERS_PER_ENTRY * ENTRY_COUNT_LIMIT as usize],
+            length: 0,
+            checksum: 0,
+            expected: 0,
+        }
+    }
+
+    /// Adds the next entry in on-disk order, discarding the name assembled so far if the entry does not continue it
+    pub(crate) fn push(&mut self, entry: &LongNameEntry) {
+        let sequence_number = entry.sequence_number();
+
+        if sequence_number == 0 || sequence_number > ENTRY_COUNT_LIMIT {
+            return self.reset();
+        }
+
+        if entry.is_last() {
+            self.length = sequence_number as usize * CHARACTERS_PER_ENTRY;
+            self.checksum = entry.checksum();
+        } else if sequence_number != self.expected || entry.checksum() != self.checksum {
+            return self.reset();
+        }
+
+        let start = (sequence_number as usize - 1) * CHARACTERS_PER_ENTRY;
+        self.characters[start..start + CHARACTERS_PER_ENTRY].copy_from_slice(&entry.characters);
+        self.expected = sequence_number - 1;
+    }
+
+    /// Takes the assembled name if it is complete and belongs to the given short name.
+    /// Resets the builder either way, as a short entry always ends a sequence of long name entries.
+    pub(crate) fn finish(&mut self, short_name: &Name) -> Option<&[u16]> {
+        let complete = self.length > 0 && self.expected == 0;
+        let belongs = self.checksum == short_name.checksum();
+        let length = self.length;
+        self.reset();
+
+        if complete && belongs {
+            // Names which do not fill up the last entry are terminated by a null character and padded with 0xFFFF
+            let characters = &self.characters[..length];
+            let end = characters
+                .iter()
+                .position(|character| *character == 0)
+                .unwrap_or(length);
+
+            Some(&characters[..end])
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.length = 0;
+        self.expected = 0;
+    }
+}
+
+/// Compares a long filename to the given name, ignoring the case
+pub(crate) fn long_name_matches(characters: &[u16], name: &str) -> bool {
+    char::decode_utf16(characters.iter().copied())
+        .map(|character| character.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .flat_map(char::to_lowercase)
+        .eq(name.chars().flat_map(char::to_lowercase))
+}
@@ -3,3 +3,7 @@ pub use volume_id::*;
 
 mod directory;
 pub use directory::*;
+
+mod long_name;
+pub use long_name::LongNameEntry;
+pub(crate) use long_name::{long_name_matches, LongNameBuilder};
@@ -3,6 +3,8 @@ use defmt::Format;
 use crate::{Block, BlockCount, BlockID, BLOCK_SIZE};
 use core::ops::Sub;
 
+const NT_FLAG_DIRTY: u8 = 0x01;
+
 #[derive(Format, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ClusterID(pub(crate) u32);
 
@@ -23,6 +25,8 @@ pub struct VolumeId {
     sectors_per_fat: BlockCount,
 
     root_dir_cluster_id: ClusterID,
+
+    dirty: bool,
 }
 
 impl VolumeId {
@@ -60,6 +64,11 @@ impl VolumeId {
     pub fn root_directory_cluster(&self) -> ClusterID {
         self.root_dir_cluster_id
     }
+
+    /// Whether the volume has been flagged as not cleanly unmounted in the boot sector
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
 }
 
 impl TryFrom<(BlockID, Block)> for VolumeId {
@@ -93,6 +102,9 @@ impl TryFrom<(BlockID, Block)> for VolumeId {
         let root_dir_cluster_id =
             ClusterID(u16::from_le_bytes([block[0x2C], block[0x2C + 1]]) as u32);
 
+        // Windows NT reuses the "current head" byte of the extended BIOS parameter block for flags
+        let dirty = block[0x41] & NT_FLAG_DIRTY != 0;
+
         let signature = u16::from_le_bytes([block[0x1FE], block[0x1FE + 1]]);
 
         if signature != 0xAA55 {
@@ -105,6 +117,7 @@ impl TryFrom<(BlockID, Block)> for VolumeId {
                 number_of_fats,
                 sectors_per_fat,
                 root_dir_cluster_id,
+                dirty,
             })
         }
     }
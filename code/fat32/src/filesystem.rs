@@ -11,6 +11,17 @@ pub enum FilesystemError<E> {
     NoFatPartitionFound,
     UnexpectedFatEntry,
     OutOfBounds,
+    /// The volume has not been unmounted cleanly and [`MountOptions::reject_dirty`] is set
+    DirtyVolume,
+}
+
+/// Mask of the "clean shutdown" bit in the second FAT entry, it is cleared while the volume is mounted
+const FAT_CLEAN_SHUTDOWN: u32 = 0x0800_0000;
+
+#[derive(Debug, Default, Clone, Copy, Format)]
+pub struct MountOptions {
+    /// Refuse to mount volumes which have not been unmounted cleanly, as their content might be corrupted
+    pub reject_dirty: bool,
 }
 
 pub struct Filesystem<E, RFut, RFn, WFut, WFn>
@@ -26,6 +37,8 @@ where
     pub mbr: MasterBootRecord,
     pub partition_index: usize,
     pub vid: VolumeId,
+
+    dirty: bool,
 }
 
 impl<E, RFut, RFn, WFut, WFn> Filesystem<E, RFut, RFn, WFut, WFn>
@@ -36,6 +49,14 @@ where
     WFn: Fn(BlockID, Block) -> WFut,
 {
     pub async fn new(read_fn: RFn, write_fn: WFn) -> Result<Self, FilesystemError<E>> {
+        Self::new_with_options(read_fn, write_fn, MountOptions::default()).await
+    }
+
+    pub async fn new_with_options(
+        read_fn: RFn,
+        write_fn: WFn,
+        options: MountOptions,
+    ) -> Result<Self, FilesystemError<E>> {
         let mbr_block = (read_fn)(BlockID::ZERO)
             .await
             .map_err(FilesystemError::DiskFailure)?;
@@ -61,12 +82,28 @@ where
 
                 defmt::trace!("vid: {:?}", vid);
 
+                let fat_block = (read_fn)(vid.fat_address())
+                    .await
+                    .map_err(FilesystemError::DiskFailure)?;
+                let fat_flags =
+                    u32::from_le_bytes([fat_block[4], fat_block[5], fat_block[6], fat_block[7]]);
+                let dirty = vid.is_dirty() || fat_flags & FAT_CLEAN_SHUTDOWN == 0;
+
+                if dirty {
+                    defmt::warn!("volume has not been unmounted cleanly");
+
+                    if options.reject_dirty {
+                        return Err(FilesystemError::DirtyVolume);
+                    }
+                }
+
                 Ok(Self {
                     read_fn,
                     write_fn,
                     mbr,
                     partition_index,
                     vid,
+                    dirty,
                 })
             }
             None => Err(FilesystemError::NoFatPartitionFound),
@@ -151,6 +188,15 @@ where
         block_stream_to_entry_stream(block_stream)
     }
 
+    /// Like [`enumerate_directory`](Self::enumerate_directory) but includes unused entries, which interrupt long filenames
+    fn enumerate_directory_index(
+        &self,
+        directory: Directory,
+    ) -> impl Stream<Item = Result<DirectoryIndexEntry, FilesystemError<E>>> + '_ {
+        let block_stream = self.block_chain(directory.cluster);
+        block_stream_to_index_entry_stream(block_stream)
+    }
+
     pub fn root_directory(&self) -> Directory {
         Directory {
             // TODO Figure out the name from the volume ID file in the root directory
@@ -182,19 +228,59 @@ where
         Ok(None)
     }
 
+    /// Searches for a file by its full name (e.g. `my-dictionary.bin`), ignoring the case.
+    /// Matches the VFAT long filename if there is one, otherwise the 8.3 short name.
+    /// Currently only searches in the root directory.
+    pub async fn find_file_long(&self, name: &str) -> Result<Option<File>, FilesystemError<E>> {
+        let entries = self.enumerate_directory_index(self.root_directory());
+        pin_mut!(entries);
+
+        let mut long_name = LongNameBuilder::new();
+
+        while let Some(entry) = entries.next().await {
+            match entry? {
+                DirectoryIndexEntry::Entry(DirectoryEntry::LFN(entry)) => long_name.push(&entry),
+                DirectoryIndexEntry::Entry(DirectoryEntry::File(file)) => {
+                    let matches = match long_name.finish(file.name()) {
+                        Some(characters) => long_name_matches(characters, name),
+                        None => file.name().matches(name),
+                    };
+
+                    if matches {
+                        return Ok(Some(file));
+                    }
+                }
+                _ => long_name.reset(),
+            }
+        }
+
+        Ok(None)
+    }
+
     pub fn volume_id(&self) -> &VolumeId {
         &self.vid
     }
+
+    /// Whether the volume has not been unmounted cleanly, either according to the boot sector or the FAT.
+    /// Its content might be corrupted, so it may be worth warning the user.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
 }
 
 fn block_stream_to_entry_stream<E>(
     stream: impl Stream<Item = Result<Block, FilesystemError<E>>>,
 ) -> impl Stream<Item = Result<DirectoryEntry, FilesystemError<E>>> {
+    block_stream_to_index_entry_stream(stream).try_filter_map(take_regular_entries)
+}
+
+fn block_stream_to_index_entry_stream<E>(
+    stream: impl Stream<Item = Result<Block, FilesystemError<E>>>,
+) -> impl Stream<Item = Result<DirectoryIndexEntry, FilesystemError<E>>> {
     stream
         .map_ok(block_to_entry_stream)
         .try_flatten()
         .take_while(not_end_of_directory)
-        .try_filter_map(take_regular_entries)
 }
 
 fn block_to_entry_stream<E>(
@@ -6,6 +6,17 @@ use crate::{
     BLOCK_SIZE,
 };
 
+/// Number of clusters [`FileReader::cache_fat`] can hold, matching the FAT entries stored in one sector
+const FAT_CACHE_LEN: usize = BLOCK_SIZE / core::mem::size_of::<u32>();
+
+/// Position to seek to within a file, mirroring `std::io::SeekFrom`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(u64),
+    End(i64),
+    Current(i64),
+}
+
 pub struct FileReader<'f, E, RFut, RFn, WFut, WFn>
 where
     RFut: Future<Output = Result<Block, BlockDeviceError<E>>>,
@@ -16,7 +27,10 @@ where
     file: File,
     filesystem: &'f Filesystem<E, RFut, RFn, WFut, WFn>,
     block_cache: (BlockID, Block),
-    cluster_cache: Option<[ClusterID; 103]>,
+    cluster_cache: Option<[ClusterID; FAT_CACHE_LEN]>,
+    /// Most recently resolved cluster as (index within the file, ClusterID) to speed up forward seeks without a cached FAT
+    cluster_cursor: (u32, ClusterID),
+    position: u32,
 }
 
 impl<'f, E, RFut, RFn, WFut, WFn> FileReader<'f, E, RFut, RFn, WFut, WFn>
@@ -31,20 +45,73 @@ where
         Self {
             block_cache: (BlockID::ZERO, Block::new([0; BLOCK_SIZE])),
             cluster_cache: None,
+            cluster_cursor: (0, file.cluster_address()),
+            position: 0,
             file,
             filesystem,
         }
     }
 
+    /// Size of the file in bytes as stored in its directory entry
+    pub fn len(&self) -> u64 {
+        self.file.size() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Current position used by [`read_next`](Self::read_next)
+    pub fn position(&self) -> u64 {
+        self.position as u64
+    }
+
+    /// Moves the position used by [`read_next`](Self::read_next), returning the new position.
+    ///
+    /// This does not touch the disk, the cluster containing the new position is resolved by the next read.
+    /// With a cached FAT (see [`cache_fat`](Self::cache_fat)) this is a simple lookup, otherwise the cluster chain
+    /// is followed from the most recently used cluster (or the start of the file when seeking backwards).
+    pub fn seek(&mut self, pos: SeekFrom) -> Result<u64, FilesystemError<E>> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => checked_add_signed(self.len(), offset),
+            SeekFrom::Current(offset) => checked_add_signed(self.position(), offset),
+        };
+
+        // Like `std`, allow seeking beyond the end of the file with subsequent reads failing
+        match position {
+            Some(position) if position <= u32::MAX as u64 => {
+                self.position = position as u32;
+                Ok(position)
+            }
+            _ => Err(FilesystemError::OutOfBounds),
+        }
+    }
+
+    /// Reads the byte at the current position and advances it by one
+    pub async fn read_next(&mut self) -> Result<u8, FilesystemError<E>> {
+        let data = self.read(self.position).await?;
+        self.position += 1;
+        Ok(data)
+    }
+
+    /// Loads the cluster chain of the file into memory so that seeks and reads no longer have to consult the FAT.
+    ///
+    /// The cache holds as many clusters as one sector of the FAT has entries, i.e. 128 clusters with 512 byte sectors.
+    /// Files spanning more clusters are left uncached, reads then keep following the chain on disk.
     pub async fn cache_fat(&mut self) -> Result<(), FilesystemError<E>> {
         let cluster_chain = self.filesystem.cluster_chain(self.file.cluster_address());
 
         pin_mut!(cluster_chain);
 
-        let mut cache = [self.file.cluster_address(); 103];
+        let mut cache = [self.file.cluster_address(); FAT_CACHE_LEN];
         let mut cluster_count = 0;
         while let Some(cluster_id) = cluster_chain.next().await {
-            cache[cluster_count] = cluster_id?;
+            match cache.get_mut(cluster_count) {
+                Some(entry) => *entry = cluster_id?,
+                None => return Ok(()),
+            }
+
             cluster_count += 1;
         }
 
@@ -54,7 +121,7 @@ where
     }
 
     pub async fn read(&mut self, offset: u32) -> Result<u8, FilesystemError<E>> {
-        if offset > self.file.size() as u32 {
+        if offset >= self.file.size() {
             return Err(FilesystemError::OutOfBounds);
         }
 
@@ -66,21 +133,7 @@ where
         let intra_cluster_offset = vid.intra_cluster_offset(block_offset);
 
         // 2. Find ClusterID of the cluster containing our offset
-        let cluster_id = if let Some(cache) = self.cluster_cache.as_ref() {
-            cache[cluster_offset as usize]
-        } else {
-            let cluster_chain = self
-                .filesystem
-                .cluster_chain(self.file.cluster_address())
-                .skip(cluster_offset as usize);
-
-            pin_mut!(cluster_chain);
-
-            cluster_chain
-                .next()
-                .await
-                .ok_or(FilesystemError::OutOfBounds)??
-        };
+        let cluster_id = self.cluster_at(cluster_offset).await?;
 
         // 3. Calculate BlockID of our data based on the ClusterID and the offset within the cluster
         let cluster_block_id = self.filesystem.volume_id().cluster_address(cluster_id);
@@ -97,4 +150,45 @@ where
         // 5. Read from block
         Ok(block[intra_block_offset as usize])
     }
+
+    /// Finds the ClusterID of the n-th cluster of the file
+    async fn cluster_at(&mut self, cluster_offset: u32) -> Result<ClusterID, FilesystemError<E>> {
+        if let Some(cache) = self.cluster_cache.as_ref() {
+            return cache
+                .get(cluster_offset as usize)
+                .copied()
+                .ok_or(FilesystemError::OutOfBounds);
+        }
+
+        // Continue from the last cluster we visited if possible, we can only follow the chain forwards
+        let (start_offset, start_cluster) = if self.cluster_cursor.0 <= cluster_offset {
+            self.cluster_cursor
+        } else {
+            (0, self.file.cluster_address())
+        };
+
+        let cluster_chain = self
+            .filesystem
+            .cluster_chain(start_cluster)
+            .skip((cluster_offset - start_offset) as usize);
+
+        pin_mut!(cluster_chain);
+
+        let cluster_id = cluster_chain
+            .next()
+            .await
+            .ok_or(FilesystemError::OutOfBounds)??;
+
+        self.cluster_cursor = (cluster_offset, cluster_id);
+
+        Ok(cluster_id)
+    }
+}
+
+fn checked_add_signed(base: u64, offset: i64) -> Option<u64> {
+    if offset < 0 {
+        base.checked_sub(offset.unsigned_abs())
+    } else {
+        base.checked_add(offset as u64)
+    }
 }
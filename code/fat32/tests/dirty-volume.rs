@@ -0,0 +1,76 @@
+use fat32::{
+    Block, BlockDeviceError, BlockID, Filesystem, FilesystemError, MountOptions, BLOCK_SIZE,
+};
+
+/// Builds a minimal image consisting of the MBR, the boot sector of a single FAT32 partition, and its FAT
+fn image(boot_sector_flags: u8, clean_shutdown: bool) -> Vec<[u8; BLOCK_SIZE]> {
+    let mut mbr = [0u8; BLOCK_SIZE];
+    mbr[0x01BE + 0x04] = 0x0C;
+    mbr[0x01BE + 0x08..0x01BE + 0x0C].copy_from_slice(&1u32.to_le_bytes());
+    mbr[0x01BE + 0x0C..0x01BE + 0x10].copy_from_slice(&2u32.to_le_bytes());
+    mbr[0x01FE] = 0x55;
+    mbr[0x01FF] = 0xAA;
+
+    let mut boot_sector = [0u8; BLOCK_SIZE];
+    boot_sector[0x0B..0x0D].copy_from_slice(&(BLOCK_SIZE as u16).to_le_bytes());
+    boot_sector[0x0D] = 1;
+    boot_sector[0x0E..0x10].copy_from_slice(&1u16.to_le_bytes());
+    boot_sector[0x10] = 1;
+    boot_sector[0x24..0x28].copy_from_slice(&1u32.to_le_bytes());
+    boot_sector[0x2C..0x30].copy_from_slice(&2u32.to_le_bytes());
+    boot_sector[0x41] = boot_sector_flags;
+    boot_sector[0x01FE] = 0x55;
+    boot_sector[0x01FF] = 0xAA;
+
+    let mut fat = [0u8; BLOCK_SIZE];
+    let flags: u32 = if clean_shutdown {
+        0x0FFF_FFFF
+    } else {
+        0x07FF_FFFF
+    };
+    fat[0..4].copy_from_slice(&0x0FFF_FFF8u32.to_le_bytes());
+    fat[4..8].copy_from_slice(&flags.to_le_bytes());
+
+    vec![mbr, boot_sector, fat]
+}
+
+async fn mount(
+    blocks: Vec<[u8; BLOCK_SIZE]>,
+    options: MountOptions,
+) -> Result<bool, FilesystemError<()>> {
+    let filesystem = Filesystem::new_with_options(
+        |address: BlockID| {
+            let block = blocks.get(address.into_inner() as usize).copied();
+            async move { block.map(Block::new).ok_or(BlockDeviceError::OutOfBounds) }
+        },
+        |_address, _block| async move { Ok(()) },
+        options,
+    )
+    .await?;
+
+    Ok(filesystem.is_dirty())
+}
+
+#[tokio::test]
+async fn mount_clean_volume() {
+    let dirty = mount(image(0x00, true), MountOptions { reject_dirty: true }).await;
+    assert!(matches!(dirty, Ok(false)));
+}
+
+#[tokio::test]
+async fn report_dirty_boot_sector() {
+    let dirty = mount(image(0x01, true), MountOptions::default()).await;
+    assert!(matches!(dirty, Ok(true)));
+}
+
+#[tokio::test]
+async fn report_unclean_shutdown_in_fat() {
+    let dirty = mount(image(0x00, false), MountOptions::default()).await;
+    assert!(matches!(dirty, Ok(true)));
+}
+
+#[tokio::test]
+async fn reject_dirty_volume() {
+    let dirty = mount(image(0x01, true), MountOptions { reject_dirty: true }).await;
+    assert!(matches!(dirty, Err(FilesystemError::DirtyVolume)));
+}
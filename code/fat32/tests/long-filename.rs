@@ -0,0 +1,181 @@
+use fat32::{Block, BlockDeviceError, BlockID, File, Filesystem, BLOCK_SIZE};
+
+const ENTRY_SIZE: usize = 32;
+/// Offsets of the UCS-2 characters within a long filename entry
+const LONG_NAME_CHARACTER_OFFSETS: [usize; 13] = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+fn checksum(short_name: &[u8; 11]) -> u8 {
+    short_name
+        .iter()
+        .fold(0u8, |sum, byte| sum.rotate_right(1).wrapping_add(*byte))
+}
+
+fn short_entry(short_name: &[u8; 11], size: u32) -> [u8; ENTRY_SIZE] {
+    let mut entry = [0u8; ENTRY_SIZE];
+    entry[..11].copy_from_slice(short_name);
+    entry[0x0B] = 0x20;
+    entry[0x1C..0x20].copy_from_slice(&size.to_le_bytes());
+    entry
+}
+
+/// Builds the long filename entries for the short name in on-disk order
+fn long_name_entries(name: &str, checksum: u8) -> Vec<[u8; ENTRY_SIZE]> {
+    let mut characters = name.encode_utf16().collect::<Vec<_>>();
+    if characters.len() % 13 != 0 {
+        characters.push(0x0000);
+    }
+    while characters.len() % 13 != 0 {
+        characters.push(0xFFFF);
+    }
+
+    let count = characters.len() / 13;
+
+    (1..=count)
+        .rev()
+        .map(|sequence_number| {
+            let mut entry = [0u8; ENTRY_SIZE];
+            entry[0] = sequence_number as u8 | if sequence_number == count { 0x40 } else { 0 };
+            entry[0x0B] = 0x0F;
+            entry[0x0D] = checksum;
+
+            let part = &characters[(sequence_number - 1) * 13..][..13];
+            for (offset, character) in LONG_NAME_CHARACTER_OFFSETS.iter().zip(part) {
+                entry[*offset..*offset + 2].copy_from_slice(&character.to_le_bytes());
+            }
+
+            entry
+        })
+        .collect()
+}
+
+/// Builds a minimal image consisting of the MBR, the boot sector of a single FAT32 partition, its FAT,
+/// and a root directory containing the given entries
+fn image(entries: Vec<[u8; ENTRY_SIZE]>) -> Vec<[u8; BLOCK_SIZE]> {
+    let mut mbr = [0u8; BLOCK_SIZE];
+    mbr[0x01BE + 0x04] = 0x0C;
+    mbr[0x01BE + 0x08..0x01BE + 0x0C].copy_from_slice(&1u32.to_le_bytes());
+    mbr[0x01BE + 0x0C..0x01BE + 0x10].copy_from_slice(&3u32.to_le_bytes());
+    mbr[0x01FE] = 0x55;
+    mbr[0x01FF] = 0xAA;
+
+    let mut boot_sector = [0u8; BLOCK_SIZE];
+    boot_sector[0x0B..0x0D].copy_from_slice(&(BLOCK_SIZE as u16).to_le_bytes());
+    boot_sector[0x0D] = 1;
+    boot_sector[0x0E..0x10].copy_from_slice(&1u16.to_le_bytes());
+    boot_sector[0x10] = 1;
+    boot_sector[0x24..0x28].copy_from_slice(&1u32.to_le_bytes());
+    boot_sector[0x2C..0x30].copy_from_slice(&2u32.to_le_bytes());
+    boot_sector[0x01FE] = 0x55;
+    boot_sector[0x01FF] = 0xAA;
+
+    // The root directory occupies only the first cluster
+    let mut fat = [0u8; BLOCK_SIZE];
+    fat[0..4].copy_from_slice(&0x0FFF_FFF8u32.to_le_bytes());
+    fat[4..8].copy_from_slice(&0x0FFF_FFFFu32.to_le_bytes());
+    fat[8..12].copy_from_slice(&0x0FFF_FFFFu32.to_le_bytes());
+
+    let mut root_directory = [0u8; BLOCK_SIZE];
+    for (index, entry) in entries.iter().enumerate() {
+        root_directory[index * ENTRY_SIZE..][..ENTRY_SIZE].copy_from_slice(entry);
+    }
+
+    vec![mbr, boot_sector, fat, root_directory]
+}
+
+async fn find_file_long(blocks: &[[u8; BLOCK_SIZE]], name: &str) -> Option<File> {
+    let filesystem = Filesystem::new(
+        |address: BlockID| {
+            let block = blocks.get(address.into_inner() as usize).copied();
+            async move {
+                block
+                    .map(Block::new)
+                    .ok_or(BlockDeviceError::<()>::OutOfBounds)
+            }
+        },
+        |_address, _block| async move { Ok(()) },
+    )
+    .await
+    .unwrap();
+
+    filesystem.find_file_long(name).await.unwrap()
+}
+
+#[tokio::test]
+async fn find_file_by_long_name() {
+    let short_name = *b"MY-DIC~1BIN";
+    let mut entries = long_name_entries("my-dictionary.bin", checksum(&short_name));
+    entries.push(short_entry(&short_name, 1));
+    let blocks = image(entries);
+
+    let file = find_file_long(&blocks, "my-dictionary.bin").await;
+    assert_eq!(file.map(|file| file.size()), Some(1));
+
+    let file = find_file_long(&blocks, "My-Dictionary.BIN").await;
+    assert_eq!(file.map(|file| file.size()), Some(1));
+
+    assert!(find_file_long(&blocks, "my-dictionary").await.is_none());
+}
+
+#[tokio::test]
+async fn find_name_spanning_multiple_entries() {
+    let short_name = *b"A-RATH~1BIN";
+    let name = "a-rather-long-dictionary-name-for-my-keyboard.bin";
+    let mut entries = long_name_entries(name, checksum(&short_name));
+    assert_eq!(entries.len(), 4);
+    entries.push(short_entry(&short_name, 2));
+    let blocks = image(entries);
+
+    let file = find_file_long(&blocks, name).await;
+    assert_eq!(file.map(|file| file.size()), Some(2));
+}
+
+#[tokio::test]
+async fn fall_back_to_short_name() {
+    let blocks = image(vec![short_entry(b"DICT    BIN", 3)]);
+
+    let file = find_file_long(&blocks, "dict.bin").await;
+    assert_eq!(file.map(|file| file.size()), Some(3));
+}
+
+#[tokio::test]
+async fn ignore_long_name_with_mismatching_checksum() {
+    let short_name = *b"OTHER   BIN";
+    let mut entries = long_name_entries("stale-name.bin", checksum(&short_name).wrapping_add(1));
+    entries.push(short_entry(&short_name, 4));
+    let blocks = image(entries);
+
+    assert!(find_file_long(&blocks, "stale-name.bin").await.is_none());
+
+    let file = find_file_long(&blocks, "other.bin").await;
+    assert_eq!(file.map(|file| file.size()), Some(4));
+}
+
+#[tokio::test]
+async fn ignore_incomplete_long_name() {
+    let short_name = *b"A-RATH~1BIN";
+    let mut entries = long_name_entries("a-rather-long-dictionary-name.bin", checksum(&short_name));
+
+    // Drop the entry holding the start of the name, leaving a gap in the sequence
+    entries.pop();
+    entries.push(short_entry(&short_name, 5));
+    let blocks = image(entries);
+
+    assert!(find_file_long(&blocks, "a-rather-long-dictionary-name.bin")
+        .await
+        .is_none());
+}
+
+#[tokio::test]
+async fn ignore_long_name_of_deleted_file() {
+    let short_name = *b"MY-DIC~1BIN";
+    let mut entries = long_name_entries("my-dictionary.bin", checksum(&short_name));
+    let mut deleted = short_entry(&short_name, 6);
+    deleted[0] = 0xE5;
+    entries.push(deleted);
+
+    // A short name which happens to share the checksum must not inherit the long name
+    entries.push(short_entry(&short_name, 7));
+    let blocks = image(entries);
+
+    assert!(find_file_long(&blocks, "my-dictionary.bin").await.is_none());
+}
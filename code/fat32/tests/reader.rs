@@ -0,0 +1,165 @@
+use fat32::{
+    Block, BlockDeviceError, BlockID, FileReader, Filesystem, FilesystemError, SeekFrom, BLOCK_SIZE,
+};
+
+/// Size of the test file, chosen so that it spans two clusters
+const FILE_SIZE: usize = BLOCK_SIZE + 88;
+
+fn content(offset: usize) -> u8 {
+    (offset % 251) as u8
+}
+
+/// Builds a minimal image consisting of the MBR, the boot sector of a single FAT32 partition, its FAT,
+/// a root directory containing a single file, and the two clusters holding the file contents
+fn image() -> Vec<[u8; BLOCK_SIZE]> {
+    let mut mbr = [0u8; BLOCK_SIZE];
+    mbr[0x01BE + 0x04] = 0x0C;
+    mbr[0x01BE + 0x08..0x01BE + 0x0C].copy_from_slice(&1u32.to_le_bytes());
+    mbr[0x01BE + 0x0C..0x01BE + 0x10].copy_from_slice(&5u32.to_le_bytes());
+    mbr[0x01FE] = 0x55;
+    mbr[0x01FF] = 0xAA;
+
+    let mut boot_sector = [0u8; BLOCK_SIZE];
+    boot_sector[0x0B..0x0D].copy_from_slice(&(BLOCK_SIZE as u16).to_le_bytes());
+    boot_sector[0x0D] = 1;
+    boot_sector[0x0E..0x10].copy_from_slice(&1u16.to_le_bytes());
+    boot_sector[0x10] = 1;
+    boot_sector[0x24..0x28].copy_from_slice(&1u32.to_le_bytes());
+    boot_sector[0x2C..0x30].copy_from_slice(&2u32.to_le_bytes());
+    boot_sector[0x01FE] = 0x55;
+    boot_sector[0x01FF] = 0xAA;
+
+    // The root directory occupies the first cluster, the file is chained from the second into the third
+    let mut fat = [0u8; BLOCK_SIZE];
+    fat[0..4].copy_from_slice(&0x0FFF_FFF8u32.to_le_bytes());
+    fat[4..8].copy_from_slice(&0x0FFF_FFFFu32.to_le_bytes());
+    fat[8..12].copy_from_slice(&0x0FFF_FFFFu32.to_le_bytes());
+    fat[12..16].copy_from_slice(&4u32.to_le_bytes());
+    fat[16..20].copy_from_slice(&0x0FFF_FFFFu32.to_le_bytes());
+
+    let mut root_directory = [0u8; BLOCK_SIZE];
+    root_directory[..11].copy_from_slice(b"DATA    BIN");
+    root_directory[0x0B] = 0x20;
+    root_directory[0x1A..0x1C].copy_from_slice(&3u16.to_le_bytes());
+    root_directory[0x1C..0x20].copy_from_slice(&(FILE_SIZE as u32).to_le_bytes());
+
+    let mut first_cluster = [0u8; BLOCK_SIZE];
+    let mut second_cluster = [0u8; BLOCK_SIZE];
+    for offset in 0..FILE_SIZE {
+        if offset < BLOCK_SIZE {
+            first_cluster[offset] = content(offset);
+        } else {
+            second_cluster[offset - BLOCK_SIZE] = content(offset);
+        }
+    }
+
+    vec![
+        mbr,
+        boot_sector,
+        fat,
+        root_directory,
+        first_cluster,
+        second_cluster,
+    ]
+}
+
+macro_rules! with_reader {
+    ($reader:ident, $body:block) => {
+        let blocks = image();
+        let filesystem = Filesystem::new(
+            |address: BlockID| {
+                let block = blocks.get(address.into_inner() as usize).copied();
+                async move {
+                    block
+                        .map(Block::new)
+                        .ok_or(BlockDeviceError::<()>::OutOfBounds)
+                }
+            },
+            |_address, _block| async move { Ok(()) },
+        )
+        .await
+        .unwrap();
+
+        let file = filesystem.find_file("DATA", "BIN").await.unwrap().unwrap();
+        let mut $reader = FileReader::new(file, &filesystem);
+        $body
+    };
+}
+
+#[tokio::test]
+async fn read_sequentially_across_clusters() {
+    with_reader!(reader, {
+        for offset in 0..FILE_SIZE {
+            assert_eq!(reader.read_next().await.unwrap(), content(offset));
+        }
+
+        assert_eq!(reader.position(), FILE_SIZE as u64);
+        assert!(matches!(
+            reader.read_next().await,
+            Err(FilesystemError::OutOfBounds)
+        ));
+    });
+}
+
+#[tokio::test]
+async fn seek_relative_to_start_current_and_end() {
+    with_reader!(reader, {
+        assert_eq!(reader.seek(SeekFrom::Start(42)).unwrap(), 42);
+        assert_eq!(reader.read_next().await.unwrap(), content(42));
+
+        let position = reader.seek(SeekFrom::Current(BLOCK_SIZE as i64)).unwrap();
+        assert_eq!(position, 43 + BLOCK_SIZE as u64);
+        assert_eq!(reader.read_next().await.unwrap(), content(43 + BLOCK_SIZE));
+
+        assert_eq!(
+            reader.seek(SeekFrom::End(-1)).unwrap(),
+            FILE_SIZE as u64 - 1
+        );
+        assert_eq!(reader.read_next().await.unwrap(), content(FILE_SIZE - 1));
+
+        // Seeking backwards has to follow the cluster chain from the start of the file again
+        assert_eq!(
+            reader.seek(SeekFrom::Current(-(FILE_SIZE as i64))).unwrap(),
+            0
+        );
+        assert_eq!(reader.read_next().await.unwrap(), content(0));
+    });
+}
+
+#[tokio::test]
+async fn read_with_cached_fat() {
+    with_reader!(reader, {
+        reader.cache_fat().await.unwrap();
+
+        for offset in 0..FILE_SIZE {
+            assert_eq!(reader.read_next().await.unwrap(), content(offset));
+        }
+
+        assert_eq!(reader.seek(SeekFrom::Start(7)).unwrap(), 7);
+        assert_eq!(reader.read_next().await.unwrap(), content(7));
+    });
+}
+
+#[tokio::test]
+async fn seek_beyond_the_end() {
+    with_reader!(reader, {
+        // Seeking past the end succeeds but subsequent reads fail
+        let position = reader.seek(SeekFrom::End(10)).unwrap();
+        assert_eq!(position, FILE_SIZE as u64 + 10);
+        assert!(matches!(
+            reader.read_next().await,
+            Err(FilesystemError::OutOfBounds)
+        ));
+
+        // Positions that can not be represented are rejected without moving the cursor
+        assert!(matches!(
+            reader.seek(SeekFrom::Start(u32::MAX as u64 + 1)),
+            Err(FilesystemError::OutOfBounds)
+        ));
+        assert!(matches!(
+            reader.seek(SeekFrom::Current(-(FILE_SIZE as i64) - 11)),
+            Err(FilesystemError::OutOfBounds)
+        ));
+        assert_eq!(reader.position(), FILE_SIZE as u64 + 10);
+    });
+}
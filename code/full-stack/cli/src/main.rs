@@ -1,15 +1,13 @@
 use std::time::{Duration, Instant};
 
-use cofit::{Transport, UsbHidTransport};
+use cofit::{
+    hid::{PRODUCT_ID, USAGE_EMBEDDED_STENO, USAGE_PAGE_VENDOR, VENDOR_ID},
+    Transport, UsbHidTransport,
+};
 use hidapi::HidApi;
 use runtime::api::RuntimeAPI;
 use tokio::select;
 
-const USAGE_PAGE_VENDOR: u16 = 0xFF00;
-const USAGE_EMBEDDED_STENO: u16 = 0x42;
-const DEVICE_VID: u16 = 0xC0DE;
-const DEVICE_PID: u16 = 0xCAFE;
-
 const DICT_OFFSET: u32 = 0; // 4096 * 700;
 
 #[tokio::main]
@@ -18,7 +16,7 @@ async fn main() {
 
     let device = api
         .device_list()
-        .filter(|d| d.vendor_id() == DEVICE_VID && d.product_id() == DEVICE_PID)
+        .filter(|d| d.vendor_id() == VENDOR_ID && d.product_id() == PRODUCT_ID)
         .filter(|d| d.usage_page() == USAGE_PAGE_VENDOR && d.usage() == USAGE_EMBEDDED_STENO)
         .map(|d| d.open_device(&api))
         .next()
@@ -30,7 +28,10 @@ async fn main() {
     let (api_task, api) = RuntimeAPI::new(&transport);
 
     let main_task = async move {
-        api.reset().await;
+        api.reset()
+            .await
+            .expect("peripheral did not confirm the reset");
+        api.wait_until_ready().await;
 
         write_test(&api).await;
         // for _ in 0..1 {
@@ -0,0 +1,166 @@
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+/// Shrinks the payload packed into each frame of [fragmented messages](super::FragmentedMessage) while the link is unreliable,
+/// see [`Transmitter::with_adaptive_payload`](super::Transmitter::with_adaptive_payload)
+///
+/// Large frames are more likely to get corrupted on a noisy link than small ones. Since cofit does not acknowledge frames itself,
+/// the application reports whether the other side answered its requests in time using [`record_ack`](super::Transmitter::record_ack)
+/// and [`record_ack_timeout`](super::Transmitter::record_ack_timeout). Frames which carry less payload are padded to the full MTU.
+///
+/// # Hysteresis
+///
+/// The payload is halved once `shrink_after` consecutive timeouts have been recorded, but never below `min_payload`.
+/// It only doubles again after `grow_after` consecutive acknowledgements, up to the capacity of a frame.
+/// Every acknowledgement resets the streak of timeouts and vice versa, and both streaks start afresh after each resize.
+///
+/// Keeping `grow_after` well above `shrink_after` stops the size from oscillating on a link which loses a share of the larger frames:
+/// It falls back quickly and only probes the larger size again after a sustained run of successful exchanges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptivePayload {
+    /// Smallest number of payload bytes per frame, values below one are treated as one
+    pub min_payload: usize,
+    /// Consecutive timeouts after which the payload is halved, values below one are treated as one
+    pub shrink_after: u8,
+    /// Consecutive acknowledgements after which the payload is doubled, values below one are treated as one
+    pub grow_after: u8,
+}
+
+impl Default for AdaptivePayload {
+    fn default() -> Self {
+        Self {
+            min_payload: 8,
+            shrink_after: 2,
+            grow_after: 16,
+        }
+    }
+}
+
+/// Current payload size of a [`Transmitter`](super::Transmitter) with adaptive payload sizing enabled
+pub(crate) struct PayloadController {
+    min: usize,
+    max: usize,
+    shrink_after: u8,
+    grow_after: u8,
+    payload: AtomicUsize,
+    timeouts: AtomicU8,
+    acks: AtomicU8,
+}
+
+impl PayloadController {
+    /// Starts out with frames carrying `max` bytes of payload
+    pub(crate) fn new(config: AdaptivePayload, max: usize) -> Self {
+        Self {
+            min: config.min_payload.clamp(1, max),
+            max,
+            shrink_after: config.shrink_after.max(1),
+            grow_after: config.grow_after.max(1),
+            payload: AtomicUsize::new(max),
+            timeouts: AtomicU8::new(0),
+            acks: AtomicU8::new(0),
+        }
+    }
+
+    pub(crate) fn payload(&self) -> usize {
+        self.payload.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_ack(&self) {
+        self.timeouts.store(0, Ordering::Relaxed);
+
+        let acks = self.acks.load(Ordering::Relaxed).saturating_add(1);
+
+        if acks >= self.grow_after {
+            self.resize((self.payload() * 2).min(self.max));
+        } else {
+            self.acks.store(acks, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn record_timeout(&self) {
+        self.acks.store(0, Ordering::Relaxed);
+
+        let timeouts = self.timeouts.load(Ordering::Relaxed).saturating_add(1);
+
+        if timeouts >= self.shrink_after {
+            self.resize((self.payload() / 2).max(self.min));
+        } else {
+            self.timeouts.store(timeouts, Ordering::Relaxed);
+        }
+    }
+
+    fn resize(&self, payload: usize) {
+        self.payload.store(payload, Ordering::Relaxed);
+        self.timeouts.store(0, Ordering::Relaxed);
+        self.acks.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+
+    fn controller() -> PayloadController {
+        let config = AdaptivePayload {
+            min_payload: 8,
+            shrink_after: 2,
+            grow_after: 4,
+        };
+
+        PayloadController::new(config, 40)
+    }
+
+    #[test]
+    fn shrink_after_repeated_timeouts() {
+        let controller = controller();
+
+        let payloads = (0..8)
+            .map(|_| {
+                controller.record_timeout();
+                controller.payload()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(payloads, [40, 20, 20, 10, 10, 8, 8, 8]);
+    }
+
+    #[test]
+    fn tolerate_isolated_timeouts() {
+        let controller = controller();
+
+        for _ in 0..4 {
+            controller.record_timeout();
+            controller.record_ack();
+        }
+
+        assert_eq!(controller.payload(), 40);
+    }
+
+    #[test]
+    fn grow_after_sustained_acknowledgements() {
+        let controller = controller();
+
+        for _ in 0..4 {
+            controller.record_timeout();
+        }
+        assert_eq!(controller.payload(), 10);
+
+        // A single timeout interrupts the streak
+        for _ in 0..3 {
+            controller.record_ack();
+        }
+        controller.record_timeout();
+        for _ in 0..3 {
+            controller.record_ack();
+        }
+        assert_eq!(controller.payload(), 10);
+
+        let payloads = (0..12)
+            .map(|_| {
+                controller.record_ack();
+                controller.payload()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(payloads, [20, 20, 20, 20, 40, 40, 40, 40, 40, 40, 40, 40]);
+    }
+}
@@ -0,0 +1,81 @@
+/// Lifecycle of the connection between host and peripheral as observed by one side of the network
+///
+/// The host moves through the states while [resetting the peripheral](super::Transmitter::reset_peripheral).
+/// The peripheral follows the reset and assignment messages it receives and becomes ready once the host starts sending regular messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// No handshake has taken place yet
+    Connecting,
+    /// The network has been reset, identifiers are assigned once the peripheral confirmed it
+    Negotiating,
+    /// Identifiers have been assigned, messages can be exchanged
+    Ready,
+    /// The connection has been dropped, a new handshake is required to communicate again
+    Disconnected(DisconnectReason),
+}
+
+/// Cause of a [`ConnectionState::Disconnected`] transition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The host reset the network while the connection was ready, e.g. because it reconnected
+    Reset,
+    /// The application closed the connection
+    Closed,
+    /// The [`Transport`](super::Transport) failed, e.g. because the device has been unplugged
+    Lost,
+}
+
+impl ConnectionState {
+    pub(crate) fn into_raw(self) -> u8 {
+        match self {
+            ConnectionState::Connecting => 0,
+            ConnectionState::Negotiating => 1,
+            ConnectionState::Ready => 2,
+            ConnectionState::Disconnected(DisconnectReason::Reset) => 3,
+            ConnectionState::Disconnected(DisconnectReason::Closed) => 4,
+            ConnectionState::Disconnected(DisconnectReason::Lost) => 5,
+        }
+    }
+
+    pub(crate) fn from_raw(raw: u8) -> Self {
+        match raw {
+            1 => ConnectionState::Negotiating,
+            2 => ConnectionState::Ready,
+            3 => ConnectionState::Disconnected(DisconnectReason::Reset),
+            4 => ConnectionState::Disconnected(DisconnectReason::Closed),
+            5 => ConnectionState::Disconnected(DisconnectReason::Lost),
+            _ => ConnectionState::Connecting,
+        }
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+
+    #[test]
+    fn restore_states_from_raw_representation() {
+        let states = [
+            ConnectionState::Connecting,
+            ConnectionState::Negotiating,
+            ConnectionState::Ready,
+            ConnectionState::Disconnected(DisconnectReason::Reset),
+            ConnectionState::Disconnected(DisconnectReason::Closed),
+            ConnectionState::Disconnected(DisconnectReason::Lost),
+        ];
+
+        for state in states {
+            assert_eq!(ConnectionState::from_raw(state.into_raw()), state);
+        }
+    }
+
+    #[test]
+    fn start_out_connecting() {
+        // Registries are zero-initialized
+        assert_eq!(ConnectionState::from_raw(0), ConnectionState::Connecting);
+        assert_eq!(
+            ConnectionState::from_raw(u8::MAX),
+            ConnectionState::Connecting
+        );
+    }
+}
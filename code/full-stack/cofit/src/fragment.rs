@@ -0,0 +1,292 @@
+#![allow(clippy::needless_lifetimes)]
+
+use super::MessageIdentifier;
+use core::{
+    future::Future,
+    sync::atomic::{AtomicU8, AtomicUsize, Ordering},
+};
+
+/// Number of bytes at the start of every fragment which are reserved for the fragment counter
+pub const FRAGMENT_HEADER_LENGTH: usize = 1;
+
+/// Number of bytes at the start of every frame sent with [adaptive payload sizing](super::AdaptivePayload),
+/// the fragment counter followed by the number of payload bytes the frame carries
+pub const ADAPTIVE_HEADER_LENGTH: usize = FRAGMENT_HEADER_LENGTH + 1;
+
+/// Largest number of fragments a single message may be split into, limited by the fragment counter
+pub const MAX_FRAGMENTS: usize = 0x80;
+
+/// Bit of the fragment counter which marks the last fragment of a message, the remaining bits hold its index
+const FINAL_FRAGMENT: u8 = 0x80;
+
+/// Message which does not fit into a single packet and is thus split into multiple fragments
+///
+/// The first [`FRAGMENT_HEADER_LENGTH`] bytes of every fragment are reserved for the fragment counter,
+/// which is written by the [`Transmitter`](super::Transmitter) and still present when the fragments are passed to
+/// [`from_fragments`](Self::from_fragments). Fragmented messages are registered like any other message when creating
+/// the network, sent using [`send_fragmented`](super::Transmitter::send_fragmented) and received through a [`Reassembler`].
+pub trait FragmentedMessage<const MTU: usize>: Sized {
+    /// Unique identifier for this message, see [`Message::IDENTIFIER`](super::Message::IDENTIFIER)
+    const IDENTIFIER: MessageIdentifier<'static>;
+
+    /// Fragments of the message, their number is checked against [`MAX_FRAGMENTS`] before the first one is sent
+    type Fragments: ExactSizeIterator<Item = [u8; MTU]>;
+
+    /// Serializes the typed message into at least one and at most [`MAX_FRAGMENTS`] packets of bytes
+    fn to_fragments(self) -> Self::Fragments;
+
+    /// Deserializes the fragments, in the order they have been serialized in, back into a typed message instance
+    #[allow(clippy::result_unit_err)]
+    fn from_fragments(fragments: &[[u8; MTU]]) -> Result<Self, ()>;
+}
+
+/// Processor for a single [`FragmentedMessage`] type, called by a [`Reassembler`] once all fragments arrived
+pub trait FragmentedHandler<const MTU: usize> {
+    /// Message type this handler can process
+    type Message: FragmentedMessage<MTU>;
+
+    type RecvFut<'s>: Future + 's
+    where
+        Self: 's;
+
+    /// Processes a reassembled message, see [`Handler::handle`](super::Handler::handle)
+    fn handle<'s>(&'s self, message: Self::Message) -> Self::RecvFut<'s>;
+}
+
+/// Buffers the fragments of a [`FragmentedMessage`] and passes the reassembled message on to a [`FragmentedHandler`]
+///
+/// It can be used with [`make_receiver_task!`](super::make_receiver_task) just like any regular [`Handler`](super::Handler).
+/// Up to `FRAGMENTS` fragments are buffered, messages consisting of more fragments are dropped. Fragments which arrive out
+/// of order drop the message as well, since most transports never reorder frames this indicates that some got lost.
+///
+/// Incomplete messages are discarded once more than the [configured](Self::with_max_unrelated_frames) number
+/// of frames of other message types arrived in between two fragments. Only frames which reach the reassembler
+/// are counted, so list it before the other handlers when creating the receiver task.
+///
+/// Messages sent with [adaptive payload sizing](super::AdaptivePayload) are only understood once it has been
+/// [enabled](Self::with_adaptive_payload) on the reassembler as well.
+pub struct Reassembler<H, const MTU: usize, const FRAGMENTS: usize> {
+    handler: H,
+    // Atomics keep the reassembler `Sync` so receiver tasks may still be sent across threads
+    fragments: [[AtomicU8; MTU]; FRAGMENTS],
+    /// Number of frames buffered so far, zero if no message is in progress
+    received: AtomicU8,
+    /// Payload bytes buffered so far when reassembling frames sent with adaptive payload sizing
+    filled: AtomicUsize,
+    /// Frames of other message types received since the last fragment
+    unrelated: AtomicU8,
+    max_unrelated_frames: u8,
+    adaptive: bool,
+}
+
+impl<H: FragmentedHandler<MTU>, const MTU: usize, const FRAGMENTS: usize>
+    Reassembler<H, MTU, FRAGMENTS>
+{
+    /// Number of payload bytes in each fragment
+    const PAYLOAD: usize = MTU - FRAGMENT_HEADER_LENGTH;
+
+    const VALID_BUFFER: () = assert!(
+        FRAGMENTS > 0 && FRAGMENTS <= MAX_FRAGMENTS && MTU > FRAGMENT_HEADER_LENGTH,
+        "reassembly buffer must hold between one and MAX_FRAGMENTS fragments"
+    );
+
+    pub fn new(handler: H) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::VALID_BUFFER;
+
+        Self {
+            handler,
+            fragments: core::array::from_fn(|_| core::array::from_fn(|_| AtomicU8::new(0))),
+            received: AtomicU8::new(0),
+            filled: AtomicUsize::new(0),
+            unrelated: AtomicU8::new(0),
+            max_unrelated_frames: 8,
+            adaptive: false,
+        }
+    }
+
+    /// Number of frames of other message types tolerated in between two fragments before the message is discarded, defaults to eight
+    pub fn with_max_unrelated_frames(mut self, frames: u8) -> Self {
+        self.max_unrelated_frames = frames;
+        self
+    }
+
+    /// Expects frames sent with [adaptive payload sizing](super::AdaptivePayload), which has to be enabled on both ends.
+    /// Up to `FRAGMENTS` fragments worth of payload are buffered, regardless of how many frames it has been spread across.
+    pub fn with_adaptive_payload(mut self) -> Self {
+        assert!(
+            MTU > ADAPTIVE_HEADER_LENGTH,
+            "frames are too small for adaptive payload sizing"
+        );
+
+        self.adaptive = true;
+        self
+    }
+
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    /// Whether some but not all fragments of a message have been received
+    pub fn is_reassembling(&self) -> bool {
+        self.received.load(Ordering::Relaxed) > 0
+    }
+
+    #[doc(hidden)]
+    #[allow(clippy::result_unit_err)]
+    pub fn handle_raw<'s>(
+        &'s self,
+        identifier: MessageIdentifier<'_>,
+        packet: &[u8; MTU],
+    ) -> Result<impl Future<Output = ()> + 's, ()> {
+        let message = self.push(identifier, packet)?;
+
+        Ok(async move {
+            if let Some(message) = message {
+                self.handler.handle(message).await;
+            }
+        })
+    }
+
+    /// Buffers the fragment, returning the message once it is complete and `Err` for frames of other message types
+    fn push(
+        &self,
+        identifier: MessageIdentifier<'_>,
+        packet: &[u8; MTU],
+    ) -> Result<Option<H::Message>, ()> {
+        if H::Message::IDENTIFIER != identifier {
+            self.skip_unrelated();
+            return Err(());
+        }
+
+        let header = packet[0];
+        let index = (header & !FINAL_FRAGMENT) as usize;
+        let expected = match index {
+            0 => 0,
+            _ => self.received.load(Ordering::Relaxed) as usize,
+        };
+
+        self.unrelated.store(0, Ordering::Relaxed);
+
+        let stored = if index != expected {
+            false
+        } else if self.adaptive {
+            self.store_payload(index, packet)
+        } else {
+            self.store_fragment(index, packet)
+        };
+
+        if !stored {
+            self.received.store(0, Ordering::Relaxed);
+            return Ok(None);
+        }
+
+        if header & FINAL_FRAGMENT == 0 {
+            self.received.store(index as u8 + 1, Ordering::Relaxed);
+            return Ok(None);
+        }
+
+        self.received.store(0, Ordering::Relaxed);
+
+        let count = if self.adaptive {
+            // The transmitter sends the payload of every fragment in full, anything else indicates a corrupted message
+            let filled = self.filled.load(Ordering::Relaxed);
+            if !filled.is_multiple_of(Self::PAYLOAD) {
+                return Ok(None);
+            }
+            filled / Self::PAYLOAD
+        } else {
+            index + 1
+        };
+
+        let mut fragments = [[0; MTU]; FRAGMENTS];
+        for (fragment, slots) in fragments.iter_mut().zip(&self.fragments).take(count) {
+            for (byte, slot) in fragment.iter_mut().zip(slots) {
+                *byte = slot.load(Ordering::Relaxed);
+            }
+        }
+
+        if self.adaptive {
+            // Frames only carry their own counter, so the ones of the fragments are restored here
+            for (index, fragment) in fragments.iter_mut().take(count).enumerate() {
+                fragment[0] = fragment_header(index, index + 1 == count)
+                    .expect("reassembly buffer holds at most MAX_FRAGMENTS fragments");
+            }
+        }
+
+        H::Message::from_fragments(&fragments[..count]).map(Some)
+    }
+
+    /// Buffers a fragment which has been sent as-is
+    fn store_fragment(&self, index: usize, packet: &[u8; MTU]) -> bool {
+        if index >= FRAGMENTS {
+            return false;
+        }
+
+        for (slot, byte) in self.fragments[index].iter().zip(packet) {
+            slot.store(*byte, Ordering::Relaxed);
+        }
+
+        true
+    }
+
+    /// Appends the payload of a frame sent with adaptive payload sizing to the fragments buffered so far
+    fn store_payload(&self, index: usize, packet: &[u8; MTU]) -> bool {
+        let filled = match index {
+            0 => 0,
+            _ => self.filled.load(Ordering::Relaxed),
+        };
+
+        let length = packet[FRAGMENT_HEADER_LENGTH] as usize;
+        if length > adaptive_capacity(MTU) || filled + length > FRAGMENTS * Self::PAYLOAD {
+            return false;
+        }
+
+        let payload = &packet[ADAPTIVE_HEADER_LENGTH..ADAPTIVE_HEADER_LENGTH + length];
+        for (position, byte) in (filled..).zip(payload) {
+            let fragment = &self.fragments[position / Self::PAYLOAD];
+            fragment[FRAGMENT_HEADER_LENGTH + position % Self::PAYLOAD]
+                .store(*byte, Ordering::Relaxed);
+        }
+
+        self.filled.store(filled + length, Ordering::Relaxed);
+        true
+    }
+
+    fn skip_unrelated(&self) {
+        if !self.is_reassembling() {
+            return;
+        }
+
+        let unrelated = self.unrelated.load(Ordering::Relaxed).saturating_add(1);
+
+        if unrelated > self.max_unrelated_frames {
+            self.received.store(0, Ordering::Relaxed);
+            self.unrelated.store(0, Ordering::Relaxed);
+        } else {
+            self.unrelated.store(unrelated, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Fragment counter for the fragment at the given index, `None` if the message consists of too many fragments
+pub(crate) fn fragment_header(index: usize, last: bool) -> Option<u8> {
+    if index >= MAX_FRAGMENTS {
+        None
+    } else if last {
+        Some(index as u8 | FINAL_FRAGMENT)
+    } else {
+        Some(index as u8)
+    }
+}
+
+/// Largest number of payload bytes a frame sent with adaptive payload sizing can carry, limited by the length byte
+pub(crate) const fn adaptive_capacity(mtu: usize) -> usize {
+    let capacity = mtu.saturating_sub(ADAPTIVE_HEADER_LENGTH);
+
+    if capacity > u8::MAX as usize {
+        u8::MAX as usize
+    } else {
+        capacity
+    }
+}
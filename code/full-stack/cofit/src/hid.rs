@@ -0,0 +1,22 @@
+//! Parameters of the vendor defined USB HID channel which peripherals expose to the host
+//!
+//! Both ends are built from these, so the firmware and the host tools can not disagree about them.
+
+/// Vendor ID of peripherals running the firmware
+pub const VENDOR_ID: u16 = 0xC0DE;
+/// Product ID of peripherals running the firmware
+pub const PRODUCT_ID: u16 = 0xCAFE;
+
+/// Vendor defined usage page by which the host identifies the channel
+pub const USAGE_PAGE_VENDOR: u16 = 0xFF00;
+/// Vendor defined usage by which the host identifies the channel
+pub const USAGE_EMBEDDED_STENO: u16 = 0x42;
+
+/// Number of bytes contained in each input and output report
+pub const REPORT_SIZE: usize = 64;
+
+/// MTU of [`Transport`](super::Transport)s over the channel, the first byte of each report carries the message ID
+pub const MTU: usize = REPORT_SIZE - 1;
+
+// Reports are transferred over a full-speed interrupt endpoint, which can not move more than 64 bytes at once
+const _: () = assert!(REPORT_SIZE <= 64);
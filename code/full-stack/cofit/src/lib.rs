@@ -23,22 +23,99 @@
 //! Additionally, assigning numeric identifiers allows for more efficient transfers since the MTU is usually very low
 //! and the overhead of transfering a dynamic-size string identifier with each is not tolerable.
 //!
-//! The peripheral will never send messages which are not supported by the host and will ignore any messages sent by the host
-//! that it can not handle (in the future the host may be notified of the peripherals capabilities so those messages wont even be sent).
+//! The peripheral will never send messages which are not supported by the host. Assignments of messages it can not handle are echoed back
+//! to the host which then no longer sends them. The host application may inspect the outcome through the [`Negotiation`](self::Negotiation).
+//!
+//! ## Late messages
+//!
+//! Some capabilities only become available after the connection has been established, for example when an add-on board is attached
+//! to a modular peripheral. Messages for these can be registered as `late_messages` when creating the network. The host does not assign
+//! them upon reset but waits for the peripheral to [`advertise`](self::Transmitter::advertise) them. It then assigns an ID which the
+//! peripheral confirms, at which point the host [`Receiver`](self::Receiver) emits a [`CapabilityAdded`](self::CapabilityAdded) message.
+//!
+//! ## Fragmentation
+//!
+//! Messages which do not fit into a single frame may implement [`FragmentedMessage`](self::FragmentedMessage) instead, which splits them
+//! into multiple frames carrying a fragment counter. They are sent using [`send_fragmented`](self::Transmitter::send_fragmented) and
+//! reassembled on the receiving side by a [`Reassembler`](self::Reassembler), whose buffer size is a const generic so memory usage
+//! is known up front.
+//!
+//! On noisy links, large frames get corrupted more often than small ones. Fragmented messages may thus optionally be spread across
+//! frames which carry less payload while requests of the application time out, see [`AdaptivePayload`](self::AdaptivePayload).
+//!
+//! ## Roles
+//!
+//! Only the host has the authority to reset the network and assign identifiers, the peripheral merely remembers them.
+//! This is enforced through the [`Host`](self::Host) and [`Peripheral`](self::Peripheral) type parameters of the
+//! [`Transmitter`](self::Transmitter) and [`Receiver`](self::Receiver), so a peripheral can not initiate assignments:
+//!
+//! ```compile_fail
+//! # #![feature(generic_associated_types)]
+//! # #![feature(type_alias_impl_trait)]
+//! # use cofit::{Message, Peripheral, MessageIdentifier, make_network, Transport, TransportError};
+//! # use core::future::Future;
+//! # const MTU: usize = 42;
+//! # struct PingMessage;
+//! # impl Message<MTU> for PingMessage {
+//! #     const IDENTIFIER: MessageIdentifier<'static> = "ping";
+//! #     fn to_packet(self) -> [u8; MTU] { unimplemented!() }
+//! #     fn from_packet(packet: [u8; MTU]) -> Result<Self, ()> { unimplemented!() }
+//! # }
+//! # struct DummyTransport;
+//! # impl Transport<MTU> for DummyTransport {
+//! #     type TxFut<'t> = impl Future<Output = Result<(), TransportError>> + 't where Self: 't;
+//! #     type RxFut<'t> = impl Future<Output = Result<(u8, [u8; MTU]), TransportError>> + 't where Self: 't;
+//! #     fn send<'t>(&'t self, id: u8, data: [u8; MTU]) -> Self::TxFut<'t> { async move { unimplemented!() } }
+//! #     fn recv<'t>(&'t self) -> Self::RxFut<'t> { async move { unimplemented!() } }
+//! # }
+//! # let transport = DummyTransport;
+//! let (tx, rx) = make_network! {
+//!     role: Peripheral,
+//!     transport: &transport,
+//!     messages: [PingMessage]
+//! };
+//!
+//! // Resetting and thus assigning identifiers is only available to the host
+//! let _ = tx.reset_peripheral(core::future::pending::<()>());
+//! ```
+//!
+//! At runtime, each side additionally drops control messages which only the other role may send.
+//!
+//! ## Reset handshake
+//!
+//! A reset clears all assignments of the peripheral, which confirms this by echoing the reset. Only then does the host
+//! transmit the new assignments, so no message is sent before the peripheral is able to understand it.
+//! Since the host [`Receiver`](self::Receiver) processes the confirmation, it has to be polled while resetting.
+//! Heartbeats reporting the pending reset complete the handshake as well, in case the confirmation got lost.
+//!
+//! ## Automatic resets
+//!
+//! Every reset carries an epoch which the peripheral remembers and reports back in its [`heartbeat`](self::Transmitter::heartbeat)s.
+//! The host [`Receiver`](self::Receiver) resets the peripheral on its own when a heartbeat reveals a fresh connection:
+//! - the host has not connected yet or the connection has been [closed](self::Transmitter::close), or
+//! - the reported epoch does not match the last reset, e.g. because the peripheral rebooted or another one has been plugged in.
+//!
+//! Without this, a peripheral would keep using stale identifier assignments after reconnecting and misinterpret messages.
+//! Applications which have to know about these resets can follow the connection using [`wait_for_state_change`](self::Transmitter::wait_for_state_change).
+//! Advanced users who want to control the handshake themselves may [disable](self::Transmitter::set_automatic_reset) it
+//! and call [`reset_peripheral`](self::Transmitter::reset_peripheral) manually.
 //!
 //! ## Usage workflow
 //!
-//! 1. Create a [`Transport`](self::Transport) implementation
+//! 1. Create a [`Transport`](self::Transport) implementation, or use the `LoopbackTransport` of the `test-util` feature in tests
 //! 2. Write some [`Message`](self::Message) trait implementations
 //! 3. Author some [`Handler`](self::Handler) impls for your message types
 //! 3. Initialize a [`Receiver`](self::Receiver) + [`Transmitter`](self::Transmitter) pair using [`make_network!`](self::make_network)
 //! 4. Build a receiver task using [`make_receiver_task!`](self::make_receiver_task)
 //! 5. Poll the task for all eternity
-//! 6. If you are the host, call [`reset_peripheral`](self::Transmitter::reset_peripheral) once connected
+//! 6. If you are the peripheral, send a [`heartbeat`](self::Transmitter::heartbeat) at a regular interval so the host resets the network
 //! 7. Send some messages!
 
 #![cfg_attr(not(feature = "std"), no_std)]
-#![cfg_attr(feature = "usb", feature(type_alias_impl_trait))]
+#![cfg_attr(
+    any(feature = "usb", feature = "test-util"),
+    feature(type_alias_impl_trait)
+)]
 #![feature(doc_auto_cfg)]
 #![feature(doc_cfg)]
 #![feature(generic_associated_types)]
@@ -51,7 +128,15 @@ type MessageID = u8;
 /// If you are writing a vendor specific extension, consider using your domain as a prefix.
 pub type MessageIdentifier<'i> = &'i str;
 
+mod adaptive;
+mod connection;
+mod fragment;
+pub mod hid;
+#[cfg(feature = "test-util")]
+mod loopback;
 mod message;
+mod negotiation;
+mod rate_limit;
 mod receiver;
 mod registry;
 mod task;
@@ -60,7 +145,14 @@ mod transport;
 #[cfg(feature = "usb")]
 mod usb_hid;
 
-pub use message::Message;
+pub use adaptive::AdaptivePayload;
+pub use connection::{ConnectionState, DisconnectReason};
+pub use fragment::*;
+#[cfg(feature = "test-util")]
+pub use loopback::LoopbackTransport;
+pub use message::{CapabilityAdded, Message};
+pub use negotiation::{NegotiatedMessage, Negotiation};
+pub use rate_limit::{Clock, NoClock, RateLimit, RateLimitPolicy};
 pub use receiver::*;
 pub use registry::*;
 pub use task::*;
@@ -81,7 +173,7 @@ pub use usb_hid::UsbHidTransport;
 /// ```
 /// # #![feature(generic_associated_types)]
 /// # #![feature(type_alias_impl_trait)]
-/// # use cofit::{Message, Host, MessageIdentifier, make_network, Transport};
+/// # use cofit::{Message, Host, MessageIdentifier, make_network, Transport, TransportError};
 /// # use core::future::Future;
 /// # const MTU: usize = 42;
 /// #
@@ -121,11 +213,11 @@ pub use usb_hid::UsbHidTransport;
 /// # }
 /// #
 /// # impl Transport<MTU> for UsbHidTransport {
-/// #     type TxFut<'t> = impl Future<Output = ()> + 't
+/// #     type TxFut<'t> = impl Future<Output = Result<(), TransportError>> + 't
 /// #     where
 /// #         Self: 't;
 /// #
-/// #     type RxFut<'t> = impl Future<Output = (u8, [u8; MTU])> + 't
+/// #     type RxFut<'t> = impl Future<Output = Result<(u8, [u8; MTU]), TransportError>> + 't
 /// #     where
 /// #         Self: 't;
 /// #
@@ -148,17 +240,37 @@ pub use usb_hid::UsbHidTransport;
 /// // Make sure to constantly call `rx.recv()` so that `tx.send(_)` operates correctly.
 /// // You may use `make_receiver_task` to create an async task that does this for you!
 /// ```
+///
+/// Messages which only become available later on can be listed separately (see [late messages](self#late-messages)):
+///
+/// ```ignore
+/// let (tx, rx) = make_network! {
+///     role: Peripheral,
+///     transport: &transport,
+///     messages: [WriteFlashMessage, ReadFlashMessage],
+///     late_messages: [AddOnMessage]
+/// };
+/// ```
+///
+/// Every message type requires its own identifier, listing two types which share one fails to compile.
 #[macro_export]
 macro_rules! make_network {
     (role: $role:ident, transport: $transport:expr, messages: [$($message:ty),+ $(,)?]) => {
+        $crate::make_network!(role: $role, transport: $transport, messages: [$($message),+], late_messages: [])
+    };
+
+    (role: $role:ident, transport: $transport:expr, messages: [$($message:ty),+ $(,)?], late_messages: [$($late_message:ty),* $(,)?]) => {
         {
-            use $crate::{make_network, IdentifierRegistry, Transmitter, Receiver, Message};
+            use $crate::{make_network, IdentifierRegistry, Transmitter, Receiver, Message, FragmentedMessage};
 
-            const _: () = IdentifierRegistry::<$role>::verify_message_count(make_network!(@count $({$message})*));
-            // TODO Verify that there are no duplicate messages at compile time (requires sub-macros that emit a compile_error! as recursion/loops are not possible in const code)
+            const _: () = IdentifierRegistry::<$role>::verify_message_count(make_network!(@count $({$message})* $({$late_message})*));
+            const _: () = IdentifierRegistry::<$role>::verify_unique_identifiers(&[$(<$message>::IDENTIFIER,)+ $(<$late_message>::IDENTIFIER,)*]);
 
-            static ASSIGNMENTS: [(core::sync::atomic::AtomicU8, $crate::MessageIdentifier<'static>); make_network!(@count $({$message})*)] = [$((core::sync::atomic::AtomicU8::new(IdentifierRegistry::<$role>::UNASSIGNED), <$message>::IDENTIFIER),)+];
-            static REGISTRY: IdentifierRegistry<$role> = IdentifierRegistry::new(&ASSIGNMENTS);
+            static ASSIGNMENTS: [(core::sync::atomic::AtomicU8, $crate::MessageIdentifier<'static>); make_network!(@count $({$message})* $({$late_message})*)] = [
+                $((core::sync::atomic::AtomicU8::new(IdentifierRegistry::<$role>::UNASSIGNED), <$message>::IDENTIFIER),)+
+                $((core::sync::atomic::AtomicU8::new(IdentifierRegistry::<$role>::UNASSIGNED), <$late_message>::IDENTIFIER),)*
+            ];
+            static REGISTRY: IdentifierRegistry<$role> = IdentifierRegistry::new(&ASSIGNMENTS, make_network!(@count $({$message})*));
 
             let transmitter = Transmitter::new($role, &REGISTRY, $transport);
             let receiver = Receiver::new($role, &REGISTRY, $transport);
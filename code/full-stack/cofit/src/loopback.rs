@@ -0,0 +1,108 @@
+use super::{MessageID, Transport, TransportError};
+use core::future::Future;
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    Mutex,
+};
+
+type Frame<const MTU: usize> = (MessageID, [u8; MTU]);
+type Filter<const MTU: usize> = Box<dyn FnMut(Frame<MTU>) -> Option<Frame<MTU>> + Send>;
+
+/// In-memory transport connecting two endpoints, intended for testing networks without any hardware
+///
+/// Frames sent on one end are received on the other in the order they have been sent. A [filter](Self::with_filter)
+/// may be installed to simulate a lossy link, either dropping frames or holding them back to deliver them later on.
+/// Dropping one end disconnects the other, failing its sends and receives with [`TransportError::Disconnected`].
+#[doc(cfg(feature = "test-util"))]
+pub struct LoopbackTransport<const MTU: usize> {
+    tx: UnboundedSender<Frame<MTU>>,
+    rx: Mutex<UnboundedReceiver<Frame<MTU>>>,
+    filter: std::sync::Mutex<Option<Filter<MTU>>>,
+}
+
+impl<const MTU: usize> LoopbackTransport<MTU> {
+    /// Creates two connected ends, e.g. one for the host and one for the peripheral
+    pub fn pair() -> (Self, Self) {
+        let (a_tx, a_rx) = unbounded_channel();
+        let (b_tx, b_rx) = unbounded_channel();
+
+        (Self::new(a_tx, b_rx), Self::new(b_tx, a_rx))
+    }
+
+    fn new(tx: UnboundedSender<Frame<MTU>>, rx: UnboundedReceiver<Frame<MTU>>) -> Self {
+        Self {
+            tx,
+            rx: Mutex::new(rx),
+            filter: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Passes every frame sent on this end through the given closure, which returns the frame to deliver instead.
+    ///
+    /// Returning `None` drops the frame. To reorder frames, the closure may hold on to a frame and return it in place of a later one.
+    pub fn with_filter(
+        self,
+        filter: impl FnMut(Frame<MTU>) -> Option<Frame<MTU>> + Send + 'static,
+    ) -> Self {
+        *self.filter.lock().unwrap() = Some(Box::new(filter));
+        self
+    }
+}
+
+impl<const MTU: usize> Transport<MTU> for LoopbackTransport<MTU> {
+    type TxFut<'t>
+        = impl Future<Output = Result<(), TransportError>> + 't
+    where
+        Self: 't;
+
+    type RxFut<'t>
+        = impl Future<Output = Result<(MessageID, [u8; MTU]), TransportError>> + 't
+    where
+        Self: 't;
+
+    fn send<'t>(&'t self, id: MessageID, data: [u8; MTU]) -> Self::TxFut<'t> {
+        let frame = match self.filter.lock().unwrap().as_mut() {
+            Some(filter) => filter((id, data)),
+            None => Some((id, data)),
+        };
+
+        async move {
+            match frame {
+                Some(frame) => self
+                    .tx
+                    .send(frame)
+                    .map_err(|_| TransportError::Disconnected),
+                None => Ok(()),
+            }
+        }
+    }
+
+    fn recv<'t>(&'t self) -> Self::RxFut<'t> {
+        async move {
+            self.rx
+                .lock()
+                .await
+                .recv()
+                .await
+                .ok_or(TransportError::Disconnected)
+        }
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+
+    #[tokio::test]
+    async fn disconnect_when_other_end_is_dropped() {
+        let (a, b) = LoopbackTransport::<4>::pair();
+
+        a.send(1, [1; 4]).await.unwrap();
+        drop(a);
+
+        // Frames sent before are still delivered
+        assert_eq!(b.recv().await, Ok((1, [1; 4])));
+        assert_eq!(b.recv().await, Err(TransportError::Disconnected));
+        assert_eq!(b.send(2, [2; 4]).await, Err(TransportError::Disconnected));
+    }
+}
@@ -8,6 +8,25 @@ pub(crate) const RESET_IDENTIFIER: MessageIdentifier<'static> = "net.reset";
 pub(crate) const ASSIGN_ID: MessageID = MessageID::MAX - 1;
 pub(crate) const ASSIGN_IDENTIFIER: MessageIdentifier<'static> = "net.assign";
 
+/// Statically allocated ID for announcing late messages
+pub(crate) const ADVERTISE_ID: MessageID = MessageID::MAX - 2;
+pub(crate) const ADVERTISE_IDENTIFIER: MessageIdentifier<'static> = "net.advertise";
+
+/// Statically allocated ID for signalling that the peripheral is connected
+pub(crate) const HEARTBEAT_ID: MessageID = MessageID::MAX - 3;
+pub(crate) const HEARTBEAT_IDENTIFIER: MessageIdentifier<'static> = "net.heartbeat";
+
+/// Statically allocated ID for echoing assignments of messages the peripheral does not support
+pub(crate) const REJECT_ID: MessageID = MessageID::MAX - 4;
+pub(crate) const REJECT_IDENTIFIER: MessageIdentifier<'static> = "net.reject";
+
+/// Statically allocated ID for echoing resets, confirming that the peripheral cleared its assignments
+pub(crate) const RESET_ACK_ID: MessageID = MessageID::MAX - 5;
+pub(crate) const RESET_ACK_IDENTIFIER: MessageIdentifier<'static> = "net.reset-ack";
+
+/// Identifier of the local-only [`CapabilityAdded`](CapabilityAdded) message, it is never sent over the wire
+pub(crate) const CAPABILITY_ADDED_IDENTIFIER: MessageIdentifier<'static> = "net.capability-added";
+
 /// Typed data packet sent over the wire, identified by a [`MessageIdentifier`](super::MessageIdentifier)
 pub trait Message<const MTU: usize>: Sized {
     /// Unique identifier for this message, this will be transferred over the wire while negotiating the communication protocol!
@@ -21,18 +40,30 @@ pub trait Message<const MTU: usize>: Sized {
     fn from_packet(packet: [u8; MTU]) -> Result<Self, ()>;
 }
 
-pub(crate) struct Reset;
+/// Carries the epoch of the reset, which the peripheral reports back in its heartbeats
+pub(crate) struct Reset(u8);
 pub(crate) struct Assign<const MTU: usize>([u8; MTU]);
+pub(crate) struct Advertise<const MTU: usize>([u8; MTU]);
+/// Carries the epoch of the last reset received by the peripheral, zero if there was none
+pub(crate) struct Heartbeat(u8);
+
+/// Notification that the peripheral gained support for a late message after the network was established
+///
+/// Emitted by the host [`Receiver`](super::Receiver) once the peripheral confirmed the assignment
+/// of a message it advertised. Provide a [`Handler`](super::Handler) for it to get notified.
+pub struct CapabilityAdded<const MTU: usize>(Assign<MTU>);
 
 impl<const MTU: usize> Message<MTU> for Reset {
     const IDENTIFIER: MessageIdentifier<'static> = RESET_IDENTIFIER;
 
     fn to_packet(self) -> [u8; MTU] {
-        [0; MTU]
+        let mut buf = [0; MTU];
+        buf[0] = self.0;
+        buf
     }
 
-    fn from_packet(_: [u8; MTU]) -> Result<Self, ()> {
-        Ok(Self)
+    fn from_packet(packet: [u8; MTU]) -> Result<Self, ()> {
+        Ok(Self(packet[0]))
     }
 }
 
@@ -52,6 +83,70 @@ impl<const MTU: usize> Message<MTU> for Assign<MTU> {
     }
 }
 
+impl<const MTU: usize> Message<MTU> for Advertise<MTU> {
+    const IDENTIFIER: MessageIdentifier<'static> = ADVERTISE_IDENTIFIER;
+
+    fn to_packet(self) -> [u8; MTU] {
+        self.0
+    }
+
+    fn from_packet(packet: [u8; MTU]) -> Result<Self, ()> {
+        let length = packet[0] as usize;
+
+        if length < MTU && core::str::from_utf8(&packet[1..1 + length]).is_ok() {
+            Ok(Self(packet))
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl<const MTU: usize> Message<MTU> for Heartbeat {
+    const IDENTIFIER: MessageIdentifier<'static> = HEARTBEAT_IDENTIFIER;
+
+    fn to_packet(self) -> [u8; MTU] {
+        let mut buf = [0; MTU];
+        buf[0] = self.0;
+        buf
+    }
+
+    fn from_packet(packet: [u8; MTU]) -> Result<Self, ()> {
+        Ok(Self(packet[0]))
+    }
+}
+
+impl<const MTU: usize> Message<MTU> for CapabilityAdded<MTU> {
+    const IDENTIFIER: MessageIdentifier<'static> = CAPABILITY_ADDED_IDENTIFIER;
+
+    fn to_packet(self) -> [u8; MTU] {
+        self.0.to_packet()
+    }
+
+    fn from_packet(packet: [u8; MTU]) -> Result<Self, ()> {
+        Assign::from_packet(packet).map(Self)
+    }
+}
+
+impl Reset {
+    pub(crate) fn new(epoch: u8) -> Self {
+        Self(epoch)
+    }
+
+    pub(crate) fn epoch(&self) -> u8 {
+        self.0
+    }
+}
+
+impl Heartbeat {
+    pub(crate) fn new(epoch: u8) -> Self {
+        Self(epoch)
+    }
+
+    pub(crate) fn epoch(&self) -> u8 {
+        self.0
+    }
+}
+
 impl<const MTU: usize> Assign<MTU> {
     pub(crate) fn new(id: MessageID, identifier: MessageIdentifier) -> Self {
         let mut buf = [0; MTU];
@@ -74,3 +169,33 @@ impl<const MTU: usize> Assign<MTU> {
         core::str::from_utf8(bytes).unwrap()
     }
 }
+
+impl<const MTU: usize> Advertise<MTU> {
+    pub(crate) fn new(identifier: MessageIdentifier) -> Self {
+        let mut buf = [0; MTU];
+
+        let identifier_bytes = identifier.as_bytes();
+        buf[0] = identifier_bytes.len() as u8;
+        buf[1..1 + identifier_bytes.len()].copy_from_slice(identifier_bytes);
+
+        Self(buf)
+    }
+
+    pub(crate) fn identifier(&self) -> MessageIdentifier {
+        let length = self.0[0] as usize;
+        let bytes = &self.0[1..1 + length];
+        core::str::from_utf8(bytes).unwrap()
+    }
+}
+
+impl<const MTU: usize> CapabilityAdded<MTU> {
+    /// Numeric ID assigned to the message
+    pub fn id(&self) -> MessageID {
+        self.0.id()
+    }
+
+    /// Identifier of the message which is now supported by the peripheral
+    pub fn identifier(&self) -> MessageIdentifier {
+        self.0.identifier()
+    }
+}
@@ -0,0 +1,81 @@
+use super::{Host, IdentifierRegistry, MessageID, MessageIdentifier};
+
+/// Outcome of the identifier assignment, as far as the host knows about it
+///
+/// Peripherals echo assignments of messages they do not support, upon which the host [`Receiver`](super::Receiver)
+/// removes them again. Thus, the negotiation is only complete once the receiver processed the responses to a
+/// [reset](super::Transmitter::reset_peripheral), which is usually the case by the time the connection is used.
+/// Peripherals running older versions of this crate do not reject assignments, in which case all messages appear to be supported.
+///
+/// The negotiation reflects the assignments at the time it is queried, late messages which are advertised afterwards
+/// or further resets are not taken into account.
+pub struct Negotiation<'r> {
+    registry: &'r IdentifierRegistry<'r, Host>,
+}
+
+/// Assignment state of one message type, see [`Negotiation`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NegotiatedMessage {
+    identifier: MessageIdentifier<'static>,
+    id: Option<MessageID>,
+    late: bool,
+}
+
+impl<'r> Negotiation<'r> {
+    pub(crate) fn new(registry: &'r IdentifierRegistry<'r, Host>) -> Self {
+        Self { registry }
+    }
+
+    /// All message types registered with the network in the order they have been listed in
+    pub fn messages(&self) -> impl Iterator<Item = NegotiatedMessage> + 'r {
+        let registry = self.registry;
+
+        registry
+            .assignments()
+            .map(move |(identifier, id)| NegotiatedMessage {
+                identifier,
+                id,
+                late: registry.is_late(identifier),
+            })
+    }
+
+    /// Message types which the peripheral accepted and which can thus be sent
+    pub fn supported(&self) -> impl Iterator<Item = MessageIdentifier<'static>> + 'r {
+        self.messages()
+            .filter(NegotiatedMessage::is_supported)
+            .map(|message| message.identifier)
+    }
+
+    /// Message types which can not be sent, either because the peripheral rejected them or because late messages have not been advertised
+    pub fn unsupported(&self) -> impl Iterator<Item = MessageIdentifier<'static>> + 'r {
+        self.messages()
+            .filter(|message| !message.is_supported())
+            .map(|message| message.identifier)
+    }
+
+    /// Whether the given message type has been assigned an ID, returns `false` for unknown messages
+    pub fn is_supported(&self, identifier: MessageIdentifier) -> bool {
+        self.messages()
+            .any(|message| message.identifier == identifier && message.is_supported())
+    }
+}
+
+impl NegotiatedMessage {
+    pub fn identifier(&self) -> MessageIdentifier<'static> {
+        self.identifier
+    }
+
+    /// Numeric ID assigned to the message, `None` if it can not be sent
+    pub fn id(&self) -> Option<MessageID> {
+        self.id
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.id.is_some()
+    }
+
+    /// Whether the message has been registered as a [late message](super#late-messages)
+    pub fn is_late(&self) -> bool {
+        self.late
+    }
+}
@@ -0,0 +1,237 @@
+use core::{
+    cell::UnsafeCell,
+    future::{ready, Future, Ready},
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    time::Duration,
+};
+
+/// Source of time for the [`RateLimit`] of a [`Transmitter`](super::Transmitter), as this crate has no notion of time on its own
+pub trait Clock {
+    type SleepFut<'c>: Future<Output = ()> + 'c
+    where
+        Self: 'c;
+
+    /// Time elapsed since an arbitrary but fixed point in the past, e.g. the boot of the device
+    fn now(&self) -> Duration;
+
+    /// Completes once [`now`](Self::now) reaches the given deadline
+    fn sleep_until<'c>(&'c self, deadline: Duration) -> Self::SleepFut<'c>;
+}
+
+/// Clock of transmitters without a [`RateLimit`], which never have to tell the time
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoClock;
+
+impl Clock for NoClock {
+    type SleepFut<'c> = Ready<()>
+    where
+        Self: 'c;
+
+    fn now(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn sleep_until<'c>(&'c self, _deadline: Duration) -> Self::SleepFut<'c> {
+        ready(())
+    }
+}
+
+/// What happens to messages sent while the [`RateLimit`] is exhausted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitPolicy {
+    /// Wait until the budget allows sending the message
+    Block,
+    /// Discard the message, failing the send with [`TransmitError::RateLimited`](super::TransmitError::RateLimited)
+    Drop,
+}
+
+/// Token bucket limiting how many messages a [`Transmitter`](super::Transmitter) sends, see [`Transmitter::with_rate_limit`](super::Transmitter::with_rate_limit)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Sustained number of messages allowed per second, values below one are treated as one
+    pub messages_per_second: u32,
+    /// Number of messages that may be sent back-to-back after the link has been idle, values below one are treated as one
+    pub burst: u32,
+    pub policy: RateLimitPolicy,
+}
+
+struct Bucket {
+    tokens: u32,
+    refilled_at: Duration,
+}
+
+pub(crate) struct RateLimiter<C: Clock> {
+    clock: C,
+    interval: Duration,
+    burst: u32,
+    policy: RateLimitPolicy,
+    bucket: Locked<Bucket>,
+    dropped: AtomicU32,
+}
+
+impl<C: Clock> RateLimiter<C> {
+    pub(crate) fn new(limit: RateLimit, clock: C) -> Self {
+        let burst = limit.burst.max(1);
+        let bucket = Bucket {
+            tokens: burst,
+            refilled_at: clock.now(),
+        };
+
+        Self {
+            interval: Duration::from_secs(1) / limit.messages_per_second.max(1),
+            burst,
+            policy: limit.policy,
+            bucket: Locked::new(bucket),
+            dropped: AtomicU32::new(0),
+            clock,
+        }
+    }
+
+    /// Takes a token from the bucket, returns false if the message should be dropped instead
+    pub(crate) async fn acquire(&self) -> bool {
+        loop {
+            let deadline = match self.bucket.with(|bucket| self.take(bucket)) {
+                Ok(()) => return true,
+                Err(deadline) => deadline,
+            };
+
+            match self.policy {
+                RateLimitPolicy::Block => self.clock.sleep_until(deadline).await,
+                RateLimitPolicy::Drop => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return false;
+                }
+            }
+        }
+    }
+
+    /// Number of messages dropped so far due to the [`RateLimitPolicy::Drop`] policy
+    pub(crate) fn dropped_count(&self) -> u32 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Takes a token if one is available, otherwise returns when the next one will be
+    fn take(&self, bucket: &mut Bucket) -> Result<(), Duration> {
+        let now = self.clock.now();
+
+        while bucket.tokens < self.burst && bucket.refilled_at + self.interval <= now {
+            bucket.tokens += 1;
+            bucket.refilled_at += self.interval;
+        }
+
+        // A full bucket does not accumulate time, otherwise the next refill would come early
+        if bucket.tokens == self.burst {
+            bucket.refilled_at = now;
+        }
+
+        if bucket.tokens > 0 {
+            bucket.tokens -= 1;
+            Ok(())
+        } else {
+            Err(bucket.refilled_at + self.interval)
+        }
+    }
+}
+
+/// Minimal spin lock, since this crate has no async mutex at hand in `no_std` environments.
+/// It is only ever held for a few instructions and never across an await point, so tasks hardly ever contend for it.
+struct Locked<T> {
+    locked: AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+// Access to the value is serialized by the lock
+unsafe impl<T: Send> Sync for Locked<T> {}
+
+impl<T> Locked<T> {
+    fn new(value: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    fn with<O>(&self, f: impl FnOnce(&mut T) -> O) -> O {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+
+        // SAFETY: The lock has been acquired above, so no other reference to the value exists
+        let output = f(unsafe { &mut *self.value.get() });
+        self.locked.store(false, Ordering::Release);
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+    use core::cell::Cell;
+
+    /// Clock which only advances when told to, sleeping jumps straight to the deadline
+    #[derive(Default)]
+    struct ManualClock(Cell<Duration>);
+
+    impl Clock for &ManualClock {
+        type SleepFut<'c> = Ready<()>
+        where
+            Self: 'c;
+
+        fn now(&self) -> Duration {
+            self.0.get()
+        }
+
+        fn sleep_until<'c>(&'c self, deadline: Duration) -> Self::SleepFut<'c> {
+            self.0.set(self.0.get().max(deadline));
+            ready(())
+        }
+    }
+
+    fn limit(policy: RateLimitPolicy) -> RateLimit {
+        RateLimit {
+            messages_per_second: 10,
+            burst: 2,
+            policy,
+        }
+    }
+
+    #[tokio::test]
+    async fn refill_tokens_over_time() {
+        let clock = ManualClock::default();
+        let limiter = RateLimiter::new(limit(RateLimitPolicy::Drop), &clock);
+
+        assert!(limiter.acquire().await);
+        assert!(limiter.acquire().await);
+        assert!(!limiter.acquire().await);
+
+        clock.0.set(Duration::from_millis(150));
+        assert!(limiter.acquire().await);
+        assert!(!limiter.acquire().await);
+
+        // Idling for long does not exceed the burst
+        clock.0.set(Duration::from_secs(10));
+        assert!(limiter.acquire().await);
+        assert!(limiter.acquire().await);
+        assert!(!limiter.acquire().await);
+
+        assert_eq!(limiter.dropped_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn wait_for_next_token() {
+        let clock = ManualClock::default();
+        let limiter = RateLimiter::new(limit(RateLimitPolicy::Block), &clock);
+
+        for _ in 0..4 {
+            assert!(limiter.acquire().await);
+        }
+
+        assert_eq!(clock.0.get(), Duration::from_millis(200));
+        assert_eq!(limiter.dropped_count(), 0);
+    }
+}
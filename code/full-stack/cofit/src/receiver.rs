@@ -1,6 +1,12 @@
 use super::{
-    message::{self, Message, ASSIGN_IDENTIFIER, RESET_IDENTIFIER},
-    Host, IdentifierRegistry, MessageIdentifier, Peripheral, Role, Transport,
+    message::{
+        self, Message, ADVERTISE_IDENTIFIER, ASSIGN_ID, ASSIGN_IDENTIFIER,
+        CAPABILITY_ADDED_IDENTIFIER, HEARTBEAT_IDENTIFIER, REJECT_ID, REJECT_IDENTIFIER,
+        RESET_ACK_ID, RESET_ACK_IDENTIFIER, RESET_IDENTIFIER,
+    },
+    transmitter::{assign_identifiers, reset_peripheral, transmit},
+    ConnectionState, DisconnectReason, Host, IdentifierRegistry, MessageID, MessageIdentifier,
+    Peripheral, Role, Transport, TransportError,
 };
 
 /// Receiving half of the network stack
@@ -19,36 +25,172 @@ impl<'r, 't, const MTU: usize, T: Transport<MTU>, R: Role> Receiver<'r, 't, MTU,
             _role: role,
         }
     }
+
+    /// Current state of the connection, see [`ConnectionState`]
+    pub fn connection_state(&self) -> ConnectionState {
+        self.registry.connection_state()
+    }
+
+    /// Receives the next frame, marking the connection as [lost](DisconnectReason::Lost) if the transport fails
+    async fn receive(&self) -> Result<(MessageID, [u8; MTU]), TransportError> {
+        let frame = self.transport.recv().await;
+
+        if frame.is_err() {
+            self.registry
+                .set_connection_state(ConnectionState::Disconnected(DisconnectReason::Lost));
+        }
+
+        frame
+    }
 }
 
 impl<'r, 't, const MTU: usize, T: Transport<MTU>> Receiver<'r, 't, MTU, T, Host> {
     /// Receives messages over the transport — this function should be polled constantly in a loop to ensure proper operation of the sending half.
     /// It is your responsibility to make sure the loop is iterated at a sufficient interval so that no incoming messages are dropped
     /// (depending on the underlying transports behaviour; many embedded implementations simply drop messages or have only a very small buffer).
-    pub async fn recv(&self) -> (MessageIdentifier, [u8; MTU]) {
+    ///
+    /// Late messages advertised by the peripheral are assigned automatically. Once the peripheral confirms the assignment,
+    /// a [`CapabilityAdded`](super::CapabilityAdded) message is returned. Heartbeats of the peripheral may additionally
+    /// trigger a reset of the network, see [automatic resets](super#automatic-resets).
+    /// Assignments rejected by the peripheral are removed, see [`Negotiation`](super::Negotiation).
+    ///
+    /// Fails once the transport does, marking the connection as [lost](DisconnectReason::Lost).
+    pub async fn recv(&self) -> Result<(MessageIdentifier, [u8; MTU]), TransportError> {
         loop {
-            let (id, packet) = self.transport.recv().await;
+            let (id, packet) = self.receive().await?;
             if let Some(identifier) = self.registry.resolve(id) {
-                return (identifier, packet);
+                match identifier {
+                    // Only the host may reset the network, a misbehaving peripheral is ignored
+                    RESET_IDENTIFIER => {}
+                    ADVERTISE_IDENTIFIER => self.handle_advertisement(packet).await,
+                    HEARTBEAT_IDENTIFIER => self.handle_heartbeat(packet).await,
+                    REJECT_IDENTIFIER => self.handle_rejection(packet),
+                    RESET_ACK_IDENTIFIER => self.handle_reset_confirmation(packet).await,
+                    ASSIGN_IDENTIFIER => {
+                        if self.is_confirmed_assignment(packet) {
+                            return Ok((CAPABILITY_ADDED_IDENTIFIER, packet));
+                        }
+                    }
+                    _ => return Ok((identifier, packet)),
+                }
             } else {
                 // TODO print a warning that we received an invalid packet
             }
         }
     }
+
+    async fn handle_advertisement(&self, packet: [u8; MTU]) {
+        if let Ok(advertisement) = message::Advertise::<MTU>::from_packet(packet) {
+            let identifier = advertisement.identifier();
+
+            if let Some(id) = self.registry.assign_late(identifier) {
+                let assignment = message::Assign::<MTU>::new(id, identifier);
+                // A failed transport is reported by the next call to `recv`
+                transmit(
+                    self.registry,
+                    self.transport,
+                    ASSIGN_ID,
+                    assignment.to_packet(),
+                )
+                .await
+                .ok();
+            } else {
+                // TODO Print a warning that the peripheral advertised a message we do not know
+            }
+        } else {
+            // TODO Print a warning that we received an invalid advertisement
+        }
+    }
+
+    /// Resets the peripheral if its heartbeat reveals a fresh connection, i.e. we are not connected yet
+    /// or it did not receive our last reset (e.g. because it rebooted or has been replaced by another device).
+    /// A heartbeat reporting the pending reset completes the handshake instead, as the peripheral evidently received it.
+    async fn handle_heartbeat(&self, packet: [u8; MTU]) {
+        if let Ok(heartbeat) = message::Heartbeat::from_packet(packet) {
+            let current = heartbeat.epoch() == self.registry.epoch();
+
+            match self.registry.connection_state() {
+                // The confirmation got lost, or the peripheral runs an older version which does not send one
+                ConnectionState::Negotiating if current => {
+                    assign_identifiers(self.registry, self.transport).await
+                }
+                ConnectionState::Ready if current => {}
+                // Resets which got lost are repeated, ones which merely crossed the heartbeat are superseded
+                _ if self.registry.automatic_reset() => {
+                    // A failed transport is reported by the next call to `recv`
+                    reset_peripheral(self.registry, self.transport).await.ok();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Assigns the identifiers once the peripheral confirmed the pending reset, confirmations of earlier ones are ignored
+    async fn handle_reset_confirmation(&self, packet: [u8; MTU]) {
+        if let Ok(reset) = message::Reset::from_packet(packet) {
+            let pending = self.registry.connection_state() == ConnectionState::Negotiating
+                && reset.epoch() == self.registry.epoch();
+
+            if pending {
+                assign_identifiers(self.registry, self.transport).await;
+            }
+        }
+    }
+
+    /// Unassigns a message the peripheral does not support so that it is no longer sent
+    fn handle_rejection(&self, packet: [u8; MTU]) {
+        if let Ok(rejection) = message::Assign::<MTU>::from_packet(packet) {
+            self.registry.reject(rejection.id(), rejection.identifier());
+        } else {
+            // TODO Print a warning that we received an invalid rejection
+        }
+    }
+
+    /// Checks whether the peripheral echoed an assignment that matches our own
+    fn is_confirmed_assignment(&self, packet: [u8; MTU]) -> bool {
+        match message::Assign::<MTU>::from_packet(packet) {
+            Ok(assignment) => {
+                self.registry.resolve(assignment.id()) == Some(assignment.identifier())
+            }
+            Err(_) => false,
+        }
+    }
 }
 
 impl<'r, 't, const MTU: usize, T: Transport<MTU>> Receiver<'r, 't, MTU, T, Peripheral> {
     /// Receives messages over the transport — this function should be polled constantly in a loop to ensure proper operation of the sending half.
     /// It is your responsibility to make sure the loop is iterated at a sufficient interval so that no incoming messages are dropped
     /// (depending on the underlying transports behaviour; many embedded implementations simply drop messages or have only a very small buffer).
-    pub async fn recv(&self) -> (MessageIdentifier, [u8; MTU]) {
+    ///
+    /// Fails once the transport does, marking the connection as [lost](DisconnectReason::Lost).
+    pub async fn recv(&self) -> Result<(MessageIdentifier, [u8; MTU]), TransportError> {
         loop {
-            let (id, packet) = self.transport.recv().await;
+            let (id, packet) = self.receive().await?;
             if let Some(identifier) = self.registry.resolve(id) {
                 match identifier {
-                    RESET_IDENTIFIER => self.registry.clear(),
-                    ASSIGN_IDENTIFIER => self.handle_assignment(packet),
-                    _ => return (identifier, packet),
+                    RESET_IDENTIFIER => {
+                        if let Ok(reset) = message::Reset::from_packet(packet) {
+                            self.registry.set_epoch(reset.epoch());
+                        }
+
+                        self.registry.clear();
+                        self.registry
+                            .set_connection_state(ConnectionState::Negotiating);
+
+                        // Echo the reset so the host starts assigning identifiers, a failed transport is reported by the next call to `recv`
+                        transmit(self.registry, self.transport, RESET_ACK_ID, packet)
+                            .await
+                            .ok();
+                    }
+                    ASSIGN_IDENTIFIER => self.handle_assignment(packet).await,
+                    // Advertisements, heartbeats, rejections and reset confirmations are only sent by peripherals, a misbehaving host is ignored
+                    ADVERTISE_IDENTIFIER | HEARTBEAT_IDENTIFIER | REJECT_IDENTIFIER
+                    | RESET_ACK_IDENTIFIER => {}
+                    _ => {
+                        // The host only sends regular messages once it finished assigning identifiers
+                        self.registry.set_connection_state(ConnectionState::Ready);
+                        return Ok((identifier, packet));
+                    }
                 }
             } else {
                 // TODO print a warning that we received an invalid packet
@@ -56,14 +198,143 @@ impl<'r, 't, const MTU: usize, T: Transport<MTU>> Receiver<'r, 't, MTU, T, Perip
         }
     }
 
-    fn handle_assignment(&self, packet: [u8; MTU]) {
-        if let Ok(assignment) = message::Assign::from_packet(packet) {
-            self.registry
-                .assign(assignment.id(), assignment.identifier());
-            // TODO Send a message that notifies the host that this message type is not supported
-            //      so that the host may unassign it again (thus never sending messages of this type).
+    async fn handle_assignment(&self, packet: [u8; MTU]) {
+        if let Ok(assignment) = message::Assign::<MTU>::from_packet(packet) {
+            let identifier = assignment.identifier();
+            let assigned = self.registry.remember(assignment.id(), identifier);
+
+            // A failed transport is reported by the next call to `recv`
+            if !assigned {
+                // Echo the assignment so the host unassigns it again and never sends messages of this type
+                transmit(self.registry, self.transport, REJECT_ID, packet)
+                    .await
+                    .ok();
+            } else if self.registry.is_late(identifier) {
+                // Late messages have been advertised by us, so the host waits for a confirmation before using them
+                transmit(self.registry, self.transport, ASSIGN_ID, packet)
+                    .await
+                    .ok();
+            }
         } else {
             // TODO Print a warning that we received an invalid assignment
         }
     }
 }
+
+#[cfg(test)]
+mod does {
+    use super::*;
+    use crate::LoopbackTransport;
+    use core::{future::Future, sync::atomic::AtomicU8};
+
+    const MTU: usize = 42;
+
+    fn assignments() -> [(AtomicU8, MessageIdentifier<'static>); 1] {
+        [(
+            AtomicU8::new(IdentifierRegistry::<Host>::UNASSIGNED),
+            "test.ping",
+        )]
+    }
+
+    /// Lets the receiver process the frames which arrived so far, expecting none of them to be returned
+    async fn process_control_messages<F: Future>(recv: F) {
+        tokio::select! {
+            biased;
+            _ = recv => panic!("receiver returned a message"),
+            _ = tokio::task::yield_now() => {}
+        }
+    }
+
+    #[tokio::test]
+    async fn follow_reset_of_host() {
+        let (transport, host) = LoopbackTransport::<MTU>::pair();
+        let assignments = assignments();
+        let registry = IdentifierRegistry::<Peripheral>::new(&assignments, 1);
+        let receiver = Receiver::new(Peripheral, &registry, &transport);
+
+        let reset: [u8; MTU] = message::Reset::new(3).to_packet();
+        host.send(message::RESET_ID, reset).await.unwrap();
+        host.send(ASSIGN_ID, message::Assign::new(1, "test.ping").to_packet())
+            .await
+            .unwrap();
+        process_control_messages(receiver.recv()).await;
+
+        // The peripheral confirms the reset but only considers itself ready once regular messages arrive
+        assert_eq!(receiver.connection_state(), ConnectionState::Negotiating);
+        assert_eq!(registry.epoch(), 3);
+        assert_eq!(host.recv().await, Ok((RESET_ACK_ID, reset)));
+
+        host.send(1, [42; MTU]).await.unwrap();
+        assert_eq!(receiver.recv().await, Ok(("test.ping", [42; MTU])));
+        assert_eq!(receiver.connection_state(), ConnectionState::Ready);
+    }
+
+    #[tokio::test]
+    async fn assign_identifiers_once_reset_is_confirmed() {
+        let (transport, peripheral) = LoopbackTransport::<MTU>::pair();
+        let assignments = assignments();
+        let registry = IdentifierRegistry::<Host>::new(&assignments, 1);
+        let receiver = Receiver::new(Host, &registry, &transport);
+
+        reset_peripheral(&registry, &transport).await.unwrap();
+        let (_, reset) = peripheral.recv().await.unwrap();
+        let epoch = registry.epoch();
+
+        // Confirmations of earlier resets are ignored
+        let stale: [u8; MTU] = message::Reset::new(epoch.wrapping_sub(1)).to_packet();
+        peripheral.send(RESET_ACK_ID, stale).await.unwrap();
+        process_control_messages(receiver.recv()).await;
+        assert_eq!(receiver.connection_state(), ConnectionState::Negotiating);
+
+        peripheral.send(RESET_ACK_ID, reset).await.unwrap();
+        process_control_messages(receiver.recv()).await;
+        assert_eq!(receiver.connection_state(), ConnectionState::Ready);
+
+        let (id, packet) = peripheral.recv().await.unwrap();
+        assert_eq!(id, ASSIGN_ID);
+        assert_eq!(
+            message::Assign::<MTU>::from_packet(packet).map(|assignment| assignment.id()),
+            Ok(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn reset_peripheral_reporting_unknown_epoch() {
+        let (transport, peripheral) = LoopbackTransport::<MTU>::pair();
+        let assignments = assignments();
+        let registry = IdentifierRegistry::<Host>::new(&assignments, 1);
+        let receiver = Receiver::new(Host, &registry, &transport);
+
+        // Device which has never been reset, e.g. because it just got plugged in
+        let heartbeat: [u8; MTU] = message::Heartbeat::new(0).to_packet();
+        peripheral
+            .send(message::HEARTBEAT_ID, heartbeat)
+            .await
+            .unwrap();
+        process_control_messages(receiver.recv()).await;
+
+        assert_eq!(receiver.connection_state(), ConnectionState::Negotiating);
+        let (id, packet) = peripheral.recv().await.unwrap();
+        assert_eq!(id, message::RESET_ID);
+        assert_eq!(
+            message::Reset::from_packet(packet).map(|reset| reset.epoch()),
+            Ok(registry.epoch())
+        );
+    }
+
+    #[tokio::test]
+    async fn lose_connection_when_transport_fails() {
+        let (transport, peer) = LoopbackTransport::<MTU>::pair();
+        let assignments = assignments();
+        let registry = IdentifierRegistry::<Peripheral>::new(&assignments, 1);
+        let receiver = Receiver::new(Peripheral, &registry, &transport);
+
+        drop(peer);
+
+        assert_eq!(receiver.recv().await, Err(TransportError::Disconnected));
+        assert_eq!(
+            receiver.connection_state(),
+            ConnectionState::Disconnected(DisconnectReason::Lost)
+        );
+    }
+}
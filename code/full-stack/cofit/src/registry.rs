@@ -1,12 +1,18 @@
-use crate::{Host, Peripheral};
+use crate::{ConnectionState, Host, Peripheral};
 use super::{
-    message::{ASSIGN_ID, ASSIGN_IDENTIFIER, RESET_ID, RESET_IDENTIFIER},
+    message::{
+        ADVERTISE_ID, ADVERTISE_IDENTIFIER, ASSIGN_ID, ASSIGN_IDENTIFIER, HEARTBEAT_ID,
+        HEARTBEAT_IDENTIFIER, REJECT_ID, REJECT_IDENTIFIER, RESET_ACK_ID, RESET_ACK_IDENTIFIER,
+        RESET_ID, RESET_IDENTIFIER,
+    },
     MessageID, MessageIdentifier, Role,
 };
 use core::{
     marker::PhantomData,
-    sync::atomic::{AtomicU8, Ordering},
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+    task::Waker,
 };
+use futures::task::AtomicWaker;
 
 pub(crate) enum RegistryLookupResult {
     ID(MessageID),
@@ -18,6 +24,18 @@ pub(crate) enum RegistryLookupResult {
 #[doc(hidden)]
 pub struct IdentifierRegistry<'a, R: Role> {
     assignments: &'a [(AtomicU8, MessageIdentifier<'static>)],
+    /// Index of the first late message in `assignments`, these are only assigned after being advertised by the peripheral
+    late_offset: usize,
+    /// Raw representation of the current [`ConnectionState`], shared by the transmitter and receiver
+    state: AtomicU8,
+    /// Counter of the last reset, sent by the host and echoed in the heartbeats of the peripheral
+    epoch: AtomicU8,
+    /// Whether the host resets the peripheral when its heartbeats reveal a fresh connection
+    automatic_reset: AtomicBool,
+    /// Task waiting for the connection state to change, e.g. for the peripheral to confirm a reset
+    waker: AtomicWaker,
+    /// Task of the application following the connection state, kept apart so that it does not displace a pending reset
+    observer: AtomicWaker,
     role: PhantomData<R>,
 }
 
@@ -27,13 +45,29 @@ impl<'a, R: Role> IdentifierRegistry<'a, R> {
     pub const UNASSIGNED: MessageID = 0;
 
     /// List of statically allocated IDs which may not be used when assigning
-    const RESERVED: &'static [MessageID] = &[RESET_ID, ASSIGN_ID];
+    const RESERVED: &'static [MessageID] = &[
+        RESET_ID,
+        ASSIGN_ID,
+        ADVERTISE_ID,
+        HEARTBEAT_ID,
+        REJECT_ID,
+        RESET_ACK_ID,
+    ];
 
     #[doc(hidden)]
-    pub const fn new(assignments: &'a [(AtomicU8, MessageIdentifier<'static>)]) -> Self {
+    pub const fn new(
+        assignments: &'a [(AtomicU8, MessageIdentifier<'static>)],
+        late_offset: usize,
+    ) -> Self {
         Self {
             role: PhantomData,
             assignments,
+            late_offset,
+            state: AtomicU8::new(0),
+            epoch: AtomicU8::new(0),
+            automatic_reset: AtomicBool::new(true),
+            waker: AtomicWaker::new(),
+            observer: AtomicWaker::new(),
         }
     }
 
@@ -45,7 +79,26 @@ impl<'a, R: Role> IdentifierRegistry<'a, R> {
         );
     }
 
-    pub(crate) fn assign(&self, id: MessageID, identifier: MessageIdentifier) -> bool {
+    /// Fails compilation when two message types share an identifier, as they would otherwise end up in the same slot.
+    /// The error names the duplicated identifier and points at `duplicate_message_identifier`.
+    #[doc(hidden)]
+    pub const fn verify_unique_identifiers(identifiers: &[MessageIdentifier]) {
+        let mut i = 0;
+        while i < identifiers.len() {
+            let mut j = i + 1;
+            while j < identifiers.len() {
+                if identifiers_equal(identifiers[i], identifiers[j]) {
+                    duplicate_message_identifier(identifiers[i]);
+                }
+                j += 1;
+            }
+            i += 1;
+        }
+    }
+
+    /// Stores an assignment, returns whether the message is known and the ID not reserved.
+    /// Only reachable through the role specific methods, as only the host may make assignments.
+    fn store(&self, id: MessageID, identifier: MessageIdentifier) -> bool {
         if Self::RESERVED.contains(&id) {
             return false;
         }
@@ -60,12 +113,77 @@ impl<'a, R: Role> IdentifierRegistry<'a, R> {
         false
     }
 
+    pub(crate) fn connection_state(&self) -> ConnectionState {
+        ConnectionState::from_raw(self.state.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn set_connection_state(&self, state: ConnectionState) {
+        self.state.store(state.into_raw(), Ordering::Relaxed);
+        self.waker.wake();
+        self.observer.wake();
+    }
+
+    /// Wakes the given task on the next change of the connection state, replacing any previously registered one
+    pub(crate) fn register_waker(&self, waker: &Waker) {
+        self.waker.register(waker);
+    }
+
+    /// Like [`register_waker`](Self::register_waker), but for the task of the application following the connection state
+    pub(crate) fn register_observer(&self, waker: &Waker) {
+        self.observer.register(waker);
+    }
+
+    pub(crate) fn epoch(&self) -> u8 {
+        self.epoch.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_epoch(&self, epoch: u8) {
+        self.epoch.store(epoch, Ordering::Relaxed);
+    }
+
+    /// Whether the message has been registered as a late message which is only assigned after being advertised
+    pub(crate) fn is_late(&self, identifier: MessageIdentifier) -> bool {
+        self.assignments[self.late_offset..]
+            .iter()
+            .any(|(_, message_identifier)| *message_identifier == identifier)
+    }
+
+    /// Registered message identifiers together with their current assignment, or `None` if they are unassigned
+    pub(crate) fn assignments(
+        &self,
+    ) -> impl Iterator<Item = (MessageIdentifier<'static>, Option<MessageID>)> + '_ {
+        self.assignments.iter().map(|(id, identifier)| {
+            let id = id.load(Ordering::Relaxed);
+            (*identifier, (id != Self::UNASSIGNED).then_some(id))
+        })
+    }
+
+    /// Message types which currently have an ID assigned, together with it.
+    ///
+    /// On the host, these are the messages the peripheral has not rejected. On the peripheral, these are the messages which
+    /// both sides support, as the host only assigns messages it supports itself and the peripheral only remembers those it knows.
+    /// Since the assignments are transmitted upon every reset, both sides end up with the same list once the negotiation completed.
+    pub fn assigned_identifiers(
+        &self,
+    ) -> impl Iterator<Item = (MessageIdentifier<'static>, MessageID)> + '_ {
+        self.assignments()
+            .filter_map(|(identifier, id)| id.map(|id| (identifier, id)))
+    }
+
     /// Looks up a message ID from a message identifier
     pub(crate) fn lookup(&self, identifier: MessageIdentifier) -> RegistryLookupResult {
         if identifier == RESET_IDENTIFIER {
             RegistryLookupResult::ID(RESET_ID)
         } else if identifier == ASSIGN_IDENTIFIER {
             RegistryLookupResult::ID(ASSIGN_ID)
+        } else if identifier == ADVERTISE_IDENTIFIER {
+            RegistryLookupResult::ID(ADVERTISE_ID)
+        } else if identifier == HEARTBEAT_IDENTIFIER {
+            RegistryLookupResult::ID(HEARTBEAT_ID)
+        } else if identifier == REJECT_IDENTIFIER {
+            RegistryLookupResult::ID(REJECT_ID)
+        } else if identifier == RESET_ACK_IDENTIFIER {
+            RegistryLookupResult::ID(RESET_ACK_ID)
         } else {
             for (id, assigned_identifier) in self.assignments.iter() {
                 let id = id.load(Ordering::Relaxed);
@@ -88,6 +206,14 @@ impl<'a, R: Role> IdentifierRegistry<'a, R> {
             Some(RESET_IDENTIFIER)
         } else if id == ASSIGN_ID {
             Some(ASSIGN_IDENTIFIER)
+        } else if id == ADVERTISE_ID {
+            Some(ADVERTISE_IDENTIFIER)
+        } else if id == HEARTBEAT_ID {
+            Some(HEARTBEAT_IDENTIFIER)
+        } else if id == REJECT_ID {
+            Some(REJECT_IDENTIFIER)
+        } else if id == RESET_ACK_ID {
+            Some(RESET_ACK_IDENTIFIER)
         } else {
             for (assigned_id, identifier) in self.assignments.iter() {
                 if assigned_id.load(Ordering::Relaxed) == id {
@@ -101,6 +227,11 @@ impl<'a, R: Role> IdentifierRegistry<'a, R> {
 }
 
 impl<'a> IdentifierRegistry<'a, Peripheral> {
+    /// Remembers an assignment received from the host, returns whether the message is supported locally
+    pub(crate) fn remember(&self, id: MessageID, identifier: MessageIdentifier) -> bool {
+        self.store(id, identifier)
+    }
+
     /// Removes all previous assignments
     pub(crate) fn clear(&self) {
         for (id, _) in self.assignments.iter() {
@@ -110,12 +241,31 @@ impl<'a> IdentifierRegistry<'a, Peripheral> {
 }
 
 impl<'a> IdentifierRegistry<'a, Host> {
-    /// Statically and locally assigns IDs to each message type.
+    /// Advances the epoch for a new reset, skipping zero which peripherals report before their first reset
+    pub(crate) fn next_epoch(&self) -> u8 {
+        let epoch = self.epoch() % u8::MAX + 1;
+        self.set_epoch(epoch);
+        epoch
+    }
+
+    pub(crate) fn automatic_reset(&self) -> bool {
+        self.automatic_reset.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_automatic_reset(&self, enabled: bool) {
+        self.automatic_reset.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Statically and locally assigns IDs to each message type except late ones, which are unassigned instead.
     pub(crate) fn assign_all(
         &self,
     ) -> impl Iterator<Item = (MessageIdentifier<'static>, MessageID)> + '_ {
-        for (new_id, (_, identifier)) in self.assignments.iter().enumerate() {
-            self.assign(new_id as u8 + 1, identifier);
+        for (new_id, (_, identifier)) in self.assignments[..self.late_offset].iter().enumerate() {
+            self.store(new_id as u8 + 1, identifier);
+        }
+
+        for (id, _) in self.assignments[self.late_offset..].iter() {
+            id.store(Self::UNASSIGNED, Ordering::Relaxed);
         }
 
         self.assignments.iter().filter_map(|(id, identifier)| {
@@ -128,4 +278,96 @@ impl<'a> IdentifierRegistry<'a, Host> {
             }
         })
     }
+
+    /// Removes an assignment which the peripheral rejected, returns whether it matched our own assignment.
+    /// Mismatches are ignored as they may stem from a previous reset.
+    pub(crate) fn reject(&self, id: MessageID, identifier: MessageIdentifier) -> bool {
+        for (assigned_id, message_identifier) in self.assignments.iter() {
+            if *message_identifier == identifier {
+                return assigned_id
+                    .compare_exchange(id, Self::UNASSIGNED, Ordering::Relaxed, Ordering::Relaxed)
+                    .is_ok();
+            }
+        }
+
+        false
+    }
+
+    /// Assigns an ID to a late message advertised by the peripheral, returns `None` if the message is not a known late message.
+    /// IDs are derived from the position in the registry so repeated advertisements yield the same assignment.
+    pub(crate) fn assign_late(&self, identifier: MessageIdentifier) -> Option<MessageID> {
+        let index = self.late_offset
+            + self.assignments[self.late_offset..]
+                .iter()
+                .position(|(_, message_identifier)| *message_identifier == identifier)?;
+
+        let id = index as MessageID + 1;
+        self.store(id, identifier);
+
+        Some(id)
+    }
+}
+
+const fn identifiers_equal(a: MessageIdentifier, b: MessageIdentifier) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+
+    true
+}
+
+/// Only panics with the identifier as const panics can not format any further, the function name provides the context in the compiler error
+const fn duplicate_message_identifier(identifier: MessageIdentifier) {
+    panic!("{}", identifier);
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+
+    // Evaluated at compile time just like in `make_network!`
+    const _: () = IdentifierRegistry::<Host>::verify_unique_identifiers(&[
+        "flash.write",
+        "flash.read",
+        "flash",
+    ]);
+
+    #[test]
+    fn accept_distinct_identifiers() {
+        IdentifierRegistry::<Host>::verify_unique_identifiers(&[]);
+        IdentifierRegistry::<Peripheral>::verify_unique_identifiers(&[
+            "flash.write",
+            "flash.writes",
+            "flash.read",
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "flash.write")]
+    fn reject_duplicate_identifiers() {
+        IdentifierRegistry::<Host>::verify_unique_identifiers(&[
+            "flash.write",
+            "flash.read",
+            "flash.write",
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "bluetooth.enable")]
+    fn reject_adjacent_duplicates() {
+        IdentifierRegistry::<Peripheral>::verify_unique_identifiers(&[
+            "bluetooth.enable",
+            "bluetooth.enable",
+        ]);
+    }
 }
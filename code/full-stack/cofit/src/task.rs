@@ -42,6 +42,9 @@ pub trait Handler<const MTU: usize> {
 /// Note that you do need to make sure that the messages your handlers can process are registered
 /// when you create the network. If you don't, the handlers will never be called!
 ///
+/// The task completes once the transport fails, resolving to its [`TransportError`](super::TransportError).
+/// See [`Receiver::recv`](super::Receiver::recv) for how this affects the connection state.
+///
 /// # Example
 ///
 /// ```ignore
@@ -65,7 +68,10 @@ macro_rules! make_receiver_task {
 
             async {
                 loop {
-                    let (identifier, packet) = $receiver.recv().await;
+                    let (identifier, packet) = match $receiver.recv().await {
+                        Ok(frame) => frame,
+                        Err(error) => break error,
+                    };
 
                     $(
                     if let Ok(handle_fut) = $handler.handle_raw(identifier, &packet) {
@@ -81,6 +87,8 @@ macro_rules! make_receiver_task {
 }
 
 /// Variant of [`make_receiver_task`](self::make_receiver_task) that takes ownership its parameters
+///
+/// Like the borrowing variant, the task resolves to the [`TransportError`](super::TransportError) which ended it.
 #[macro_export]
 macro_rules! make_owned_receiver_task {
     ($receiver:expr, [$($handler:expr),+ $(,)?]) => {
@@ -91,7 +99,10 @@ macro_rules! make_owned_receiver_task {
 
             async move {
                 loop {
-                    let (identifier, packet) = $receiver.recv().await;
+                    let (identifier, packet) = match $receiver.recv().await {
+                        Ok(frame) => frame,
+                        Err(error) => break error,
+                    };
 
                     $(
                     if let Ok(handle_fut) = $handler.handle_raw(identifier, &packet) {
@@ -1,12 +1,77 @@
 use super::{
-    message, Host, IdentifierRegistry, Message, MessageID, MessageIdentifier, RegistryLookupResult,
-    Role, Transport,
+    adaptive::PayloadController,
+    fragment::{
+        adaptive_capacity, fragment_header, ADAPTIVE_HEADER_LENGTH, FRAGMENT_HEADER_LENGTH,
+        MAX_FRAGMENTS,
+    },
+    message::{self, ASSIGN_ID, RESET_ID},
+    rate_limit::RateLimiter,
+    AdaptivePayload, Clock, ConnectionState, DisconnectReason, FragmentedMessage, Host,
+    IdentifierRegistry, Message, MessageID, MessageIdentifier, Negotiation, NoClock, Peripheral,
+    RateLimit, RegistryLookupResult, Role, Transport, TransportError,
 };
+use core::{future::Future, task::Poll};
+use futures::pin_mut;
+
+/// Reason why a message could not be [sent](Transmitter::send)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransmitError {
+    /// The other side did not assign an ID to the message type, either because it does not support it or because no assignment has happened yet.
+    /// See [`is_supported`](Transmitter::is_supported)
+    Unsupported,
+    /// The message consists of more than [`MAX_FRAGMENTS`] fragments, nothing has been sent
+    TooLarge,
+    /// The connection has been reset since the [`Responder`] has been created, the response has been dropped
+    Stale,
+    /// The [`RateLimit`](super::RateLimit) is exhausted and its [policy](super::RateLimitPolicy::Drop) discarded the message
+    RateLimited,
+    /// The [`Transport`](super::Transport) failed, the connection is [lost](super::DisconnectReason::Lost) from then on
+    Transport(TransportError),
+}
+
+/// Reason why the peripheral could not be [reset](Transmitter::reset_peripheral)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetError {
+    /// The peripheral did not confirm the reset before the timeout completed, e.g. because it is not connected
+    TimedOut,
+    /// The connection has been [closed](Transmitter::close) while waiting for the peripheral
+    Interrupted,
+    /// The [`Transport`](super::Transport) failed, the connection is [lost](super::DisconnectReason::Lost) from then on
+    Transport(TransportError),
+}
+
+/// Replies to the message a [`Handler`](super::Handler) is processing, see [`Transmitter::responder`]
+pub struct Responder<'a, 'r, 't, const MTU: usize, T: Transport<MTU>, R: Role, C: Clock = NoClock> {
+    tx: &'a Transmitter<'r, 't, MTU, T, R, C>,
+    /// Epoch of the connection the message arrived on
+    epoch: u8,
+}
+
+impl<'a, 'r, 't, const MTU: usize, T: Transport<MTU>, R: Role, C: Clock>
+    Responder<'a, 'r, 't, MTU, T, R, C>
+{
+    /// Sends a response to the message, see [`Transmitter::send`] for when this fails or panics.
+    /// Fails with [`TransmitError::Stale`] without sending anything if the connection has been reset since the message arrived,
+    /// as the other side no longer waits for the response and may have assigned its ID to another message type.
+    pub async fn respond<M: Message<MTU>>(&self, response: M) -> Result<(), TransmitError> {
+        if self.tx.registry.epoch() != self.epoch {
+            return Err(TransmitError::Stale);
+        }
+
+        self.tx.send(response).await
+    }
+}
 
 /// Transmitting half of the network stack
-pub struct Transmitter<'r, 't, const MTU: usize, T: Transport<MTU>, R: Role> {
+///
+/// The [`Clock`] is only used by the [rate limit](Self::with_rate_limit), transmitters without one never tell the time.
+pub struct Transmitter<'r, 't, const MTU: usize, T: Transport<MTU>, R: Role, C: Clock = NoClock> {
     registry: &'r IdentifierRegistry<'r, R>,
     transport: &'t T,
+    /// Payload per frame of fragmented messages, `None` if adaptive payload sizing is disabled
+    payload: Option<PayloadController>,
+    /// Budget of outgoing messages, `None` if sending is unlimited
+    rate_limiter: Option<RateLimiter<C>>,
     _role: R,
 }
 
@@ -16,41 +81,655 @@ impl<'r, 't, const MTU: usize, T: Transport<MTU>, R: Role> Transmitter<'r, 't, M
         Self {
             registry,
             transport,
+            payload: None,
+            rate_limiter: None,
             _role: role,
         }
     }
 
+    /// Limits the rate at which messages are [sent](Self::send), protecting slower links from being flooded by high-rate events.
+    /// Sending is unlimited by default.
+    ///
+    /// [Fragmented messages](Self::send_fragmented) take a single token regardless of their number of frames.
+    /// Control messages like [heartbeats](Self::heartbeat) and the handshake are exempt, so they can not be starved.
+    /// Since this crate has no notion of time, the caller provides the [`Clock`] the token bucket refills by.
+    pub fn with_rate_limit<C: Clock>(
+        self,
+        limit: RateLimit,
+        clock: C,
+    ) -> Transmitter<'r, 't, MTU, T, R, C> {
+        Transmitter {
+            registry: self.registry,
+            transport: self.transport,
+            payload: self.payload,
+            rate_limiter: Some(RateLimiter::new(limit, clock)),
+            _role: self._role,
+        }
+    }
+}
+
+impl<'r, 't, const MTU: usize, T: Transport<MTU>, R: Role, C: Clock>
+    Transmitter<'r, 't, MTU, T, R, C>
+{
+    /// Enables [adaptive payload sizing](AdaptivePayload) for [fragmented messages](Self::send_fragmented), it is disabled by default.
+    ///
+    /// Each frame then carries a length byte after the fragment counter, so the [`Reassembler`](super::Reassembler)
+    /// on the other side has to [expect](super::Reassembler::with_adaptive_payload) this as well.
+    /// Regular messages are always sent in a single frame and thus unaffected.
+    pub fn with_adaptive_payload(mut self, config: AdaptivePayload) -> Self {
+        let capacity = adaptive_capacity(MTU);
+        assert!(
+            capacity > 0,
+            "frames are too small for adaptive payload sizing"
+        );
+
+        self.payload = Some(PayloadController::new(config, capacity));
+        self
+    }
+
+    /// Reports that the other side answered a request in time, see [`AdaptivePayload`]. Does nothing unless adaptive payload sizing is enabled.
+    pub fn record_ack(&self) {
+        if let Some(payload) = &self.payload {
+            payload.record_ack();
+        }
+    }
+
+    /// Reports that the other side did not answer a request in time, see [`AdaptivePayload`]. Does nothing unless adaptive payload sizing is enabled.
+    pub fn record_ack_timeout(&self) {
+        if let Some(payload) = &self.payload {
+            payload.record_timeout();
+        }
+    }
+
+    /// Number of payload bytes currently packed into each frame of fragmented messages, `None` unless adaptive payload sizing is enabled
+    pub fn payload_per_frame(&self) -> Option<usize> {
+        self.payload.as_ref().map(PayloadController::payload)
+    }
+
+    /// Number of outgoing frames buffered by the transport, see [`Transport::queued_frames`].
+    /// Bulk senders like [`send_fragmented`](Self::send_fragmented) can use this to throttle themselves before the buffer fills up.
+    pub fn queued_frames(&self) -> usize {
+        self.transport.queued_frames()
+    }
+
+    /// Number of messages discarded because the [`RateLimit`] has been exhausted, see [`with_rate_limit`](Self::with_rate_limit)
+    pub fn dropped_messages(&self) -> u32 {
+        self.rate_limiter
+            .as_ref()
+            .map(RateLimiter::dropped_count)
+            .unwrap_or(0)
+    }
+
+    /// Current state of the connection, see [`ConnectionState`]
+    pub fn connection_state(&self) -> ConnectionState {
+        self.registry.connection_state()
+    }
+
+    /// Waits until the connection leaves the given state and returns the new one.
+    ///
+    /// Intended for following the connection state, e.g. to notify the application about [automatic resets](super#automatic-resets)
+    /// performed by the [`Receiver`](super::Receiver). Only one task may wait at a time. States which are left again before
+    /// the task gets polled are skipped, the returned state is always the current one.
+    pub async fn wait_for_state_change(&self, state: ConnectionState) -> ConnectionState {
+        core::future::poll_fn(|cx| {
+            // Registering before checking the state ensures that no change goes unnoticed
+            self.registry.register_observer(cx.waker());
+
+            match self.registry.connection_state() {
+                current if current == state => Poll::Pending,
+                current => Poll::Ready(current),
+            }
+        })
+        .await
+    }
+
+    /// Marks the connection as closed, e.g. because the application is shutting down.
+    /// Failures of the transport mark the connection as [lost](DisconnectReason::Lost) on their own.
+    /// The host has to [reset the peripheral](Transmitter::reset_peripheral) to establish it again,
+    /// which happens on the next heartbeat of the peripheral unless [automatic resets](super#automatic-resets) are disabled.
+    pub fn close(&self) {
+        self.registry
+            .set_connection_state(ConnectionState::Disconnected(DisconnectReason::Closed));
+    }
+
+    /// Creates a [`Responder`] for the message a [`Handler`](super::Handler) is about to process.
+    /// Handlers should create it right away, as it only sends responses belonging to the connection that is current at this point.
+    pub fn responder(&self) -> Responder<'_, 'r, 't, MTU, T, R, C> {
+        Responder {
+            tx: self,
+            epoch: self.registry.epoch(),
+        }
+    }
+
     /// Attempts to transmit the provided message on the underlying [`Transport`](super::Transport).
     ///
     /// Panics when the message type has not been previously registered while creating the network.
-    /// Additionally, the message is dropped if no numeric identifier has been assigned yet.
-    pub async fn send<M: Message<MTU>>(&self, message: M) {
-        match self.registry.lookup(M::IDENTIFIER) {
-            RegistryLookupResult::ID(id) => self.transport.send(id, message.to_packet()).await,
-            RegistryLookupResult::Unassigned => {}
+    /// Fails with [`TransmitError::Unsupported`] without sending anything if no numeric identifier has been assigned yet,
+    /// the peripheral rejected it (see [`Negotiation`](super::Negotiation)), or the host does not support it.
+    /// With a [rate limit](Self::with_rate_limit), it may wait for the budget or fail with [`TransmitError::RateLimited`].
+    pub async fn send<M: Message<MTU>>(&self, message: M) -> Result<(), TransmitError> {
+        self.acquire_send_budget().await?;
+        self.send_unlimited(message).await
+    }
+
+    /// Sends control messages which bypass the rate limit
+    async fn send_unlimited<M: Message<MTU>>(&self, message: M) -> Result<(), TransmitError> {
+        let id = self.id(M::IDENTIFIER)?;
+
+        transmit(self.registry, self.transport, id, message.to_packet())
+            .await
+            .map_err(TransmitError::Transport)
+    }
+
+    /// Transmits the fragments of the provided message one after another, see [`send`](Self::send) for when this fails or panics.
+    ///
+    /// The fragment counter is written to the first byte of each fragment, overwriting whatever the message put there.
+    /// Other tasks sending on the same transport may interleave their messages, which the [`Reassembler`](super::Reassembler)
+    /// tolerates up to a configurable limit.
+    ///
+    /// With [adaptive payload sizing](Self::with_adaptive_payload), the payload of the fragments is spread across as many frames as
+    /// the current [payload per frame](Self::payload_per_frame) requires. Messages which would need more than [`MAX_FRAGMENTS`]
+    /// frames at that size are packed more densely instead, so they only fail if they do not fit into full frames either.
+    pub async fn send_fragmented<M: FragmentedMessage<MTU>>(
+        &self,
+        message: M,
+    ) -> Result<(), TransmitError> {
+        let id = self.id(M::IDENTIFIER)?;
+        self.acquire_send_budget().await?;
+        let fragments = message.to_fragments();
+
+        if let Some(payload) = &self.payload {
+            return self.send_repacked(id, fragments, payload.payload()).await;
+        }
+
+        let count = fragments.len();
+
+        // Checked upfront so the receiver never sees the leading fragments of a message which can not be completed
+        if count > MAX_FRAGMENTS {
+            return Err(TransmitError::TooLarge);
+        }
+
+        for (index, mut fragment) in fragments.enumerate() {
+            fragment[0] =
+                fragment_header(index, index + 1 == count).ok_or(TransmitError::TooLarge)?;
+            transmit(self.registry, self.transport, id, fragment)
+                .await
+                .map_err(TransmitError::Transport)?;
+        }
+
+        Ok(())
+    }
+
+    /// Spreads the payload of the fragments across frames carrying at most `payload` bytes each, preceded by their length
+    async fn send_repacked(
+        &self,
+        id: MessageID,
+        fragments: impl ExactSizeIterator<Item = [u8; MTU]>,
+        payload: usize,
+    ) -> Result<(), TransmitError> {
+        let total = fragments.len() * (MTU - FRAGMENT_HEADER_LENGTH);
+        let payload = payload.max(total.div_ceil(MAX_FRAGMENTS));
+
+        if payload > adaptive_capacity(MTU) {
+            return Err(TransmitError::TooLarge);
+        }
+
+        let count = total.div_ceil(payload);
+        let mut bytes =
+            fragments.flat_map(|fragment| fragment.into_iter().skip(FRAGMENT_HEADER_LENGTH));
+
+        for index in 0..count {
+            let length = payload.min(total - index * payload);
+            let mut frame = [0; MTU];
+
+            frame[0] = fragment_header(index, index + 1 == count).ok_or(TransmitError::TooLarge)?;
+            frame[FRAGMENT_HEADER_LENGTH] = length as u8;
+            for (slot, byte) in frame[ADAPTIVE_HEADER_LENGTH..ADAPTIVE_HEADER_LENGTH + length]
+                .iter_mut()
+                .zip(&mut bytes)
+            {
+                *slot = byte;
+            }
+
+            transmit(self.registry, self.transport, id, frame)
+                .await
+                .map_err(TransmitError::Transport)?;
+        }
+
+        Ok(())
+    }
+
+    /// Waits for the rate limiter to allow sending another message, if one is configured
+    async fn acquire_send_budget(&self) -> Result<(), TransmitError> {
+        match &self.rate_limiter {
+            Some(limiter) if !limiter.acquire().await => Err(TransmitError::RateLimited),
+            _ => Ok(()),
+        }
+    }
+
+    fn id(&self, identifier: MessageIdentifier) -> Result<MessageID, TransmitError> {
+        match self.registry.lookup(identifier) {
+            RegistryLookupResult::ID(id) => Ok(id),
+            RegistryLookupResult::Unassigned => Err(TransmitError::Unsupported),
             RegistryLookupResult::Unknown => {
                 panic!("attempted to send message of type which is not in the registry")
             }
         }
     }
-}
 
-impl<'r, 't, const MTU: usize, T: Transport<MTU>> Transmitter<'r, 't, MTU, T, Host> {
-    /// Performs a reset of the remote devices' network stack to establish communication. This should be called whenever you connect or reconnect to a peripheral!
-    // TODO Potentially make this a 2-way handshake so we can be sure that the other side received it and is answering as expected
-    pub async fn reset_peripheral(&self) {
-        self.send(message::Reset).await;
+    /// Message types which can currently be [sent](Self::send) together with their ID, see [`IdentifierRegistry::assigned_identifiers`]
+    pub fn assigned_identifiers(
+        &self,
+    ) -> impl Iterator<Item = (MessageIdentifier<'static>, MessageID)> + 'r {
+        self.registry.assigned_identifiers()
+    }
 
-        let assignments = self.registry.assign_all();
-        self.transmit_assignments(assignments).await;
+    /// Whether messages of the given type can currently be [sent](Self::send), i.e. an ID has been assigned to them.
+    /// Returns `false` for message types which have not been registered while creating the network.
+    ///
+    /// On the peripheral, this reflects the capabilities of the host as it only assigns the messages it supports.
+    /// Note that the answer may change with every reset of the connection.
+    pub fn is_supported<M: Message<MTU>>(&self) -> bool {
+        matches!(
+            self.registry.lookup(M::IDENTIFIER),
+            RegistryLookupResult::ID(_)
+        )
     }
+}
 
-    async fn transmit_assignments(
+impl<'r, 't, const MTU: usize, T: Transport<MTU>, C: Clock> Transmitter<'r, 't, MTU, T, Host, C> {
+    /// Performs a reset of the remote devices' network stack to establish communication.
+    ///
+    /// The [`Receiver`](super::Receiver) does this automatically whenever the heartbeats of the peripheral reveal a fresh connection,
+    /// so calling it is only required if [automatic resets](super#automatic-resets) have been disabled.
+    ///
+    /// The peripheral confirms the reset once it cleared its assignments, upon which the receiver transmits all assignments
+    /// and the connection becomes [ready](ConnectionState::Ready). Thus, the receiver has to be polled while waiting.
+    /// Since this crate has no notion of time, the caller provides the `timeout` future (e.g. `tokio::time::sleep`).
+    /// If it completes first, the connection returns to [connecting](ConnectionState::Connecting) and the reset has to be repeated.
+    ///
+    /// The returned [`Negotiation`] reports which messages the peripheral supports once the receiver processed its responses.
+    pub async fn reset_peripheral(
         &self,
-        assignments: impl Iterator<Item = (MessageIdentifier<'static>, MessageID)>,
-    ) {
-        for (identifier, id) in assignments {
-            self.send(message::Assign::new(id, identifier)).await;
+        timeout: impl Future,
+    ) -> Result<Negotiation<'r>, ResetError> {
+        reset_peripheral(self.registry, self.transport)
+            .await
+            .map_err(ResetError::Transport)?;
+
+        pin_mut!(timeout);
+
+        let state = core::future::poll_fn(|cx| {
+            // Registering before checking the state ensures that no change goes unnoticed
+            self.registry.register_waker(cx.waker());
+
+            match self.registry.connection_state() {
+                ConnectionState::Negotiating => timeout.as_mut().poll(cx).map(|_| None),
+                state => Poll::Ready(Some(state)),
+            }
+        })
+        .await;
+
+        match state {
+            Some(ConnectionState::Ready) => Ok(self.negotiation()),
+            Some(ConnectionState::Disconnected(DisconnectReason::Lost)) => {
+                Err(ResetError::Transport(TransportError::Disconnected))
+            }
+            Some(_) => Err(ResetError::Interrupted),
+            None => {
+                // Late confirmations are ignored from now on and the next heartbeat triggers another reset
+                self.registry
+                    .set_connection_state(ConnectionState::Connecting);
+                Err(ResetError::TimedOut)
+            }
         }
     }
+
+    /// Whether a reset has been sent to the peripheral which it did not confirm yet.
+    /// Resets are the only frames the other side acknowledges, regular messages count as delivered once the transport accepted them.
+    pub fn is_awaiting_ack(&self) -> bool {
+        self.registry.connection_state() == ConnectionState::Negotiating
+    }
+
+    /// Messages which the peripheral supports according to the last reset, e.g. to tell users about missing features
+    pub fn negotiation(&self) -> Negotiation<'r> {
+        Negotiation::new(self.registry)
+    }
+
+    /// Enables or disables [automatic resets](super#automatic-resets), they are enabled by default.
+    /// When disabled, you have to call [`reset_peripheral`](Self::reset_peripheral) whenever you connect or reconnect to a peripheral!
+    pub fn set_automatic_reset(&self, enabled: bool) {
+        self.registry.set_automatic_reset(enabled);
+    }
+}
+
+impl<'r, 't, const MTU: usize, T: Transport<MTU>, C: Clock>
+    Transmitter<'r, 't, MTU, T, Peripheral, C>
+{
+    /// Signals the host that the peripheral is connected, reporting the last reset it received.
+    ///
+    /// Should be called at a regular interval, e.g. every second, so the host notices fresh connections
+    /// and resets the network on its own, see [automatic resets](super#automatic-resets).
+    pub async fn heartbeat(&self) {
+        // Control messages have reserved IDs and are thus always supported
+        self.send_unlimited(message::Heartbeat::new(self.registry.epoch()))
+            .await
+            .ok();
+    }
+
+    /// Announces a late message to the host, e.g. because an add-on board providing it has been connected.
+    ///
+    /// The host assigns an ID and the [`Receiver`](super::Receiver) confirms it, after which the message can be sent like any other.
+    /// Since resets clear all assignments, late messages have to be advertised again after the host reconnects.
+    ///
+    /// Panics when the message type has not been registered as a late message while creating the network.
+    pub async fn advertise<M: Message<MTU>>(&self) {
+        if !self.registry.is_late(M::IDENTIFIER) {
+            panic!("attempted to advertise message which is not registered as a late message");
+        }
+
+        self.send_unlimited(message::Advertise::new(M::IDENTIFIER))
+            .await
+            .ok();
+    }
+}
+
+/// Resets the peripheral, shared by the [`Transmitter`] and the automatic resets of the [`Receiver`](super::Receiver).
+/// The receiver [assigns the identifiers](assign_identifiers) once the peripheral confirmed the reset.
+pub(crate) async fn reset_peripheral<const MTU: usize, T: Transport<MTU>>(
+    registry: &IdentifierRegistry<'_, Host>,
+    transport: &T,
+) -> Result<(), TransportError> {
+    registry.set_connection_state(ConnectionState::Negotiating);
+
+    let reset = message::Reset::new(registry.next_epoch());
+    transmit(registry, transport, RESET_ID, reset.to_packet()).await
+}
+
+/// Assigns all identifiers after the peripheral confirmed a reset, completing the handshake
+pub(crate) async fn assign_identifiers<const MTU: usize, T: Transport<MTU>>(
+    registry: &IdentifierRegistry<'_, Host>,
+    transport: &T,
+) {
+    let assignments = registry.assign_all();
+    if transmit_assignments(registry, transport, assignments)
+        .await
+        .is_ok()
+    {
+        registry.set_connection_state(ConnectionState::Ready);
+    }
+}
+
+async fn transmit_assignments<const MTU: usize, T: Transport<MTU>>(
+    registry: &IdentifierRegistry<'_, Host>,
+    transport: &T,
+    assignments: impl Iterator<Item = (MessageIdentifier<'static>, MessageID)>,
+) -> Result<(), TransportError> {
+    for (identifier, id) in assignments {
+        let assignment = message::Assign::<MTU>::new(id, identifier);
+        transmit(registry, transport, ASSIGN_ID, assignment.to_packet()).await?;
+    }
+
+    Ok(())
+}
+
+/// Sends a packet, marking the connection as [lost](DisconnectReason::Lost) if the transport fails
+pub(crate) async fn transmit<const MTU: usize, T: Transport<MTU>, R: Role>(
+    registry: &IdentifierRegistry<'_, R>,
+    transport: &T,
+    id: MessageID,
+    packet: [u8; MTU],
+) -> Result<(), TransportError> {
+    let result = transport.send(id, packet).await;
+
+    if result.is_err() {
+        registry.set_connection_state(ConnectionState::Disconnected(DisconnectReason::Lost));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+    use crate::{LoopbackTransport, RateLimitPolicy};
+    use core::{sync::atomic::AtomicU8, time::Duration};
+
+    const MTU: usize = 42;
+
+    fn assignments() -> [(AtomicU8, MessageIdentifier<'static>); 1] {
+        [(
+            AtomicU8::new(IdentifierRegistry::<Host>::UNASSIGNED),
+            "test.ping",
+        )]
+    }
+
+    #[tokio::test]
+    async fn negotiate_until_identifiers_are_assigned() {
+        let (transport, peer) = LoopbackTransport::<MTU>::pair();
+        let assignments = assignments();
+        let registry = IdentifierRegistry::<Host>::new(&assignments, 1);
+
+        reset_peripheral(&registry, &transport).await.unwrap();
+        assert_eq!(registry.connection_state(), ConnectionState::Negotiating);
+
+        let (id, packet) = peer.recv().await.unwrap();
+        assert_eq!(id, RESET_ID);
+        assert_eq!(
+            message::Reset::from_packet(packet).map(|reset| reset.epoch()),
+            Ok(registry.epoch())
+        );
+
+        assign_identifiers(&registry, &transport).await;
+        assert_eq!(registry.connection_state(), ConnectionState::Ready);
+
+        let (id, packet) = peer.recv().await.unwrap();
+        let assignment = message::Assign::<MTU>::from_packet(packet).unwrap();
+        assert_eq!(id, ASSIGN_ID);
+        assert_eq!(assignment.identifier(), "test.ping");
+        assert_eq!(registry.resolve(assignment.id()), Some("test.ping"));
+    }
+
+    #[tokio::test]
+    async fn report_unconfirmed_resets() {
+        let (transport, _peer) = LoopbackTransport::<MTU>::pair();
+        let assignments = assignments();
+        let registry = IdentifierRegistry::<Host>::new(&assignments, 1);
+        let transmitter = Transmitter::new(Host, &registry, &transport);
+
+        assert!(!transmitter.is_awaiting_ack());
+
+        reset_peripheral(&registry, &transport).await.unwrap();
+        assert!(transmitter.is_awaiting_ack());
+
+        assign_identifiers(&registry, &transport).await;
+        assert!(!transmitter.is_awaiting_ack());
+        // The loopback transport hands frames over immediately instead of buffering them
+        assert_eq!(transmitter.queued_frames(), 0);
+    }
+
+    #[tokio::test]
+    async fn lose_connection_when_transport_fails() {
+        let (transport, peer) = LoopbackTransport::<MTU>::pair();
+        let assignments = assignments();
+        let registry = IdentifierRegistry::<Host>::new(&assignments, 1);
+
+        drop(peer);
+
+        assert_eq!(
+            reset_peripheral(&registry, &transport).await,
+            Err(TransportError::Disconnected)
+        );
+        assert_eq!(
+            registry.connection_state(),
+            ConnectionState::Disconnected(DisconnectReason::Lost)
+        );
+
+        // Assignments which could not be sent leave the connection lost instead of ready
+        assign_identifiers(&registry, &transport).await;
+        assert_eq!(
+            registry.connection_state(),
+            ConnectionState::Disconnected(DisconnectReason::Lost)
+        );
+    }
+
+    #[tokio::test]
+    async fn interrupt_reset_when_closed() {
+        let (transport, _peer) = LoopbackTransport::<MTU>::pair();
+        let assignments = assignments();
+        let registry = IdentifierRegistry::<Host>::new(&assignments, 1);
+        let transmitter = Transmitter::new(Host, &registry, &transport);
+
+        // The reset waits for the confirmation once it sent the request, which gives the second branch a chance to close
+        let (result, _) = tokio::join!(
+            transmitter.reset_peripheral(futures::future::pending::<()>()),
+            async { transmitter.close() }
+        );
+
+        assert!(matches!(result, Err(ResetError::Interrupted)));
+        assert_eq!(
+            transmitter.connection_state(),
+            ConnectionState::Disconnected(DisconnectReason::Closed)
+        );
+    }
+
+    #[tokio::test]
+    async fn notify_about_state_changes_while_resetting() {
+        let (transport, _peer) = LoopbackTransport::<MTU>::pair();
+        let assignments = assignments();
+        let registry = IdentifierRegistry::<Host>::new(&assignments, 1);
+        let transmitter = Transmitter::new(Host, &registry, &transport);
+
+        // Following the state must not keep the reset from noticing that it has been interrupted
+        let (result, state) = tokio::join!(
+            transmitter.reset_peripheral(futures::future::pending::<()>()),
+            async {
+                let state = transmitter
+                    .wait_for_state_change(ConnectionState::Connecting)
+                    .await;
+                transmitter.close();
+                state
+            }
+        );
+
+        assert_eq!(state, ConnectionState::Negotiating);
+        assert!(matches!(result, Err(ResetError::Interrupted)));
+        assert_eq!(
+            transmitter
+                .wait_for_state_change(ConnectionState::Negotiating)
+                .await,
+            ConnectionState::Disconnected(DisconnectReason::Closed)
+        );
+    }
+
+    #[tokio::test]
+    async fn drop_responses_after_reset() {
+        let (transport, peer) = LoopbackTransport::<MTU>::pair();
+        let assignments = assignments();
+        let registry = IdentifierRegistry::<Peripheral>::new(&assignments, 1);
+        let transmitter = Transmitter::new(Peripheral, &registry, &transport);
+
+        let responder = transmitter.responder();
+        assert_eq!(responder.respond(message::Heartbeat::new(0)).await, Ok(()));
+        assert_eq!(peer.recv().await.unwrap().0, message::HEARTBEAT_ID);
+
+        registry.set_epoch(1);
+        assert_eq!(
+            responder.respond(message::Heartbeat::new(0)).await,
+            Err(TransmitError::Stale)
+        );
+        assert_eq!(
+            transmitter
+                .responder()
+                .respond(message::Heartbeat::new(0))
+                .await,
+            Ok(())
+        );
+    }
+
+    /// Clock following the tokio runtime, starting at the moment it has been created
+    struct TokioClock(tokio::time::Instant);
+
+    impl Clock for TokioClock {
+        type SleepFut<'c> = tokio::time::Sleep
+        where
+            Self: 'c;
+
+        fn now(&self) -> Duration {
+            self.0.elapsed()
+        }
+
+        fn sleep_until<'c>(&'c self, deadline: Duration) -> Self::SleepFut<'c> {
+            tokio::time::sleep_until(self.0 + deadline)
+        }
+    }
+
+    #[tokio::test]
+    async fn pace_rate_limited_sends() {
+        let (transport, _peer) = LoopbackTransport::<MTU>::pair();
+        let assignments = assignments();
+        let registry = IdentifierRegistry::<Peripheral>::new(&assignments, 1);
+        let limit = RateLimit {
+            messages_per_second: 20,
+            burst: 1,
+            policy: RateLimitPolicy::Block,
+        };
+        let transmitter = Transmitter::new(Peripheral, &registry, &transport)
+            .with_rate_limit(limit, TokioClock(tokio::time::Instant::now()));
+
+        let start = tokio::time::Instant::now();
+        for _ in 0..4 {
+            assert_eq!(transmitter.send(message::Heartbeat::new(0)).await, Ok(()));
+        }
+
+        // The first message uses the burst, every following one has to wait for the 50ms refill interval
+        assert!(start.elapsed() >= Duration::from_millis(150));
+        assert_eq!(transmitter.dropped_messages(), 0);
+    }
+
+    #[tokio::test]
+    async fn drop_rate_limited_sends() {
+        let (transport, peer) = LoopbackTransport::<MTU>::pair();
+        let assignments = assignments();
+        let registry = IdentifierRegistry::<Peripheral>::new(&assignments, 1);
+        let limit = RateLimit {
+            messages_per_second: 1,
+            burst: 2,
+            policy: RateLimitPolicy::Drop,
+        };
+        let transmitter = Transmitter::new(Peripheral, &registry, &transport)
+            .with_rate_limit(limit, TokioClock(tokio::time::Instant::now()));
+
+        assert_eq!(transmitter.send(message::Heartbeat::new(0)).await, Ok(()));
+        assert_eq!(transmitter.send(message::Heartbeat::new(0)).await, Ok(()));
+        assert_eq!(
+            transmitter.send(message::Heartbeat::new(0)).await,
+            Err(TransmitError::RateLimited)
+        );
+        assert_eq!(transmitter.dropped_messages(), 1);
+
+        // Control messages are exempt from the limit
+        transmitter.heartbeat().await;
+        for _ in 0..3 {
+            assert_eq!(peer.recv().await.unwrap().0, message::HEARTBEAT_ID);
+        }
+    }
+
+    #[tokio::test]
+    async fn report_epoch_of_last_reset_in_heartbeats() {
+        let (transport, peer) = LoopbackTransport::<MTU>::pair();
+        let assignments = assignments();
+        let registry = IdentifierRegistry::<Peripheral>::new(&assignments, 1);
+        let transmitter = Transmitter::new(Peripheral, &registry, &transport);
+
+        registry.set_epoch(7);
+        transmitter.heartbeat().await;
+
+        let (id, packet) = peer.recv().await.unwrap();
+        assert_eq!(id, message::HEARTBEAT_ID);
+        assert_eq!(
+            message::Heartbeat::from_packet(packet).map(|heartbeat| heartbeat.epoch()),
+            Ok(7)
+        );
+    }
 }
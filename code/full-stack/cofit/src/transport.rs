@@ -3,15 +3,22 @@
 use super::MessageID;
 use core::future::Future;
 
+/// Failure of a [`Transport`], after which it is not expected to recover
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportError {
+    /// The connection to the other side has been lost, e.g. because the device has been unplugged
+    Disconnected,
+}
+
 /// Physical data transfer layer
 ///
 /// Asynchronously transmits and receives packets over a given physical medium like a USB wire or Bluetooth wireless connection.
 pub trait Transport<const MTU: usize> {
-    type TxFut<'t>: Future<Output = ()> + 't
+    type TxFut<'t>: Future<Output = Result<(), TransportError>> + 't
     where
         Self: 't;
 
-    type RxFut<'t>: Future<Output = (MessageID, [u8; MTU])> + 't
+    type RxFut<'t>: Future<Output = Result<(MessageID, [u8; MTU]), TransportError>> + 't
     where
         Self: 't;
 
@@ -28,4 +35,10 @@ pub trait Transport<const MTU: usize> {
     /// though it is recommended that the transport maintains a small internal buffer to allow for
     /// minor lags while processing messages.
     fn recv<'t>(&'t self) -> Self::RxFut<'t>;
+
+    /// Number of frames passed to [`send`](Self::send) which have not yet been written to the wire.
+    /// Transports without an internal queue complete sends immediately and may keep the default.
+    fn queued_frames(&self) -> usize {
+        0
+    }
 }
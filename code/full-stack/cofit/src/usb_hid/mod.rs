@@ -1,7 +1,13 @@
-use super::{MessageID, Transport};
+use super::{
+    hid::{MTU, REPORT_SIZE},
+    MessageID, Transport, TransportError,
+};
 use core::future::Future;
 use hidapi::HidDevice;
-use std::sync::{mpsc, Arc};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    mpsc, Arc,
+};
 use tokio::sync::{
     broadcast::{self, error::RecvError},
     Mutex,
@@ -16,25 +22,33 @@ mod wrapper;
 /// Uses [`hidapi`](https://docs.rs/hidapi/latest/hidapi/) under the hood. Spawns two threads upon initialization which will handle data transfer in the background.
 #[doc(cfg(feature = "usb"))]
 pub struct UsbHidTransport {
-    tx: mpsc::Sender<[u8; 64]>,
-    rx: Mutex<broadcast::Receiver<[u8; 64]>>,
+    tx: mpsc::Sender<[u8; REPORT_SIZE]>,
+    rx: Mutex<broadcast::Receiver<[u8; REPORT_SIZE]>>,
+    /// Packets handed to the writer thread that have not yet been written to the device
+    queued: Arc<AtomicUsize>,
 }
 
 impl UsbHidTransport {
     pub fn new(device: HidDevice) -> Self {
-        let (tx, rx) = Self::spawn_communication_thread(device);
+        let queued = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = Self::spawn_communication_thread(device, queued.clone());
         Self {
             tx,
             rx: Mutex::new(rx),
+            queued,
         }
     }
 
     fn spawn_communication_thread(
         device: HidDevice,
-    ) -> (mpsc::Sender<[u8; 64]>, broadcast::Receiver<[u8; 64]>) {
+        queued: Arc<AtomicUsize>,
+    ) -> (
+        mpsc::Sender<[u8; REPORT_SIZE]>,
+        broadcast::Receiver<[u8; REPORT_SIZE]>,
+    ) {
         // TODO Make sure the threads are cleaned up when the instance is dropped!
 
-        let (hd_tx, hd_rx) = mpsc::channel::<[u8; 64]>();
+        let (hd_tx, hd_rx) = mpsc::channel::<[u8; REPORT_SIZE]>();
         let (dh_tx, dh_rx) = broadcast::channel(64_000);
 
         device
@@ -44,22 +58,28 @@ impl UsbHidTransport {
         let device_tx = Arc::new(HidDeviceWrapper::new(device));
         let device_rx = device_tx.clone();
 
+        // Both threads exit once the device is gone, dropping their end of the channel so the transport reports the disconnect
         std::thread::spawn(move || {
             while let Ok(packet) = hd_rx.recv() {
                 if let Err(e) = device_tx.write(&packet) {
                     eprintln!("failed to send packet to USB device {e:?}");
+                    break;
                 }
+
+                queued.fetch_sub(1, Ordering::Relaxed);
             }
 
             eprintln!("host-device writer thread exited");
         });
 
         std::thread::spawn(move || loop {
-            let mut buf = [0; 64];
+            let mut buf = [0; REPORT_SIZE];
             if let Err(e) = device_rx.read(&mut buf) {
                 eprintln!("failed to receive packet from USB device {e:?}");
-            } else {
-                dh_tx.send(buf).expect("failed to forward received packet");
+                break;
+            } else if dh_tx.send(buf).is_err() {
+                // Transport has been dropped, nobody is interested in the packets anymore
+                break;
             }
         });
 
@@ -67,24 +87,26 @@ impl UsbHidTransport {
     }
 }
 
-impl Transport<63> for UsbHidTransport {
-    type TxFut<'t> = impl Future<Output = ()> + 't
+impl Transport<MTU> for UsbHidTransport {
+    type TxFut<'t> = impl Future<Output = Result<(), TransportError>> + 't
     where
         Self: 't;
 
-    type RxFut<'t> = impl Future<Output = (MessageID, [u8; 63])> + 't
+    type RxFut<'t> = impl Future<Output = Result<(MessageID, [u8; MTU]), TransportError>> + 't
     where
         Self: 't;
 
-    fn send<'t>(&'t self, id: MessageID, data: [u8; 63]) -> Self::TxFut<'t> {
-        let mut packet = [0; 64];
+    fn send<'t>(&'t self, id: MessageID, data: [u8; MTU]) -> Self::TxFut<'t> {
+        let mut packet = [0; REPORT_SIZE];
         packet[0] = id;
         packet[1..].copy_from_slice(&data);
 
         async move {
-            self.tx
-                .send(packet)
-                .expect("failed to forward packet to send thread")
+            self.queued.fetch_add(1, Ordering::Relaxed);
+            self.tx.send(packet).map_err(|_| {
+                self.queued.fetch_sub(1, Ordering::Relaxed);
+                TransportError::Disconnected
+            })
         }
     }
 
@@ -93,14 +115,18 @@ impl Transport<63> for UsbHidTransport {
             loop {
                 match self.rx.lock().await.recv().await {
                     Ok(packet) => {
-                        let mut data = [0; 63];
+                        let mut data = [0; MTU];
                         data.copy_from_slice(&packet[1..]);
-                        return (packet[0], data);
+                        return Ok((packet[0], data));
                     }
                     Err(RecvError::Lagged(_)) => continue,
-                    Err(RecvError::Closed) => panic!("channel to packet receiver thread dropped"),
+                    Err(RecvError::Closed) => return Err(TransportError::Disconnected),
                 }
             }
         }
     }
+
+    fn queued_frames(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
 }
@@ -0,0 +1,20 @@
+//! Fixtures shared by the integration tests, not every test uses all of them
+#![allow(dead_code)]
+
+use cofit::{Message, MessageIdentifier};
+
+pub const MTU: usize = 42;
+
+pub struct PingMessage;
+
+impl Message<MTU> for PingMessage {
+    const IDENTIFIER: MessageIdentifier<'static> = "test.ping";
+
+    fn to_packet(self) -> [u8; MTU] {
+        [0; MTU]
+    }
+
+    fn from_packet(_: [u8; MTU]) -> Result<Self, ()> {
+        Ok(Self)
+    }
+}
@@ -1,21 +1,25 @@
 #![feature(generic_associated_types)]
 #![feature(type_alias_impl_trait)]
 
+mod common;
+
 use cofit::{
-    make_network, make_receiver_task, Handler, Host, Message, MessageIdentifier, Transport,
+    make_network, make_receiver_task, CapabilityAdded, Handler, Host, LoopbackTransport, Message,
+    MessageIdentifier, Peripheral, Transport, TransportError,
 };
-use core::future::Future;
-
-const MTU: usize = 42;
+use common::MTU;
+use core::{future::Future, time::Duration};
 
 struct DummyTransport;
 
 impl Transport<MTU> for DummyTransport {
-    type TxFut<'t> = impl Future<Output = ()> + 't
+    type TxFut<'t>
+        = impl Future<Output = Result<(), TransportError>> + 't
     where
         Self: 't;
 
-    type RxFut<'t> = impl Future<Output = (u8, [u8; MTU])> + 't
+    type RxFut<'t>
+        = impl Future<Output = Result<(u8, [u8; MTU]), TransportError>> + 't
     where
         Self: 't;
 
@@ -48,11 +52,11 @@ impl Message<MTU> for MessageB {
     const IDENTIFIER: MessageIdentifier<'static> = "dummy.b";
 
     fn to_packet(self) -> [u8; MTU] {
-        unimplemented!()
+        [0; MTU]
     }
 
-    fn from_packet(packet: [u8; MTU]) -> Result<Self, ()> {
-        unimplemented!()
+    fn from_packet(_: [u8; MTU]) -> Result<Self, ()> {
+        Ok(Self)
     }
 }
 
@@ -61,7 +65,8 @@ struct MessageAHandler;
 impl Handler<MTU> for MessageAHandler {
     type Message = MessageA;
 
-    type RecvFut<'s> = impl Future + 's
+    type RecvFut<'s>
+        = impl Future + 's
     where
         Self: 's;
 
@@ -75,7 +80,8 @@ struct MessageBHandler;
 impl Handler<MTU> for MessageBHandler {
     type Message = MessageB;
 
-    type RecvFut<'s> = impl Future + 's
+    type RecvFut<'s>
+        = impl Future + 's
     where
         Self: 's;
 
@@ -97,3 +103,52 @@ fn it_does_stuff() {
     };
     let rx_task = make_receiver_task!(rx, [handler_a, handler_b]);
 }
+
+#[tokio::test]
+async fn it_does_late_stuff() {
+    let (host_transport, peripheral_transport) = LoopbackTransport::<MTU>::pair();
+
+    let (host_tx, host_rx) = make_network! {
+        role: Host,
+        transport: &host_transport,
+        messages: [MessageA],
+        late_messages: [MessageB]
+    };
+
+    let (peripheral_tx, peripheral_rx) = make_network! {
+        role: Peripheral,
+        transport: &peripheral_transport,
+        messages: [MessageA],
+        late_messages: [MessageB]
+    };
+
+    host_tx.set_automatic_reset(false);
+
+    let peripheral_task = async {
+        loop {
+            peripheral_rx.recv().await.unwrap();
+        }
+    };
+
+    let exchange = async {
+        assert!(!peripheral_tx.is_supported::<MessageB>());
+        peripheral_tx.advertise::<MessageB>().await;
+
+        // The host assigns an ID and reports the new capability once the peripheral confirmed it
+        let (identifier, packet) = host_rx.recv().await.unwrap();
+        assert_eq!(identifier, CapabilityAdded::<MTU>::IDENTIFIER);
+        let added = CapabilityAdded::<MTU>::from_packet(packet).unwrap();
+        assert_eq!(added.identifier(), MessageB::IDENTIFIER);
+        assert!(peripheral_tx.is_supported::<MessageB>());
+
+        peripheral_tx.send(MessageB).await.unwrap();
+        let (identifier, _) = host_rx.recv().await.unwrap();
+        assert_eq!(identifier, MessageB::IDENTIFIER);
+    };
+
+    tokio::select! {
+        biased;
+        result = tokio::time::timeout(Duration::from_secs(1), exchange) => result.expect("host did not receive late message"),
+        _ = peripheral_task => unreachable!(),
+    }
+}
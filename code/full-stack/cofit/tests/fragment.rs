@@ -0,0 +1,426 @@
+#![feature(generic_associated_types)]
+#![feature(type_alias_impl_trait)]
+
+mod common;
+
+use cofit::{
+    make_network, make_receiver_task, AdaptivePayload, FragmentedHandler, FragmentedMessage, Host,
+    LoopbackTransport, Message, MessageIdentifier, Peripheral, Reassembler, TransmitError,
+    ADAPTIVE_HEADER_LENGTH, FRAGMENT_HEADER_LENGTH, MAX_FRAGMENTS,
+};
+use common::{PingMessage, MTU};
+use core::{
+    future::Future,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use std::sync::Arc;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+const PAYLOAD: usize = MTU - FRAGMENT_HEADER_LENGTH;
+
+/// Arbitrary bytes spread across as many fragments as necessary
+#[derive(Debug, PartialEq, Eq)]
+struct BlobMessage(Vec<u8>);
+
+impl FragmentedMessage<MTU> for BlobMessage {
+    const IDENTIFIER: MessageIdentifier<'static> = "test.blob";
+
+    type Fragments = std::vec::IntoIter<[u8; MTU]>;
+
+    fn to_fragments(self) -> Self::Fragments {
+        let length = self.0.len() as u8;
+
+        core::iter::once(&[length][..])
+            .chain(self.0.chunks(PAYLOAD))
+            .map(|chunk| {
+                let mut fragment = [0; MTU];
+                fragment[FRAGMENT_HEADER_LENGTH..FRAGMENT_HEADER_LENGTH + chunk.len()]
+                    .copy_from_slice(chunk);
+                fragment
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn from_fragments(fragments: &[[u8; MTU]]) -> Result<Self, ()> {
+        let (length, data) = fragments.split_first().ok_or(())?;
+        let length = length[FRAGMENT_HEADER_LENGTH] as usize;
+
+        let bytes = data
+            .iter()
+            .flat_map(|fragment| &fragment[FRAGMENT_HEADER_LENGTH..])
+            .take(length)
+            .copied()
+            .collect::<Vec<_>>();
+
+        if bytes.len() == length {
+            Ok(Self(bytes))
+        } else {
+            Err(())
+        }
+    }
+}
+
+struct BlobHandler(UnboundedSender<BlobMessage>);
+
+impl FragmentedHandler<MTU> for BlobHandler {
+    type Message = BlobMessage;
+
+    type RecvFut<'s>
+        = impl Future + 's
+    where
+        Self: 's;
+
+    fn handle<'s>(&'s self, message: Self::Message) -> Self::RecvFut<'s> {
+        async move {
+            self.0.send(message).ok();
+        }
+    }
+}
+
+#[tokio::test]
+async fn reassemble_messages_spanning_multiple_frames() {
+    let (host_transport, peripheral_transport) = LoopbackTransport::<MTU>::pair();
+
+    let (host_tx, host_rx) = make_network! {
+        role: Host,
+        transport: &host_transport,
+        messages: [PingMessage, BlobMessage]
+    };
+
+    let (_peripheral_tx, peripheral_rx) = make_network! {
+        role: Peripheral,
+        transport: &peripheral_transport,
+        messages: [PingMessage, BlobMessage]
+    };
+
+    host_tx.set_automatic_reset(false);
+
+    let (blob_tx, mut blob_rx) = unbounded_channel();
+    let reassembler = Reassembler::<_, MTU, 4>::new(BlobHandler(blob_tx));
+
+    let host_task = async {
+        loop {
+            host_rx.recv().await.unwrap();
+        }
+    };
+
+    let peripheral_task = make_receiver_task!(peripheral_rx, [reassembler]);
+
+    let exchange = async {
+        host_tx
+            .reset_peripheral(tokio::time::sleep(Duration::from_millis(100)))
+            .await
+            .unwrap();
+
+        let blob = (0..100).collect::<Vec<u8>>();
+        host_tx
+            .send_fragmented(BlobMessage(blob.clone()))
+            .await
+            .unwrap();
+        assert_eq!(blob_rx.recv().await, Some(BlobMessage(blob)));
+
+        // Messages which fit into the first fragment work just the same
+        host_tx
+            .send_fragmented(BlobMessage(vec![42]))
+            .await
+            .unwrap();
+        assert_eq!(blob_rx.recv().await, Some(BlobMessage(vec![42])));
+    };
+
+    tokio::select! {
+        biased;
+        result = tokio::time::timeout(Duration::from_secs(1), exchange) => result.expect("peripheral did not receive blob"),
+        _ = host_task => unreachable!(),
+        _ = peripheral_task => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn drop_messages_with_lost_fragments() {
+    let (host_transport, peripheral_transport) = LoopbackTransport::<MTU>::pair();
+
+    // Loses the second frame sent after the assignments, which is the second fragment of the first blob
+    let mut frames = 0;
+    let host_transport = host_transport.with_filter(move |(id, data)| {
+        frames += 1;
+        (frames != 4).then_some((id, data))
+    });
+
+    let (host_tx, host_rx) = make_network! {
+        role: Host,
+        transport: &host_transport,
+        messages: [BlobMessage]
+    };
+
+    let (_peripheral_tx, peripheral_rx) = make_network! {
+        role: Peripheral,
+        transport: &peripheral_transport,
+        messages: [BlobMessage]
+    };
+
+    host_tx.set_automatic_reset(false);
+
+    let (blob_tx, mut blob_rx) = unbounded_channel();
+    let reassembler = Reassembler::<_, MTU, 4>::new(BlobHandler(blob_tx));
+
+    let host_task = async {
+        loop {
+            host_rx.recv().await.unwrap();
+        }
+    };
+
+    let peripheral_task = make_receiver_task!(peripheral_rx, [reassembler]);
+
+    let exchange = async {
+        host_tx
+            .reset_peripheral(tokio::time::sleep(Duration::from_millis(100)))
+            .await
+            .unwrap();
+
+        host_tx
+            .send_fragmented(BlobMessage(vec![1; 50]))
+            .await
+            .unwrap();
+        host_tx
+            .send_fragmented(BlobMessage(vec![2; 50]))
+            .await
+            .unwrap();
+
+        assert_eq!(blob_rx.recv().await, Some(BlobMessage(vec![2; 50])));
+    };
+
+    tokio::select! {
+        biased;
+        result = tokio::time::timeout(Duration::from_secs(1), exchange) => result.expect("peripheral did not receive blob"),
+        _ = host_task => unreachable!(),
+        _ = peripheral_task => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn reject_oversized_messages_before_sending() {
+    let (host_transport, peripheral_transport) = LoopbackTransport::<MTU>::pair();
+
+    let frames = Arc::new(AtomicUsize::new(0));
+    let host_transport = host_transport.with_filter({
+        let frames = frames.clone();
+        move |frame| {
+            frames.fetch_add(1, Ordering::Relaxed);
+            Some(frame)
+        }
+    });
+
+    let (host_tx, host_rx) = make_network! {
+        role: Host,
+        transport: &host_transport,
+        messages: [BlobMessage]
+    };
+
+    let (_peripheral_tx, peripheral_rx) = make_network! {
+        role: Peripheral,
+        transport: &peripheral_transport,
+        messages: [BlobMessage]
+    };
+
+    host_tx.set_automatic_reset(false);
+
+    let (blob_tx, _blob_rx) = unbounded_channel();
+    let reassembler = Reassembler::<_, MTU, 4>::new(BlobHandler(blob_tx));
+
+    let host_task = async {
+        loop {
+            host_rx.recv().await.unwrap();
+        }
+    };
+
+    let peripheral_task = make_receiver_task!(peripheral_rx, [reassembler]);
+
+    let exchange = async {
+        host_tx
+            .reset_peripheral(tokio::time::sleep(Duration::from_millis(100)))
+            .await
+            .unwrap();
+
+        // One fragment for the length plus enough payload to exceed the limit
+        let sent = frames.load(Ordering::Relaxed);
+        let blob = vec![0; MAX_FRAGMENTS * PAYLOAD];
+        assert_eq!(
+            host_tx.send_fragmented(BlobMessage(blob)).await,
+            Err(TransmitError::TooLarge)
+        );
+        assert_eq!(frames.load(Ordering::Relaxed), sent);
+
+        // Messages right at the limit still go through
+        let blob = vec![0; (MAX_FRAGMENTS - 1) * PAYLOAD];
+        host_tx.send_fragmented(BlobMessage(blob)).await.unwrap();
+        assert_eq!(frames.load(Ordering::Relaxed), sent + MAX_FRAGMENTS);
+    };
+
+    tokio::select! {
+        biased;
+        result = tokio::time::timeout(Duration::from_secs(1), exchange) => result.expect("host did not finish sending"),
+        _ = host_task => unreachable!(),
+        _ = peripheral_task => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn reassemble_messages_in_shrunk_frames() {
+    let (host_transport, peripheral_transport) = LoopbackTransport::<MTU>::pair();
+
+    // Records the payload length of every blob frame
+    let (lengths_tx, mut lengths_rx) = unbounded_channel();
+    let host_transport = host_transport.with_filter(move |(id, data)| {
+        lengths_tx.send((id, data[FRAGMENT_HEADER_LENGTH])).ok();
+        Some((id, data))
+    });
+
+    let (host_tx, host_rx) = make_network! {
+        role: Host,
+        transport: &host_transport,
+        messages: [BlobMessage]
+    };
+
+    let (_peripheral_tx, peripheral_rx) = make_network! {
+        role: Peripheral,
+        transport: &peripheral_transport,
+        messages: [BlobMessage]
+    };
+
+    host_tx.set_automatic_reset(false);
+    let host_tx = host_tx.with_adaptive_payload(AdaptivePayload {
+        min_payload: 10,
+        shrink_after: 1,
+        grow_after: 1,
+    });
+
+    let (blob_tx, mut blob_rx) = unbounded_channel();
+    let reassembler = Reassembler::<_, MTU, 4>::new(BlobHandler(blob_tx)).with_adaptive_payload();
+
+    let host_task = async {
+        loop {
+            host_rx.recv().await.unwrap();
+        }
+    };
+
+    let peripheral_task = make_receiver_task!(peripheral_rx, [reassembler]);
+
+    let exchange = async {
+        host_tx
+            .reset_peripheral(tokio::time::sleep(Duration::from_millis(100)))
+            .await
+            .unwrap();
+
+        let blob = (0..100).collect::<Vec<u8>>();
+        let blob_id = host_tx
+            .assigned_identifiers()
+            .find(|(identifier, _)| *identifier == BlobMessage::IDENTIFIER)
+            .map(|(_, id)| id);
+
+        // Frames start out full and shrink with every timeout until they reach the minimum
+        assert_eq!(
+            host_tx.payload_per_frame(),
+            Some(MTU - ADAPTIVE_HEADER_LENGTH)
+        );
+        for payload in [20, 10, 10] {
+            host_tx.record_ack_timeout();
+            assert_eq!(host_tx.payload_per_frame(), Some(payload));
+        }
+
+        while lengths_rx.try_recv().is_ok() {}
+        host_tx
+            .send_fragmented(BlobMessage(blob.clone()))
+            .await
+            .unwrap();
+        assert_eq!(blob_rx.recv().await, Some(BlobMessage(blob.clone())));
+
+        // The length and the blob make up four fragments holding 41 bytes of payload each
+        let lengths = std::iter::from_fn(|| lengths_rx.try_recv().ok())
+            .filter(|(id, _)| Some(*id) == blob_id)
+            .map(|(_, length)| length)
+            .collect::<Vec<_>>();
+        assert_eq!(lengths, [[10; 16].as_slice(), &[4]].concat());
+
+        // Acknowledgements restore the full size
+        host_tx.record_ack();
+        host_tx.record_ack();
+        assert_eq!(
+            host_tx.payload_per_frame(),
+            Some(MTU - ADAPTIVE_HEADER_LENGTH)
+        );
+        host_tx
+            .send_fragmented(BlobMessage(blob.clone()))
+            .await
+            .unwrap();
+        assert_eq!(blob_rx.recv().await, Some(BlobMessage(blob)));
+    };
+
+    tokio::select! {
+        biased;
+        result = tokio::time::timeout(Duration::from_secs(1), exchange) => result.expect("peripheral did not receive blob"),
+        _ = host_task => unreachable!(),
+        _ = peripheral_task => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn discard_incomplete_messages() {
+    let (blob_tx, mut blob_rx) = unbounded_channel();
+    let reassembler =
+        Reassembler::<_, MTU, 4>::new(BlobHandler(blob_tx)).with_max_unrelated_frames(1);
+
+    let mut fragments = BlobMessage(vec![7; 50]).to_fragments();
+    let mut first = fragments.next().unwrap();
+    let mut second = fragments.next().unwrap();
+    let mut last = fragments.next().unwrap();
+    first[0] = 0;
+    second[0] = 1;
+    last[0] = 0x82;
+
+    // Interleaved frames of other messages are tolerated up to the limit
+    reassembler
+        .handle_raw(BlobMessage::IDENTIFIER, &first)
+        .unwrap()
+        .await;
+    assert!(reassembler
+        .handle_raw(PingMessage::IDENTIFIER, &[0; MTU])
+        .is_err());
+    reassembler
+        .handle_raw(BlobMessage::IDENTIFIER, &second)
+        .unwrap()
+        .await;
+    assert!(reassembler.is_reassembling());
+
+    // Exceeding it drops the message, including the remaining fragments
+    for _ in 0..2 {
+        assert!(reassembler
+            .handle_raw(PingMessage::IDENTIFIER, &[0; MTU])
+            .is_err());
+    }
+    assert!(!reassembler.is_reassembling());
+    reassembler
+        .handle_raw(BlobMessage::IDENTIFIER, &last)
+        .unwrap()
+        .await;
+    assert!(blob_rx.try_recv().is_err());
+
+    // Missing fragments drop the message as well
+    for fragment in [&first, &last] {
+        reassembler
+            .handle_raw(BlobMessage::IDENTIFIER, fragment)
+            .unwrap()
+            .await;
+    }
+    assert!(!reassembler.is_reassembling());
+    assert!(blob_rx.try_recv().is_err());
+
+    for fragment in [&first, &second, &last] {
+        reassembler
+            .handle_raw(BlobMessage::IDENTIFIER, fragment)
+            .unwrap()
+            .await;
+    }
+    assert_eq!(blob_rx.try_recv(), Ok(BlobMessage(vec![7; 50])));
+}
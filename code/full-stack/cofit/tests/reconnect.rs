@@ -0,0 +1,476 @@
+#![feature(generic_associated_types)]
+#![feature(type_alias_impl_trait)]
+
+mod common;
+
+use cofit::{
+    make_network, make_receiver_task, ConnectionState, DisconnectReason, Handler, Host,
+    LoopbackTransport, Message, MessageIdentifier, Peripheral, ResetError, TransmitError,
+    TransportError,
+};
+use common::{PingMessage, MTU};
+use core::{
+    future::{ready, Ready},
+    time::Duration,
+};
+
+/// Time the peripheral has to confirm a reset, it only has to process a single frame
+const RESET_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Gives the host receiver, which runs alongside in the same task, a chance to process the packets sent so far
+async fn let_host_receive() {
+    tokio::task::yield_now().await;
+}
+
+#[tokio::test]
+async fn reset_reconnected_peripheral_automatically() {
+    let (host_transport, peripheral_transport) = LoopbackTransport::<MTU>::pair();
+
+    let (host_tx, host_rx) = make_network! {
+        role: Host,
+        transport: &host_transport,
+        messages: [PingMessage]
+    };
+
+    let (peripheral_tx, peripheral_rx) = make_network! {
+        role: Peripheral,
+        transport: &peripheral_transport,
+        messages: [PingMessage]
+    };
+
+    // Device connected in place of the first one, e.g. after it rebooted, which starts out without any assignments
+    let (replacement_tx, replacement_rx) = make_network! {
+        role: Peripheral,
+        transport: &peripheral_transport,
+        messages: [PingMessage]
+    };
+
+    let host_task = async {
+        loop {
+            host_rx.recv().await.unwrap();
+        }
+    };
+
+    let exchange = async {
+        for (tx, rx) in [
+            (&peripheral_tx, &peripheral_rx),
+            (&replacement_tx, &replacement_rx),
+        ] {
+            tx.heartbeat().await;
+            let_host_receive().await;
+            assert_eq!(host_tx.connection_state(), ConnectionState::Negotiating);
+
+            // The peripheral confirms the reset while waiting for the ping, upon which the host assigns identifiers
+            let (received, _) = tokio::join!(rx.recv(), async {
+                while host_tx.connection_state() != ConnectionState::Ready {
+                    let_host_receive().await;
+                }
+
+                // Pings are only understood if the host assigned identifiers again
+                host_tx.send(PingMessage).await.unwrap();
+            });
+
+            let (identifier, _) = received.unwrap();
+            assert_eq!(identifier, PingMessage::IDENTIFIER);
+            assert_eq!(rx.connection_state(), ConnectionState::Ready);
+        }
+    };
+
+    tokio::select! {
+        biased;
+        result = tokio::time::timeout(Duration::from_secs(1), exchange) => result.expect("peripheral did not receive ping"),
+        _ = host_task => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn leave_reset_to_host_if_disabled() {
+    let (host_transport, peripheral_transport) = LoopbackTransport::<MTU>::pair();
+
+    let (host_tx, host_rx) = make_network! {
+        role: Host,
+        transport: &host_transport,
+        messages: [PingMessage]
+    };
+
+    let (peripheral_tx, peripheral_rx) = make_network! {
+        role: Peripheral,
+        transport: &peripheral_transport,
+        messages: [PingMessage]
+    };
+
+    host_tx.set_automatic_reset(false);
+
+    let host_task = async {
+        loop {
+            host_rx.recv().await.unwrap();
+        }
+    };
+
+    let exchange = async {
+        peripheral_tx.heartbeat().await;
+        let_host_receive().await;
+        assert_eq!(host_tx.connection_state(), ConnectionState::Connecting);
+
+        let (received, _) = tokio::join!(peripheral_rx.recv(), async {
+            host_tx
+                .reset_peripheral(tokio::time::sleep(RESET_TIMEOUT))
+                .await
+                .unwrap();
+            host_tx.send(PingMessage).await.unwrap();
+        });
+
+        let (identifier, _) = received.unwrap();
+        assert_eq!(identifier, PingMessage::IDENTIFIER);
+    };
+
+    tokio::select! {
+        biased;
+        result = tokio::time::timeout(Duration::from_secs(1), exchange) => result.expect("peripheral did not receive ping"),
+        _ = host_task => unreachable!(),
+    }
+}
+
+struct EraseMessage;
+
+impl Message<MTU> for EraseMessage {
+    const IDENTIFIER: MessageIdentifier<'static> = "test.erase";
+
+    fn to_packet(self) -> [u8; MTU] {
+        [0; MTU]
+    }
+
+    fn from_packet(_: [u8; MTU]) -> Result<Self, ()> {
+        Ok(Self)
+    }
+}
+
+#[tokio::test]
+async fn report_messages_rejected_by_peripheral() {
+    let (host_transport, peripheral_transport) = LoopbackTransport::<MTU>::pair();
+
+    let (host_tx, host_rx) = make_network! {
+        role: Host,
+        transport: &host_transport,
+        messages: [PingMessage, EraseMessage]
+    };
+
+    // Older peripheral which does not know about erasing yet
+    let (_peripheral_tx, peripheral_rx) = make_network! {
+        role: Peripheral,
+        transport: &peripheral_transport,
+        messages: [PingMessage]
+    };
+
+    host_tx.set_automatic_reset(false);
+
+    let host_task = async {
+        loop {
+            host_rx.recv().await.unwrap();
+        }
+    };
+
+    let exchange = async {
+        // Process the assignments on the peripheral, which rejects the unknown one
+        let (received, negotiation) = tokio::join!(peripheral_rx.recv(), async {
+            let negotiation = host_tx
+                .reset_peripheral(tokio::time::sleep(RESET_TIMEOUT))
+                .await
+                .unwrap();
+            host_tx.send(PingMessage).await.unwrap();
+            negotiation
+        });
+
+        let (identifier, _) = received.unwrap();
+        assert_eq!(identifier, PingMessage::IDENTIFIER);
+        let_host_receive().await;
+
+        assert!(negotiation.is_supported(PingMessage::IDENTIFIER));
+        assert!(!negotiation.is_supported(EraseMessage::IDENTIFIER));
+        assert_eq!(
+            negotiation.unsupported().collect::<Vec<_>>(),
+            [EraseMessage::IDENTIFIER]
+        );
+
+        // Rejected messages are no longer transmitted
+        assert_eq!(
+            host_tx.send(EraseMessage).await,
+            Err(TransmitError::Unsupported)
+        );
+        host_tx.send(PingMessage).await.unwrap();
+        let (identifier, _) = peripheral_rx.recv().await.unwrap();
+        assert_eq!(identifier, PingMessage::IDENTIFIER);
+    };
+
+    tokio::select! {
+        biased;
+        result = tokio::time::timeout(Duration::from_secs(1), exchange) => result.expect("peripheral did not receive ping"),
+        _ = host_task => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn refuse_to_send_messages_unsupported_by_host() {
+    let (host_transport, peripheral_transport) = LoopbackTransport::<MTU>::pair();
+
+    // Older host which does not know about erasing yet
+    let (host_tx, host_rx) = make_network! {
+        role: Host,
+        transport: &host_transport,
+        messages: [PingMessage]
+    };
+
+    let (peripheral_tx, peripheral_rx) = make_network! {
+        role: Peripheral,
+        transport: &peripheral_transport,
+        messages: [PingMessage, EraseMessage]
+    };
+
+    host_tx.set_automatic_reset(false);
+
+    let host_task = async {
+        loop {
+            host_rx.recv().await.unwrap();
+        }
+    };
+
+    let exchange = async {
+        assert!(!peripheral_tx.is_supported::<PingMessage>());
+
+        // Process the assignments on the peripheral
+        let (received, _) = tokio::join!(peripheral_rx.recv(), async {
+            host_tx
+                .reset_peripheral(tokio::time::sleep(RESET_TIMEOUT))
+                .await
+                .unwrap();
+            host_tx.send(PingMessage).await.unwrap();
+        });
+
+        let (identifier, _) = received.unwrap();
+        assert_eq!(identifier, PingMessage::IDENTIFIER);
+
+        assert!(peripheral_tx.is_supported::<PingMessage>());
+        assert!(!peripheral_tx.is_supported::<EraseMessage>());
+        assert_eq!(
+            peripheral_tx
+                .assigned_identifiers()
+                .map(|(identifier, _)| identifier)
+                .collect::<Vec<_>>(),
+            [PingMessage::IDENTIFIER]
+        );
+        assert_eq!(
+            peripheral_tx.send(EraseMessage).await,
+            Err(TransmitError::Unsupported)
+        );
+        assert_eq!(peripheral_tx.send(PingMessage).await, Ok(()));
+    };
+
+    tokio::select! {
+        biased;
+        result = tokio::time::timeout(Duration::from_secs(1), exchange) => result.expect("peripheral did not receive ping"),
+        _ = host_task => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn time_out_reset_unconfirmed_by_peripheral() {
+    let (host_transport, peripheral_transport) = LoopbackTransport::<MTU>::pair();
+
+    let (host_tx, host_rx) = make_network! {
+        role: Host,
+        transport: &host_transport,
+        messages: [PingMessage]
+    };
+
+    // Peripheral which is busy doing something else and does not process the reset right away
+    let (_peripheral_tx, peripheral_rx) = make_network! {
+        role: Peripheral,
+        transport: &peripheral_transport,
+        messages: [PingMessage]
+    };
+
+    host_tx.set_automatic_reset(false);
+
+    let host_task = async {
+        loop {
+            host_rx.recv().await.unwrap();
+        }
+    };
+
+    let exchange = async {
+        let result = host_tx
+            .reset_peripheral(tokio::time::sleep(RESET_TIMEOUT))
+            .await;
+        assert!(matches!(result, Err(ResetError::TimedOut)));
+        assert_eq!(host_tx.connection_state(), ConnectionState::Connecting);
+
+        // Confirmations arriving after the timeout are ignored
+        tokio::time::timeout(RESET_TIMEOUT, peripheral_rx.recv())
+            .await
+            .expect_err("peripheral received a regular message");
+        let_host_receive().await;
+        assert_eq!(host_tx.connection_state(), ConnectionState::Connecting);
+    };
+
+    tokio::select! {
+        biased;
+        result = tokio::time::timeout(Duration::from_secs(1), exchange) => result.expect("reset did not time out"),
+        _ = host_task => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn complete_reset_if_confirmation_got_lost() {
+    let (host_transport, peripheral_transport) = LoopbackTransport::<MTU>::pair();
+
+    // Loses the echo of the reset, which uses the last reserved ID
+    let peripheral_transport =
+        peripheral_transport.with_filter(|(id, data)| (id != u8::MAX - 5).then_some((id, data)));
+
+    let (host_tx, host_rx) = make_network! {
+        role: Host,
+        transport: &host_transport,
+        messages: [PingMessage]
+    };
+
+    let (peripheral_tx, peripheral_rx) = make_network! {
+        role: Peripheral,
+        transport: &peripheral_transport,
+        messages: [PingMessage]
+    };
+
+    host_tx.set_automatic_reset(false);
+
+    let host_task = async {
+        loop {
+            host_rx.recv().await.unwrap();
+        }
+    };
+
+    let exchange = async {
+        let (received, _) = tokio::join!(peripheral_rx.recv(), async {
+            // The heartbeat following the reset reports its epoch, proving that the peripheral received it
+            let heartbeat = async {
+                while peripheral_tx.connection_state() != ConnectionState::Negotiating {
+                    let_host_receive().await;
+                }
+
+                peripheral_tx.heartbeat().await;
+            };
+
+            let (result, _) = tokio::join!(
+                host_tx.reset_peripheral(tokio::time::sleep(RESET_TIMEOUT)),
+                heartbeat
+            );
+            result.unwrap();
+            host_tx.send(PingMessage).await.unwrap();
+        });
+
+        let (identifier, _) = received.unwrap();
+        assert_eq!(identifier, PingMessage::IDENTIFIER);
+    };
+
+    tokio::select! {
+        biased;
+        result = tokio::time::timeout(Duration::from_secs(1), exchange) => result.expect("peripheral did not receive ping"),
+        _ = host_task => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn lose_connection_when_peer_disconnects() {
+    let (host_transport, peripheral_transport) = LoopbackTransport::<MTU>::pair();
+
+    let (host_tx, host_rx) = make_network! {
+        role: Host,
+        transport: &host_transport,
+        messages: [PingMessage]
+    };
+
+    let (_peripheral_tx, peripheral_rx) = make_network! {
+        role: Peripheral,
+        transport: &peripheral_transport,
+        messages: [PingMessage]
+    };
+
+    host_tx.set_automatic_reset(false);
+
+    let host_task = async {
+        loop {
+            host_rx.recv().await.unwrap();
+        }
+    };
+
+    let handshake = async {
+        let (received, _) = tokio::join!(peripheral_rx.recv(), async {
+            host_tx
+                .reset_peripheral(tokio::time::sleep(RESET_TIMEOUT))
+                .await
+                .unwrap();
+            host_tx.send(PingMessage).await.unwrap();
+        });
+
+        received.unwrap();
+    };
+
+    tokio::select! {
+        biased;
+        result = tokio::time::timeout(Duration::from_secs(1), handshake) => result.expect("peripheral did not receive ping"),
+        _ = host_task => unreachable!(),
+    }
+
+    assert_eq!(host_tx.connection_state(), ConnectionState::Ready);
+
+    // Unplug the peripheral, its network is not used anymore from here on
+    drop(peripheral_transport);
+
+    assert_eq!(
+        host_tx.send(PingMessage).await,
+        Err(TransmitError::Transport(TransportError::Disconnected))
+    );
+    assert_eq!(
+        host_tx.connection_state(),
+        ConnectionState::Disconnected(DisconnectReason::Lost)
+    );
+    assert_eq!(
+        host_rx.recv().await.err(),
+        Some(TransportError::Disconnected)
+    );
+}
+
+struct PingHandler;
+
+impl Handler<MTU> for PingHandler {
+    type Message = PingMessage;
+
+    type RecvFut<'s> = Ready<()>;
+
+    fn handle(&self, _: Self::Message) -> Self::RecvFut<'_> {
+        ready(())
+    }
+}
+
+#[tokio::test]
+async fn end_receiver_task_when_peer_disconnects() {
+    let (host_transport, peripheral_transport) = LoopbackTransport::<MTU>::pair();
+
+    let (_peripheral_tx, peripheral_rx) = make_network! {
+        role: Peripheral,
+        transport: &peripheral_transport,
+        messages: [PingMessage]
+    };
+
+    // Unplug the host
+    drop(host_transport);
+
+    let peripheral_task = make_receiver_task!(peripheral_rx, [PingHandler]);
+    let error = tokio::time::timeout(Duration::from_secs(1), peripheral_task)
+        .await
+        .expect("receiver task did not complete");
+
+    assert_eq!(error, TransportError::Disconnected);
+    assert_eq!(
+        peripheral_rx.connection_state(),
+        ConnectionState::Disconnected(DisconnectReason::Lost)
+    );
+}
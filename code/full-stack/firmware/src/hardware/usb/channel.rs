@@ -1,4 +1,7 @@
-use cofit::Transport;
+use cofit::{
+    hid::{MTU, REPORT_SIZE, USAGE_EMBEDDED_STENO, USAGE_PAGE_VENDOR},
+    Transport, TransportError,
+};
 use core::future::Future;
 use defmt::warn;
 use embassy_nrf::usb::PowerUsb;
@@ -10,15 +13,14 @@ use embassy_util::{
     Forever,
 };
 use futures::future::join;
-use usbd_hid::descriptor::{gen_hid_descriptor, SerializedDescriptor};
 
 const POLL_INTERVAL_MS: u8 = 1;
 
 const INCOMING_BUFFER_LEN: usize = 8;
 const OUTGOING_BUFFER_LEN: usize = 8;
 
-const INCOMING_USB_BUFFER_LEN: usize = 64;
-const OUTGOING_USB_BUFFER_LEN: usize = 64;
+const INCOMING_USB_BUFFER_LEN: usize = REPORT_SIZE;
+const OUTGOING_USB_BUFFER_LEN: usize = REPORT_SIZE;
 
 static STATE: Forever<State> = Forever::new();
 static HOST_DEVICE_CHANNEL: Forever<EmbassyChannel<NoopRawMutex, Packet, INCOMING_BUFFER_LEN>> =
@@ -27,7 +29,12 @@ static DEVICE_HOST_CHANNEL: Forever<EmbassyChannel<NoopRawMutex, Packet, OUTGOIN
     Forever::new();
 static REQUEST_HANDLER: Forever<CommandChannelRequestHandler> = Forever::new();
 
-pub struct Packet([u8; 64]);
+static REPORT_DESCRIPTOR: [u8; VendorReportDescriptor::LEN] =
+    VendorReportDescriptor::new(USAGE_PAGE_VENDOR, USAGE_EMBEDDED_STENO)
+        .report_size(REPORT_SIZE as u16)
+        .build();
+
+pub struct Packet([u8; REPORT_SIZE]);
 
 pub struct Channel<'c> {
     tx: &'c EmbassyChannel<NoopRawMutex, Packet, OUTGOING_BUFFER_LEN>,
@@ -40,24 +47,28 @@ pub struct ChannelRuntime<'r, D: Driver<'static>> {
     device_host_receiver: Receiver<'static, NoopRawMutex, Packet, OUTGOING_BUFFER_LEN>,
 }
 
-impl<'c> Transport<63> for Channel<'c> {
-    type TxFut<'t> = impl Future<Output = ()> + 't where Self: 't;
-    type RxFut<'t> = impl Future<Output = (u8, [u8; 63])> + 't where Self: 't;
+impl<'c> Transport<MTU> for Channel<'c> {
+    type TxFut<'t> = impl Future<Output = Result<(), TransportError>> + 't where Self: 't;
+    type RxFut<'t> = impl Future<Output = Result<(u8, [u8; MTU]), TransportError>> + 't where Self: 't;
 
-    fn send<'t>(&'t self, id: u8, data: [u8; 63]) -> Self::TxFut<'t> {
-        let mut packet = [0; 64];
+    fn send<'t>(&'t self, id: u8, data: [u8; MTU]) -> Self::TxFut<'t> {
+        let mut packet = [0; REPORT_SIZE];
         packet[0] = id;
         packet[1..].copy_from_slice(&data);
 
-        self.tx.send(Packet(packet))
+        // The USB channel lives as long as the firmware, sending can not fail
+        async move {
+            self.tx.send(Packet(packet)).await;
+            Ok(())
+        }
     }
 
     fn recv<'t>(&'t self) -> Self::RxFut<'t> {
         async move {
             let packet = self.rx.recv().await.0;
-            let mut data = [0; 63];
+            let mut data = [0; MTU];
             data.copy_from_slice(&packet[1..]);
-            (packet[0], data)
+            Ok((packet[0], data))
         }
     }
 }
@@ -73,10 +84,10 @@ pub fn configure<D: Driver<'static>>(
     });
 
     let config = embassy_usb_hid::Config {
-        report_descriptor: BidirectionalReport::desc(),
+        report_descriptor: &REPORT_DESCRIPTOR,
         request_handler: Some(request_handler),
         poll_ms: POLL_INTERVAL_MS,
-        max_packet_size: 64,
+        max_packet_size: REPORT_SIZE as u16,
     };
 
     let reader_writer = HidReaderWriter::<_, OUTGOING_USB_BUFFER_LEN, INCOMING_USB_BUFFER_LEN>::new(
@@ -120,15 +131,55 @@ pub async fn run(
     join(reader_fut, writer_fut).await;
 }
 
-#[gen_hid_descriptor(
-    (report_id = 0x0, collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x42) = {
-        input=input;
-        output=output;
+/// Builder for a HID report descriptor with one vendor defined input and output report each
+///
+/// All items use fixed-width encodings so the resulting descriptor always has the same length,
+/// regardless of the usage or report size.
+pub struct VendorReportDescriptor {
+    usage_page: u16,
+    usage: u16,
+    report_size: u16,
+}
+
+impl VendorReportDescriptor {
+    /// Length of the descriptor in bytes
+    pub const LEN: usize = 30;
+
+    pub const fn new(usage_page: u16, usage: u16) -> Self {
+        Self {
+            usage_page,
+            usage,
+            report_size: REPORT_SIZE as u16,
+        }
+    }
+
+    /// Sets the number of bytes contained in each input and output report
+    pub const fn report_size(mut self, report_size: u16) -> Self {
+        self.report_size = report_size;
+        self
+    }
+
+    pub const fn build(self) -> [u8; Self::LEN] {
+        let [page_lo, page_hi] = self.usage_page.to_le_bytes();
+        let [usage_lo, usage_hi] = self.usage.to_le_bytes();
+        let [count_lo, count_hi] = self.report_size.to_le_bytes();
+
+        [
+            0x06, page_lo, page_hi, // Usage Page
+            0x0A, usage_lo, usage_hi, // Usage
+            0xA1, 0x01, // Collection (Application)
+            0x15, 0x00, // Logical Minimum (0)
+            0x26, 0xFF, 0x00, // Logical Maximum (255)
+            0x75, 0x08, // Report Size (8 bits)
+            0x96, count_lo, count_hi, // Report Count
+            0x09, 0x01, // Usage (0x01)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0x96, count_lo, count_hi, // Report Count
+            0x09, 0x02, // Usage (0x02)
+            0x91, 0x02, // Output (Data, Variable, Absolute)
+            0xC0, // End Collection
+        ]
     }
-)]
-struct BidirectionalReport {
-    input: [u8; 64],
-    output: [u8; 64],
 }
 
 struct CommandChannelRequestHandler {
@@ -141,11 +192,11 @@ impl RequestHandler for CommandChannelRequestHandler {
     }
 
     fn set_report(&self, id: ReportId, data: &[u8]) -> OutResponse {
-        if id != ReportId::Out(0) || data.len() != 64 {
+        if id != ReportId::Out(0) || data.len() != REPORT_SIZE {
             return OutResponse::Rejected;
         }
 
-        let mut payload = [0; 64];
+        let mut payload = [0; REPORT_SIZE];
         payload.copy_from_slice(&data);
 
         let command = Packet(payload);
@@ -17,8 +17,11 @@ static REMOTE_WAKEUP: Signal<()> = Signal::new();
 static SUSPENDED: AtomicBool = AtomicBool::new(false);
 static CONFIGURED: AtomicBool = AtomicBool::new(false);
 
+/// Size of the buffer holding the configuration descriptor (including all class descriptors)
+const CONFIG_DESCRIPTOR_LEN: usize = 256;
+
 static DEVICE_DESCRIPTOR: Forever<[u8; 256]> = Forever::new();
-static CONFIG_DESCRIPTOR: Forever<[u8; 256]> = Forever::new();
+static CONFIG_DESCRIPTOR: Forever<[u8; CONFIG_DESCRIPTOR_LEN]> = Forever::new();
 static BOS_DESCRIPTOR: Forever<[u8; 256]> = Forever::new();
 static CONTROL_BUF: Forever<[u8; 64]> = Forever::new();
 
@@ -53,7 +56,7 @@ pub fn configure<P: UsbSupply + 'static>(
         driver,
         config,
         DEVICE_DESCRIPTOR.put([0; 256]),
-        CONFIG_DESCRIPTOR.put([0; 256]),
+        CONFIG_DESCRIPTOR.put([0; CONFIG_DESCRIPTOR_LEN]),
         BOS_DESCRIPTOR.put([0; 256]),
         CONTROL_BUF.put([0; 64]),
         Some(&STATE_HANDLER),
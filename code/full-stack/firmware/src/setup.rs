@@ -3,7 +3,10 @@ use crate::hardware::{
     self,
     usb::{self, keyboard::Keyboard},
 };
-use cofit::Transport;
+use cofit::{
+    hid::{PRODUCT_ID, VENDOR_ID},
+    Transport,
+};
 use embassy_executor::time::Duration;
 use embassy_nrf::{
     gpio::{AnyPin, Pin},
@@ -23,9 +26,6 @@ macro_rules! make_keymap {
     };
 }
 
-const USB_VID: u16 = 0xc0de;
-const USB_PID: u16 = 0xcafe;
-
 const FLASH_SIZE: usize = 2usize.pow(16) * 256;
 
 const ACTIVE_SCAN_PERIOD: Duration = Duration::from_millis(15);
@@ -98,7 +98,7 @@ fn setup_usb(
     usbd: peripherals::USBD,
 ) -> (Keyboard<'static>, impl Transport<63>) {
     // Create config for the USB peripheral
-    let mut config = embassy_usb::Config::new(USB_VID, USB_PID);
+    let mut config = embassy_usb::Config::new(VENDOR_ID, PRODUCT_ID);
     config.manufacturer = Some("Evil Steno Corp");
     config.product = Some("Goldcrest v0.0.1");
     config.serial_number = Some("0.0.1");
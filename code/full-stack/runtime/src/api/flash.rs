@@ -1,7 +1,7 @@
 use crate::message::flash::{
     EraseFlash, FlashContent, FlashErased, FlashWritten, ReadFlash, WriteFlash,
 };
-use cofit::{Handler, Host, Transmitter, Transport};
+use cofit::{Handler, Host, TransmitError, Transmitter, Transport};
 use core::future::Future;
 use core::time::Duration;
 use futures::StreamExt;
@@ -32,6 +32,8 @@ enum FlashMessage {
 pub enum FlashError {
     /// Data transmission was not acknowledged within time
     TimedOut,
+    /// The request could not be sent, e.g. because the peripheral does not support it
+    Transmit(TransmitError),
 }
 
 pub struct FlashAPI<'t, T: Transport<63>> {
@@ -61,7 +63,7 @@ impl<'t, T: Transport<63>> FlashAPI<'t, T> {
         };
 
         self.clear_rx();
-        self.tx.send(message).await;
+        self.tx.send(message).await.map_err(FlashError::Transmit)?;
 
         for (offset, chunk) in bytes
             .chunks_mut(60)
@@ -106,7 +108,7 @@ impl<'t, T: Transport<63>> FlashAPI<'t, T> {
         let sector_count = message.end_sector as u32 - message.start_sector as u32;
 
         self.clear_rx();
-        self.tx.send(message).await;
+        self.tx.send(message).await.map_err(FlashError::Transmit)?;
 
         let ack_fut = async {
             while let Some(msg) = self.rx.next().await {
@@ -226,6 +228,11 @@ impl<'r, 't, T: Transport<63>> FlashWriteTask<'r, 't, T> {
     }
 
     pub async fn next(&mut self) -> Result<Option<f64>, FlashError> {
+        // Chunks which can not be sent would never be acknowledged, so bail out instead of timing out
+        if !self.flash.tx.is_supported::<WriteFlash>() {
+            return Err(FlashError::Transmit(TransmitError::Unsupported));
+        }
+
         let remaining = self.queue.len();
         let next_fut = async {
             loop {
@@ -249,7 +256,8 @@ impl<'r, 't, T: Transport<63>> FlashWriteTask<'r, 't, T> {
         // Send the data
         let start = Instant::now();
         let message = self.message(index)?;
-        self.flash.tx.send(message).await;
+        // Unsent chunks are not acknowledged and thus retried like lost ones
+        self.flash.tx.send(message).await.ok();
 
         // Look if we have some ACK waiting :)
         let _ = timeout(
@@ -1,19 +1,27 @@
 use super::message::flash::{
     EraseFlash, FlashContent, FlashErased, FlashWritten, ReadFlash, WriteFlash,
 };
-use cofit::{make_network, make_owned_receiver_task, Host, Transmitter, Transport};
-use core::{future::Future, ops::DerefMut};
-use futures::lock::Mutex;
+use cofit::{
+    make_network, make_owned_receiver_task, ConnectionState, DisconnectReason, Host, ResetError,
+    Transmitter, Transport,
+};
+use core::{future::Future, ops::DerefMut, time::Duration};
+use futures::{future::select, lock::Mutex};
 use std::sync::Arc;
+use tokio::sync::watch;
 
 mod flash;
 
 pub use flash::FlashAPI;
 
+/// Time the peripheral has to confirm a reset
+const TIMEOUT_RESET: Duration = Duration::from_millis(250);
+
 #[derive(Clone)]
 pub struct RuntimeAPI<'t, T: Transport<63>> {
     tx: Arc<Transmitter<'static, 't, 63, T, Host>>,
     flash: Arc<Mutex<FlashAPI<'t, T>>>,
+    state: Arc<watch::Sender<ConnectionState>>,
 }
 
 impl<'t, T: Transport<63>> RuntimeAPI<'t, T> {
@@ -40,11 +48,87 @@ impl<'t, T: Transport<63>> RuntimeAPI<'t, T> {
             [flash_read_handler, flash_write_handler, flash_erase_handler]
         );
 
-        (rx_task, Self { tx, flash })
+        let (state, _) = watch::channel(ConnectionState::Connecting);
+        let state = Arc::new(state);
+
+        let api = Self { tx, flash, state };
+
+        // Resets performed by the receiver on its own have to reach the observers as well
+        let observer = Self {
+            tx: api.tx.clone(),
+            flash: api.flash.clone(),
+            state: api.state.clone(),
+        };
+        let state_task = async move {
+            let mut state = observer.tx.connection_state();
+
+            loop {
+                state = observer.tx.wait_for_state_change(state).await;
+                observer.follow(state);
+            }
+        };
+
+        (select(Box::pin(rx_task), Box::pin(state_task)), api)
+    }
+
+    /// Resets the peripheral and (re-)assigns all message identifiers.
+    /// If the connection was ready before, a [`DisconnectReason::Reset`] is emitted first.
+    /// Fails if the peripheral does not confirm the reset in time, see [`Transmitter::reset_peripheral`].
+    pub async fn reset(&self) -> Result<(), ResetError> {
+        self.follow(ConnectionState::Negotiating);
+        let result = self
+            .tx
+            .reset_peripheral(tokio::time::sleep(TIMEOUT_RESET))
+            .await;
+        self.follow(self.tx.connection_state());
+
+        result.map(|_| ())
+    }
+
+    /// Closes the connection, e.g. after the transport failed. Call [`reset`](Self::reset) to reconnect.
+    pub fn close(&self) {
+        self.tx.close();
+        self.follow(self.tx.connection_state());
+    }
+
+    /// Current state of the connection to the peripheral
+    pub fn connection_state(&self) -> ConnectionState {
+        *self.state.borrow()
+    }
+
+    /// Subscribes to changes of the connection state
+    pub fn connection_states(&self) -> watch::Receiver<ConnectionState> {
+        self.state.subscribe()
+    }
+
+    /// Waits until the connection becomes ready, returns immediately if it already is
+    pub async fn wait_until_ready(&self) {
+        let mut states = self.connection_states();
+
+        while *states.borrow_and_update() != ConnectionState::Ready {
+            // The sender lives as long as `self`, thus this can not fail
+            let _ = states.changed().await;
+        }
+    }
+
+    /// Publishes a new state of the connection, every change passes through here regardless of who initiated it.
+    /// Emits a [`DisconnectReason::Reset`] first when a ready connection is being reset.
+    fn follow(&self, state: ConnectionState) {
+        if state == ConnectionState::Negotiating
+            && self.connection_state() == ConnectionState::Ready
+        {
+            self.publish(ConnectionState::Disconnected(DisconnectReason::Reset));
+        }
+
+        self.publish(state);
     }
 
-    pub async fn reset(&self) {
-        self.tx.reset_peripheral().await;
+    fn publish(&self, state: ConnectionState) {
+        self.state.send_if_modified(|current| {
+            let modified = *current != state;
+            *current = state;
+            modified
+        });
     }
 
     /// Acquires a mutable handle to the flash API
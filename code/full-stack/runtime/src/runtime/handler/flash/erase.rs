@@ -24,6 +24,7 @@ impl<'f, 't, F: AsyncNorFlash, T: Transport<63>> Handler<63> for FlashEraseHandl
 
     fn handle<'s>(&'s self, message: Self::Message) -> Self::RecvFut<'s> {
         async move {
+            let responder = self.tx.responder();
             let start = (message.start_sector as usize * F::ERASE_SIZE) as u32;
             let end = (message.end_sector as usize * F::ERASE_SIZE) as u32;
 
@@ -32,7 +33,7 @@ impl<'f, 't, F: AsyncNorFlash, T: Transport<63>> Handler<63> for FlashEraseHandl
             match result {
                 Ok(_) => {
                     let acknowledgement: FlashErased<63> = message.into();
-                    self.tx.send(acknowledgement).await;
+                    responder.respond(acknowledgement).await.ok();
                 }
                 Err(_) => {
                     // TODO Print a warning!
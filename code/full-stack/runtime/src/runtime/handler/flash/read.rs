@@ -24,6 +24,7 @@ impl<'f, 't, F: AsyncNorFlash, T: Transport<63>> Handler<63> for FlashReadHandle
 
     fn handle<'s>(&'s self, message: Self::Message) -> Self::RecvFut<'s> {
         async move {
+            let responder = self.tx.responder();
             let mut offset = *message.start;
             while offset < *message.end {
                 let mut content = FlashContent {
@@ -38,7 +39,10 @@ impl<'f, 't, F: AsyncNorFlash, T: Transport<63>> Handler<63> for FlashReadHandle
                     .await
                 {
                     Ok(_) => {
-                        self.tx.send(content).await;
+                        // No point in reading further if the host does not understand the content or has been reset
+                        if responder.respond(content).await.is_err() {
+                            break;
+                        }
                     }
                     Err(_) => {
                         // TODO Print a warning that the read failed, maybe send a error message
@@ -24,6 +24,7 @@ impl<'f, 't, F: AsyncNorFlash, T: Transport<63>> Handler<63> for FlashWriteHandl
 
     fn handle<'s>(&'s self, message: Self::Message) -> Self::RecvFut<'s> {
         async move {
+            let responder = self.tx.responder();
             let result = self
                 .flash
                 .lock()
@@ -34,7 +35,7 @@ impl<'f, 't, F: AsyncNorFlash, T: Transport<63>> Handler<63> for FlashWriteHandl
             match result {
                 Ok(_) => {
                     let acknowledgement: FlashWritten = message.into();
-                    self.tx.send(acknowledgement).await;
+                    responder.respond(acknowledgement).await.ok();
                 }
                 Err(_) => {
                     // TODO Print a warning!
@@ -8,7 +8,7 @@ use self::{
 use super::message::flash::{
     EraseFlash, FlashContent, FlashErased, FlashWritten, ReadFlash, WriteFlash,
 };
-use cofit::{make_network, make_receiver_task, Peripheral, Transport};
+use cofit::{make_network, make_receiver_task, Peripheral, Transmitter, Transport};
 use embedded_storage_async::nor_flash::AsyncNorFlash;
 use engine::{InputState, OutputCommand};
 use futures::{future::select, pin_mut, Sink, Stream};
@@ -21,6 +21,9 @@ mod old_engine;
 pub use hardware::HardwareStack;
 pub use old_engine::{DurationDriver, InstantDriver, TimeDriver};
 
+/// Interval at which the host is reminded that we are connected, in milliseconds
+const HEARTBEAT_INTERVAL: u64 = 1000;
+
 #[doc(cfg(feature = "runtime"))]
 pub struct Runtime;
 
@@ -32,7 +35,7 @@ impl Runtime {
         O: Sink<OutputCommand>,
     >(
         hardware: HardwareStack<I, C, F, O>,
-        time_driver: impl old_engine::TimeDriver,
+        time_driver: impl TimeDriver,
     ) {
         // Initialize the network stack
         let (usb_tx, usb_rx) = make_network! {
@@ -65,15 +68,34 @@ impl Runtime {
 
         let flash_task = select(flash_read_task, select(flash_write_task, flash_erase_task));
 
-        // Build the network task
+        // Build the network tasks
         let usb_rx_task = make_receiver_task!(usb_rx, [flash_read_handler, flash_write_handler]);
         pin_mut!(usb_rx_task);
 
+        let heartbeat_task = send_heartbeats(&usb_tx, &time_driver);
+        pin_mut!(heartbeat_task);
+
+        let network_task = select(usb_rx_task, heartbeat_task);
+
         // Build the engine task
-        let engine_task = old_engine::run(hardware.input, hardware.usb_output, &flash, time_driver);
+        let engine_task =
+            old_engine::run(hardware.input, hardware.usb_output, &flash, &time_driver);
         pin_mut!(engine_task);
 
         // Run the runtime :)
-        select(usb_rx_task, select(engine_task, flash_task)).await;
+        select(network_task, select(engine_task, flash_task)).await;
+    }
+}
+
+/// Keeps reporting to the host that we are connected, so it resets the network whenever it (re-)connects to us
+async fn send_heartbeats<C: Transport<63>, T: TimeDriver>(
+    tx: &Transmitter<'_, '_, 63, C, Peripheral>,
+    time_driver: &T,
+) {
+    loop {
+        tx.heartbeat().await;
+
+        let next = time_driver.now() + T::Duration::from_millis(HEARTBEAT_INTERVAL);
+        time_driver.wait_until(next).await;
     }
 }
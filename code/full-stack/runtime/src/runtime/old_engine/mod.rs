@@ -8,7 +8,7 @@ use futures::{pin_mut, Sink, SinkExt, Stream, StreamExt};
 use repeat::KeypressRepeater;
 pub use repeat::{DurationDriver, InstantDriver, TimeDriver};
 use shittyengine::{
-    dict::{DataSource, RadixTreeDictionary},
+    dict::{DataSource, FallbackDictionary, RadixTreeDictionary},
     formatter::Formatter,
     matcher::{CommitType, OutlineMatcher},
     Stroke,
@@ -86,6 +86,7 @@ pub async fn run<T: TimeDriver>(
     let mut dict = RadixTreeDictionary::new(data_source)
         .await
         .expect("failed to open dictionary");
+    let fallback = FallbackDictionary::new();
     let mut matcher = OutlineMatcher::<Stroke, 32>::new(11);
     let mut formatter = Formatter::<32>::new();
 
@@ -111,11 +112,13 @@ pub async fn run<T: TimeDriver>(
         matcher.add(stroke);
 
         while matcher.uncommitted_count() > 0 {
-            // 2. Search the dictionary for the uncommitted strokes and commit matching prefixes
+            // 2. Search the dictionary for the uncommitted strokes and commit matching prefixes,
+            //    writing out the first stroke if it is not part of any outline
             let dict_match = dict
                 .match_prefix(matcher.uncommitted_strokes())
                 .await
-                .unwrap();
+                .unwrap()
+                .or_else(|| fallback.match_prefix(matcher.uncommitted_strokes()));
 
             // The following section can be externalised into a crate contained struct really well.
             // Take everything but the dictionary, stuff it into a struct. Add a method to call
@@ -140,7 +143,7 @@ pub async fn run<T: TimeDriver>(
                         Err(trailing_outline) => {
                             // Undo the trailing outline
                             for _ in 0..trailing_outline.outline().commands {
-                                if let Some(command) = formatter.undo() {
+                                if let Some(command) = formatter.revert() {
                                     output.apply(command).await;
                                 }
                             }
@@ -151,11 +154,6 @@ pub async fn run<T: TimeDriver>(
                         }
                     }
                 }
-            } else {
-                // In a "real" engine implementation you would have a fallback dictionary that outputs the human readable representation
-                // TODO Write a default impl for such a fallback dictionary
-                defmt::warn!("Omitting trailing strokes for now until more strokes are received");
-                break;
             }
             // --------- SECTION END ---------
         }
@@ -178,6 +176,12 @@ impl<'s, S: Sink<OutputCommand> + Unpin> SinkOutput<'s, S> {
                     self.0.send(OutputCommand::Write(c)).await.ok();
                 }
             }
+            shittyengine::output::OutputCommand::Replace(count, characters) => {
+                self.0.send(OutputCommand::Backspace(count)).await.ok();
+                for c in characters {
+                    self.0.send(OutputCommand::Write(c)).await.ok();
+                }
+            }
         }
     }
 }
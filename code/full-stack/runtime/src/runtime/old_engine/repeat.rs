@@ -16,6 +16,21 @@ pub trait TimeDriver {
     fn wait_until(&self, instant: Self::Instant) -> Self::TimerFut;
 }
 
+/// Lets a single driver be shared by multiple tasks
+impl<T: TimeDriver> TimeDriver for &T {
+    type Duration = T::Duration;
+    type Instant = T::Instant;
+    type TimerFut = T::TimerFut;
+
+    fn now(&self) -> Self::Instant {
+        (*self).now()
+    }
+
+    fn wait_until(&self, instant: Self::Instant) -> Self::TimerFut {
+        (*self).wait_until(instant)
+    }
+}
+
 pub trait InstantDriver: Add<Self::Duration, Output = Self> + Copy {
     type Duration: DurationDriver;
 
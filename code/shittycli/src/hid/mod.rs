@@ -1,7 +1,10 @@
 use core::future::Future;
 use hidapi::HidDevice;
-use shittyruntime::cofit::Transport;
-use std::sync::{mpsc, Arc};
+use shittyruntime::cofit::{Transport, TransportError};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    mpsc, Arc,
+};
 use tokio::sync::{
     broadcast::{self, error::RecvError},
     Mutex,
@@ -14,19 +17,24 @@ mod wrapper;
 pub struct UsbHidTransport {
     tx: mpsc::Sender<[u8; 64]>,
     rx: Mutex<broadcast::Receiver<[u8; 64]>>,
+    /// Packets handed to the writer thread that have not yet been written to the device
+    queued: Arc<AtomicUsize>,
 }
 
 impl UsbHidTransport {
     pub fn new(device: HidDevice) -> Self {
-        let (tx, rx) = Self::spawn_communication_thread(device);
+        let queued = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = Self::spawn_communication_thread(device, queued.clone());
         Self {
             tx,
             rx: Mutex::new(rx),
+            queued,
         }
     }
 
     fn spawn_communication_thread(
         device: HidDevice,
+        queued: Arc<AtomicUsize>,
     ) -> (mpsc::Sender<[u8; 64]>, broadcast::Receiver<[u8; 64]>) {
         let (hd_tx, hd_rx) = mpsc::channel::<[u8; 64]>();
         let (dh_tx, dh_rx) = broadcast::channel(256);
@@ -38,10 +46,15 @@ impl UsbHidTransport {
         let device_tx = Arc::new(HidDeviceWrapper::new(device));
         let device_rx = device_tx.clone();
 
+        // Both threads exit once the device is gone, dropping their end of the channel so the transport reports the disconnect
         std::thread::spawn(move || {
             while let Ok(packet) = hd_rx.recv() {
-                if let Err(e) = device_tx.write(&packet) {
+                let written = device_tx.write(&packet);
+                queued.fetch_sub(1, Ordering::Relaxed);
+
+                if let Err(e) = written {
                     eprintln!("failed to send packet to USB device {e:?}");
+                    break;
                 }
             }
 
@@ -52,8 +65,10 @@ impl UsbHidTransport {
             let mut buf = [0; 64];
             if let Err(e) = device_rx.read(&mut buf) {
                 eprintln!("failed to receive packet from USB device {e:?}");
-            } else {
-                dh_tx.send(buf).expect("failed to forward received packet");
+                break;
+            } else if dh_tx.send(buf).is_err() {
+                // Transport has been dropped, nobody is interested in the packets anymore
+                break;
             }
         });
 
@@ -62,19 +77,21 @@ impl UsbHidTransport {
 }
 
 impl Transport<64> for UsbHidTransport {
-    type TxFut<'t> = impl Future<Output = ()> + 't
+    type TxFut<'t> = impl Future<Output = Result<(), TransportError>> + 't
     where
         Self: 't;
 
-    type RxFut<'t> = impl Future<Output = [u8; 64]> + 't
+    type RxFut<'t> = impl Future<Output = Result<[u8; 64], TransportError>> + 't
     where
         Self: 't;
 
     fn send<'t>(&'t self, data: [u8; 64]) -> Self::TxFut<'t> {
         async move {
-            self.tx
-                .send(data)
-                .expect("failed to forward packet to send thread")
+            self.queued.fetch_add(1, Ordering::Relaxed);
+            self.tx.send(data).map_err(|_| {
+                self.queued.fetch_sub(1, Ordering::Relaxed);
+                TransportError::Disconnected
+            })
         }
     }
 
@@ -82,11 +99,15 @@ impl Transport<64> for UsbHidTransport {
         async move {
             loop {
                 match self.rx.lock().await.recv().await {
-                    Ok(packet) => return packet,
+                    Ok(packet) => return Ok(packet),
                     Err(RecvError::Lagged(_)) => continue,
-                    Err(RecvError::Closed) => panic!("channel to packet receiver thread dropped"),
+                    Err(RecvError::Closed) => return Err(TransportError::Disconnected),
                 }
             }
         }
     }
+
+    fn queued_frames(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
 }
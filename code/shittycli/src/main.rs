@@ -2,10 +2,11 @@
 #![feature(generic_associated_types)]
 
 use core::future::Future;
+use futures::TryStreamExt;
 use hid::UsbHidTransport;
 use hidapi::HidApi;
 use shittyruntime::{
-    cofit::{MessageAcknowledger, MessageHandler, Transport, UsbNetwork},
+    cofit::{MessageAcknowledger, MessageHandler, RetryPolicy, Role, Transport, UsbNetwork},
     firmware::{executor_support::Channel, Mpsc},
     messaging::{DataRange, Message, TestFormat},
 };
@@ -39,10 +40,17 @@ async fn main() {
     let network = UsbNetwork::new(
         transport,
         TestFormat,
+        Role::Host,
         ack_channel.split(),
         stream_channel.split(),
         message_channel.split(),
-    );
+    )
+    .with_retry_policy(RetryPolicy {
+        max_attempts: 3,
+        ack_timeout_ms: 2_000,
+        initial_backoff_ms: 100,
+        max_backoff_ms: 1_000,
+    });
 
     println!("opened network");
 
@@ -52,29 +60,33 @@ async fn main() {
     let send_fut = async {
         const read_size: usize = 4096 * 8;
 
-        let mut reader = network.create_stream_reader().await;
+        let reader = network.create_stream_reader().await.into_stream();
 
         network
-            .send(Message::ReadFlash(DataRange {
-                offset: 0,
-                length: read_size as u64,
-            }))
+            .send_cancellable(
+                Message::ReadFlash(DataRange {
+                    offset: 0,
+                    length: read_size as u64,
+                }),
+                tokio::signal::ctrl_c(),
+            )
             .await
-            .expect("failed to send message"); // TODO This times out (?)
+            .expect("failed to send message");
 
         println!("sent read request");
 
-        let mut buffer = Vec::with_capacity(read_size);
-        while let Some(data) = reader.recv().await {
-            buffer.extend_from_slice(&data);
-        }
+        let chunks: Vec<_> = reader
+            .try_collect()
+            .await
+            .expect("received corrupted flash data");
+        let buffer = chunks.concat();
 
         println!("received buffer! {}", buffer.len());
         dbg!(buffer);
     };
 
     select! {
-        _ = recv_fut => {},
+        error = recv_fut => eprintln!("lost connection to the device: {error:?}"),
         _ = send_fut => {},
     };
 }
@@ -89,7 +101,7 @@ impl<'d, T: Transport<64>> TestMessageHandler<'d, T> {
     }
 }
 
-impl<'d, T: Transport<64>, const MTU: usize> MessageHandler<Message, MTU>
+impl<'d, T: Transport<64>, const MTU: usize> MessageHandler<TestFormat, MTU>
     for TestMessageHandler<'d, T>
 where
     T: 'd,
@@ -101,7 +113,7 @@ where
     fn handle<'s>(
         &'s mut self,
         message: Message,
-        acknowledger: MessageAcknowledger<'s, MTU>,
+        acknowledger: MessageAcknowledger<'s, TestFormat, MTU>,
     ) -> Self::HandlerFut<'s> {
         async move {
             acknowledger.acknowledge().await;
@@ -109,6 +121,12 @@ where
                 Message::WriteFlash(_) => unimplemented!(),
                 Message::ReadFlash(_) => unimplemented!(),
                 Message::EraseFlash(_) => unimplemented!(),
+                Message::FlashError(range) => {
+                    println!(
+                        "Peripheral failed to access flash range {} + {}",
+                        range.offset, range.length
+                    )
+                }
             }
         }
     }
@@ -1,6 +1,6 @@
 use shittyengine::{
     compile::{BufferedSource, Compiler},
-    dict::RadixTreeDictionary,
+    dict::{FallbackDictionary, RadixTreeDictionary},
     formatter::Formatter,
     matcher::{CommitType, OutlineMatcher},
     output::{OSOutput, OutputProcessor},
@@ -20,6 +20,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Build all the needed structures
     let input = geminipr_stroke_iterator();
     let mut dict = RadixTreeDictionary::new(&mut dict_source).unwrap();
+    let fallback = FallbackDictionary::new();
     let mut matcher = OutlineMatcher::<Stroke, 32>::new(11);
     let mut formatter = Formatter::<32>::new();
     let mut output = OSOutput::new();
@@ -31,8 +32,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         matcher.add(stroke);
 
         while matcher.uncommitted_count() > 0 {
-            // 2. Search the dictionary for the uncommitted strokes and commit matching prefixes
-            let dict_match = dict.match_prefix(matcher.uncommitted_strokes()).unwrap();
+            // 2. Search the dictionary for the uncommitted strokes and commit matching prefixes,
+            //    writing out the first stroke if it is not part of any outline
+            let dict_match = dict
+                .match_prefix(matcher.uncommitted_strokes())
+                .unwrap()
+                .or_else(|| fallback.match_prefix(matcher.uncommitted_strokes()));
 
             // The following section can be externalised into a crate contained struct really well.
             // Take everything but the dictionary, stuff it into a struct. Add a method to call 
@@ -57,7 +62,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         Err(trailing_outline) => {
                             // Undo the trailing outline
                             for _ in 0..trailing_outline.outline().commands {
-                                if let Some(command) = formatter.undo() {
+                                if let Some(command) = formatter.revert() {
                                     output.apply(command);
                                 }
                             }
@@ -68,11 +73,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         }
                     }
                 }
-            } else {
-                // In a "real" engine implementation you would have a fallback dictionary that outputs the human readable representation
-                // TODO Write a default impl for such a fallback dictionary
-                println!("Omitting trailing strokes for now until more strokes are received");
-                continue;
             }
             // --------- SECTION END ---------
         }
@@ -7,7 +7,7 @@ use alloc::vec::Vec;
 use combine::{
     any, attempt, between, choice, eof,
     error::Commit,
-    many, many1, one_of, optional,
+    look_ahead, many, many1, one_of, optional,
     parser::{
         char::{char, spaces, string},
         function,
@@ -223,11 +223,16 @@ fn meta_operator<'s, Input>() -> impl Parser<Input, Output = CommandList>
 where
     Input: Stream<Token = char>,
 {
+    let hyphen_content = attempt(string("^-^").skip(look_ahead(char('}'))))
+        .map(|_| vec![vec![OwnedFormatterCommand::JoinWithHyphen].into()]);
     let glue_content = meta_operator_glue().map(|c| vec![c]);
     let any_content = many1(meta_operator_item());
     let no_content = produce(|| vec![vec![OwnedFormatterCommand::ResetFormatting].into()]);
 
-    let content = glue_content.or(any_content).or(no_content);
+    let content = hyphen_content
+        .or(glue_content)
+        .or(any_content)
+        .or(no_content);
 
     between(char('{'), char('}'), content).map(|command_lists: Vec<CommandList>| {
         command_lists
@@ -5,9 +5,13 @@ use core::future::Future;
 use crate::{
     dict::{DataSource, RadixTreeDictionary},
     formatter::FormatterCommand,
+    HEADER_FLAG_WORD_INDEX,
 };
 use alloc::{string::String, vec, vec::Vec};
 
+mod index;
+pub use index::WordIndex;
+
 mod json;
 
 mod tree;
@@ -16,50 +20,87 @@ pub use tree::*;
 mod translations;
 use translations::TranslationPointers;
 
+/// Optional sections the [`Compiler`] appends to the dictionary
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CompileOptions {
+    /// Appends a [`WordIndex`] after the tree, speeding up [reverse lookups](crate::dict::RadixTreeDictionary::reverse_lookup)
+    /// at the cost of roughly doubling the dictionary size. See [`WordIndex`] for details on the overhead.
+    pub word_index: bool,
+}
+
 pub struct Compiler;
 
 impl Compiler {
     /// Builds a compiled tree from a JSON dictionary. Returns the raw tree and the compiled version.
     /// Panics if the input data is invalid.
-    // TODO Propagate the errors instead of panicking
+    ///
+    /// Parsed entries are handed to the tree builder without collecting them first, see [`TreeNode::new`] for the memory
+    /// this still takes up. For verification, the input is parsed a second time instead of keeping a copy around.
     pub async fn compile_from_json(json: &str) -> (TreeNode, Vec<u8>) {
-        // 1. Parse the dictionary
-        let output = json::dict(json).unwrap();
-
-        // 2. Build an array of entries
-        let mut entries = Vec::new();
-        let mut longest_outline_len = 0;
-        for entry in output {
-            let (outline, translation) = entry.unwrap();
-            longest_outline_len = longest_outline_len.max(outline.len());
-            entries.push((outline, translation));
-        }
+        Self::compile_from_json_with_options(json, CompileOptions::default()).await
+    }
 
-        // 3. Create a tree data structure and allocate a buffer
-        let tree = TreeNode::new(entries.clone());
-        let mut buffer = vec![0, 0, 0, 0];
+    /// Like [`compile_from_json`](Self::compile_from_json), additionally emitting the sections enabled in the options
+    // TODO Propagate the errors instead of panicking
+    pub async fn compile_from_json_with_options(
+        json: &str,
+        options: CompileOptions,
+    ) -> (TreeNode, Vec<u8>) {
+        // 1. Parse the dictionary and stream its entries into a tree data structure
+        let entries = json::dict(json)
+            .unwrap()
+            .map(|entry| entry.expect("failed to parse dictionary entry"));
+        let tree = TreeNode::new(entries);
+
+        // 2. Allocate a buffer, the header is followed by the word index offset if there is one
+        let mut buffer = if options.word_index {
+            vec![0; 8]
+        } else {
+            vec![0; 4]
+        };
 
-        // 4. Serialize the translations into the buffer and build a list of pointers
+        // 3. Serialize the translations into the buffer and build a list of pointers
         let translations =
             TranslationPointers::new_by_serializing_into(&mut buffer, tree.command_lists());
 
-        // 5. Inject the starting location of the tree into the buffer
+        // 4. Inject the starting location of the tree into the buffer, the most significant byte holds flags
         let tree_offset_bytes = (buffer.len() as u32).to_be_bytes();
-        buffer[0] = tree_offset_bytes[0];
+        assert_eq!(
+            tree_offset_bytes[0], 0,
+            "translations exceed 24-bit address space"
+        );
         buffer[1] = tree_offset_bytes[1];
         buffer[2] = tree_offset_bytes[2];
         buffer[3] = tree_offset_bytes[3];
 
-        // 6. Serialize the tree into the buffer
+        // 5. Serialize the tree into the buffer
         tree.serialize_into_buffer(&mut buffer, &translations);
 
+        // 6. Append the word index, parsing the input once more so the tree does not have to keep the words around
+        let word_index = if options.word_index {
+            let entries = json::dict(json)
+                .unwrap()
+                .map(|entry| entry.expect("failed to parse dictionary entry"));
+            let word_index = WordIndex::new(entries);
+
+            let index_offset_bytes = (buffer.len() as u32).to_be_bytes();
+            buffer[0] |= HEADER_FLAG_WORD_INDEX;
+            buffer[4..8].copy_from_slice(&index_offset_bytes);
+
+            word_index.serialize_into_buffer(&mut buffer);
+            Some(word_index)
+        } else {
+            None
+        };
+
         // 7. Verify that all the entries are readable and return the correct translation
         let mut source = BufferedSource::new(&buffer);
         let mut dict = RadixTreeDictionary::new(&mut source)
             .await
             .expect("failed to construct dictionary from compiled buffer");
 
-        for (outline, commands) in entries {
+        for entry in json::dict(json).unwrap() {
+            let (outline, commands) = entry.unwrap();
             let outline_len = outline.len();
             let match_err = alloc::format!("outline {outline} not found in compiled dictionary");
 
@@ -74,6 +115,16 @@ impl Compiler {
             assert_eq!(commands.0, translation_vec);
         }
 
+        // 8. Verify that every indexed word can be found again
+        for word in word_index.iter().flat_map(WordIndex::words) {
+            let outline = dict
+                .reverse_lookup::<{ u8::MAX as usize }>(word)
+                .await
+                .expect("unexpected buffer read error while verifying word index");
+
+            assert!(outline.is_some(), "word {word} not found in word index");
+        }
+
         (tree, buffer)
     }
 }
@@ -117,8 +168,59 @@ impl PartialEq<FormatterCommand<&str>> for FormatterCommand<String> {
             ChangeCapitalization(c) => ChangeCapitalization(*c),
             ChangeAttachment(a) => ChangeAttachment(*a),
             ResetFormatting => ResetFormatting,
+            JoinWithHyphen => JoinWithHyphen,
         };
 
         self_ref.eq(other)
     }
 }
+
+#[cfg(test)]
+mod does {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn find_entries_among_many_same_length_prefixes() {
+        // Every combination of left hand keys, once on its own and once followed by a second stroke
+        let entries = (1..128u8)
+            .flat_map(|mask| {
+                let left = "STKPWHR"
+                    .chars()
+                    .enumerate()
+                    .filter(|(i, _)| mask & (1 << i) != 0)
+                    .map(|(_, key)| key)
+                    .collect::<String>();
+
+                [
+                    alloc::format!("\"{left}-Z\": \"word{mask}\""),
+                    alloc::format!("\"{left}-Z/-T\": \"other{mask}\""),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let json = alloc::format!("{{{}}}", entries.join(",\n"));
+
+        // Compiling verifies that every entry can be looked up again
+        let (tree, buffer) = block_on(Compiler::compile_from_json(&json));
+        assert!(tree.children.len() > 64);
+
+        let mut source = BufferedSource::new(&buffer);
+        let mut dict = block_on(RadixTreeDictionary::new(&mut source)).unwrap();
+
+        let outline = [crate::Stroke::from(0b01100000_00000_0000000001_0)];
+        let (prefix_length, translation) = block_on(dict.match_prefix(outline.iter()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(prefix_length, 1);
+        assert_eq!(
+            translation.iter().next(),
+            Some(FormatterCommand::Write("word3"))
+        );
+
+        // Outlines starting with an unknown stroke do not match anything
+        let unknown = [crate::Stroke::from(0b00000000_10000_0000000000_0)];
+        assert!(block_on(dict.match_prefix(unknown.iter()))
+            .unwrap()
+            .is_none());
+    }
+}
@@ -86,6 +86,7 @@ impl<S: AsRef<str> + Display> FormatterCommand<S> {
             ChangeAttachment(Always) => 0b10_11_0000,
 
             ResetFormatting => 0b110_00000,
+            JoinWithHyphen => 0b111_00000,
         });
 
         if let Some(string_data) = string_data {
@@ -4,11 +4,12 @@ use super::json::{CommandList, Outline};
 use super::TranslationPointers;
 use crate::PREFIX_ARRAY_SIZE_LIMIT;
 use alloc::collections::{BTreeMap, BTreeSet};
-use alloc::{vec, vec::Vec};
+use alloc::vec::Vec;
 
 /// Node in a radix tree containing its children and optionally some leaf data
 #[derive(Debug)]
 pub struct TreeNode {
+    /// Children sorted by their prefix, allowing lookups to bisect the serialized prefix array
     pub children: Vec<(Vec<u8>, Child)>,
     pub leaf_data: Option<CommandList>,
     pub prefix_length: usize,
@@ -22,8 +23,12 @@ pub enum Child {
 }
 
 impl TreeNode {
-    /// Recursively constructs a radix tree from the given dictionary entries
-    pub fn new(entries: Vec<(Outline, CommandList)>) -> Self {
+    /// Recursively constructs a radix tree from the given dictionary entries.
+    ///
+    /// Entries are converted into their raw key bytes while being consumed, so they can be passed directly from a parser
+    /// without holding the parsed outlines in memory. This is not a streaming build though: The prefix length of each node
+    /// depends on all keys below it, so the converted entries are collected before the tree is built from them.
+    pub fn new(entries: impl IntoIterator<Item = (Outline, CommandList)>) -> Self {
         let raw_entries = entries
             .into_iter()
             .map(|(o, t)| (o.into_bytes().collect(), t))
@@ -108,6 +113,11 @@ impl TreeNode {
             buffer.extend([0, 0, 0]);
         }
 
+        assert!(
+            self.children.windows(2).all(|pair| pair[0].0 < pair[1].0),
+            "encountered node with unsorted or duplicate child prefixes"
+        );
+
         // Build the prefix/key array
         for (prefix, _) in &self.children {
             assert_eq!(
@@ -174,12 +184,12 @@ fn build_tree(entries: Vec<(Vec<u8>, CommandList)>) -> TreeNode {
 
             prefix_map
                 .entry(prefix)
-                .and_modify(|e| e.push((remainder.clone(), value.clone())))
-                .or_insert(vec![(remainder, value)]);
+                .or_default()
+                .push((remainder, value));
         }
     }
 
-    // Build child nodes from the groups
+    // Build child nodes from the groups, the map yields them sorted by prefix
     let children = prefix_map
         .into_iter()
         .map(|(prefix, entries)| {
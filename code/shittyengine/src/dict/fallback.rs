@@ -0,0 +1,100 @@
+use super::TranslationBuffer;
+use crate::Stroke;
+use arrayvec::ArrayString;
+use core::fmt::Write;
+
+/// Length of the longest human-readable stroke, which has every key pressed (`#STKPWHRAO*EUFRPBLGTSDZ`)
+const STROKE_TEXT_LIMIT: usize = 23;
+
+/// Dictionary which translates any stroke into its human-readable representation (e.g. `KPA*`).
+///
+/// It is meant to be consulted only after the main dictionary yielded no match for the uncommitted strokes,
+/// so that unknown strokes and misstrokes show up in the output instead of being dropped:
+///
+/// ```ignore
+/// let dict_match = dict
+///     .match_prefix(matcher.uncommitted_strokes())
+///     .await?
+///     .or_else(|| fallback.match_prefix(matcher.uncommitted_strokes()));
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FallbackDictionary;
+
+impl FallbackDictionary {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Matches the first of the given strokes, returning a prefix length of one and a translation writing the stroke.
+    /// Returns `None` only if there are no strokes.
+    pub fn match_prefix<'s>(
+        &self,
+        mut strokes: impl Iterator<Item = &'s Stroke>,
+    ) -> Option<(usize, TranslationBuffer)> {
+        strokes
+            .next()
+            .map(|stroke| (1, self.fallback_translation(stroke)))
+    }
+
+    /// Translation writing the human-readable representation of the stroke
+    pub fn fallback_translation(&self, stroke: &Stroke) -> TranslationBuffer {
+        let mut text = ArrayString::<STROKE_TEXT_LIMIT>::new();
+        write!(text, "{}", stroke).expect("stroke exceeds the human-readable length limit");
+        TranslationBuffer::from_text(&text)
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+    use crate::formatter::FormatterCommand;
+
+    #[test]
+    fn write_stroke() {
+        //                           #STKPWHR AO*EU FRPBLGTSDZ
+        let stroke = Stroke::from(0b00000010_00000_0000100000_0);
+        let (prefix_length, translation) = FallbackDictionary::new()
+            .match_prefix([stroke].iter())
+            .unwrap();
+
+        assert_eq!(prefix_length, 1);
+        assert_eq!(translation.len(), 1);
+
+        let mut commands = translation.iter();
+        assert_eq!(commands.next(), Some(FormatterCommand::Write("H-L")));
+        assert_eq!(commands.next(), None);
+    }
+
+    #[test]
+    fn match_first_stroke_only() {
+        let strokes = [
+            Stroke::from(0b00000000_10000_0000000000_0),
+            Stroke::from(0b00000000_01000_0000000000_0),
+        ];
+        let (prefix_length, translation) = FallbackDictionary::new()
+            .match_prefix(strokes.iter())
+            .unwrap();
+
+        assert_eq!(prefix_length, 1);
+        assert_eq!(
+            translation.iter().next(),
+            Some(FormatterCommand::Write("A"))
+        );
+    }
+
+    #[test]
+    fn write_longest_stroke() {
+        let stroke = Stroke::from(0b11111111_11111_1111111111_0);
+        let translation = FallbackDictionary::new().fallback_translation(&stroke);
+
+        assert_eq!(
+            translation.iter().next(),
+            Some(FormatterCommand::Write("#STKPWHRAO*EUFRPBLGTSDZ"))
+        );
+    }
+
+    #[test]
+    fn yield_nothing_without_strokes() {
+        assert!(FallbackDictionary::new().match_prefix([].iter()).is_none());
+    }
+}
@@ -9,7 +9,9 @@
 // #[cfg(feature = "alloc")]
 // pub use inmemory::InMemoryDictionary;
 
+mod fallback;
 mod tree;
+pub use fallback::*;
 pub use tree::*;
 
 // #[derive(Debug, PartialEq, Eq)]
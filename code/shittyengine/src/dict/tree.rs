@@ -1,8 +1,19 @@
+use core::cmp::Ordering;
 use core::future::Future;
 
 use crate::formatter::{AttachmentMode, CapitalizationMode, FormatterCommand};
 use crate::Stroke;
-use crate::{NODE_HEADER_SIZE, PREFIX_ARRAY_SIZE_LIMIT, TRANSLATION_SIZE_LIMIT};
+use crate::{
+    HEADER_FLAG_WORD_INDEX, NODE_HEADER_SIZE, PREFIX_ARRAY_SIZE_LIMIT, TRANSLATION_SIZE_LIMIT,
+};
+
+#[cfg(feature = "alloc")]
+mod cursor;
+mod reverse;
+mod stats;
+#[cfg(feature = "alloc")]
+pub use cursor::{TreeChild, TreeCursor, TreeCursorError};
+pub use stats::{LayerStats, TreeStats, TreeStatsError};
 
 pub trait DataSource {
     type Error;
@@ -15,18 +26,33 @@ pub trait DataSource {
 
 pub struct RadixTreeDictionary<D: DataSource> {
     tree_start: u32,
+    /// Location of the word index, if the dictionary has been compiled with one
+    word_index: Option<u32>,
     source: D,
 }
 
 impl<D: DataSource> RadixTreeDictionary<D> {
     pub async fn new(mut source: D) -> Result<Self, D::Error> {
-        let mut tree_start_bytes = [0; 4];
-        source.read_exact(0, &mut tree_start_bytes).await?;
+        let mut header = [0; 4];
+        source.read_exact(0, &mut header).await?;
 
         // TODO Somehow verify that we are looking at a valid dictionary, magic number(s) at the start?
 
+        // The most significant byte is unused by the 24-bit tree location and holds flags for optional sections
+        let flags = header[0];
+        let tree_start = u32::from_be_bytes([0, header[1], header[2], header[3]]);
+
+        let word_index = if flags & HEADER_FLAG_WORD_INDEX != 0 {
+            let mut word_index_bytes = [0; 4];
+            source.read_exact(4, &mut word_index_bytes).await?;
+            Some(u32::from_be_bytes(word_index_bytes))
+        } else {
+            None
+        };
+
         Ok(Self {
-            tree_start: u32::from_be_bytes(tree_start_bytes),
+            tree_start,
+            word_index,
             source,
         })
     }
@@ -42,13 +68,18 @@ impl<D: DataSource> RadixTreeDictionary<D> {
     /// Reads the node's pointer to the given child
     async fn read_child_pointer(
         &mut self,
-        node: Node,
+        node: &Node,
         child_index: usize,
     ) -> Result<ChildPointer, D::Error> {
         assert!(child_index < node.child_count());
 
-        let mut buffer = [0; 3];
         let location = node.pointer_array_location() + (child_index as u32) * 3;
+        self.read_pointer_at(location).await
+    }
+
+    /// Reads a child pointer from the given location within a node's pointer array
+    async fn read_pointer_at(&mut self, location: u32) -> Result<ChildPointer, D::Error> {
+        let mut buffer = [0; 3];
         self.source.read_exact(location, &mut buffer).await?;
 
         let child_pointer = u32::from_be_bytes([0, buffer[0], buffer[1], buffer[2]]);
@@ -107,7 +138,7 @@ impl<D: DataSource> RadixTreeDictionary<D> {
                     }
 
                     // Read and check what kind of child node we have
-                    match self.read_child_pointer(node, child_index).await? {
+                    match self.read_child_pointer(&node, child_index).await? {
                         ChildPointer::Node(child_node_pointer) => {
                             // Descend further down the tree
                             location = child_node_pointer;
@@ -168,6 +199,22 @@ impl<D: DataSource> RadixTreeDictionary<D> {
 pub struct TranslationBuffer([u8; TRANSLATION_SIZE_LIMIT]);
 
 impl TranslationBuffer {
+    /// Builds a translation consisting of a single command writing the given text
+    pub(super) fn from_text(text: &str) -> Self {
+        // Write commands store their length in the lower six bits of the header byte
+        assert!(
+            text.len() < 0b01_000000,
+            "text too long for a single write command"
+        );
+
+        let mut buffer = [0; TRANSLATION_SIZE_LIMIT];
+        buffer[0] = text.len() as u8;
+        buffer[1..1 + text.len()].copy_from_slice(text.as_bytes());
+        buffer[1 + text.len()] = 0xFF;
+
+        Self(buffer)
+    }
+
     pub fn len(&self) -> usize {
         self.iter().count()
     }
@@ -219,6 +266,7 @@ impl TranslationBuffer {
                 0b10_11_0000 => ChangeAttachment(Always),
 
                 0b110_00000 => ResetFormatting,
+                0b111_00000 => JoinWithHyphen,
 
                 0xFF => return None,
 
@@ -282,19 +330,29 @@ impl Node {
 
     /// Locates a child matching the given prefix. Returns the index of the child matching the given prefix.
     /// Truncates the prefix if it is longer than the nodes prefix length and returns `None` if it is shorter.
+    ///
+    /// Relies on the compiler storing the children sorted by prefix to bisect the prefix array.
     fn find_child(&self, prefix: impl Iterator<Item = u8> + Clone) -> Option<usize> {
-        let prefix = prefix.take(self.prefix_length());
-
-        self.prefix_array()
-            .chunks_exact(self.prefix_length())
-            .enumerate()
-            .find_map(|(i, p)| {
-                if prefix.clone().eq(p.into_iter().cloned()) {
-                    Some(i)
-                } else {
-                    None
-                }
-            })
+        let prefix_length = self.prefix_length();
+        let prefix = prefix.take(prefix_length);
+        let prefix_array = self.prefix_array();
+
+        // The prefixes are fixed-width entries within a flat byte array, so bisect by child index
+        let mut low = 0;
+        let mut high = self.child_count();
+
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let candidate = &prefix_array[middle * prefix_length..][..prefix_length];
+
+            match candidate.iter().cloned().cmp(prefix.clone()) {
+                Ordering::Less => low = middle + 1,
+                Ordering::Greater => high = middle,
+                Ordering::Equal => return Some(middle),
+            }
+        }
+
+        None
     }
 }
 
@@ -303,3 +361,69 @@ impl From<(u32, [u8; PREFIX_ARRAY_SIZE_LIMIT + NODE_HEADER_SIZE])> for Node {
         Node { location, buffer }
     }
 }
+
+#[cfg(test)]
+mod does {
+    use super::*;
+
+    /// Builds a node with three byte prefixes, which stem from the given child indices
+    fn node_with_children(children: impl Iterator<Item = u8>) -> Node {
+        let mut buffer = [0; PREFIX_ARRAY_SIZE_LIMIT + NODE_HEADER_SIZE];
+        let mut child_count = 0;
+
+        for (i, child) in children.enumerate() {
+            buffer[NODE_HEADER_SIZE + i * 3..][..3].copy_from_slice(&[child / 16, child, 0xAA]);
+            child_count += 1;
+        }
+
+        buffer[0] = child_count - 1;
+        buffer[1] = 3;
+
+        Node::from((0, buffer))
+    }
+
+    #[test]
+    fn find_each_of_many_children() {
+        // Only every other prefix exists to make sure that the gaps are not matched
+        let node = node_with_children((0..80).map(|i| i * 2));
+
+        for i in 0..80 {
+            let child = i * 2;
+            assert_eq!(
+                node.find_child([child / 16, child, 0xAA].into_iter()),
+                Some(i as usize)
+            );
+        }
+
+        for i in 0..80 {
+            let child = i * 2 + 1;
+            assert_eq!(node.find_child([child / 16, child, 0xAA].into_iter()), None);
+        }
+    }
+
+    #[test]
+    fn find_child_with_longer_prefix() {
+        let node = node_with_children(0..80);
+        assert_eq!(node.find_child([0, 4, 0xAA, 1, 2, 3].into_iter()), Some(4));
+    }
+
+    #[test]
+    fn reject_shorter_prefix() {
+        let node = node_with_children(0..80);
+        assert_eq!(node.find_child([0, 4].into_iter()), None);
+    }
+
+    #[test]
+    fn reject_prefix_outside_of_range() {
+        let node = node_with_children(10..20);
+        assert_eq!(node.find_child([0, 0, 0xAA].into_iter()), None);
+        assert_eq!(node.find_child([0xFF, 0xFF, 0xFF].into_iter()), None);
+    }
+
+    #[test]
+    fn find_only_child() {
+        let node = node_with_children(5..6);
+        assert_eq!(node.find_child([0, 5, 0xAA].into_iter()), Some(0));
+        assert_eq!(node.find_child([0, 6, 0xAA].into_iter()), None);
+    }
+}
@@ -0,0 +1,303 @@
+//! Read-only traversal of a serialized tree, e.g. for visualization or decompilation tools
+
+use super::{ChildPointer, DataSource, Node, RadixTreeDictionary, TranslationBuffer};
+use alloc::boxed::Box;
+use arrayvec::ArrayVec;
+
+/// Maximum number of nodes on the path from the root the cursor can descend into
+const CURSOR_DEPTH_LIMIT: usize = 64;
+/// Maximum number of outline bytes on the path from the root the cursor can descend into
+const CURSOR_PATH_LIMIT: usize = 255;
+
+/// Child of the node a [`TreeCursor`] is positioned at
+pub enum TreeChild {
+    /// Sub-tree node which the cursor can [`descend`](TreeCursor::descend) into
+    Node,
+    /// Translation of an outline which is not continued by any longer outlines.
+    /// Boxed as the translation is a lot larger than the node variant.
+    Leaf(Box<TranslationBuffer>),
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TreeCursorError<E> {
+    /// Reading from the underlying data source failed
+    Source(E),
+    /// The child is a leaf and holds no node to descend into
+    Leaf,
+    /// The path from the root exceeds the limits of the cursor, which usually indicates a corrupted dictionary
+    TooDeep,
+}
+
+/// Parent of the current node, visited again when ascending
+struct Frame {
+    location: u32,
+    /// Length of the outline leading up to the parent
+    path_length: usize,
+}
+
+/// Cursor over the nodes of a serialized [`RadixTreeDictionary`], reading them directly from its [`DataSource`].
+///
+/// Starts at the root node and moves along child pointers, keeping track of the outline bytes on the way.
+/// Nothing is cached besides the current node, so walking the whole tree reads every node at least once.
+pub struct TreeCursor<'d, D: DataSource> {
+    dictionary: &'d mut RadixTreeDictionary<D>,
+    node: Node,
+    parents: ArrayVec<Frame, CURSOR_DEPTH_LIMIT>,
+    path: ArrayVec<u8, CURSOR_PATH_LIMIT>,
+}
+
+impl<D: DataSource> RadixTreeDictionary<D> {
+    /// Creates a [`TreeCursor`] positioned at the root node of the tree
+    pub async fn cursor(&mut self) -> Result<TreeCursor<'_, D>, D::Error> {
+        let node = self.read_node_at(self.tree_start).await?;
+
+        Ok(TreeCursor {
+            dictionary: self,
+            node,
+            parents: ArrayVec::new(),
+            path: ArrayVec::new(),
+        })
+    }
+}
+
+impl<'d, D: DataSource> TreeCursor<'d, D> {
+    /// Location of the current node within the data source
+    pub fn location(&self) -> u32 {
+        self.node.location
+    }
+
+    /// Number of nodes between the root and the current node, the root node is located at depth zero
+    pub fn depth(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Outline bytes leading up to the current node. Each stroke takes three bytes, though
+    /// the prefix may end in the middle of a stroke for nodes which do not carry a translation.
+    pub fn prefix(&self) -> &[u8] {
+        &self.path
+    }
+
+    /// Prefixes of the children of the current node, in the order they are stored in.
+    /// The position of a prefix is the index to pass into [`child`](Self::child) and [`descend`](Self::descend).
+    pub fn children(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.node
+            .prefix_array()
+            .chunks_exact(self.node.prefix_length())
+    }
+
+    /// Number of children of the current node
+    pub fn child_count(&self) -> usize {
+        self.node.child_count()
+    }
+
+    /// Reads the translation of the current node, if the outline leading up to it has one
+    pub async fn leaf_data(&mut self) -> Result<Option<TranslationBuffer>, D::Error> {
+        match self.node.translation_pointer() {
+            Some(pointer) => Ok(Some(self.dictionary.read_translation(pointer).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the child with the given index, loading its translation if it is a leaf
+    pub async fn child(&mut self, index: usize) -> Result<TreeChild, D::Error> {
+        match self
+            .dictionary
+            .read_child_pointer(&self.node, index)
+            .await?
+        {
+            ChildPointer::Node(_) => Ok(TreeChild::Node),
+            ChildPointer::Translation(pointer) => Ok(TreeChild::Leaf(Box::new(
+                self.dictionary.read_translation(pointer).await?,
+            ))),
+        }
+    }
+
+    /// Moves the cursor to the child with the given index, which has to be a [`Node`](TreeChild::Node).
+    /// The cursor remains at the current node if an error is returned.
+    pub async fn descend(&mut self, index: usize) -> Result<(), TreeCursorError<D::Error>> {
+        let location = match self
+            .dictionary
+            .read_child_pointer(&self.node, index)
+            .await
+            .map_err(TreeCursorError::Source)?
+        {
+            ChildPointer::Node(location) => location,
+            ChildPointer::Translation(_) => return Err(TreeCursorError::Leaf),
+        };
+
+        let prefix_length = self.node.prefix_length();
+        let prefix = &self.node.prefix_array()[index * prefix_length..][..prefix_length];
+
+        if self.parents.is_full() || self.path.remaining_capacity() < prefix_length {
+            return Err(TreeCursorError::TooDeep);
+        }
+
+        let node = self
+            .dictionary
+            .read_node_at(location)
+            .await
+            .map_err(TreeCursorError::Source)?;
+
+        self.parents.push(Frame {
+            location: self.node.location,
+            path_length: self.path.len(),
+        });
+        self.path.extend(prefix.iter().copied());
+        self.node = node;
+
+        Ok(())
+    }
+
+    /// Moves the cursor back to the parent of the current node. Returns `false` if it is already positioned at the root.
+    pub async fn ascend(&mut self) -> Result<bool, D::Error> {
+        let frame = match self.parents.last() {
+            Some(frame) => frame,
+            None => return Ok(false),
+        };
+
+        self.node = self.dictionary.read_node_at(frame.location).await?;
+        self.path.truncate(frame.path_length);
+        self.parents.pop();
+
+        Ok(true)
+    }
+}
+
+#[cfg(all(test, feature = "compile"))]
+mod does {
+    use super::*;
+    use crate::compile::{BufferedSource, Compiler};
+    use crate::formatter::FormatterCommand;
+    use crate::Stroke;
+    use alloc::{string::String, vec, vec::Vec};
+    use futures::executor::block_on;
+
+    /// Formats the outline bytes and text written by a translation like the dictionary source
+    fn entry(outline: &[u8], translation: &TranslationBuffer) -> (String, String) {
+        let outline = outline
+            .chunks_exact(3)
+            .map(|stroke| {
+                alloc::format!("{}", Stroke::from_bytes([stroke[0], stroke[1], stroke[2]]))
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let text = translation
+            .iter()
+            .filter_map(|command| match command {
+                FormatterCommand::Write(text) => Some(text),
+                _ => None,
+            })
+            .collect();
+
+        (outline, text)
+    }
+
+    /// Walks the whole tree depth-first and collects the entries of all nodes and leaves
+    async fn collect_entries<D: DataSource>(
+        cursor: &mut TreeCursor<'_, D>,
+    ) -> Result<Vec<(String, String)>, TreeCursorError<D::Error>> {
+        let mut entries = Vec::new();
+        // Index of the next child to visit for the current node and each of its parents
+        let mut next_children = vec![0];
+
+        loop {
+            let index = *next_children.last().unwrap();
+
+            if index == cursor.child_count() {
+                next_children.pop();
+
+                if cursor.ascend().await.map_err(TreeCursorError::Source)? {
+                    continue;
+                } else {
+                    break;
+                }
+            }
+
+            *next_children.last_mut().unwrap() += 1;
+
+            match cursor.child(index).await.map_err(TreeCursorError::Source)? {
+                TreeChild::Leaf(translation) => {
+                    let prefix = cursor.children().nth(index).unwrap();
+                    let outline = [cursor.prefix(), prefix].concat();
+                    entries.push(entry(&outline, &translation));
+                }
+                TreeChild::Node => {
+                    cursor.descend(index).await?;
+                    next_children.push(0);
+
+                    if let Some(translation) =
+                        cursor.leaf_data().await.map_err(TreeCursorError::Source)?
+                    {
+                        entries.push(entry(cursor.prefix(), &translation));
+                    }
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    #[test]
+    fn visit_every_entry() {
+        let (_, buffer) = block_on(Compiler::compile_from_json(
+            r#"{
+                "A": "apple",
+                "A/PW": "apple bee",
+                "A/PW/-Z": "apple bee zebra",
+                "PW-R": "bar",
+                "PW/A": "bar",
+                "-Z": "zebra"
+            }"#,
+        ));
+
+        let mut source = BufferedSource::new(&buffer);
+        let mut dict = block_on(RadixTreeDictionary::new(&mut source)).unwrap();
+        let mut cursor = block_on(dict.cursor()).unwrap();
+
+        let mut entries = block_on(collect_entries(&mut cursor)).unwrap();
+        entries.sort();
+
+        let expected = [
+            ("-Z", "zebra"),
+            ("A", "apple"),
+            ("A/PW-", "apple bee"),
+            ("A/PW-/-Z", "apple bee zebra"),
+            ("PW-/A", "bar"),
+            ("PW-R", "bar"),
+        ];
+        let mut expected = expected
+            .iter()
+            .map(|(outline, text)| (String::from(*outline), String::from(*text)))
+            .collect::<Vec<_>>();
+        expected.sort();
+
+        assert_eq!(entries, expected);
+
+        // The traversal ends back at the root
+        assert_eq!(cursor.depth(), 0);
+        assert!(cursor.prefix().is_empty());
+    }
+
+    #[test]
+    fn refuse_to_descend_into_leaves() {
+        let (_, buffer) = block_on(Compiler::compile_from_json(r#"{"-Z": "zebra"}"#));
+
+        let mut source = BufferedSource::new(&buffer);
+        let mut dict = block_on(RadixTreeDictionary::new(&mut source)).unwrap();
+        let mut cursor = block_on(dict.cursor()).unwrap();
+
+        // The outline may be split across multiple nodes, follow them down to the leaf
+        while let TreeChild::Node = block_on(cursor.child(0)).unwrap() {
+            block_on(cursor.descend(0)).unwrap();
+        }
+
+        let depth = cursor.depth();
+        assert!(matches!(
+            block_on(cursor.descend(0)),
+            Err(TreeCursorError::Leaf)
+        ));
+        assert_eq!(cursor.depth(), depth);
+    }
+}
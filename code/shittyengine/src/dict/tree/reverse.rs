@@ -0,0 +1,351 @@
+//! Reverse lookup from a written word to an outline producing it
+
+use super::{ChildPointer, DataSource, RadixTreeDictionary, TranslationBuffer};
+use crate::formatter::FormatterCommand;
+use crate::{Stroke, INDEX_WORD_LENGTH_LIMIT, NODE_HEADER_SIZE, PREFIX_ARRAY_SIZE_LIMIT};
+use arrayvec::ArrayVec;
+
+/// Maximum number of nodes on the path from the root while scanning the tree, deeper sub-trees are skipped
+const SCAN_DEPTH_LIMIT: usize = 64;
+/// Maximum number of outline bytes on the path from the root while scanning the tree
+const SCAN_PATH_LIMIT: usize = 255;
+
+/// Node whose children are currently being scanned
+struct Frame {
+    location: u32,
+    pointer_array_location: u32,
+    prefix_length: usize,
+    child_count: usize,
+    next_child: usize,
+    /// Length of the outline leading up to this node
+    path_length: usize,
+}
+
+impl<D: DataSource> RadixTreeDictionary<D> {
+    /// Whether the dictionary has been compiled with a word index, see [`CompileOptions`](crate::compile::CompileOptions)
+    pub fn has_word_index(&self) -> bool {
+        self.word_index.is_some()
+    }
+
+    /// Finds the shortest outline whose translation writes exactly the given word, ignoring any formatting commands.
+    /// Outlines with more than `STROKES` strokes are not considered.
+    ///
+    /// Uses the word index if the dictionary contains one, otherwise every node and translation is read
+    /// which takes considerably longer on slow storage.
+    pub async fn reverse_lookup<const STROKES: usize>(
+        &mut self,
+        word: &str,
+    ) -> Result<Option<ArrayVec<Stroke, STROKES>>, D::Error> {
+        if word.is_empty() {
+            return Ok(None);
+        }
+
+        match self.word_index {
+            Some(location) if word.len() <= INDEX_WORD_LENGTH_LIMIT => {
+                self.search_word_index(location, word).await
+            }
+            _ => self.scan_for_word(word).await,
+        }
+    }
+
+    /// Binary searches the sorted word index
+    async fn search_word_index<const STROKES: usize>(
+        &mut self,
+        location: u32,
+        word: &str,
+    ) -> Result<Option<ArrayVec<Stroke, STROKES>>, D::Error> {
+        let mut count_bytes = [0; 4];
+        self.source.read_exact(location, &mut count_bytes).await?;
+        let count = u32::from_be_bytes(count_bytes);
+
+        // Locate the first entry which is not smaller than the word
+        let (mut low, mut high) = (0, count);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let (entry_word, _) = self.read_index_entry(location, middle).await?;
+
+            if entry_word.as_slice() < word.as_bytes() {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+
+        // Entries of the same word are sorted by outline length, so the first one that fits is the shortest
+        for position in low..count {
+            let (entry_word, outline_location) = self.read_index_entry(location, position).await?;
+
+            if entry_word.as_slice() != word.as_bytes() {
+                break;
+            }
+
+            let mut stroke_count = [0; 1];
+            self.source
+                .read_exact(outline_location, &mut stroke_count)
+                .await?;
+
+            let stroke_count = stroke_count[0] as usize;
+            if stroke_count > STROKES {
+                continue;
+            }
+
+            let mut outline = ArrayVec::new();
+            for i in 0..stroke_count {
+                let mut stroke = [0; 3];
+                let stroke_location = outline_location + 1 + (i as u32) * 3;
+                self.source.read_exact(stroke_location, &mut stroke).await?;
+                outline.push(Stroke::from_bytes(stroke));
+            }
+
+            return Ok(Some(outline));
+        }
+
+        Ok(None)
+    }
+
+    /// Reads the word of an entry in the word index and returns it together with the location of its outline
+    async fn read_index_entry(
+        &mut self,
+        location: u32,
+        position: u32,
+    ) -> Result<(ArrayVec<u8, INDEX_WORD_LENGTH_LIMIT>, u32), D::Error> {
+        let mut offset = [0; 4];
+        let offset_location = location + 4 + position * 4;
+        self.source.read_exact(offset_location, &mut offset).await?;
+        let entry_location = u32::from_be_bytes(offset);
+
+        // Only read the bytes belonging to the entry, as the last one ends right at the end of the data
+        let mut word_length = [0; 1];
+        self.source
+            .read_exact(entry_location, &mut word_length)
+            .await?;
+
+        let word_length = (word_length[0] as usize).min(INDEX_WORD_LENGTH_LIMIT);
+        let mut buffer = [0; INDEX_WORD_LENGTH_LIMIT];
+        self.source
+            .read_exact(entry_location + 1, &mut buffer[..word_length])
+            .await?;
+
+        let word = buffer[..word_length].iter().copied().collect();
+
+        Ok((word, entry_location + 1 + word_length as u32))
+    }
+
+    /// Walks the whole tree in search of translations writing the word
+    async fn scan_for_word<const STROKES: usize>(
+        &mut self,
+        word: &str,
+    ) -> Result<Option<ArrayVec<Stroke, STROKES>>, D::Error> {
+        let mut best = None;
+        let mut path = ArrayVec::<u8, SCAN_PATH_LIMIT>::new();
+        let mut stack = ArrayVec::<Frame, SCAN_DEPTH_LIMIT>::new();
+        let mut next_node = Some(self.tree_start);
+
+        loop {
+            if let Some(location) = next_node.take() {
+                let node = self.read_node_at(location).await?;
+
+                if let Some(translation) = node.translation_pointer() {
+                    self.consider_translation(translation, &path, word, &mut best)
+                        .await?;
+                }
+
+                // Sub-trees beyond the depth limit are skipped, continuing with the next sibling
+                let _ = stack.try_push(Frame {
+                    location,
+                    pointer_array_location: node.pointer_array_location(),
+                    prefix_length: node.prefix_length(),
+                    child_count: node.child_count(),
+                    next_child: 0,
+                    path_length: path.len(),
+                });
+            }
+
+            let frame = match stack.last_mut() {
+                Some(frame) => frame,
+                None => break,
+            };
+
+            if frame.next_child == frame.child_count {
+                stack.pop();
+                continue;
+            }
+
+            let child_index = frame.next_child;
+            frame.next_child += 1;
+
+            let prefix_length = frame.prefix_length;
+            let prefix_location = frame.location
+                + NODE_HEADER_SIZE as u32
+                + (child_index * frame.prefix_length) as u32;
+            let pointer_location = frame.pointer_array_location + (child_index as u32) * 3;
+            path.truncate(frame.path_length);
+
+            let mut prefix = [0; PREFIX_ARRAY_SIZE_LIMIT];
+            self.source
+                .read_exact(prefix_location, &mut prefix[..prefix_length])
+                .await?;
+
+            if path
+                .try_extend_from_slice(&prefix[..prefix_length])
+                .is_err()
+            {
+                continue;
+            }
+
+            match self.read_pointer_at(pointer_location).await? {
+                ChildPointer::Node(child) => next_node = Some(child),
+                ChildPointer::Translation(translation) => {
+                    self.consider_translation(translation, &path, word, &mut best)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(best)
+    }
+
+    /// Replaces the best match with the outline if it is shorter and its translation writes the word
+    async fn consider_translation<const STROKES: usize>(
+        &mut self,
+        translation: u32,
+        outline: &[u8],
+        word: &str,
+        best: &mut Option<ArrayVec<Stroke, STROKES>>,
+    ) -> Result<(), D::Error> {
+        let stroke_count = outline.len() / 3;
+
+        if outline.len() % 3 != 0 || stroke_count > STROKES {
+            return Ok(());
+        }
+
+        if matches!(best, Some(best) if best.len() <= stroke_count) {
+            return Ok(());
+        }
+
+        if self.read_translation(translation).await?.writes(word) {
+            *best = Some(
+                outline
+                    .chunks_exact(3)
+                    .map(|stroke| Stroke::from_bytes([stroke[0], stroke[1], stroke[2]]))
+                    .collect(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl TranslationBuffer {
+    /// Whether the text written by the translation equals the word, ignoring any formatting commands
+    fn writes(&self, word: &str) -> bool {
+        let mut remaining = word;
+
+        for command in self.iter() {
+            if let FormatterCommand::Write(string) = command {
+                match remaining.strip_prefix(string) {
+                    Some(rest) => remaining = rest,
+                    None => return false,
+                }
+            }
+        }
+
+        remaining.is_empty()
+    }
+}
+
+#[cfg(all(test, feature = "compile"))]
+mod does {
+    use super::*;
+    use crate::compile::{BufferedSource, CompileOptions, Compiler};
+    use alloc::{string::String, vec::Vec};
+    use core::future::{ready, Ready};
+    use futures::executor::block_on;
+
+    const DICTIONARY: &str = r#"{
+        "A": "apple",
+        "PW-R": "bar",
+        "PW/A": "bar",
+        "TPAOU": "{^}foo",
+        "-Z": "zebra"
+    }"#;
+
+    /// Data source which fails reads extending past the end of the data, unlike the [`BufferedSource`]
+    struct StrictSource<'b>(&'b [u8]);
+
+    impl<'b> DataSource for StrictSource<'b> {
+        type Error = ();
+        type ReadFut<'s>
+            = Ready<Result<(), ()>>
+        where
+            Self: 's;
+
+        fn read_exact<'s>(&'s mut self, location: u32, buffer: &'s mut [u8]) -> Self::ReadFut<'s> {
+            let start = location as usize;
+            let result = self
+                .0
+                .get(start..start + buffer.len())
+                .map(|data| buffer.copy_from_slice(data))
+                .ok_or(());
+
+            ready(result)
+        }
+    }
+
+    /// Looks up the outline writing the word and formats it like the dictionary source
+    fn lookup<D: DataSource>(dict: &mut RadixTreeDictionary<D>, word: &str) -> Option<String>
+    where
+        D::Error: core::fmt::Debug,
+    {
+        block_on(dict.reverse_lookup::<4>(word))
+            .unwrap()
+            .map(|outline| {
+                let strokes = outline.iter().map(|stroke| alloc::format!("{stroke}"));
+                strokes.collect::<Vec<_>>().join("/")
+            })
+    }
+
+    #[test]
+    fn find_words_in_index() {
+        let options = CompileOptions { word_index: true };
+        let (_, buffer) = block_on(Compiler::compile_from_json_with_options(
+            DICTIONARY, options,
+        ));
+
+        let mut dict = block_on(RadixTreeDictionary::new(StrictSource(&buffer))).unwrap();
+        assert!(dict.has_word_index());
+
+        assert_eq!(lookup(&mut dict, "apple").as_deref(), Some("A"));
+        assert_eq!(lookup(&mut dict, "bar").as_deref(), Some("PW-R"));
+        assert_eq!(lookup(&mut dict, "foo").as_deref(), Some("TPAOU"));
+        assert_eq!(lookup(&mut dict, "unknown"), None);
+    }
+
+    #[test]
+    fn find_final_word_in_index() {
+        let options = CompileOptions { word_index: true };
+        let (_, buffer) = block_on(Compiler::compile_from_json_with_options(
+            DICTIONARY, options,
+        ));
+
+        // Sorts last, so its entry ends right at the end of the buffer
+        assert!(buffer.ends_with(b"zebra\x01\x00\x00\x02"));
+
+        let mut dict = block_on(RadixTreeDictionary::new(StrictSource(&buffer))).unwrap();
+        assert_eq!(lookup(&mut dict, "zebra").as_deref(), Some("-Z"));
+    }
+
+    #[test]
+    fn scan_tree_without_index() {
+        let (_, buffer) = block_on(Compiler::compile_from_json(DICTIONARY));
+
+        let mut source = BufferedSource::new(&buffer);
+        let mut dict = block_on(RadixTreeDictionary::new(&mut source)).unwrap();
+        assert!(!dict.has_word_index());
+
+        assert_eq!(lookup(&mut dict, "apple").as_deref(), Some("A"));
+        assert_eq!(lookup(&mut dict, "bar").as_deref(), Some("PW-R"));
+        assert_eq!(lookup(&mut dict, "zebra").as_deref(), Some("-Z"));
+        assert_eq!(lookup(&mut dict, "unknown"), None);
+    }
+}
@@ -0,0 +1,203 @@
+//! Runtime statistics about the shape of a serialized tree
+
+use super::{ChildPointer, DataSource, RadixTreeDictionary};
+use arrayvec::ArrayVec;
+
+/// Metrics collected for all nodes at one depth of the tree
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LayerStats {
+    /// Number of nodes in this layer
+    pub nodes: u32,
+    /// Total number of children referenced by nodes in this layer
+    pub children: u32,
+    /// Number of children which point directly to a translation instead of another node
+    pub leaf_children: u32,
+    /// Number of nodes in this layer which carry a translation themselves
+    pub translations: u32,
+}
+
+impl LayerStats {
+    /// Average number of children per node in this layer
+    pub fn avg_children(&self) -> f32 {
+        if self.nodes == 0 {
+            0.0
+        } else {
+            self.children as f32 / self.nodes as f32
+        }
+    }
+}
+
+/// Shape of a [`RadixTreeDictionary`](super::RadixTreeDictionary), gathered by walking the serialized tree.
+/// Only the first `LAYERS` layers can be recorded, which also bounds the depth of the walk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeStats<const LAYERS: usize> {
+    /// Index of the deepest layer, the root node is located at layer zero
+    pub depth: usize,
+    /// Per-layer metrics, indexed by depth
+    pub layers: [LayerStats; LAYERS],
+}
+
+impl<const LAYERS: usize> TreeStats<LAYERS> {
+    /// Total number of nodes in the tree
+    pub fn node_count(&self) -> u32 {
+        self.layers.iter().map(|layer| layer.nodes).sum()
+    }
+
+    /// Total number of translations reachable through the tree
+    pub fn translation_count(&self) -> u32 {
+        self.layers
+            .iter()
+            .map(|layer| layer.translations + layer.leaf_children)
+            .sum()
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TreeStatsError<E> {
+    /// Reading from the underlying data source failed
+    Source(E),
+    /// The tree has more layers than the statistics can hold, which usually indicates a corrupted dictionary
+    TooDeep,
+}
+
+/// Node whose children are currently being visited
+struct Frame {
+    pointer_array_location: u32,
+    child_count: usize,
+    next_child: usize,
+}
+
+impl<D: DataSource> RadixTreeDictionary<D> {
+    /// Walks the whole tree and reports its shape, e.g. to verify that a complete dictionary was loaded.
+    /// Every node is read once, so this can take a while for large dictionaries on slow storage.
+    pub async fn stats<const LAYERS: usize>(
+        &mut self,
+    ) -> Result<TreeStats<LAYERS>, TreeStatsError<D::Error>> {
+        let mut stats = TreeStats {
+            depth: 0,
+            layers: [LayerStats::default(); LAYERS],
+        };
+
+        let mut stack = ArrayVec::<Frame, LAYERS>::new();
+        let mut next_node = Some(self.tree_start);
+
+        loop {
+            if let Some(location) = next_node.take() {
+                let layer = stack.len();
+                let node = self
+                    .read_node_at(location)
+                    .await
+                    .map_err(TreeStatsError::Source)?;
+
+                let layer_stats = stats.layers.get_mut(layer).ok_or(TreeStatsError::TooDeep)?;
+                layer_stats.nodes += 1;
+                layer_stats.children += node.child_count() as u32;
+                if node.translation_pointer().is_some() {
+                    layer_stats.translations += 1;
+                }
+
+                stats.depth = stats.depth.max(layer);
+
+                stack
+                    .try_push(Frame {
+                        pointer_array_location: node.pointer_array_location(),
+                        child_count: node.child_count(),
+                        next_child: 0,
+                    })
+                    .map_err(|_| TreeStatsError::TooDeep)?;
+            }
+
+            let frame = match stack.last_mut() {
+                Some(frame) => frame,
+                None => break,
+            };
+
+            if frame.next_child == frame.child_count {
+                stack.pop();
+                continue;
+            }
+
+            let location = frame.pointer_array_location + (frame.next_child as u32) * 3;
+            frame.next_child += 1;
+
+            match self
+                .read_pointer_at(location)
+                .await
+                .map_err(TreeStatsError::Source)?
+            {
+                ChildPointer::Node(child) => next_node = Some(child),
+                ChildPointer::Translation(_) => stats.layers[stack.len() - 1].leaf_children += 1,
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+#[cfg(all(test, feature = "compile"))]
+mod does {
+    use super::*;
+    use crate::compile::{BufferedSource, Child, Compiler, TreeNode};
+    use futures::executor::block_on;
+
+    const DICTIONARY: &str = r#"{
+        "A": "apple",
+        "A/PW": "apple bee",
+        "A/PW/-Z": "apple bee zebra",
+        "PW-R": "bar",
+        "PW/A": "bar",
+        "TPAOU": "foo",
+        "-Z": "zebra"
+    }"#;
+
+    /// Records the shape of the tree the compiler built in memory, which the serialized tree has to match
+    fn expected_layers(node: &TreeNode, depth: usize, layers: &mut [LayerStats]) {
+        let layer = &mut layers[depth];
+        layer.nodes += 1;
+        layer.children += node.children.len() as u32;
+        layer.translations += node.leaf_data.is_some() as u32;
+
+        for (_, child) in node.children.iter() {
+            match child {
+                Child::Leaf(_) => layers[depth].leaf_children += 1,
+                Child::Tree(child) => expected_layers(child, depth + 1, layers),
+            }
+        }
+    }
+
+    #[test]
+    fn count_nodes_and_translations_per_layer() {
+        let (tree, buffer) = block_on(Compiler::compile_from_json(DICTIONARY));
+
+        let mut source = BufferedSource::new(&buffer);
+        let mut dict = block_on(RadixTreeDictionary::new(&mut source)).unwrap();
+        let stats = block_on(dict.stats::<16>()).unwrap();
+
+        let mut layers = [LayerStats::default(); 16];
+        expected_layers(&tree, 0, &mut layers);
+
+        assert_eq!(stats.layers, layers);
+        assert_eq!(stats.layers[0].nodes, 1);
+        assert_eq!(stats.translation_count(), 7);
+        assert_eq!(
+            stats.depth + 1,
+            layers.iter().filter(|l| l.nodes > 0).count()
+        );
+        assert!(stats.node_count() > 1);
+    }
+
+    #[test]
+    fn reject_trees_deeper_than_the_layers() {
+        let (_, buffer) = block_on(Compiler::compile_from_json(DICTIONARY));
+
+        let mut source = BufferedSource::new(&buffer);
+        let mut dict = block_on(RadixTreeDictionary::new(&mut source)).unwrap();
+
+        assert!(matches!(
+            block_on(dict.stats::<1>()),
+            Err(TreeStatsError::TooDeep)
+        ));
+    }
+}
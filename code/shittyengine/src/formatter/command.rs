@@ -5,6 +5,8 @@ pub enum FormatterCommand<S: AsRef<str>> {
     ChangeCapitalization(CapitalizationMode),
     ChangeAttachment(AttachmentMode),
     ResetFormatting,
+    /// Joins the previous and next word with a hyphen, e.g. for compounds like `well-being`
+    JoinWithHyphen,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
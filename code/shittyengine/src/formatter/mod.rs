@@ -1,38 +1,39 @@
 use crate::buffer::HistoryBuffer;
 use crate::output::OutputCommand;
 use crate::ORTHOGRAPHIC_SUFFIX_LENGTH;
-use arrayvec::ArrayString;
+use arrayvec::{ArrayString, ArrayVec};
 
 mod command;
+mod orthography;
 mod state;
 
 use self::state::TextFormatterState;
 pub use command::*;
+pub use state::FormatterStateSnapshot;
 
-type EmptyCharIter = core::iter::Empty<char>;
+type RestoredCharIter = arrayvec::IntoIter<char, ORTHOGRAPHIC_SUFFIX_LENGTH>;
 
 struct UndoInfo {
     character_count: u8,
-    trailing_suffix: Option<u8>,
+    /// Original trailing characters of the previous word which have been removed by an orthographic rule
+    replaced_suffix: ArrayVec<char, ORTHOGRAPHIC_SUFFIX_LENGTH>,
 }
 
 impl UndoInfo {
     const EMPTY: Self = Self {
         character_count: 0,
-        trailing_suffix: None,
+        replaced_suffix: ArrayVec::new_const(),
     };
 }
 
 pub struct Formatter<const HISTORY_SIZE: usize> {
     history: HistoryBuffer<(TextFormatterState, UndoInfo), HISTORY_SIZE>,
-    latest_suffix: Option<ArrayString<ORTHOGRAPHIC_SUFFIX_LENGTH>>,
 }
 
 impl<const HISTORY_SIZE: usize> Formatter<HISTORY_SIZE> {
     pub fn new() -> Self {
         Self {
             history: HistoryBuffer::new(),
-            latest_suffix: None,
         }
     }
 
@@ -44,14 +45,49 @@ impl<const HISTORY_SIZE: usize> Formatter<HISTORY_SIZE> {
             .unwrap_or_default()
     }
 
-    pub fn undo(&mut self) -> Option<OutputCommand<EmptyCharIter>> {
-        self.latest_suffix = None;
-        self.history.pop().map(|(_, undo_info)| {
-            debug_assert!(
-                undo_info.trailing_suffix.is_none(),
-                "orthography-aware undo not implemented yet"
-            );
-            OutputCommand::Backspace(undo_info.character_count)
+    /// Formatting state that will be applied to the next written word, e.g. for displaying indicators in a UI
+    pub fn current_state(&self) -> FormatterStateSnapshot {
+        let state = self.state();
+
+        FormatterStateSnapshot {
+            capitalization: state.capitalization,
+            attachment: state.attachment,
+            suffix_buffered: state.suffix.is_some(),
+        }
+    }
+
+    /// Reverts applied commands up to and including the most recent one which produced output,
+    /// returning the backspaces required to remove it. Commands which did not output anything (e.g. formatting changes)
+    /// are skipped, only restoring the state from before them.
+    /// If attaching a suffix rewrote the end of the previous word (e.g. `manage` → `managing`), its original characters are restored.
+    pub fn undo(&mut self) -> Option<OutputCommand<RestoredCharIter>> {
+        while self.history.back().is_some() {
+            if let Some(output) = self.revert() {
+                return Some(output);
+            }
+        }
+
+        None
+    }
+
+    /// Reverts only the most recently applied command, returning the backspaces required to remove its output.
+    ///
+    /// Every command occupies one history entry so that callers can undo an outline by calling this once per command.
+    /// Commands which did not output anything only restore the previous state and return `None`.
+    /// The restored state includes the end of the previous output, so re-applying the reverted commands
+    /// (e.g. when the engine re-matches trailing strokes) reproduces their output exactly.
+    pub fn revert(&mut self) -> Option<OutputCommand<RestoredCharIter>> {
+        self.history.pop().and_then(|(_, undo_info)| {
+            if !undo_info.replaced_suffix.is_empty() {
+                Some(OutputCommand::Replace(
+                    undo_info.character_count,
+                    undo_info.replaced_suffix.into_iter(),
+                ))
+            } else if undo_info.character_count > 0 {
+                Some(OutputCommand::Backspace(undo_info.character_count))
+            } else {
+                None
+            }
         })
     }
 
@@ -64,14 +100,26 @@ impl<const HISTORY_SIZE: usize> Formatter<HISTORY_SIZE> {
 
         let (undo_info, output) = match command {
             Write(input) => {
-                // 1. Mutate string according to current state
-                let output = state.apply(input.as_ref());
+                // 1. Apply orthographic rules when attaching a suffix to the previous word
+                let mut replaced_suffix = ArrayVec::<char, ORTHOGRAPHIC_SUFFIX_LENGTH>::new();
+                let mut inserted = None;
+
+                if let (Some(tail), AttachmentMode::Next) = (&state.suffix, state.attachment) {
+                    if let Some(rewrite) = orthography::rewrite(tail, input.as_ref()) {
+                        let kept = tail.chars().count() - rewrite.removed as usize;
+                        replaced_suffix.extend(tail.chars().skip(kept));
+                        inserted = rewrite.inserted;
+                    }
+                }
+
+                // 2. Mutate string according to current state
+                let output = inserted.into_iter().chain(state.apply(input.as_ref()));
                 let output_len = output.clone().count();
 
-                // 2. Advance state
+                // 3. Advance state
                 state.tick();
 
-                // 3. Update the suffix
+                // 4. Update the suffix
                 let mut suffix = ArrayString::<ORTHOGRAPHIC_SUFFIX_LENGTH>::new();
                 let suffix_length = ORTHOGRAPHIC_SUFFIX_LENGTH.min(output_len);
 
@@ -80,15 +128,21 @@ impl<const HISTORY_SIZE: usize> Formatter<HISTORY_SIZE> {
                     .skip(output_len - suffix_length)
                     .for_each(|c| suffix.push(c));
 
-                self.latest_suffix = Some(suffix);
+                state.suffix = Some(suffix);
+
+                // 5. Output result
+                let output = if replaced_suffix.is_empty() {
+                    OutputCommand::Write(output)
+                } else {
+                    OutputCommand::Replace(replaced_suffix.len() as u8, output)
+                };
 
-                // 3. Output result
                 (
                     UndoInfo {
                         character_count: output_len as u8,
-                        trailing_suffix: None,
+                        replaced_suffix,
                     },
-                    Some(OutputCommand::Write(output)),
+                    Some(output),
                 )
             }
             ChangeCapitalization(capitalization) => {
@@ -100,9 +154,32 @@ impl<const HISTORY_SIZE: usize> Formatter<HISTORY_SIZE> {
                 (UndoInfo::EMPTY, None)
             }
             ResetFormatting => {
-                state = TextFormatterState::default();
+                // Formatting is reset but the previous output stays in place
+                state = TextFormatterState {
+                    suffix: state.suffix,
+                    ..TextFormatterState::default()
+                };
                 (UndoInfo::EMPTY, None)
             }
+            JoinWithHyphen => {
+                // Attach the hyphen to the previous word without consuming the capitalization of the next one
+                let mut hyphen_state = state.clone();
+                hyphen_state.attachment = AttachmentMode::Next;
+                hyphen_state.capitalization = CapitalizationMode::Unchanged;
+                let output = None.into_iter().chain(hyphen_state.apply("-"));
+
+                // Orthographic rules do not apply across hyphens
+                state.suffix = None;
+                state.attachment = AttachmentMode::Next;
+
+                (
+                    UndoInfo {
+                        character_count: 1,
+                        replaced_suffix: ArrayVec::new(),
+                    },
+                    Some(OutputCommand::Write(output)),
+                )
+            }
         };
 
         self.history.push((state, undo_info));
@@ -150,4 +227,253 @@ mod does {
 
         assert_eq!(*aggregator, "Hello Hello helloWorldJohn");
     }
+
+    #[test]
+    fn skip_formatting_commands_on_undo() {
+        let mut formatter = Formatter::<10>::new();
+        let mut aggregator = OutputAggregator::new();
+
+        let commands = [
+            FormatterCommand::Write("hello"),
+            FormatterCommand::Write("world"),
+            FormatterCommand::ChangeCapitalization(CapitalizationMode::Uppercase),
+            FormatterCommand::ChangeAttachment(AttachmentMode::Always),
+        ];
+
+        for command in commands.iter() {
+            if let Some(output) = formatter.apply(command) {
+                aggregator.apply(output);
+            }
+        }
+
+        // Formatting changes are skipped instead of emitting no-op backspaces
+        aggregator.apply(formatter.undo().unwrap());
+        assert_eq!(*aggregator, "Hello");
+
+        // The state from before the formatting changes is restored
+        aggregator.apply(formatter.apply(&FormatterCommand::Write("there")).unwrap());
+        assert_eq!(*aggregator, "Hello there");
+    }
+
+    #[test]
+    fn revert_single_commands() {
+        let mut formatter = Formatter::<10>::new();
+        let mut aggregator = OutputAggregator::new();
+
+        let commands = [
+            FormatterCommand::Write("hello"),
+            FormatterCommand::ChangeAttachment(AttachmentMode::Always),
+        ];
+
+        for command in commands.iter() {
+            if let Some(output) = formatter.apply(command) {
+                aggregator.apply(output);
+            }
+        }
+
+        // Reverting an outline which only changed the formatting leaves the previous output in place
+        assert!(formatter.revert().is_none());
+        assert_eq!(*aggregator, "Hello");
+
+        aggregator.apply(formatter.apply(&FormatterCommand::Write("world")).unwrap());
+        assert_eq!(*aggregator, "Hello world");
+    }
+
+    #[test]
+    fn join_compounds_with_hyphen() {
+        let mut formatter = Formatter::<10>::new();
+        let mut aggregator = OutputAggregator::new();
+
+        let commands = [
+            FormatterCommand::Write("well"),
+            FormatterCommand::JoinWithHyphen,
+            FormatterCommand::Write("being"),
+            FormatterCommand::Write("matters"),
+        ];
+
+        for command in commands.iter() {
+            if let Some(output) = formatter.apply(command) {
+                aggregator.apply(output);
+            }
+        }
+
+        assert_eq!(*aggregator, "Well-being matters");
+
+        // Undoing the compound removes the hyphen as well
+        aggregator.apply(formatter.undo().unwrap());
+        aggregator.apply(formatter.undo().unwrap());
+        aggregator.apply(formatter.undo().unwrap());
+        assert_eq!(*aggregator, "Well");
+
+        aggregator.apply(formatter.apply(&FormatterCommand::Write("done")).unwrap());
+        assert_eq!(*aggregator, "Well done");
+    }
+
+    #[test]
+    fn double_consonants_when_attaching_suffixes() {
+        let mut formatter = Formatter::<10>::new();
+        let mut aggregator = OutputAggregator::new();
+
+        let commands = [
+            FormatterCommand::Write("we"),
+            FormatterCommand::Write("drop"),
+            FormatterCommand::ChangeAttachment(AttachmentMode::Next),
+            FormatterCommand::Write("ing"),
+        ];
+
+        for command in commands.iter() {
+            if let Some(output) = formatter.apply(command) {
+                aggregator.apply(output);
+            }
+        }
+
+        assert_eq!(*aggregator, "We dropping");
+
+        // Undoing the suffix removes the doubled consonant as well
+        aggregator.apply(formatter.undo().unwrap());
+        assert_eq!(*aggregator, "We drop");
+    }
+
+    #[test]
+    fn restore_silent_e_on_undo() {
+        let mut formatter = Formatter::<10>::new();
+        let mut aggregator = OutputAggregator::new();
+
+        let commands = [
+            FormatterCommand::Write("we"),
+            FormatterCommand::Write("manage"),
+            FormatterCommand::ChangeAttachment(AttachmentMode::Next),
+            FormatterCommand::Write("ing"),
+        ];
+
+        for command in commands.iter() {
+            if let Some(output) = formatter.apply(command) {
+                aggregator.apply(output);
+            }
+        }
+
+        assert_eq!(*aggregator, "We managing");
+
+        aggregator.apply(formatter.undo().unwrap());
+        assert_eq!(*aggregator, "We manage");
+
+        // Suffixes starting with a consonant leave the previous word untouched
+        let mut formatter = Formatter::<10>::new();
+        formatter.apply(&FormatterCommand::Write("manage"));
+        formatter.apply(&FormatterCommand::<&str>::ChangeAttachment(
+            AttachmentMode::Next,
+        ));
+        let output = formatter.apply(&FormatterCommand::Write("ment"));
+        assert!(matches!(output, Some(OutputCommand::Write(_))));
+    }
+
+    #[test]
+    fn restore_final_y_on_undo() {
+        let mut formatter = Formatter::<10>::new();
+        let mut aggregator = OutputAggregator::new();
+
+        let commands = [
+            FormatterCommand::Write("we"),
+            FormatterCommand::Write("happy"),
+            FormatterCommand::ChangeAttachment(AttachmentMode::Next),
+            FormatterCommand::Write("ly"),
+            FormatterCommand::Write("carry"),
+            FormatterCommand::ChangeAttachment(AttachmentMode::Next),
+            FormatterCommand::Write("ing"),
+        ];
+
+        for command in commands.iter() {
+            if let Some(output) = formatter.apply(command) {
+                aggregator.apply(output);
+            }
+        }
+
+        assert_eq!(*aggregator, "We happily carrying");
+
+        aggregator.apply(formatter.undo().unwrap());
+        aggregator.apply(formatter.undo().unwrap());
+        assert_eq!(*aggregator, "We happily");
+
+        // Undoing the suffix restores the original spelling of the previous word
+        aggregator.apply(formatter.undo().unwrap());
+        assert_eq!(*aggregator, "We happy");
+    }
+
+    #[test]
+    fn reproduce_output_when_rematching() {
+        let mut formatter = Formatter::<10>::new();
+        let mut aggregator = OutputAggregator::new();
+
+        let outlines: [&[FormatterCommand<&str>]; 3] = [
+            &[FormatterCommand::Write("the")],
+            &[FormatterCommand::Write("manage")],
+            &[
+                FormatterCommand::ChangeAttachment(AttachmentMode::Next),
+                FormatterCommand::Write("ing"),
+            ],
+        ];
+
+        for outline in outlines.iter() {
+            for command in outline.iter() {
+                if let Some(output) = formatter.apply(command) {
+                    aggregator.apply(output);
+                }
+            }
+        }
+
+        let first_pass = (*aggregator).clone();
+        assert_eq!(first_pass, "The managing");
+
+        // Revert the trailing outlines command by command like the engine does when it re-matches strokes
+        for outline in outlines[1..].iter().rev() {
+            for _ in 0..outline.len() {
+                if let Some(output) = formatter.revert() {
+                    aggregator.apply(output);
+                }
+            }
+        }
+
+        assert_eq!(*aggregator, "The");
+
+        for outline in outlines[1..].iter() {
+            for command in outline.iter() {
+                if let Some(output) = formatter.apply(command) {
+                    aggregator.apply(output);
+                }
+            }
+        }
+
+        assert_eq!(*aggregator, first_pass);
+    }
+
+    #[test]
+    fn expose_current_state() {
+        let mut formatter = Formatter::<10>::new();
+
+        let state = formatter.current_state();
+        assert_eq!(state.capitalization, CapitalizationMode::CapitalizeNext);
+        assert_eq!(state.attachment, AttachmentMode::Next);
+        assert!(!state.suffix_buffered);
+
+        formatter.apply(&FormatterCommand::Write("hello"));
+        formatter.apply(&FormatterCommand::<&str>::ChangeCapitalization(
+            CapitalizationMode::Uppercase,
+        ));
+        formatter.apply(&FormatterCommand::<&str>::ChangeAttachment(
+            AttachmentMode::Always,
+        ));
+
+        let state = formatter.current_state();
+        assert_eq!(state.capitalization, CapitalizationMode::Uppercase);
+        assert_eq!(state.attachment, AttachmentMode::Always);
+        assert!(state.suffix_buffered);
+
+        // Reverting a formatting change keeps the end of the previous output buffered
+        formatter.revert();
+        assert_eq!(
+            formatter.current_state().attachment,
+            AttachmentMode::Delimited
+        );
+        assert!(formatter.current_state().suffix_buffered);
+    }
 }
@@ -0,0 +1,143 @@
+use crate::ORTHOGRAPHIC_SUFFIX_LENGTH;
+
+/// Change to the end of the previous word which is required to attach a suffix to it
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Rewrite {
+    /// Number of trailing characters removed from the previous word
+    pub(super) removed: u8,
+    /// Character inserted between the previous word and the suffix
+    pub(super) inserted: Option<char>,
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c.to_ascii_lowercase(), 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+fn is_consonant(c: char) -> bool {
+    c.is_ascii_alphabetic() && !is_vowel(c)
+}
+
+/// Determines how the previous word has to change when attaching the given suffix, based on the trailing characters of the previous output.
+/// Only suffixes with at least two characters are considered, leaving e.g. glued letters when fingerspelling alone.
+/// Apart from turning a final y into an i, they also have to start with a vowel.
+pub(super) fn rewrite(tail: &str, suffix: &str) -> Option<Rewrite> {
+    let mut suffix = suffix.chars();
+    let first = match (suffix.next(), suffix.next()) {
+        (Some(first), Some(_)) => first,
+        _ => return None,
+    };
+
+    // Only the characters after the last delimiter belong to the previous word.
+    // Words filling the whole tail may have started before it, so their syllables can not be counted.
+    let word = tail.rsplit(char::is_whitespace).next().unwrap_or(tail);
+    let word_length = word.chars().count();
+    let complete = word_length < ORTHOGRAPHIC_SUFFIX_LENGTH;
+
+    let mut chars = word.chars().rev();
+    let (last, previous, before) = (chars.next()?, chars.next()?, chars.next());
+
+    // A final y following a consonant turns into an i unless the suffix starts with one, e.g. happy → happily but carry → carrying
+    if word_length >= 3
+        && last.eq_ignore_ascii_case(&'y')
+        && is_consonant(previous)
+        && !first.eq_ignore_ascii_case(&'i')
+    {
+        return Some(Rewrite {
+            removed: 1,
+            inserted: Some(if last.is_ascii_uppercase() { 'I' } else { 'i' }),
+        });
+    }
+
+    if !is_vowel(first) {
+        return None;
+    }
+
+    // Silent e is dropped, e.g. manage → managing
+    if word_length >= 3 && last.eq_ignore_ascii_case(&'e') && is_consonant(previous) {
+        return Some(Rewrite {
+            removed: 1,
+            inserted: None,
+        });
+    }
+
+    // The final consonant of single-syllable words is doubled, e.g. drop → dropping
+    let vowel_count = word.chars().filter(|c| is_vowel(*c)).count();
+    let doubles = is_consonant(last) && !matches!(last.to_ascii_lowercase(), 'w' | 'x' | 'y');
+    if complete
+        && vowel_count == 1
+        && doubles
+        && is_vowel(previous)
+        && before.map_or(false, is_consonant)
+    {
+        return Some(Rewrite {
+            removed: 0,
+            inserted: Some(last),
+        });
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+
+    #[test]
+    fn drop_silent_e() {
+        let expected = Some(Rewrite {
+            removed: 1,
+            inserted: None,
+        });
+
+        assert_eq!(rewrite("anage", "ing"), expected);
+        assert_eq!(rewrite(" make", "ed"), expected);
+        assert_eq!(rewrite("anage", "ment"), None);
+        assert_eq!(rewrite(" free", "ing"), None);
+        assert_eq!(rewrite(" be", "ing"), None);
+    }
+
+    #[test]
+    fn double_final_consonants() {
+        assert_eq!(
+            rewrite(" drop", "ing"),
+            Some(Rewrite {
+                removed: 0,
+                inserted: Some('p'),
+            })
+        );
+        assert_eq!(
+            rewrite("Run", "er"),
+            Some(Rewrite {
+                removed: 0,
+                inserted: Some('n'),
+            })
+        );
+
+        // Multiple syllables, vowel pairs, unknown word starts and excluded consonants
+        assert_eq!(rewrite(" open", "ing"), None);
+        assert_eq!(rewrite(" rain", "ing"), None);
+        assert_eq!(rewrite("admit", "ing"), None);
+        assert_eq!(rewrite(" fix", "ing"), None);
+    }
+
+    #[test]
+    fn replace_final_y() {
+        let expected = Some(Rewrite {
+            removed: 1,
+            inserted: Some('i'),
+        });
+
+        assert_eq!(rewrite("happy", "ly"), expected);
+        assert_eq!(rewrite(" carry", "ed"), expected);
+        assert_eq!(rewrite(" carry", "ing"), None);
+        assert_eq!(rewrite(" play", "er"), None);
+        assert_eq!(rewrite(" my", "er"), None);
+    }
+
+    #[test]
+    fn ignore_short_and_consonant_suffixes() {
+        assert_eq!(rewrite(" drop", "s"), None);
+        assert_eq!(rewrite(" hop", "i"), None);
+        assert_eq!(rewrite(" make", ""), None);
+    }
+}
@@ -1,9 +1,14 @@
 use super::{AttachmentMode, CapitalizationMode};
+use crate::ORTHOGRAPHIC_SUFFIX_LENGTH;
+use arrayvec::ArrayString;
 
 #[derive(Clone)]
 pub(super) struct TextFormatterState {
     pub(super) attachment: AttachmentMode,
     pub(super) capitalization: CapitalizationMode,
+    /// Trailing characters of the previous output, used by orthographic rules when attaching a suffix.
+    /// Stored alongside the rest of the state so that reverting a command restores exactly how the output ended.
+    pub(super) suffix: Option<ArrayString<ORTHOGRAPHIC_SUFFIX_LENGTH>>,
 }
 
 impl TextFormatterState {
@@ -18,11 +23,21 @@ impl TextFormatterState {
     }
 }
 
+/// Read-only copy of the formatting state which will be applied to the next written word
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FormatterStateSnapshot {
+    pub capitalization: CapitalizationMode,
+    pub attachment: AttachmentMode,
+    /// Whether the trailing characters of the previous word are retained for orthographic rules
+    pub suffix_buffered: bool,
+}
+
 impl Default for TextFormatterState {
     fn default() -> Self {
         Self {
             attachment: AttachmentMode::Next,
             capitalization: CapitalizationMode::CapitalizeNext,
+            suffix: None,
         }
     }
 }
@@ -12,6 +12,10 @@ const NODE_HEADER_SIZE: usize = 1 + 1 + 3;
 const PREFIX_ARRAY_SIZE_LIMIT: usize = 256;
 /// Maximum number of bytes a translation may use in serialized form
 const TRANSLATION_SIZE_LIMIT: usize = 256;
+/// Set in the most significant byte of the dictionary header if a word index follows the tree
+const HEADER_FLAG_WORD_INDEX: u8 = 0b1;
+/// Longest word stored in the word index, longer ones can only be found by scanning the tree
+const INDEX_WORD_LENGTH_LIMIT: usize = 63;
 
 pub mod dict;
 pub mod formatter;
@@ -131,6 +131,7 @@ impl TreeInfo {
                     shittyengine::formatter::FormatterCommand::ChangeCapitalization(_) => 1,
                     shittyengine::formatter::FormatterCommand::ChangeAttachment(_) => 1,
                     shittyengine::formatter::FormatterCommand::ResetFormatting => 1,
+                    shittyengine::formatter::FormatterCommand::JoinWithHyphen => 1,
                 }
             }
 
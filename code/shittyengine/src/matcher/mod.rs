@@ -4,6 +4,7 @@
 mod entry;
 mod mutator;
 mod resolver;
+mod snapshot;
 mod state;
 
 use mutator::StateMutator;
@@ -12,6 +13,7 @@ use state::State;
 
 pub use entry::{HistoryEntry, OutlineInformation};
 pub use resolver::{CommitType, TrailingOutline};
+pub use snapshot::SnapshotError;
 
 // Wrapper around all the lower-level structs to make the API more concise
 pub struct OutlineMatcher<Stroke, const HISTORY_SIZE: usize> {
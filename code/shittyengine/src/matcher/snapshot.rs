@@ -0,0 +1,155 @@
+//! Serialization of the matcher state so it survives a suspend-resume cycle, e.g. in retained RAM or flash
+
+use super::{entry::HistoryEntry, OutlineInformation, OutlineMatcher, State};
+use crate::Stroke;
+
+/// Bytes used by the snapshot header (entry count and uncommitted count)
+const HEADER_SIZE: usize = 4;
+
+/// Bytes used by each history entry (stroke, outline length, and command count)
+const ENTRY_SIZE: usize = 5;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// Provided buffer can not hold the state, see [`OutlineMatcher::STATE_SIZE`]
+    BufferTooSmall,
+    /// Buffer does not contain a state that fits into this matcher
+    InvalidState,
+}
+
+impl<const HISTORY_SIZE: usize> OutlineMatcher<Stroke, HISTORY_SIZE> {
+    /// Number of bytes required to hold an exported state
+    pub const STATE_SIZE: usize = HEADER_SIZE + HISTORY_SIZE * ENTRY_SIZE;
+
+    /// Writes the committed and uncommitted history into the buffer and returns the number of bytes used
+    pub fn export_state(&self, buffer: &mut [u8]) -> Result<usize, SnapshotError> {
+        let count = self.state.strokes.len();
+        let size = HEADER_SIZE + count * ENTRY_SIZE;
+
+        if buffer.len() < size {
+            return Err(SnapshotError::BufferTooSmall);
+        }
+
+        buffer[0..2].copy_from_slice(&(count as u16).to_be_bytes());
+        buffer[2..4].copy_from_slice(&(self.state.uncommitted_count as u16).to_be_bytes());
+
+        // Entries are written oldest first so they can be pushed back in order
+        for (offset, chunk) in (0..count)
+            .rev()
+            .zip(buffer[HEADER_SIZE..size].chunks_exact_mut(ENTRY_SIZE))
+        {
+            let entry = &self.state.strokes[offset];
+            let (length, commands) = entry
+                .outline
+                .as_ref()
+                .map(|outline| (outline.length, outline.commands))
+                .unwrap_or((0, 0));
+
+            chunk[0..3].copy_from_slice(entry.stroke.as_bytes());
+            chunk[3] = length;
+            chunk[4] = commands;
+        }
+
+        Ok(size)
+    }
+
+    /// Replaces the current history with one previously written by [`export_state`](Self::export_state)
+    pub fn import_state(&mut self, buffer: &[u8]) -> Result<(), SnapshotError> {
+        if buffer.len() < HEADER_SIZE {
+            return Err(SnapshotError::BufferTooSmall);
+        }
+
+        let count = u16::from_be_bytes([buffer[0], buffer[1]]) as usize;
+        let uncommitted_count = u16::from_be_bytes([buffer[2], buffer[3]]) as usize;
+        let size = HEADER_SIZE + count * ENTRY_SIZE;
+
+        if count > HISTORY_SIZE || uncommitted_count > count {
+            return Err(SnapshotError::InvalidState);
+        } else if buffer.len() < size {
+            return Err(SnapshotError::BufferTooSmall);
+        }
+
+        let mut state = State::new();
+
+        for chunk in buffer[HEADER_SIZE..size].chunks_exact(ENTRY_SIZE) {
+            let stroke = Stroke::from_bytes([chunk[0], chunk[1], chunk[2]]);
+            let entry = match (chunk[3], chunk[4]) {
+                (0, 0) => HistoryEntry::new(stroke),
+                (0, _) => return Err(SnapshotError::InvalidState),
+                (length, commands) => {
+                    HistoryEntry::with_outline(stroke, OutlineInformation { length, commands })
+                }
+            };
+
+            state.strokes.push(entry);
+        }
+
+        state.uncommitted_count = uncommitted_count;
+        self.state = state;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+    use crate::buf;
+    use alloc::{vec, vec::Vec};
+
+    type Matcher = OutlineMatcher<Stroke, 8>;
+
+    #[test]
+    fn restore_partial_outline() {
+        let outline = |length, commands| OutlineInformation { length, commands };
+
+        // Two committed outlines followed by the strokes of one that is still being written
+        let mut matcher = Matcher::new(4);
+        matcher.state.strokes = buf![
+            HistoryEntry::with_outline(Stroke::from_right_aligned(1), outline(1, 2)),
+            HistoryEntry::with_outline(Stroke::from_right_aligned(2), outline(2, 1)),
+            HistoryEntry::new(Stroke::from_right_aligned(3)),
+            HistoryEntry::new(Stroke::from_right_aligned(4)),
+            HistoryEntry::new(Stroke::from_right_aligned(5))
+        ];
+        matcher.state.uncommitted_count = 2;
+
+        let mut buffer = vec![0; Matcher::STATE_SIZE];
+        let size = matcher.export_state(&mut buffer).unwrap();
+
+        let mut restored = Matcher::new(4);
+        restored.import_state(&buffer[..size]).unwrap();
+
+        assert_eq!(restored.uncommitted_count(), matcher.uncommitted_count());
+        assert_eq!(
+            restored.uncommitted_strokes().collect::<Vec<_>>(),
+            matcher.uncommitted_strokes().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            restored.committed_strokes().collect::<Vec<_>>(),
+            matcher.committed_strokes().collect::<Vec<_>>()
+        );
+        assert_eq!(restored.committed_strokes().count(), 3);
+
+        for _ in 0..5 {
+            assert_eq!(restored.pop(), matcher.pop());
+            assert_eq!(restored.uncommitted_count(), matcher.uncommitted_count());
+        }
+    }
+
+    #[test]
+    fn reject_oversized_state() {
+        let mut matcher = Matcher::new(4);
+        let mut buffer = [0; Matcher::STATE_SIZE];
+        buffer[0..2].copy_from_slice(&9u16.to_be_bytes());
+
+        assert_eq!(
+            matcher.import_state(&buffer),
+            Err(SnapshotError::InvalidState)
+        );
+        assert_eq!(
+            matcher.export_state(&mut [0; 2]),
+            Err(SnapshotError::BufferTooSmall)
+        );
+    }
+}
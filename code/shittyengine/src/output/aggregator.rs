@@ -13,18 +13,7 @@ impl OutputAggregator {
 
 impl OutputProcessor for OutputAggregator {
     fn apply<I: Iterator<Item = char>>(&mut self, command: OutputCommand<I>) {
-        match command {
-            OutputCommand::Backspace(n) => {
-                for _ in 0..n {
-                    self.0.pop();
-                }
-            }
-            OutputCommand::Write(string) => {
-                for c in string {
-                    self.0.push(c);
-                }
-            }
-        }
+        command.reduce_to_string(&mut self.0);
     }
 }
 
@@ -1,5 +1,89 @@
-#[derive(Debug)]
+use core::fmt::{Debug, Write as _};
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
 pub enum OutputCommand<CharIter: Iterator<Item = char>> {
     Backspace(u8),
     Write(CharIter),
+    /// Removes the given number of characters and writes new ones in their place, e.g. when a suffix rewrites the end of the previous word
+    Replace(u8, CharIter),
+}
+
+impl<CharIter: Iterator<Item = char>> OutputCommand<CharIter> {
+    /// Applies the command to the given text, removing characters from its end or appending to it
+    #[cfg(feature = "alloc")]
+    pub fn reduce_to_string(self, current: &mut String) {
+        match self {
+            OutputCommand::Backspace(n) => {
+                for _ in 0..n {
+                    current.pop();
+                }
+            }
+            OutputCommand::Write(chars) => current.extend(chars),
+            OutputCommand::Replace(n, chars) => {
+                OutputCommand::<CharIter>::Backspace(n).reduce_to_string(current);
+                current.extend(chars);
+            }
+        }
+    }
+}
+
+/// Prints the characters of `Write` commands instead of the iterator producing them
+impl<CharIter: Iterator<Item = char> + Clone> Debug for OutputCommand<CharIter> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            OutputCommand::Backspace(n) => f.debug_tuple("Backspace").field(n).finish(),
+            OutputCommand::Write(chars) => {
+                f.write_str("Write(\"")?;
+                write_escaped(f, chars.clone())?;
+                f.write_str("\")")
+            }
+            OutputCommand::Replace(n, chars) => {
+                write!(f, "Replace({}, \"", n)?;
+                write_escaped(f, chars.clone())?;
+                f.write_str("\")")
+            }
+        }
+    }
+}
+
+fn write_escaped(
+    f: &mut core::fmt::Formatter<'_>,
+    chars: impl Iterator<Item = char>,
+) -> core::fmt::Result {
+    for c in chars.flat_map(char::escape_debug) {
+        f.write_char(c)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod does {
+    use super::OutputCommand;
+    use alloc::{format, string::String};
+
+    #[test]
+    fn reduce_to_string() {
+        let mut text = String::from("Hello");
+
+        OutputCommand::Write(" world".chars()).reduce_to_string(&mut text);
+        OutputCommand::<core::str::Chars>::Backspace(3).reduce_to_string(&mut text);
+        OutputCommand::Replace(1, "rk".chars()).reduce_to_string(&mut text);
+
+        assert_eq!(text, "Hello wrk");
+    }
+
+    #[test]
+    fn print_written_characters() {
+        let write = OutputCommand::Write("say \"hi\"".chars());
+        let backspace = OutputCommand::<core::str::Chars>::Backspace(2);
+
+        assert_eq!(format!("{:?}", write), r#"Write("say \"hi\"")"#);
+        assert_eq!(format!("{:?}", backspace), "Backspace(2)");
+
+        let replace = OutputCommand::Replace(1, "ing".chars());
+        assert_eq!(format!("{:?}", replace), r#"Replace(1, "ing")"#);
+    }
 }
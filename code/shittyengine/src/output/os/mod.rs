@@ -24,6 +24,10 @@ impl OutputProcessor for OSOutput {
                     tap(&Character(c), &[]);
                 }
             }
+            OutputCommand::Replace(n, string) => {
+                self.apply(OutputCommand::<I>::Backspace(n));
+                self.apply(OutputCommand::Write(string));
+            }
         }
     }
 }
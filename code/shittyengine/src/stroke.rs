@@ -34,6 +34,10 @@ impl EnglishStroke {
         self.0
     }
 
+    pub fn from_bytes(bytes: [u8; 3]) -> Self {
+        Self(bytes)
+    }
+
     fn contains_vowel(&self) -> bool {
         (self.0[1] & 0b11111000) > 0
     }
@@ -1,10 +1,17 @@
 use serde::{Deserialize, Serialize};
 
-/// 6-bit identifier
+/// 5-bit identifier
 #[derive(PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Debug)]
 #[repr(transparent)]
 pub struct ID(u8);
 
+/// Side of the connection, used to tag messages and acknowledgements with the side that originally sent the message
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Role {
+    Host,
+    Peripheral,
+}
+
 #[derive(Debug)]
 pub enum PacketHeaderParseError {
     UnknownPacketType,
@@ -12,8 +19,12 @@ pub enum PacketHeaderParseError {
 
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum PacketHeader {
-    Message(ID),
-    MessageAck(ID),
+    /// Message sent by the given role
+    Message(Role, ID),
+    /// Acknowledgement of a message that has been sent by the given role
+    MessageAck(Role, ID),
+    /// Negative acknowledgement, the payload holds the sequence number and [`Message`](PacketHeader::Message) header of the rejected message
+    MessageNack,
     StreamPacket(StreamPacketHeader),
 }
 
@@ -22,15 +33,44 @@ pub enum StreamPacketHeader {
     Content(u8),
     Revert,
     Closed,
+    /// Sent by the reader in response to [`Closed`](StreamPacketHeader::Closed), so that it can be told apart from the end of a stream written by the other side
+    CloseAck,
 }
 
 impl From<u8> for ID {
     fn from(src: u8) -> Self {
-        assert!(src < 2u8.pow(6));
+        assert!(src < 2u8.pow(5));
         Self(src)
     }
 }
 
+impl Role {
+    const BIT: u8 = 0b00_100000;
+
+    /// Role of the other side of the connection
+    pub fn peer(self) -> Self {
+        match self {
+            Role::Host => Role::Peripheral,
+            Role::Peripheral => Role::Host,
+        }
+    }
+
+    fn from_bit(src: u8) -> Self {
+        if src & Self::BIT == 0 {
+            Role::Host
+        } else {
+            Role::Peripheral
+        }
+    }
+
+    fn bit(self) -> u8 {
+        match self {
+            Role::Host => 0,
+            Role::Peripheral => Self::BIT,
+        }
+    }
+}
+
 impl From<ID> for u8 {
     fn from(src: ID) -> Self {
         src.0
@@ -46,15 +86,19 @@ impl TryFrom<u8> for PacketHeader {
         use StreamPacketHeader::*;
 
         let header = if src & 0b11_000000 == 0 {
-            Message(ID::from(src))
+            Message(Role::from_bit(src), ID::from(src & 0b11111))
         } else if src & 0b11_000000 == 0b01_000000 {
-            MessageAck(ID::from(src & 0b111111))
+            MessageAck(Role::from_bit(src), ID::from(src & 0b11111))
         } else if src & 0b11_000000 == 0b10_000000 {
             StreamPacket(Content(src & 0b111111))
         } else if src == 0b11000000 {
             StreamPacket(Revert)
         } else if src == 0b11000001 {
             StreamPacket(Closed)
+        } else if src == 0b11000010 {
+            MessageNack
+        } else if src == 0b11000011 {
+            StreamPacket(CloseAck)
         } else {
             return Err(UnknownPacketType);
         };
@@ -68,11 +112,45 @@ impl From<PacketHeader> for u8 {
         use PacketHeader::*;
         use StreamPacketHeader::*;
         match src {
-            Message(id) => id.into(),
-            MessageAck(id) => 0b01_000000 | <ID as Into<u8>>::into(id),
+            Message(role, id) => role.bit() | <ID as Into<u8>>::into(id),
+            MessageAck(role, id) => 0b01_000000 | role.bit() | <ID as Into<u8>>::into(id),
+            MessageNack => 0b11000010,
             StreamPacket(Content(id)) => 0b10_000000 | id,
             StreamPacket(Revert) => 0b11000000,
             StreamPacket(Closed) => 0b11000001,
+            StreamPacket(CloseAck) => 0b11000011,
+        }
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+
+    #[test]
+    fn tag_messages_with_role() {
+        for role in [Role::Host, Role::Peripheral] {
+            for header in [
+                PacketHeader::Message(role, ID::from(31)),
+                PacketHeader::MessageAck(role, ID::from(0)),
+                PacketHeader::MessageNack,
+            ] {
+                let byte: u8 = header.into();
+                assert!(PacketHeader::try_from(byte).unwrap() == header);
+            }
+        }
+
+        assert!(
+            u8::from(PacketHeader::Message(Role::Host, ID::from(1)))
+                != u8::from(PacketHeader::Message(Role::Peripheral, ID::from(1)))
+        );
+    }
+
+    #[test]
+    fn distinguish_stream_end_from_acknowledgement() {
+        for header in [StreamPacketHeader::Closed, StreamPacketHeader::CloseAck] {
+            let byte: u8 = PacketHeader::StreamPacket(header).into();
+            assert!(PacketHeader::try_from(byte).unwrap() == PacketHeader::StreamPacket(header));
         }
     }
 }
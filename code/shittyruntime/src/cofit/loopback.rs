@@ -0,0 +1,57 @@
+use super::{Transport, TransportError};
+use crate::firmware::{
+    executor_support::{Channel, Mutex},
+    Mpsc, MpscReceiver, MpscSender, Mutex as MutexTrait,
+};
+use core::future::Future;
+
+type FrameSender<'c, const MTU: usize> = <Channel<[u8; MTU]> as Mpsc>::Sender<'c>;
+type FrameReceiver<'c, const MTU: usize> = <Channel<[u8; MTU]> as Mpsc>::Receiver<'c>;
+
+/// In-memory transport which directly connects two networks, e.g. for testing
+pub struct LoopbackTransport<'c, const MTU: usize> {
+    tx: FrameSender<'c, MTU>,
+    rx: Mutex<FrameReceiver<'c, MTU>>,
+}
+
+impl<'c, const MTU: usize> LoopbackTransport<'c, MTU> {
+    /// Creates two connected transports, frames sent by one of them are received by the other.
+    /// The first channel carries frames from the first transport to the second one and vice versa.
+    pub fn pair(a_to_b: &'c Channel<[u8; MTU]>, b_to_a: &'c Channel<[u8; MTU]>) -> (Self, Self) {
+        let a = Self {
+            tx: a_to_b.sender(),
+            rx: Mutex::new(b_to_a.receiver()),
+        };
+
+        let b = Self {
+            tx: b_to_a.sender(),
+            rx: Mutex::new(a_to_b.receiver()),
+        };
+
+        (a, b)
+    }
+}
+
+impl<'c, const MTU: usize> Transport<MTU> for LoopbackTransport<'c, MTU> {
+    type TxFut<'t> = impl Future<Output = Result<(), TransportError>> + 't where Self: 't;
+    type RxFut<'t> = impl Future<Output = Result<[u8; MTU], TransportError>> + 't where Self: 't;
+
+    /// Never fails as the channels outlive both ends
+    fn send<'t>(&'t self, data: [u8; MTU]) -> Self::TxFut<'t> {
+        async move {
+            self.tx.send(data).await;
+            Ok(())
+        }
+    }
+
+    fn recv<'t>(&'t self) -> Self::RxFut<'t> {
+        async move {
+            let mut rx = self.rx.lock().await;
+            loop {
+                if let Some(data) = rx.recv_timeout(u32::MAX).await {
+                    return Ok(data);
+                }
+            }
+        }
+    }
+}
@@ -1,32 +1,70 @@
 //! Communication over fixed interval transports (like USB HID)
 
+use self::priority::UrgentLane;
+use self::rate_limit::RateLimiter;
+use self::sequence::{SequenceCounter, SequenceTracker};
+use self::statistics::NetworkCounters;
 use self::stream::{StreamReadHandle, StreamWriteHandle};
 use crate::firmware::{
-    executor_support::{Channel, Mutex},
-    Mpsc, MpscReceiver, MpscSender, Mutex as MutexTrait,
+    executor_support::{Channel, Mutex, TimeDriver},
+    DurationDriver, Mpsc, MpscReceiver, MpscSender, Mutex as MutexTrait,
+    TimeDriver as TimeDriverTrait,
+};
+use core::{future::Future, task::Poll};
+use futures::{
+    future::{select, Either},
+    pin_mut, Stream,
 };
-use core::future::Future;
 
 const ACK_TIMEOUT_MS: u32 = 10_000; // 500;
 const STREAM_RECV_TIMEOUT_MS: u32 = 10_000;
 
 mod header;
+mod loopback;
+mod poll;
+mod priority;
+mod rate_limit;
+mod retry;
+mod sequence;
+mod statistics;
 mod stream;
+#[cfg(any(feature = "test-util", all(test, feature = "tokio")))]
+pub mod testing;
 
 pub use header::*;
-pub use stream::StreamPacket;
+pub use loopback::LoopbackTransport;
+pub use poll::{PollOperation, PollingNetwork, PollingState};
+pub use priority::Priority;
+pub use rate_limit::{RateLimit, RateLimitPolicy};
+pub use retry::RetryPolicy;
+pub use sequence::SequencedMessage;
+pub use statistics::NetworkStatistics;
+pub use stream::{StreamError, StreamPacket, STREAM_REPLAY_CAPACITY};
+
+/// Failure of the underlying [`Transport`], after which it is not expected to recover
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportError {
+    /// The connection to the other side has been lost, e.g. because the device has been unplugged
+    Disconnected,
+}
 
 pub trait Transport<const MTU: usize> {
-    type TxFut<'t>: Future<Output = ()> + 't
+    type TxFut<'t>: Future<Output = Result<(), TransportError>> + 't
     where
         Self: 't;
 
-    type RxFut<'t>: Future<Output = [u8; MTU]> + 't
+    type RxFut<'t>: Future<Output = Result<[u8; MTU], TransportError>> + 't
     where
         Self: 't;
 
     fn send<'t>(&'t self, data: [u8; MTU]) -> Self::TxFut<'t>;
     fn recv<'t>(&'t self) -> Self::RxFut<'t>;
+
+    /// Number of frames passed to [`send`](Self::send) which have not yet been written to the wire.
+    /// Transports without an internal queue complete sends immediately and may keep the default.
+    fn queued_frames(&self) -> usize {
+        0
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
@@ -41,38 +79,183 @@ pub trait WireFormat<const MTU: usize> {
 
     fn serialize(&self, message: Self::Message) -> Result<SerializedMessage<MTU>, Self::Error>;
     fn deserialize(&self, packet: SerializedMessage<MTU>) -> Result<Self::Message, Self::Error>;
+
+    /// Number of bytes at the start of the payload which messages with the given ID occupy, at most `MTU`.
+    ///
+    /// The remaining bytes are padding which [`serialize`](Self::serialize) has to leave zeroed. Incoming messages
+    /// carrying anything else in them are considered malformed and rejected before reaching [`deserialize`](Self::deserialize)
+    /// or any handler. Defaults to the whole payload, which disables the check.
+    ///
+    /// Formats overriding this should also reject such messages in [`deserialize`](Self::deserialize) using
+    /// [`is_padded`](Self::is_padded), since it is called on messages that did not pass through a network as well.
+    fn message_length(&self, _id: ID) -> usize {
+        MTU
+    }
+
+    /// Whether the payload is zeroed beyond the [length](Self::message_length) of the message
+    fn is_padded(&self, packet: &SerializedMessage<MTU>) -> bool {
+        let length = self.message_length(packet.id).min(MTU);
+        packet.bytes[length..].iter().all(|byte| *byte == 0)
+    }
+
+    /// Whether messages with the given ID are acknowledged by the receiving side.
+    /// Messages that are not have to be sent using [`Network::send_unreliable`].
+    fn is_reliable(&self, _id: ID) -> bool {
+        true
+    }
+
+    /// Lane messages with the given ID are sent in by [`Network::send`], see [`Priority`].
+    /// Messages can also be sent in the urgent lane explicitly using [`Network::send_priority`].
+    fn priority(&self, _id: ID) -> Priority {
+        Priority::Normal
+    }
+
+    /// Whether the received acknowledgement belongs to the sent message.
+    /// Defaults to requiring an exact echo of the message, protocols which reply with e.g. a result
+    /// in the acknowledgement payload can match by [`ID`] only instead.
+    fn matches_ack(&self, sent: &SerializedMessage<MTU>, ack: &SerializedMessage<MTU>) -> bool {
+        sent == ack
+    }
+}
+
+/// Answer queued by a [`MessageAcknowledger`] for transmission by the network
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum Reply<const MTU: usize> {
+    Ack(SequencedMessage<MTU>),
+    Nack(u8, ID),
+}
+
+type ReplySender<'c, const MTU: usize> = <Channel<Reply<MTU>> as Mpsc>::Sender<'c>;
+type ReplyReceiver<'c, const MTU: usize> = <Channel<Reply<MTU>> as Mpsc>::Receiver<'c>;
+type ResponseSender<'c, const MTU: usize> = <Channel<SerializedMessage<MTU>> as Mpsc>::Sender<'c>;
+type ResponseReceiver<'c, const MTU: usize> =
+    <Channel<SerializedMessage<MTU>> as Mpsc>::Receiver<'c>;
+
+/// Answer of the receiving side to a reliable message
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Acknowledgement<const MTU: usize> {
+    /// The message has been accepted, carrying the acknowledgement payload
+    Ack(SequencedMessage<MTU>),
+    /// The message with the given sequence number and ID has been rejected, see [`MessageAcknowledger::reject`]
+    Nack(u8, ID),
+}
+
+impl<const MTU: usize> Acknowledgement<MTU> {
+    /// Sequence number of the message this acknowledgement answers
+    pub fn sequence(&self) -> u8 {
+        match self {
+            Acknowledgement::Ack(acknowledgement) => acknowledgement.sequence,
+            Acknowledgement::Nack(sequence, _) => *sequence,
+        }
+    }
 }
 
-pub struct MessageAcknowledger<'n, const MTU: usize> {
-    serialized: SerializedMessage<MTU>,
-    sender: <Channel<SerializedMessage<MTU>> as Mpsc>::Sender<'n>,
+/// Handle to answer an incoming reliable message, either by acknowledging or rejecting it.
+///
+/// Handlers may hold on to it across awaits to acknowledge a message only after doing some work (e.g. erasing flash).
+/// If the acknowledger is dropped without answering, for example because the future holding it got cancelled,
+/// the message is rejected instead so the sender fails with [`NetworkError::Rejected`] rather than waiting for its timeout.
+/// Should the reply queue be full at that point, the rejection is dropped and the sender times out.
+pub struct MessageAcknowledger<'n, F: WireFormat<MTU>, const MTU: usize> {
+    received: SequencedMessage<MTU>,
+    sender: ReplySender<'n, MTU>,
+    responder: ResponseSender<'n, MTU>,
+    format: &'n F,
     sent: bool,
 }
 
-impl<'n, const MTU: usize> MessageAcknowledger<'n, MTU> {
+impl<'n, F: WireFormat<MTU>, const MTU: usize> MessageAcknowledger<'n, F, MTU> {
+    /// Borrows the serialized payload of the incoming message, e.g. to process flash content without copying it.
+    /// Answering the message consumes the acknowledger, so the payload can not be held on to past the acknowledgement.
+    pub fn payload(&self) -> &[u8] {
+        &self.received.message.bytes
+    }
+
+    /// Acknowledges the incoming message, this is a no-op for unreliable messages
     pub async fn acknowledge(mut self) {
-        self.sender.send(self.serialized).await;
-        self.sent = true;
+        if !self.sent {
+            self.sender.send(Reply::Ack(self.received)).await;
+            self.sent = true;
+        }
+    }
+
+    /// Rejects the incoming message, failing the send on the other side with [`NetworkError::Rejected`].
+    /// This is a no-op for unreliable messages.
+    pub async fn reject(mut self) {
+        if !self.sent {
+            let rejection = Reply::Nack(self.received.sequence, self.received.message.id);
+            self.sender.send(rejection).await;
+            self.sent = true;
+        }
+    }
+
+    /// Acknowledges the incoming message and sends the given response right after it.
+    /// Since only one message may be in-flight at any time, the response is correlated with the incoming message by order.
+    ///
+    /// The response is sent like any other reliable message, but from a task of its own so that waiting for its
+    /// acknowledgement does not hold up the replies to other incoming messages. This only waits until the response
+    /// has been queued, a failure to deliver it is logged. The incoming message is acknowledged even if the response
+    /// can not be serialized.
+    pub async fn respond(mut self, response: F::Message) -> Result<(), CofitError<F::Error>> {
+        let serialized_response = self
+            .format
+            .serialize(response)
+            .map_err(NetworkError::FormatError);
+
+        if !self.sent {
+            self.sender.send(Reply::Ack(self.received)).await;
+            self.sent = true;
+        }
+
+        self.responder.send(serialized_response?).await;
+
+        Ok(())
     }
 }
 
-impl<const MTU: usize> Drop for MessageAcknowledger<'_, MTU> {
+impl<F: WireFormat<MTU>, const MTU: usize> Drop for MessageAcknowledger<'_, F, MTU> {
     fn drop(&mut self) {
-        if !self.sent {
-            panic!("Dropped MessageAcknowledger without acknowledging message");
+        if self.sent {
+            return;
+        }
+
+        // Dropping an unanswered message usually means the handler has been cancelled, reject it so the sender does not wait in vain
+        let rejection = Reply::Nack(self.received.sequence, self.received.message.id);
+        if self.sender.try_send(rejection).is_err() {
+            #[cfg(feature = "defmt")]
+            defmt::warn!("Dropped unanswered message without rejecting it, reply queue is full");
         }
     }
 }
 
-pub trait MessageHandler<M, const MTU: usize> {
+pub trait MessageHandler<F: WireFormat<MTU>, const MTU: usize> {
+    type HandlerFut<'s>: Future<Output = ()> + 's
+    where
+        Self: 's,
+        F: 's;
+
+    fn handle<'s>(
+        &'s mut self,
+        message: F::Message,
+        acknowledger: MessageAcknowledger<'s, F, MTU>,
+    ) -> Self::HandlerFut<'s>;
+}
+
+/// Alternative to [`MessageHandler`] which receives messages without deserializing them.
+///
+/// The payload is lent to the handler by its [`MessageAcknowledger::payload`] instead of being copied into a deserialized
+/// message, which matters for large payloads on memory-constrained devices. Messages are handed over regardless of
+/// whether the [`WireFormat`] would be able to deserialize them, so the handler has to check the [`ID`] itself.
+pub trait RawMessageHandler<F: WireFormat<MTU>, const MTU: usize> {
     type HandlerFut<'s>: Future<Output = ()> + 's
     where
-        Self: 's;
+        Self: 's,
+        F: 's;
 
     fn handle<'s>(
         &'s mut self,
-        message: M,
-        acknowledger: MessageAcknowledger<'s, MTU>,
+        id: ID,
+        acknowledger: MessageAcknowledger<'s, F, MTU>,
     ) -> Self::HandlerFut<'s>;
 }
 
@@ -80,31 +263,149 @@ pub trait MessageHandler<M, const MTU: usize> {
 pub enum NetworkError<E> {
     FormatError(E),
     InvalidPacketHeader(PacketHeaderParseError),
-    TimedOut,
+    /// No acknowledgement has been received after transmitting the message `attempts` times, see [`RetryPolicy`]
+    TimedOut {
+        attempts: u8,
+    },
     UnexpectedAck,
+    /// The receiver rejected the message, e.g. because its handler has been cancelled before acknowledging it
+    Rejected,
+    /// Attempted to send a message unreliably which the receiver would acknowledge
+    ReliableMessage,
+    /// Waiting for the acknowledgement has been cancelled, see [`Network::send_cancellable`]
+    Cancelled,
+    /// Message has been dropped because the [`RateLimit`] is exhausted
+    RateLimited,
+    /// The [`Transport`] failed to send the message, e.g. because the device has been unplugged
+    Transport(TransportError),
+}
+
+/// Any error returned by the public cofit APIs, so that applications only have to handle a single type.
+/// Wraps the more specific errors, with `E` being the error type of the [`WireFormat`].
+///
+/// Sending messages returns it directly. Stream handles and the [receive task](Network::recv_task) report the narrower
+/// [`StreamError`] and [`TransportError`] respectively, both of which convert into it using `?`.
+/// Malformed incoming frames are not reported to the application, they are dropped and counted in the [`NetworkStatistics`].
+#[derive(Debug)]
+pub enum CofitError<E> {
+    Network(NetworkError<E>),
+    Stream(StreamError),
+}
+
+impl<E> From<NetworkError<E>> for CofitError<E> {
+    fn from(error: NetworkError<E>) -> Self {
+        Self::Network(error)
+    }
+}
+
+impl<E> From<StreamError> for CofitError<E> {
+    fn from(error: StreamError) -> Self {
+        Self::Stream(error)
+    }
+}
+
+impl<E> From<TransportError> for CofitError<E> {
+    fn from(error: TransportError) -> Self {
+        Self::Network(NetworkError::Transport(error))
+    }
 }
 
-type MessageSender<'c, const PMTU: usize> = <Channel<SerializedMessage<PMTU>> as Mpsc>::Sender<'c>;
+type MessageSender<'c, const PMTU: usize> = <Channel<SequencedMessage<PMTU>> as Mpsc>::Sender<'c>;
 type MessageReceiver<'c, const PMTU: usize> =
-    <Channel<SerializedMessage<PMTU>> as Mpsc>::Receiver<'c>;
-type AckSender<'c, const PMTU: usize> = <Channel<SerializedMessage<PMTU>> as Mpsc>::Sender<'c>;
-type AckReceiver<'c, const PMTU: usize> = <Channel<SerializedMessage<PMTU>> as Mpsc>::Receiver<'c>;
+    <Channel<SequencedMessage<PMTU>> as Mpsc>::Receiver<'c>;
+type AckSender<'c, const PMTU: usize> = <Channel<Acknowledgement<PMTU>> as Mpsc>::Sender<'c>;
+type AckReceiver<'c, const PMTU: usize> = <Channel<Acknowledgement<PMTU>> as Mpsc>::Receiver<'c>;
 type StreamSender<'c, const PMTU: usize> = <Channel<StreamPacket<PMTU>> as Mpsc>::Sender<'c>;
 type StreamReceiver<'c, const PMTU: usize> = <Channel<StreamPacket<PMTU>> as Mpsc>::Receiver<'c>;
 type StreamReceiverLock<'t, const PMTU: usize> =
     <Mutex<StreamReceiver<'t, PMTU>> as MutexTrait>::Guard<'t>;
 
+/// Number of bytes at the start of each frame occupied by the packet header and sequence number
+const FRAME_HEADER_SIZE: usize = 2;
+/// Number of bytes at the start of each stream packet occupied by the stream sequence ID, in addition to the frame header
+///
+/// Stream frames only carry the one byte packet header and no sequence number, but their packets are buffered in
+/// the same `PMTU` sized arrays as messages. This leaves the last byte of every stream frame unused in exchange for
+/// deriving both MTUs the same way: A [`UsbNetwork`] streams chunks of 60 bytes instead of 61, a multiple of the
+/// 4 byte write size of the flash they are written to, so that consecutive chunks stay aligned.
+const STREAM_HEADER_SIZE: usize = 2;
+
+/// Protocol MTU of a network whose frames are `transport_mtu` bytes long, with the last `reserved` bytes set aside for the application.
+/// Use this to derive the `PMTU` parameter of a [`Network`].
+pub const fn protocol_mtu(transport_mtu: usize, reserved: usize) -> usize {
+    transport_mtu - reserved - FRAME_HEADER_SIZE
+}
+
+/// Stream MTU of a network whose frames are `transport_mtu` bytes long, with the last `reserved` bytes set aside for the application.
+/// Use this to derive the `SMTU` parameter of a [`Network`].
+pub const fn stream_mtu(transport_mtu: usize, reserved: usize) -> usize {
+    protocol_mtu(transport_mtu, reserved) - STREAM_HEADER_SIZE
+}
+
+/// Protocol and stream MTUs of a network whose frames are `TMTU` bytes long, with the last `RESERVED` bytes set aside for the application
+///
+/// Spelling out the const parameters of a [`Network`] through these leaves only the transport MTU to be chosen:
+///
+/// ```ignore
+/// type BleNetwork<'c, T, F> = Network<'c, 20, 0, { DerivedMtu::<20>::PMTU }, { DerivedMtu::<20>::SMTU }, T, F>;
+/// ```
+pub struct DerivedMtu<const TMTU: usize, const RESERVED: usize = 0>;
+
+impl<const TMTU: usize, const RESERVED: usize> DerivedMtu<TMTU, RESERVED> {
+    /// See [`protocol_mtu`]
+    pub const PMTU: usize = protocol_mtu(TMTU, RESERVED);
+    /// See [`stream_mtu`]
+    pub const SMTU: usize = stream_mtu(TMTU, RESERVED);
+}
+
 /// Variant of `Network` with MTUs for USB HID RAW transfer
-pub type UsbNetwork<'c, T, F> = Network<'c, 64, 63, 61, T, F>;
+pub type UsbNetwork<'c, T, F> =
+    Network<'c, 64, 0, { DerivedMtu::<64>::PMTU }, { DerivedMtu::<64>::SMTU }, T, F>;
 
-// TODO PMTU & SMTU are constants derived from the TMTU. Somehow make them actually derive instead of having the user "guess" them
 // TODO Make sure there can only ever be one message in-flight, because otherwise stuff will dead-lock :(
 //      Unless we off-load stream processing into its own (semi-global) task per stream type, sending a message while a stream is being processed will break everything :D
 //      A possibility would be to lock the send fn until no stream is active and no other message is in-flight.
 //      => Actually, in that case, since we are not processing stuff in parallel anymore anyways, we can just go to a simple req/ack and req/res model not requiring the stream stuff anymore ...
+/// Reliable messaging over a [`Transport`], either as the host or the peripheral side of a connection
+///
+/// Both sides may send messages at any time, including in the same transport interval. Every message and
+/// acknowledgement is tagged with the [`Role`] of the side that sent the message, so crossing frames are never
+/// confused: An incoming message is handed to [`recv_with`](Self::recv_with) and acknowledged independently
+/// of any message still awaiting its own acknowledgement, while only acknowledgements of messages sent by this side
+/// complete a [`send`](Self::send). Frames which could only have been sent by this side itself (e.g. when echoed
+/// back by the transport) are dropped.
+///
+/// # Ordering
+///
+/// Message frames carry a sequence number, counted separately for reliable and unreliable messages. The receiving side
+/// hands messages of each kind to [`recv_with`](Self::recv_with) in the order they have been sent and at most once:
+/// Retransmissions of a message that already arrived are dropped (repeating its reply in case that got lost), as are
+/// messages arriving after a later one of the same kind. Reliable messages are numbered once they hold the acknowledgement
+/// lock, so concurrent calls to [`send`](Self::send) are delivered in the order they acquired it. There is no ordering
+/// between reliable and unreliable messages, nor between messages and stream packets.
+///
+/// # Reserved bytes
+///
+/// Applications may set aside the last `RESERVED` bytes of each transport frame for their own framing, e.g. a checksum
+/// computed by a wrapping [`Transport`]. The network leaves them zeroed in outgoing frames and ignores them in incoming ones.
+/// The protocol and stream MTUs shrink accordingly and have to be derived using [`DerivedMtu`]:
+///
+/// ```ignore
+/// type CrcNetwork<'c, T, F> =
+///     Network<'c, 64, 1, { DerivedMtu::<64, 1>::PMTU }, { DerivedMtu::<64, 1>::SMTU }, T, F>;
+/// ```
+///
+/// MTUs which do not match these derivations are rejected at compile time.
+///
+/// # Streams
+///
+/// By default, all stream packets share a single channel, so only one stream can be open at a time — either a
+/// [reader](Self::create_stream_reader) or a [writer](Self::create_stream_writer). Networks which have to transfer data
+/// in both directions at once can be given a second channel using [`with_duplex_streams`](Self::with_duplex_streams).
 pub struct Network<
     'c,
     const TMTU: usize,
+    const RESERVED: usize,
     const PMTU: usize,
     const SMTU: usize,
     T: Transport<TMTU>,
@@ -112,43 +413,62 @@ pub struct Network<
 > {
     transport: T,
     format: F,
+    role: Role,
 
     ack_sender: AckSender<'c, PMTU>,
     ack_receiver: Mutex<AckReceiver<'c, PMTU>>,
 
     stream_sender: StreamSender<'c, PMTU>,
     stream_receiver: Mutex<StreamReceiver<'c, PMTU>>,
+    /// Channel for reverts and close acknowledgements of streams written by this side, see [`with_duplex_streams`](Self::with_duplex_streams)
+    duplex_stream: Option<(StreamSender<'c, PMTU>, Mutex<StreamReceiver<'c, PMTU>>)>,
 
     message_sender: MessageSender<'c, PMTU>,
     message_receiver: Mutex<MessageReceiver<'c, PMTU>>,
+
+    reliable_sequence: SequenceCounter,
+    unreliable_sequence: SequenceCounter,
+    reliable_received: SequenceTracker,
+    unreliable_received: SequenceTracker,
+    /// Latest acknowledgement frame sent, repeated when the peer retransmits the message it answers
+    last_reply: Mutex<Option<[u8; TMTU]>>,
+
+    rate_limiter: Option<RateLimiter<TimeDriver>>,
+    retry_policy: RetryPolicy,
+    counters: NetworkCounters,
+    urgent_lane: UrgentLane,
 }
 
 impl<
         'c,
         const TMTU: usize,
+        const RESERVED: usize,
         const PMTU: usize,
         const SMTU: usize,
         T: Transport<TMTU> + 'c,
         F: WireFormat<PMTU> + 'c,
-    > Network<'c, TMTU, PMTU, SMTU, T, F>
+    > Network<'c, TMTU, RESERVED, PMTU, SMTU, T, F>
 {
+    const MTU_DERIVATION: () = {
+        assert!(
+            PMTU == protocol_mtu(TMTU, RESERVED),
+            "protocol MTU has to be derived from the transport MTU using `DerivedMtu::PMTU`"
+        );
+        assert!(
+            SMTU == stream_mtu(TMTU, RESERVED),
+            "stream MTU has to be derived from the transport MTU using `DerivedMtu::SMTU`"
+        );
+    };
+
     pub fn new(
         transport: T,
         format: F,
+        role: Role,
         ack_channel: (AckSender<'c, PMTU>, AckReceiver<'c, PMTU>),
         stream_channel: (StreamSender<'c, PMTU>, StreamReceiver<'c, PMTU>),
         message_channel: (MessageSender<'c, PMTU>, MessageReceiver<'c, PMTU>),
     ) -> Self {
-        assert_eq!(
-            TMTU - 1,
-            PMTU,
-            "protocol MTU has to match transport MTU minus one"
-        );
-        assert_eq!(
-            PMTU - 2,
-            SMTU,
-            "stream MTU has to match protocol MTU minus two / transport MTU minus three"
-        );
+        let () = Self::MTU_DERIVATION;
 
         let (ack_sender, ack_receiver) = ack_channel;
         let ack_receiver = Mutex::new(ack_receiver);
@@ -162,32 +482,86 @@ impl<
         Self {
             transport,
             format,
+            role,
             ack_sender,
             ack_receiver,
             stream_sender,
             stream_receiver,
+            duplex_stream: None,
             message_sender,
             message_receiver,
+            reliable_sequence: SequenceCounter::default(),
+            unreliable_sequence: SequenceCounter::default(),
+            reliable_received: SequenceTracker::default(),
+            unreliable_received: SequenceTracker::default(),
+            last_reply: Mutex::new(None),
+            rate_limiter: None,
+            retry_policy: RetryPolicy::default(),
+            counters: NetworkCounters::default(),
+            urgent_lane: UrgentLane::default(),
         }
     }
 
-    pub async fn create_stream_writer<
-        'm,
-        DataFut: Future<Output = Option<[u8; SMTU]>>,
-        DataSource: FnMut(u64) -> DataFut,
-    >(
+    /// Limits the rate at which messages are sent, protecting slower links from being flooded by high-rate events.
+    /// Applies to both reliable and unreliable messages but not to acknowledgements or streams. Unlimited by default.
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limiter = Some(RateLimiter::new(limit, TimeDriver::default()));
+        self
+    }
+
+    /// Retransmits reliable messages which have not been acknowledged in time, helping over transient link glitches.
+    /// Messages are sent exactly once by default. Retransmissions of a message that already arrived are not handled again,
+    /// the receiver repeats its acknowledgement instead.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Operates streams in full-duplex, allowing a [`StreamReadHandle`] and a [`StreamWriteHandle`] to be open at the same time.
+    ///
+    /// The given channel carries the reverts and close acknowledgements for the stream written by this side, while the
+    /// channel passed to [`new`](Self::new) is left to the stream read by this side. Each stream keeps its own sequence IDs
+    /// and handles its own reverts, so the two directions do not interfere. This costs a second stream channel of
+    /// `CHANNEL_CAPACITY` packets, on top of the replay buffer the writer retains anyway (see [`STREAM_REPLAY_CAPACITY`]).
+    ///
+    /// Only one reader and one writer can be open at once. Both sides of the connection have to be configured alike,
+    /// as each relies on the other to send the acknowledgements of its stream writer.
+    pub fn with_duplex_streams(
+        mut self,
+        channel: (StreamSender<'c, PMTU>, StreamReceiver<'c, PMTU>),
+    ) -> Self {
+        let (sender, receiver) = channel;
+        self.duplex_stream = Some((sender, Mutex::new(receiver)));
+        self
+    }
+
+    /// Number of messages dropped because the [`RateLimit`] has been exceeded
+    pub fn dropped_message_count(&self) -> u32 {
+        self.rate_limiter
+            .as_ref()
+            .map(RateLimiter::dropped_count)
+            .unwrap_or(0)
+    }
+
+    /// Opens a stream transmitting the chunks of the given source, see [`StreamWriteHandle`].
+    /// Sources which are not [`Unpin`] can be pinned using e.g. [`futures::pin_mut`].
+    ///
+    /// Waits for any open stream to finish, unless the network operates in [full-duplex](Self::with_duplex_streams)
+    /// in which case it only waits for another writer.
+    pub async fn create_stream_writer<'m, S: Stream<Item = [u8; SMTU]> + Unpin>(
         &'m self,
-        data_source: DataSource,
-    ) -> StreamWriteHandle<'m, TMTU, PMTU, SMTU, T, DataFut, DataSource>
+        source: S,
+    ) -> StreamWriteHandle<'m, TMTU, PMTU, SMTU, T, S>
     where
         'm: 'c,
     {
+        let receiver = match &self.duplex_stream {
+            Some((_, receiver)) => receiver,
+            None => &self.stream_receiver,
+        };
+
         // TODO Clear any left-over messages in the receiver
-        StreamWriteHandle::new(
-            self.stream_receiver.lock().await,
-            &self.transport,
-            data_source,
-        )
+        StreamWriteHandle::new(receiver.lock().await, &self.transport, source)
     }
 
     pub async fn create_stream_reader<'m>(&'m self) -> StreamReadHandle<'m, TMTU, PMTU, SMTU, T>
@@ -198,111 +572,445 @@ impl<
         StreamReadHandle::new(self.stream_receiver.lock().await, &self.transport)
     }
 
-    pub async fn send(&self, message: F::Message) -> Result<(), NetworkError<F::Error>> {
+    /// Number of outgoing frames buffered by the transport, see [`Transport::queued_frames`].
+    /// Bulk senders can use this to throttle themselves before the transport starts dropping data.
+    pub fn queued_frames(&self) -> usize {
+        self.transport.queued_frames()
+    }
+
+    /// Counters of the frames that passed through this network since it has been created.
+    /// Helps to tell whether a stalled connection is caused by the transport or by the message handler.
+    pub fn statistics(&self) -> NetworkStatistics {
+        self.counters.snapshot()
+    }
+
+    /// Whether a message has been sent for which no acknowledgement has been received yet.
+    /// While this is the case, calls to [`send`](Self::send) will wait for the in-flight message.
+    pub fn is_awaiting_ack(&self) -> bool {
+        self.ack_receiver.try_lock().is_none()
+    }
+
+    pub async fn send(&self, message: F::Message) -> Result<(), CofitError<F::Error>> {
+        let serialized = self
+            .format
+            .serialize(message)
+            .map_err(NetworkError::FormatError)?;
+
+        Ok(self.send_serialized(serialized).await?)
+    }
+
+    /// Like [`send`](Self::send), but in the [urgent](Priority::Urgent) lane regardless of the [`WireFormat::priority`] of the message.
+    /// Intended for e.g. aborting an ongoing transfer while further messages of it are already waiting to be sent.
+    pub async fn send_priority(&self, message: F::Message) -> Result<(), CofitError<F::Error>> {
+        let serialized = self
+            .format
+            .serialize(message)
+            .map_err(NetworkError::FormatError)?;
+
+        Ok(self
+            .send_serialized_until(
+                serialized,
+                Priority::Urgent,
+                futures::future::pending::<()>(),
+            )
+            .await?)
+    }
+
+    /// Like [`send`](Self::send), but stops waiting for the acknowledgement once the `cancel` future completes.
+    /// Intended for aborting a stuck transfer on user request, the network remains usable afterwards.
+    ///
+    /// Acknowledgements that already arrived are discarded on cancellation. One arriving later is ignored by
+    /// the next [`send`](Self::send) as its sequence number belongs to the cancelled message.
+    pub async fn send_cancellable(
+        &self,
+        message: F::Message,
+        cancel: impl Future,
+    ) -> Result<(), CofitError<F::Error>> {
+        let serialized = self
+            .format
+            .serialize(message)
+            .map_err(NetworkError::FormatError)?;
+
+        let priority = self.format.priority(serialized.id);
+        Ok(self
+            .send_serialized_until(serialized, priority, cancel)
+            .await?)
+    }
+
+    /// Transmits a message without acquiring the acknowledgement lock or waiting for an acknowledgement.
+    /// Intended for high-rate, one-way data like telemetry where per-message acknowledgements would saturate the link.
+    ///
+    /// Delivery is not guaranteed, messages arriving after a later one are dropped instead of being delivered out of order
+    /// (see [Ordering](Self#ordering)). Concurrent calls are not ordered amongst each other.
+    /// The message has to be flagged as unreliable by the [`WireFormat`]
+    /// (see [`WireFormat::is_reliable`]) so that the receiver does not acknowledge it. Otherwise, its acknowledgement
+    /// may be mistaken for the one of a concurrently sent reliable message.
+    pub async fn send_unreliable(&self, message: F::Message) -> Result<(), CofitError<F::Error>> {
         let serialized = self
             .format
             .serialize(message)
             .map_err(NetworkError::FormatError)?;
-        let header = PacketHeader::Message(serialized.id);
+
+        if self.format.is_reliable(serialized.id) {
+            return Err(NetworkError::ReliableMessage.into());
+        }
+
+        self.acquire_send_budget().await?;
 
         let mut data = [0; TMTU];
-        data[0] = header.into();
-        data[1..].copy_from_slice(&serialized.bytes);
+        data[0] = PacketHeader::Message(self.role, serialized.id).into();
+        data[1] = self.unreliable_sequence.next();
+        data[2..2 + PMTU].copy_from_slice(&serialized.bytes);
+
+        self.transport
+            .send(data)
+            .await
+            .map_err(NetworkError::Transport)?;
+        self.counters.record_sent();
+
+        Ok(())
+    }
+
+    async fn send_serialized(
+        &self,
+        serialized: SerializedMessage<PMTU>,
+    ) -> Result<(), NetworkError<F::Error>> {
+        let priority = self.format.priority(serialized.id);
+        self.send_serialized_until(serialized, priority, futures::future::pending::<()>())
+            .await
+    }
+
+    async fn send_serialized_until(
+        &self,
+        serialized: SerializedMessage<PMTU>,
+        priority: Priority,
+        cancel: impl Future,
+    ) -> Result<(), NetworkError<F::Error>> {
+        self.acquire_send_budget().await?;
 
         // Get hold of the acknowledgement mutex (there may only ever be one non-acked message in-flight)
-        let mut ack_receiver = self.ack_receiver.lock().await;
+        let mut ack_receiver = self.lock_ack_receiver(priority).await;
 
-        // Remove any pending acknowledgements
-        let dropped_ack_count = ack_receiver.clear();
-        if dropped_ack_count > 0 {
-            #[cfg(feature = "defmt")]
-            defmt::warn!("Dropped {} unexpected acknowledgements", dropped_ack_count);
+        // Number the message only now so that sequence numbers go out in order, retransmissions reuse it
+        let sequence = self.reliable_sequence.next();
+        let header = PacketHeader::Message(self.role, serialized.id);
+
+        let mut data = [0; TMTU];
+        data[0] = header.into();
+        data[1] = sequence;
+        data[2..2 + PMTU].copy_from_slice(&serialized.bytes);
+
+        pin_mut!(cancel);
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+
+            // Remove any pending acknowledgements
+            let dropped_ack_count = ack_receiver.clear();
+            if dropped_ack_count > 0 {
+                self.counters.record_unexpected_acks(dropped_ack_count);
+                #[cfg(feature = "defmt")]
+                defmt::warn!("Dropped {} unexpected acknowledgements", dropped_ack_count);
+            }
+
+            // Send the actual data
+            self.transport
+                .send(data)
+                .await
+                .map_err(NetworkError::Transport)?;
+            self.counters.record_sent();
+
+            // Wait for the ACK unless we are cancelled in the meantime, skipping late ones of previous messages
+            let acknowledgement = loop {
+                let ack = ack_receiver.recv_timeout(self.retry_policy.ack_timeout_ms);
+                pin_mut!(ack);
+
+                match select(ack, cancel.as_mut()).await {
+                    Either::Left((Some(ack), _)) if ack.sequence() != sequence => {
+                        self.counters.record_unexpected_acks(1);
+                        #[cfg(feature = "defmt")]
+                        defmt::debug!("dropped stale acknowledgement");
+                    }
+                    Either::Left((acknowledgement, _)) => break Ok(acknowledgement),
+                    Either::Right(_) => break Err(NetworkError::Cancelled),
+                }
+            };
+
+            // Discard acknowledgements which arrived while cancelling so they do not confuse the next send
+            if acknowledgement.is_err() {
+                ack_receiver.clear();
+            }
+
+            // Verify the ACK
+            match acknowledgement? {
+                Some(Acknowledgement::Ack(acknowledgement)) => {
+                    return if self
+                        .format
+                        .matches_ack(&serialized, &acknowledgement.message)
+                    {
+                        Ok(())
+                    } else {
+                        Err(NetworkError::UnexpectedAck)
+                    };
+                }
+                Some(Acknowledgement::Nack(_, id)) if id == serialized.id => {
+                    return Err(NetworkError::Rejected)
+                }
+                Some(Acknowledgement::Nack(..)) => return Err(NetworkError::UnexpectedAck),
+                None => {}
+            }
+
+            if attempts >= self.retry_policy.max_attempts() {
+                return Err(NetworkError::TimedOut { attempts });
+            }
+
+            // Back off before retransmitting, the message may still be cancelled in the meantime
+            let backoff_ms = self.retry_policy.backoff_ms(attempts);
+            if backoff_ms > 0 {
+                let driver = TimeDriver::default();
+                let deadline = driver.now()
+                    + <TimeDriver as TimeDriverTrait>::Duration::from_micros(
+                        backoff_ms as u64 * 1000,
+                    );
+
+                if let Either::Right(_) = select(driver.wait_until(deadline), cancel.as_mut()).await
+                {
+                    ack_receiver.clear();
+                    return Err(NetworkError::Cancelled);
+                }
+            }
         }
+    }
+
+    /// Acquires the acknowledgement lock, normal messages hand it over to urgent ones which are waiting for it as well
+    async fn lock_ack_receiver(
+        &self,
+        priority: Priority,
+    ) -> <Mutex<AckReceiver<'c, PMTU>> as MutexTrait>::Guard<'_> {
+        if priority == Priority::Urgent {
+            let _ticket = self.urgent_lane.enter();
+            return self.ack_receiver.lock().await;
+        }
+
+        let _queue = self.urgent_lane.queue_normal().await;
 
-        // Send the actual data
-        self.transport.send(data).await;
+        loop {
+            self.urgent_lane.vacated().await;
 
-        // Wait for and verify the ACK
-        if let Some(acknowledgement) = ack_receiver.recv_timeout(ACK_TIMEOUT_MS).await {
-            if acknowledgement == serialized {
-                Ok(())
-            } else {
-                Err(NetworkError::UnexpectedAck)
+            let ack_receiver = self.ack_receiver.lock().await;
+            if !self.urgent_lane.is_occupied() {
+                return ack_receiver;
             }
-        } else {
-            Err(NetworkError::TimedOut)
+
+            // An urgent message started waiting while we were, hand the lock over and park until it has taken it
+            drop(ack_receiver);
+        }
+    }
+
+    /// Waits for the rate limiter to allow sending another message, if one is configured
+    async fn acquire_send_budget(&self) -> Result<(), NetworkError<F::Error>> {
+        match &self.rate_limiter {
+            Some(limiter) if !limiter.acquire().await => Err(NetworkError::RateLimited),
+            _ => Ok(()),
         }
     }
 
     // Network task that processes incoming messages — has to be polled continously in the background for other functions to operate correctly
-    pub async fn recv_task(&self) {
+    //
+    // All internal channels hold at most `CHANNEL_CAPACITY` entries. Incoming messages and acknowledgements are dropped
+    // when their channel is full, while stream packets apply backpressure to the transport until the stream catches up.
+    //
+    // Only completes once the transport fails, returning its error. Messages still waiting for an acknowledgement time out.
+    pub async fn recv_task(&self) -> TransportError {
         loop {
-            let data = self.transport.recv().await;
-            let header: Result<PacketHeader, _> = data[0].try_into();
-
-            match header {
-                Ok(PacketHeader::Message(id)) => {
-                    let mut serialized = SerializedMessage {
-                        id,
-                        bytes: [0; PMTU],
-                    };
+            match self.transport.recv().await {
+                Ok(data) => self.handle_frame(data).await,
+                Err(error) => return error,
+            }
+        }
+    }
 
-                    serialized.bytes.copy_from_slice(&data[1..]);
+    /// Processes at most one incoming frame, returning whether there has been one available.
+    /// Intended for firmware which interleaves the network with other work from its own loop instead of
+    /// polling [`recv_task`](Self::recv_task) in a dedicated task.
+    ///
+    /// The loop has to call this often enough that incoming messages are acknowledged before the peer times out
+    /// waiting for them (see [`RetryPolicy`]). Likewise, a [`send`](Self::send) only completes once a later step
+    /// processed its acknowledgement, so the two have to be polled alongside each other, e.g. using [`futures::join!`].
+    ///
+    /// The receive future of the transport is dropped if no frame is available yet, which it has to tolerate without
+    /// losing frames. Once a frame has been received, the returned future has to be polled to completion.
+    /// Fails like [`recv_task`](Self::recv_task) once the transport does.
+    pub async fn recv_step(&self) -> Result<bool, TransportError> {
+        let receive = self.transport.recv();
+        pin_mut!(receive);
 
-                    self.message_sender.send(serialized).await;
-                }
-                Ok(PacketHeader::MessageAck(id)) => {
-                    let mut bytes = [0; PMTU];
-                    bytes.copy_from_slice(&data[1..]);
+        match futures::poll!(receive) {
+            Poll::Ready(data) => {
+                self.handle_frame(data?).await;
+                Ok(true)
+            }
+            Poll::Pending => Ok(false),
+        }
+    }
+
+    async fn handle_frame(&self, data: [u8; TMTU]) {
+        self.counters.record_received();
+        let header: Result<PacketHeader, _> = data[0].try_into();
+
+        match header {
+            Ok(PacketHeader::Message(role, _)) if role == self.role => {
+                #[cfg(feature = "defmt")]
+                defmt::debug!("dropped message sent by this side");
+            }
+            Ok(PacketHeader::MessageAck(role, _)) if role != self.role => {
+                #[cfg(feature = "defmt")]
+                defmt::debug!("dropped acknowledgement sent by this side");
+            }
+            Ok(PacketHeader::Message(_, id)) => {
+                let mut message = SerializedMessage {
+                    id,
+                    bytes: [0; PMTU],
+                };
+
+                let sequence = data[1];
+                message.bytes.copy_from_slice(&data[2..2 + PMTU]);
+
+                let reliable = self.format.is_reliable(id);
+                let received = if reliable {
+                    &self.reliable_received
+                } else {
+                    &self.unreliable_received
+                };
 
-                    self.ack_sender.send(SerializedMessage { id, bytes }).await;
+                if !received.is_due(sequence) {
+                    #[cfg(feature = "defmt")]
+                    defmt::debug!("dropped retransmitted or overtaken message");
+                    if reliable {
+                        self.repeat_reply(sequence).await;
+                    }
+                    return;
                 }
-                Ok(PacketHeader::StreamPacket(header)) => {
-                    // Check if someone has the stream receiver locked / a stream is open
-                    if self.stream_receiver.try_lock().is_some() {
-                        #[cfg(feature = "defmt")]
-                        defmt::warn!("received stream packet while no stream is open");
+
+                // Keep malformed messages away from the handlers, rejecting them right away so the sender does not time out
+                if !self.format.is_padded(&message) {
+                    self.counters.record_malformed_payload();
+                    #[cfg(feature = "defmt")]
+                    defmt::warn!("rejected message with data beyond its length");
+                    received.advance(sequence);
+                    if reliable {
+                        self.send_nack(sequence, id).await;
                     }
+                    return;
+                }
+
+                // Waiting for the handler could dead-lock if it awaits an acknowledgement itself, so drop the
+                // message instead. The sender will not receive an acknowledgement and time out.
+                if self
+                    .message_sender
+                    .try_send(SequencedMessage { sequence, message })
+                    .is_err()
+                {
+                    self.counters.record_dropped();
+                    #[cfg(feature = "defmt")]
+                    defmt::warn!("dropped incoming message, handler is lagging behind");
+                } else {
+                    self.counters.record_dispatched();
+                    received.advance(sequence);
+                }
+            }
+            Ok(PacketHeader::MessageAck(_, id)) => {
+                let mut bytes = [0; PMTU];
+                bytes.copy_from_slice(&data[2..2 + PMTU]);
 
-                    // Forward the stream packet
-                    let mut bytes = [0; PMTU];
-                    bytes.copy_from_slice(&data[1..]);
+                let acknowledgement = SequencedMessage {
+                    sequence: data[1],
+                    message: SerializedMessage { id, bytes },
+                };
 
-                    let packet = StreamPacket { header, bytes };
-                    self.stream_sender.send(packet).await;
+                if self
+                    .ack_sender
+                    .try_send(Acknowledgement::Ack(acknowledgement))
+                    .is_err()
+                {
+                    self.counters.record_unexpected_acks(1);
+                    #[cfg(feature = "defmt")]
+                    defmt::warn!("dropped unexpected acknowledgement");
+                }
+            }
+            Ok(PacketHeader::MessageNack) => match data[2].try_into() {
+                Ok(PacketHeader::Message(role, id)) if role == self.role => {
+                    let rejection = Acknowledgement::Nack(data[1], id);
+                    if self.ack_sender.try_send(rejection).is_err() {
+                        self.counters.record_unexpected_acks(1);
+                        #[cfg(feature = "defmt")]
+                        defmt::warn!("dropped unexpected rejection");
+                    }
+                }
+                _ => {
+                    #[cfg(feature = "defmt")]
+                    defmt::debug!("dropped rejection of a message not sent by this side");
                 }
-                Err(_) => {
+            },
+            Ok(PacketHeader::StreamPacket(header)) => {
+                // Feedback for the stream written by this side travels separately in full-duplex mode
+                let (sender, receiver) = match (&self.duplex_stream, header) {
+                    (
+                        Some((sender, receiver)),
+                        StreamPacketHeader::Revert | StreamPacketHeader::CloseAck,
+                    ) => (sender, receiver),
+                    _ => (&self.stream_sender, &self.stream_receiver),
+                };
+
+                // Check if someone has the stream receiver locked / a stream is open.
+                // Nobody would drain the packets otherwise, blocking this task once the channel is full.
+                if receiver.try_lock().is_some() {
+                    self.counters.record_orphaned_stream_packet();
                     #[cfg(feature = "defmt")]
-                    defmt::debug!("received packet with invalid header");
+                    defmt::warn!("dropped stream packet while no stream is open");
+                    return;
                 }
+
+                // Forward the stream packet, waiting for the open stream to make room.
+                // Its last byte is not used, see `STREAM_HEADER_SIZE`.
+                let mut bytes = [0; PMTU];
+                bytes.copy_from_slice(&data[1..1 + PMTU]);
+
+                let packet = StreamPacket { header, bytes };
+                sender.send(packet).await;
+            }
+            Err(_) => {
+                self.counters.record_malformed();
+                #[cfg(feature = "defmt")]
+                defmt::debug!("received packet with invalid header");
             }
         }
     }
 
     /// Receives incoming messages and processes them using the given message handler
-    pub async fn recv_with<H: MessageHandler<F::Message, PMTU>>(&self, mut handler: H) {
+    pub async fn recv_with<H: MessageHandler<F, PMTU>>(&self, mut handler: H) {
         let mut receiver = self
             .message_receiver
             .try_lock()
             .expect("unable to lock message receiver, did you call recv_with twice?");
 
-        let send_ack_channel = Channel::new();
-        let mut send_ack_rx = send_ack_channel.receiver();
+        let reply_channel = Channel::new();
+        let response_channel = Channel::new();
 
         let recv_task = async {
             loop {
                 match receiver
                     .recv_timeout(u32::MAX)
                     .await
-                    .map(|serialized| (serialized, self.format.deserialize(serialized)))
+                    .map(|received| (received, self.format.deserialize(received.message)))
                 {
-                    Some((serialized, Ok(message))) => {
-                        let acknowledger = MessageAcknowledger {
-                            serialized,
-                            sender: send_ack_channel.sender(),
-                            sent: false,
-                        };
-
+                    Some((received, Ok(message))) => {
+                        let acknowledger = self.acknowledger(
+                            received,
+                            reply_channel.sender(),
+                            response_channel.sender(),
+                        );
                         handler.handle(message, acknowledger).await
                     }
                     Some((_, Err(_))) => {
@@ -314,24 +1022,1159 @@ impl<
             }
         };
 
-        let ack_task = async {
+        futures::join!(
+            recv_task,
+            self.reply_task(reply_channel.receiver()),
+            self.response_task(response_channel.receiver())
+        );
+    }
+
+    /// Receives incoming messages without deserializing them and processes them using the given raw message handler.
+    /// Use this instead of [`recv_with`](Self::recv_with) to avoid copying large payloads, see [`RawMessageHandler`].
+    pub async fn recv_raw_with<H: RawMessageHandler<F, PMTU>>(&self, mut handler: H) {
+        let mut receiver = self
+            .message_receiver
+            .try_lock()
+            .expect("unable to lock message receiver, did you call recv_with twice?");
+
+        let reply_channel = Channel::new();
+        let response_channel = Channel::new();
+
+        let recv_task = async {
             loop {
-                if let Some(serialized) = send_ack_rx.recv_timeout(u32::MAX).await {
-                    self.send_ack(serialized).await;
+                if let Some(received) = receiver.recv_timeout(u32::MAX).await {
+                    let acknowledger = self.acknowledger(
+                        received,
+                        reply_channel.sender(),
+                        response_channel.sender(),
+                    );
+                    handler.handle(received.message.id, acknowledger).await
                 }
             }
         };
 
-        futures::join!(recv_task, ack_task);
+        futures::join!(
+            recv_task,
+            self.reply_task(reply_channel.receiver()),
+            self.response_task(response_channel.receiver())
+        );
+    }
+
+    fn acknowledger<'a>(
+        &'a self,
+        received: SequencedMessage<PMTU>,
+        sender: ReplySender<'a, PMTU>,
+        responder: ResponseSender<'a, PMTU>,
+    ) -> MessageAcknowledger<'a, F, PMTU> {
+        // Unreliable messages are never acknowledged, so treat them as if they already were
+        MessageAcknowledger {
+            received,
+            sender,
+            responder,
+            format: &self.format,
+            sent: !self.format.is_reliable(received.message.id),
+        }
+    }
+
+    /// Transmits the replies queued by the [`MessageAcknowledger`]s of a message handler
+    async fn reply_task(&self, mut replies: ReplyReceiver<'_, PMTU>) {
+        loop {
+            match replies.recv_timeout(u32::MAX).await {
+                Some(Reply::Ack(received)) => self.send_ack(received).await,
+                Some(Reply::Nack(sequence, id)) => self.send_nack(sequence, id).await,
+                None => {}
+            }
+        }
+    }
+
+    /// Sends the responses queued by the [`MessageAcknowledger`]s of a message handler.
+    /// Kept apart from the [`reply_task`](Self::reply_task) as each response waits for its own acknowledgement.
+    async fn response_task(&self, mut responses: ResponseReceiver<'_, PMTU>) {
+        loop {
+            if let Some(serialized) = responses.recv_timeout(u32::MAX).await {
+                if self.send_serialized(serialized).await.is_err() {
+                    #[cfg(feature = "defmt")]
+                    defmt::error!("Failed to send response");
+                }
+            }
+        }
     }
 
-    async fn send_ack(&self, serialized: SerializedMessage<PMTU>) {
-        let header = PacketHeader::MessageAck(serialized.id);
+    async fn send_ack(&self, received: SequencedMessage<PMTU>) {
+        let header = PacketHeader::MessageAck(self.role.peer(), received.message.id);
 
         let mut data = [0; TMTU];
         data[0] = header.into();
-        data[1..].copy_from_slice(&serialized.bytes);
+        data[1] = received.sequence;
+        data[2..2 + PMTU].copy_from_slice(&received.message.bytes);
+
+        self.send_reply(data).await;
+    }
+
+    async fn send_nack(&self, sequence: u8, id: ID) {
+        let mut data = [0; TMTU];
+        data[0] = PacketHeader::MessageNack.into();
+        data[1] = sequence;
+        data[2] = PacketHeader::Message(self.role.peer(), id).into();
+
+        self.send_reply(data).await;
+    }
+
+    /// Sends an acknowledgement frame, remembering it in case the peer retransmits the message
+    async fn send_reply(&self, data: [u8; TMTU]) {
+        *self.last_reply.lock().await = Some(data);
+        self.transmit_reply(data).await;
+    }
+
+    /// Repeats the reply to a retransmitted message in case the original one got lost.
+    /// Nothing is sent while the message is still being handled, its handler will reply once done.
+    async fn repeat_reply(&self, sequence: u8) {
+        let last_reply = match self.last_reply.try_lock() {
+            Some(last_reply) => *last_reply,
+            None => None,
+        };
+
+        if let Some(data) = last_reply.filter(|data| data[1] == sequence) {
+            self.transmit_reply(data).await;
+        }
+    }
+
+    /// Replies are best-effort, a failed transport surfaces through the [`recv_task`](Self::recv_task) instead
+    async fn transmit_reply(&self, data: [u8; TMTU]) {
+        if self.transport.send(data).await.is_ok() {
+            self.counters.record_sent();
+        } else {
+            #[cfg(feature = "defmt")]
+            defmt::warn!("failed to send reply");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod does {
+    use super::testing::{LoopbackLink, RawFormat};
+    use super::*;
+    use crate::firmware::executor_support::CHANNEL_CAPACITY;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use futures::future::{select, Either};
+
+    /// Like [`RawFormat`] but matches acknowledgements by their ID only
+    struct IdMatchingFormat;
+
+    impl WireFormat<7> for IdMatchingFormat {
+        type Message = SerializedMessage<7>;
+        type Error = ();
+
+        fn serialize(&self, message: Self::Message) -> Result<SerializedMessage<7>, ()> {
+            Ok(message)
+        }
+
+        fn deserialize(&self, packet: SerializedMessage<7>) -> Result<Self::Message, ()> {
+            Ok(packet)
+        }
+
+        fn matches_ack(&self, sent: &SerializedMessage<7>, ack: &SerializedMessage<7>) -> bool {
+            sent.id == ack.id
+        }
+    }
+
+    /// Like [`RawFormat`] but never expects acknowledgements
+    struct UnreliableFormat;
+
+    impl WireFormat<7> for UnreliableFormat {
+        type Message = SerializedMessage<7>;
+        type Error = ();
+
+        fn serialize(&self, message: Self::Message) -> Result<SerializedMessage<7>, ()> {
+            Ok(message)
+        }
+
+        fn deserialize(&self, packet: SerializedMessage<7>) -> Result<Self::Message, ()> {
+            Ok(packet)
+        }
+
+        fn is_reliable(&self, _id: ID) -> bool {
+            false
+        }
+    }
+
+    /// Like [`RawFormat`] but with messages occupying only the first three bytes of the payload
+    struct ShortFormat;
+
+    impl WireFormat<7> for ShortFormat {
+        type Message = SerializedMessage<7>;
+        type Error = ();
+
+        fn serialize(&self, message: Self::Message) -> Result<SerializedMessage<7>, ()> {
+            Ok(message)
+        }
+
+        fn deserialize(&self, packet: SerializedMessage<7>) -> Result<Self::Message, ()> {
+            self.is_padded(&packet).then_some(packet).ok_or(())
+        }
+
+        fn message_length(&self, _id: ID) -> usize {
+            3
+        }
+    }
+
+    /// Transport whose other end is gone, failing every send and receive
+    struct UnpluggedTransport;
+
+    impl Transport<9> for UnpluggedTransport {
+        type TxFut<'t> = core::future::Ready<Result<(), TransportError>>;
+        type RxFut<'t> = core::future::Ready<Result<[u8; 9], TransportError>>;
+
+        fn send(&self, _data: [u8; 9]) -> Self::TxFut<'_> {
+            core::future::ready(Err(TransportError::Disconnected))
+        }
+
+        fn recv(&self) -> Self::RxFut<'_> {
+            core::future::ready(Err(TransportError::Disconnected))
+        }
+    }
+
+    /// Sends a message from a host using the given format and acknowledges it with a different payload
+    async fn send_with_altered_ack<F: WireFormat<7, Message = SerializedMessage<7>>>(
+        format: F,
+    ) -> Result<(), CofitError<F::Error>> {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral_transport) = link.host(format);
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        let peer = async {
+            let mut ack = peripheral_transport.recv().await.unwrap();
+            ack[0] = PacketHeader::MessageAck(Role::Host, message.id).into();
+            ack[2..].copy_from_slice(&[0; 7]);
+            peripheral_transport.send(ack).await.unwrap();
+        };
+
+        let send = async { futures::join!(host.send(message), peer).0 };
+
+        let result = match select(Box::pin(send), Box::pin(host.recv_task())).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => unreachable!("receive task never completes"),
+        };
+
+        result
+    }
+
+    /// Acknowledges every incoming message and counts them
+    struct CountingHandler<'h>(&'h AtomicUsize);
+
+    impl<'h> MessageHandler<RawFormat, 7> for CountingHandler<'h> {
+        type HandlerFut<'s> = impl Future<Output = ()> + 's where Self: 's, RawFormat: 's;
+
+        fn handle<'s>(
+            &'s mut self,
+            _message: SerializedMessage<7>,
+            acknowledger: MessageAcknowledger<'s, RawFormat, 7>,
+        ) -> Self::HandlerFut<'s> {
+            async move {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                acknowledger.acknowledge().await;
+            }
+        }
+    }
+
+    /// Records the payload of every incoming message by borrowing it, then acknowledges it
+    struct PayloadHandler<'h>(&'h std::sync::Mutex<Vec<u8>>);
+
+    impl<'h> RawMessageHandler<RawFormat, 7> for PayloadHandler<'h> {
+        type HandlerFut<'s> = impl Future<Output = ()> + 's where Self: 's, RawFormat: 's;
+
+        fn handle<'s>(
+            &'s mut self,
+            _id: ID,
+            acknowledger: MessageAcknowledger<'s, RawFormat, 7>,
+        ) -> Self::HandlerFut<'s> {
+            async move {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .extend_from_slice(acknowledger.payload());
+                acknowledger.acknowledge().await;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn lend_payload_to_raw_handlers() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral) = link.connect(RawFormat, RawFormat);
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [1, 2, 3, 4, 5, 6, 7],
+        };
+
+        let received = std::sync::Mutex::new(Vec::new());
+        let background = async {
+            futures::join!(
+                host.recv_task(),
+                peripheral.recv_task(),
+                peripheral.recv_raw_with(PayloadHandler(&received)),
+            )
+        };
+
+        let result = match select(Box::pin(host.send(message)), Box::pin(background)).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => unreachable!("background tasks never complete"),
+        };
+
+        assert!(result.is_ok());
+        assert_eq!(*received.lock().unwrap(), message.bytes);
+    }
+
+    #[tokio::test]
+    async fn report_in_flight_messages() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral) = link.connect(RawFormat, RawFormat);
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        assert!(!host.is_awaiting_ack());
+        assert_eq!(host.queued_frames(), 0);
+
+        // Nobody is receiving on the peripheral yet, so the message stays in-flight
+        let mut send = Box::pin(host.send(message));
+        assert!(futures::poll!(send.as_mut()).is_pending());
+        assert!(host.is_awaiting_ack());
+
+        let count = AtomicUsize::new(0);
+        let background = async {
+            futures::join!(
+                host.recv_task(),
+                peripheral.recv_task(),
+                peripheral.recv_with(CountingHandler(&count)),
+            )
+        };
+
+        let result = match select(send, Box::pin(background)).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => unreachable!("background tasks never complete"),
+        };
+
+        assert!(result.is_ok());
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(!host.is_awaiting_ack());
+        // The loopback transport hands frames over immediately instead of buffering them
+        assert_eq!(host.queued_frames(), 0);
+    }
+
+    #[tokio::test]
+    async fn transfer_streams() {
+        use futures::TryStreamExt;
+
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral) = link.connect(RawFormat, RawFormat);
+
+        let chunks = [[1; 5], [2; 5], [3; 5]];
+
+        let transfer = async {
+            let reader = peripheral.create_stream_reader().await.into_stream();
+            let mut writer = host
+                .create_stream_writer(futures::stream::iter(chunks))
+                .await;
+
+            let write = async {
+                while !writer.send().await? {}
+                Ok::<_, StreamError>(())
+            };
+
+            futures::join!(write, reader.try_collect::<Vec<_>>())
+        };
+
+        let background = async { futures::join!(host.recv_task(), peripheral.recv_task()) };
+
+        let (written, read) = match select(Box::pin(transfer), Box::pin(background)).await {
+            Either::Left((results, _)) => results,
+            Either::Right(_) => unreachable!("background tasks never complete"),
+        };
+
+        assert_eq!(written, Ok(()));
+        assert_eq!(read, Ok(chunks.to_vec()));
+    }
+
+    #[tokio::test]
+    async fn transfer_streams_in_both_directions() {
+        use futures::TryStreamExt;
+
+        let host_duplex_channel = Channel::new();
+        let peripheral_duplex_channel = Channel::new();
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral) = link.connect(RawFormat, RawFormat);
+        let host = host.with_duplex_streams(host_duplex_channel.split());
+        let peripheral = peripheral.with_duplex_streams(peripheral_duplex_channel.split());
+
+        let upload = [[1; 5], [2; 5], [3; 5]];
+        let download = [[4; 5], [5; 5]];
+
+        let transfer = async {
+            // Both sides have a reader and a writer open at the same time
+            let host_reader = host.create_stream_reader().await.into_stream();
+            let peripheral_reader = peripheral.create_stream_reader().await.into_stream();
+            let mut host_writer = host
+                .create_stream_writer(futures::stream::iter(upload))
+                .await;
+            let mut peripheral_writer = peripheral
+                .create_stream_writer(futures::stream::iter(download))
+                .await;
+
+            let write_upload = async {
+                while !host_writer.send().await? {}
+                Ok::<_, StreamError>(())
+            };
+
+            let write_download = async {
+                while !peripheral_writer.send().await? {}
+                Ok::<_, StreamError>(())
+            };
+
+            futures::join!(
+                write_upload,
+                write_download,
+                peripheral_reader.try_collect::<Vec<_>>(),
+                host_reader.try_collect::<Vec<_>>()
+            )
+        };
+
+        let background = async { futures::join!(host.recv_task(), peripheral.recv_task()) };
+
+        let (uploaded, downloaded, upload_read, download_read) =
+            match select(Box::pin(transfer), Box::pin(background)).await {
+                Either::Left((results, _)) => results,
+                Either::Right(_) => unreachable!("background tasks never complete"),
+            };
+
+        assert_eq!(uploaded, Ok(()));
+        assert_eq!(downloaded, Ok(()));
+        assert_eq!(upload_read, Ok(upload.to_vec()));
+        assert_eq!(download_read, Ok(download.to_vec()));
+    }
+
+    #[tokio::test]
+    async fn exchange_messages_by_polling() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral) = link.connect(RawFormat, RawFormat);
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        // Emulates a bare polling loop, yielding only to let the host side make progress
+        let polling = async {
+            let network = peripheral.polling();
+            pin_mut!(network);
+
+            let received = loop {
+                match network.as_mut().poll_recv() {
+                    Err(nb::Error::WouldBlock) => tokio::task::yield_now().await,
+                    result => break result,
+                }
+            };
+
+            assert_eq!(received.unwrap(), message);
+
+            loop {
+                match network.as_mut().poll_send(message) {
+                    Err(nb::Error::WouldBlock) => tokio::task::yield_now().await,
+                    result => break result,
+                }
+            }
+        };
+
+        let count = AtomicUsize::new(0);
+        let exchange = async { futures::join!(host.send(message), polling) };
+        let background =
+            async { futures::join!(host.recv_task(), host.recv_with(CountingHandler(&count))) };
+
+        let (sent, replied) = match select(Box::pin(exchange), Box::pin(background)).await {
+            Either::Left((results, _)) => results,
+            Either::Right(_) => unreachable!("background tasks never complete"),
+        };
+
+        assert!(sent.is_ok());
+        assert!(replied.is_ok());
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    /// Starts working on every incoming message but gives up before acknowledging it
+    struct AbandoningHandler;
+
+    impl MessageHandler<RawFormat, 7> for AbandoningHandler {
+        type HandlerFut<'s> = impl Future<Output = ()> + 's where Self: 's, RawFormat: 's;
+
+        fn handle<'s>(
+            &'s mut self,
+            _message: SerializedMessage<7>,
+            acknowledger: MessageAcknowledger<'s, RawFormat, 7>,
+        ) -> Self::HandlerFut<'s> {
+            async move {
+                let work = async move {
+                    futures::future::pending::<()>().await;
+                    acknowledger.acknowledge().await;
+                };
+
+                select(Box::pin(work), futures::future::ready(())).await;
+            }
+        }
+    }
+
+    /// Records the first payload byte of every incoming message, acknowledging some of them only after a while
+    struct RecordingHandler<'h>(&'h std::sync::Mutex<Vec<u8>>);
+
+    impl<'h> MessageHandler<RawFormat, 7> for RecordingHandler<'h> {
+        type HandlerFut<'s> = impl Future<Output = ()> + 's where Self: 's, RawFormat: 's;
+
+        fn handle<'s>(
+            &'s mut self,
+            message: SerializedMessage<7>,
+            acknowledger: MessageAcknowledger<'s, RawFormat, 7>,
+        ) -> Self::HandlerFut<'s> {
+            async move {
+                let index = message.bytes[0];
+                self.0.lock().unwrap().push(index);
+
+                if index % 3 == 1 {
+                    tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+                }
+
+                acknowledger.acknowledge().await;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn deliver_messages_in_order() {
+        const MESSAGE_COUNT: u8 = 32;
+
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral) = link.connect(RawFormat, RawFormat);
+        let host = host.with_retry_policy(RetryPolicy {
+            max_attempts: 5,
+            ack_timeout_ms: 20,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 5,
+        });
+
+        let received = std::sync::Mutex::new(Vec::new());
+
+        // Acknowledging slower than the timeout makes the host retransmit while a message is still being handled
+        let sends = async {
+            for index in 0..MESSAGE_COUNT {
+                let message = SerializedMessage {
+                    id: 1.into(),
+                    bytes: [index; 7],
+                };
+
+                host.send(message).await?;
+            }
+
+            Ok::<_, CofitError<()>>(())
+        };
+
+        let background = async {
+            futures::join!(
+                host.recv_task(),
+                peripheral.recv_task(),
+                peripheral.recv_with(RecordingHandler(&received)),
+            )
+        };
+
+        let result = match select(Box::pin(sends), Box::pin(background)).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => unreachable!("background tasks never complete"),
+        };
+
+        assert!(result.is_ok());
+        assert!(host.statistics().frames_sent > MESSAGE_COUNT as u32);
+        assert_eq!(
+            *received.lock().unwrap(),
+            (0..MESSAGE_COUNT).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn reject_messages_of_cancelled_handlers() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral) = link.connect(RawFormat, RawFormat);
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        let background = async {
+            futures::join!(
+                host.recv_task(),
+                peripheral.recv_task(),
+                peripheral.recv_with(AbandoningHandler),
+            )
+        };
+
+        let result = match select(Box::pin(host.send(message)), Box::pin(background)).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => unreachable!("background tasks never complete"),
+        };
+
+        assert!(matches!(
+            result,
+            Err(CofitError::Network(NetworkError::Rejected))
+        ));
+    }
+
+    /// Responds to every incoming message with its payload incremented by one
+    struct RespondingHandler;
+
+    impl MessageHandler<RawFormat, 7> for RespondingHandler {
+        type HandlerFut<'s> = impl Future<Output = ()> + 's where Self: 's, RawFormat: 's;
+
+        fn handle<'s>(
+            &'s mut self,
+            message: SerializedMessage<7>,
+            acknowledger: MessageAcknowledger<'s, RawFormat, 7>,
+        ) -> Self::HandlerFut<'s> {
+            async move {
+                let response = SerializedMessage {
+                    id: message.id,
+                    bytes: message.bytes.map(|byte| byte + 1),
+                };
+
+                assert!(acknowledger.respond(response).await.is_ok());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn respond_to_messages() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral) = link.connect(RawFormat, RawFormat);
+
+        let received = std::sync::Mutex::new(Vec::new());
+
+        let request = async {
+            let message = SerializedMessage {
+                id: 1.into(),
+                bytes: [2; 7],
+            };
+
+            host.send(message).await?;
+
+            while received.lock().unwrap().is_empty() {
+                tokio::task::yield_now().await;
+            }
+
+            Ok::<_, CofitError<()>>(())
+        };
+
+        let background = async {
+            futures::join!(
+                host.recv_task(),
+                host.recv_with(RecordingHandler(&received)),
+                peripheral.recv_task(),
+                peripheral.recv_with(RespondingHandler),
+            )
+        };
+
+        let exchange = select(Box::pin(request), Box::pin(background));
+        let result = match tokio::time::timeout(std::time::Duration::from_secs(1), exchange).await {
+            Ok(Either::Left((result, _))) => result,
+            Ok(Either::Right(_)) => unreachable!("background tasks never complete"),
+            Err(_) => panic!("response did not arrive in time"),
+        };
+
+        assert!(result.is_ok());
+        assert_eq!(*received.lock().unwrap(), vec![3]);
+    }
+
+    #[tokio::test]
+    async fn deliver_simultaneous_messages() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral) = link.connect(RawFormat, RawFormat);
+
+        let host_received = AtomicUsize::new(0);
+        let peripheral_received = AtomicUsize::new(0);
+
+        // Both sides send the exact same message so their frames only differ by the role
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        let send = async { futures::join!(host.send(message), peripheral.send(message)) };
+        let background = async {
+            futures::join!(
+                host.recv_task(),
+                peripheral.recv_task(),
+                host.recv_with(CountingHandler(&host_received)),
+                peripheral.recv_with(CountingHandler(&peripheral_received)),
+            )
+        };
+
+        let (host_result, peripheral_result) =
+            match select(Box::pin(send), Box::pin(background)).await {
+                Either::Left((results, _)) => results,
+                Either::Right(_) => unreachable!("background tasks never complete"),
+            };
+
+        assert!(host_result.is_ok());
+        assert!(peripheral_result.is_ok());
+        assert_eq!(host_received.load(Ordering::SeqCst), 1);
+        assert_eq!(peripheral_received.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn drop_messages_when_saturated() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral_transport) = link.host(RawFormat);
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        // Flood the host with more messages than it can buffer while nobody handles them and a garbled frame,
+        // then acknowledge the message it sent in the meantime
+        let peer = async {
+            let mut frame = [0; 9];
+            frame[0] = PacketHeader::Message(Role::Peripheral, message.id).into();
+            frame[2..].copy_from_slice(&message.bytes);
+
+            for sequence in 0..CHANNEL_CAPACITY * 2 {
+                frame[1] = sequence as u8;
+                peripheral_transport.send(frame).await.unwrap();
+            }
+
+            peripheral_transport.send([0xFF; 9]).await.unwrap();
+
+            let mut ack = peripheral_transport.recv().await.unwrap();
+            ack[0] = PacketHeader::MessageAck(Role::Host, message.id).into();
+            peripheral_transport.send(ack).await.unwrap();
+        };
+
+        let send = async { futures::join!(host.send(message), peer).0 };
+
+        let result = match select(Box::pin(send), Box::pin(host.recv_task())).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => unreachable!("receive task never completes"),
+        };
+
+        // The receive task kept processing frames and only buffered what fits
+        assert!(result.is_ok());
+        let mut receiver = host.message_receiver.try_lock().unwrap();
+        assert_eq!(receiver.clear(), CHANNEL_CAPACITY);
+
+        let statistics = host.statistics();
+        assert_eq!(statistics.frames_sent, 1);
+        assert_eq!(statistics.frames_received, CHANNEL_CAPACITY as u32 * 2 + 2);
+        assert_eq!(statistics.malformed_headers, 1);
+        assert_eq!(statistics.dispatched_messages, CHANNEL_CAPACITY as u32);
+        assert_eq!(statistics.dropped_messages, CHANNEL_CAPACITY as u32);
+    }
+
+    #[tokio::test]
+    async fn count_frames_nobody_expected() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral_transport) = link.host(RawFormat);
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        // Acknowledge a message that has never been sent and write to a stream that is not open
+        let mut frame = [0; 9];
+        frame[0] = PacketHeader::MessageAck(Role::Host, message.id).into();
+        peripheral_transport.send(frame).await.unwrap();
+        frame[0] = PacketHeader::StreamPacket(StreamPacketHeader::Content(0)).into();
+        peripheral_transport.send(frame).await.unwrap();
+
+        assert_eq!(host.recv_step().await, Ok(true));
+        assert_eq!(host.recv_step().await, Ok(true));
+        assert_eq!(host.statistics().orphaned_stream_packets, 1);
+
+        // The acknowledgement is only discarded once the next message goes out
+        let peer = async {
+            let mut ack = peripheral_transport.recv().await.unwrap();
+            ack[0] = PacketHeader::MessageAck(Role::Host, message.id).into();
+            peripheral_transport.send(ack).await.unwrap();
+        };
+
+        let send = async { futures::join!(host.send(message), peer).0 };
+
+        let result = match select(Box::pin(send), Box::pin(host.recv_task())).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => unreachable!("receive task never completes"),
+        };
+
+        assert!(result.is_ok());
+        let statistics = host.statistics();
+        assert_eq!(statistics.unexpected_acknowledgements, 1);
+        assert_eq!(statistics.dispatched_messages, 0);
+    }
+
+    #[tokio::test]
+    async fn reject_messages_with_data_beyond_their_length() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral_transport) = link.host(ShortFormat);
+
+        // Send a message padded with zeros followed by one carrying junk in its padding
+        let peer = async {
+            let mut frame = [0; 9];
+            frame[0] = PacketHeader::Message(Role::Peripheral, 1.into()).into();
+            frame[2..5].copy_from_slice(&[1, 2, 3]);
+            peripheral_transport.send(frame).await.unwrap();
+
+            frame[1] = 1;
+            frame[5] = 4;
+            peripheral_transport.send(frame).await.unwrap();
+
+            peripheral_transport.recv().await.unwrap()
+        };
+
+        let reply = match select(Box::pin(peer), Box::pin(host.recv_task())).await {
+            Either::Left((reply, _)) => reply,
+            Either::Right(_) => unreachable!("receive task never completes"),
+        };
+
+        let nack: u8 = PacketHeader::MessageNack.into();
+        assert_eq!(reply[0], nack);
+        assert_eq!(reply[1], 1);
+
+        let mut receiver = host.message_receiver.try_lock().unwrap();
+        assert_eq!(receiver.clear(), 1);
+        assert_eq!(host.statistics().malformed_payloads, 1);
+    }
+
+    #[tokio::test]
+    async fn process_one_frame_per_step() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral_transport) = link.host(RawFormat);
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        // Steps return right away while there is nothing to receive
+        assert_eq!(host.recv_step().await, Ok(false));
+
+        let mut frame = [0; 9];
+        frame[0] = PacketHeader::Message(Role::Peripheral, message.id).into();
+        frame[2..].copy_from_slice(&message.bytes);
+
+        for sequence in 0..2 {
+            frame[1] = sequence;
+            peripheral_transport.send(frame).await.unwrap();
+        }
+
+        assert_eq!(host.recv_step().await, Ok(true));
+        let mut receiver = host.message_receiver.try_lock().unwrap();
+        assert_eq!(
+            receiver.try_recv().map(|received| received.message),
+            Some(message)
+        );
+        assert!(receiver.try_recv().is_none());
+        drop(receiver);
+
+        assert_eq!(host.recv_step().await, Ok(true));
+        assert_eq!(host.recv_step().await, Ok(false));
+        assert_eq!(host.statistics().frames_received, 2);
+    }
+
+    #[tokio::test]
+    async fn reject_unreliable_send_of_reliable_message() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, _peripheral_transport) = link.host(RawFormat);
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        assert!(matches!(
+            host.send_unreliable(message).await,
+            Err(CofitError::Network(NetworkError::ReliableMessage))
+        ));
+    }
+
+    #[tokio::test]
+    async fn match_acks_by_content() {
+        assert!(matches!(
+            send_with_altered_ack(RawFormat).await,
+            Err(CofitError::Network(NetworkError::UnexpectedAck))
+        ));
+    }
+
+    #[tokio::test]
+    async fn match_acks_by_id() {
+        assert!(send_with_altered_ack(IdMatchingFormat).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn recover_from_cancelled_send() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral_transport) = link.host(RawFormat);
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        let sends = async {
+            // Give up once the message reached the peer, which never acknowledges it
+            let cancelled = host
+                .send_cancellable(message, peripheral_transport.recv())
+                .await;
+
+            let peer = async {
+                let mut ack = peripheral_transport.recv().await.unwrap();
+                ack[0] = PacketHeader::MessageAck(Role::Host, message.id).into();
+                peripheral_transport.send(ack).await.unwrap();
+            };
+
+            (cancelled, futures::join!(host.send(message), peer).0)
+        };
+
+        let (cancelled, sent) = match select(Box::pin(sends), Box::pin(host.recv_task())).await {
+            Either::Left((results, _)) => results,
+            Either::Right(_) => unreachable!("receive task never completes"),
+        };
+
+        assert!(matches!(
+            cancelled,
+            Err(CofitError::Network(NetworkError::Cancelled))
+        ));
+        assert!(sent.is_ok());
+    }
+
+    #[tokio::test]
+    async fn pace_rate_limited_sends() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, _peripheral_transport) = link.host(UnreliableFormat);
+        let host = host.with_rate_limit(RateLimit {
+            messages_per_second: 20,
+            burst: 1,
+            policy: RateLimitPolicy::Block,
+        });
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        let start = std::time::Instant::now();
+        for _ in 0..4 {
+            assert!(host.send_unreliable(message).await.is_ok());
+        }
+
+        // The first message uses the burst, every following one has to wait for the 50ms refill interval
+        assert!(start.elapsed() >= std::time::Duration::from_millis(150));
+        assert_eq!(host.dropped_message_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn drop_rate_limited_sends() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, _peripheral_transport) = link.host(UnreliableFormat);
+        let host = host.with_rate_limit(RateLimit {
+            messages_per_second: 1,
+            burst: 2,
+            policy: RateLimitPolicy::Drop,
+        });
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        assert!(host.send_unreliable(message).await.is_ok());
+        assert!(host.send_unreliable(message).await.is_ok());
+        assert!(matches!(
+            host.send_unreliable(message).await,
+            Err(CofitError::Network(NetworkError::RateLimited))
+        ));
+        assert_eq!(host.dropped_message_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn retransmit_unacknowledged_messages() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral_transport) = link.host(RawFormat);
+        let host = host.with_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            ack_timeout_ms: 20,
+            initial_backoff_ms: 5,
+            max_backoff_ms: 10,
+        });
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        // Lose the first transmission and acknowledge the second one
+        let peer = async {
+            peripheral_transport.recv().await.unwrap();
+
+            let mut ack = peripheral_transport.recv().await.unwrap();
+            ack[0] = PacketHeader::MessageAck(Role::Host, message.id).into();
+            peripheral_transport.send(ack).await.unwrap();
+        };
+
+        let sends = async {
+            let acknowledged = futures::join!(host.send(message), peer).0;
+            (acknowledged, host.send(message).await)
+        };
+
+        let (acknowledged, unacknowledged) =
+            match select(Box::pin(sends), Box::pin(host.recv_task())).await {
+                Either::Left((results, _)) => results,
+                Either::Right(_) => unreachable!("receive task never completes"),
+            };
+
+        assert!(acknowledged.is_ok());
+        assert!(matches!(
+            unacknowledged,
+            Err(CofitError::Network(NetworkError::TimedOut { attempts: 3 }))
+        ));
+        assert_eq!(host.statistics().frames_sent, 5);
+    }
+
+    #[tokio::test]
+    async fn leave_reserved_bytes_to_the_application() {
+        type Mtu = DerivedMtu<10, 1>;
+        let link = LoopbackLink::<10, 1, { Mtu::PMTU }, { Mtu::SMTU }>::new();
+        let (host, peripheral_transport) = link.host(RawFormat);
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        // Acknowledge the message with the reserved byte set, like e.g. a checksumming transport would
+        let peer = async {
+            let mut ack = peripheral_transport.recv().await.unwrap();
+            let reserved = ack[9];
+            ack[0] = PacketHeader::MessageAck(Role::Host, message.id).into();
+            ack[9] = 0xFF;
+            peripheral_transport.send(ack).await.unwrap();
+            reserved
+        };
+
+        let send = async { futures::join!(host.send(message), peer) };
+
+        let (result, reserved) = match select(Box::pin(send), Box::pin(host.recv_task())).await {
+            Either::Left((results, _)) => results,
+            Either::Right(_) => unreachable!("receive task never completes"),
+        };
+
+        assert!(result.is_ok());
+        assert_eq!(reserved, 0);
+    }
+
+    #[tokio::test]
+    async fn send_urgent_messages_before_queued_ones() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral_transport) = link.host(RawFormat);
+
+        let [first, queued, urgent] = [1, 2, 3].map(|n: u8| SerializedMessage {
+            id: n.into(),
+            bytes: [n; 7],
+        });
+
+        // Acknowledges every message, recording the order they arrived in
+        let peer = async {
+            let mut order = Vec::new();
+
+            for _ in 0..3 {
+                let mut ack = peripheral_transport.recv().await.unwrap();
+                order.push(ack[2]);
+                ack[0] = PacketHeader::MessageAck(Role::Host, ack[2].into()).into();
+                peripheral_transport.send(ack).await.unwrap();
+            }
+
+            order
+        };
+
+        // The first message is in-flight by the time the other two start waiting for the acknowledgement lock
+        let send = async {
+            futures::join!(
+                host.send(first),
+                host.send(queued),
+                host.send_priority(urgent),
+                peer
+            )
+        };
+
+        let (first, queued, urgent, order) =
+            match select(Box::pin(send), Box::pin(host.recv_task())).await {
+                Either::Left((results, _)) => results,
+                Either::Right(_) => unreachable!("receive task never completes"),
+            };
+
+        assert!(first.is_ok() && queued.is_ok() && urgent.is_ok());
+        assert_eq!(order, [1, 3, 2]);
+    }
+
+    #[tokio::test]
+    async fn report_transport_failures() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let host = link.host_on(UnpluggedTransport, RawFormat);
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        assert!(matches!(
+            host.send(message).await,
+            Err(CofitError::Network(NetworkError::Transport(
+                TransportError::Disconnected
+            )))
+        ));
+        assert_eq!(host.recv_task().await, TransportError::Disconnected);
+        assert_eq!(host.recv_step().await, Err(TransportError::Disconnected));
+        assert_eq!(host.statistics().frames_sent, 0);
+    }
+
+    #[tokio::test]
+    async fn report_transport_failures_while_polling() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let host = link.host_on(UnpluggedTransport, RawFormat);
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [42; 7],
+        };
+
+        let network = host.polling();
+        pin_mut!(network);
+
+        // The failure is remembered, later calls report it without polling the finished receive task again
+        for _ in 0..2 {
+            assert!(matches!(
+                network.as_mut().poll_recv(),
+                Err(nb::Error::Other(CofitError::Network(
+                    NetworkError::Transport(TransportError::Disconnected)
+                )))
+            ));
+        }
 
-        self.transport.send(data).await
+        assert!(matches!(
+            network.as_mut().poll_send(message),
+            Err(nb::Error::Other(CofitError::Network(
+                NetworkError::Transport(TransportError::Disconnected)
+            )))
+        ));
     }
 }
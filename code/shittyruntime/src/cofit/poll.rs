@@ -0,0 +1,268 @@
+use super::{
+    CofitError, MessageReceiver, Network, NetworkError, SequencedMessage, Transport,
+    TransportError, WireFormat,
+};
+use crate::firmware::{executor_support::Mutex, MpscReceiver, Mutex as MutexTrait};
+use core::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use futures::task::noop_waker_ref;
+use pin_project_lite::pin_project;
+
+type MessageReceiverLock<'n, 'c, const PMTU: usize> =
+    <Mutex<MessageReceiver<'c, PMTU>> as MutexTrait>::Guard<'n>;
+
+/// Operation of a [`PollingNetwork`] which is started with some input and then polled to completion.
+/// Implemented by closures returning a future, it merely exists to name the type of said future.
+pub trait PollOperation<I> {
+    type Output;
+    type Future: Future<Output = Self::Output>;
+
+    fn start(&self, input: I) -> Self::Future;
+}
+
+impl<I, Fut: Future, C: Fn(I) -> Fut> PollOperation<I> for C {
+    type Output = Fut::Output;
+    type Future = Fut;
+
+    fn start(&self, input: I) -> Self::Future {
+        self(input)
+    }
+}
+
+/// Marker which lets the opaque types returned by [`Network::polling`] capture the lifetime of the network's contents
+pub trait Captures<'a> {}
+
+impl<'a, T: ?Sized> Captures<'a> for T {}
+
+pin_project! {
+    /// Facade over a [`Network`] for targets which drive it from a bare polling loop instead of an async executor.
+    /// Created by [`Network::polling`], it has to be pinned (e.g. using [`futures::pin_mut`]) before use.
+    ///
+    /// Every call to [`poll_send`](Self::poll_send) or [`poll_recv`](Self::poll_recv) polls the underlying futures once
+    /// using a waker which does nothing, also processing incoming frames like [`recv_task`](Network::recv_task) does.
+    /// Both return [`WouldBlock`](nb::Error::WouldBlock) until they can complete, so the loop has to call them repeatedly.
+    ///
+    /// # Limitations
+    ///
+    /// Only the simple single-message path is covered:
+    /// - Streams are not supported, incoming stream packets are dropped unless a stream has been opened through the network itself.
+    /// - Incoming reliable messages are acknowledged as soon as they are returned, they can neither be rejected nor responded to.
+    /// - The next message is only returned once the acknowledgement of the previous one has been transmitted.
+    ///
+    /// The futures are still built upon the channels, mutexes and timers of the configured executor support. These have to
+    /// make progress when being polled without ever being woken, timers may additionally require e.g. a runtime context.
+    pub struct PollingNetwork<N, R, M, K, S: PollOperation<M>, A: PollOperation<K>> {
+        state: N,
+        #[pin]
+        recv_task: R,
+        start_send: S,
+        #[pin]
+        sending: Option<S::Future>,
+        start_ack: A,
+        #[pin]
+        acknowledging: Option<A::Future>,
+        inputs: PhantomData<fn(M, K)>,
+    }
+}
+
+/// Part of a [`PollingNetwork`] which depends on the configuration of the wrapped [`Network`]
+pub struct PollingState<
+    'n,
+    'c,
+    const TMTU: usize,
+    const RESERVED: usize,
+    const PMTU: usize,
+    const SMTU: usize,
+    T,
+    F,
+> where
+    'c: 'n,
+    T: Transport<TMTU>,
+    F: WireFormat<PMTU>,
+{
+    network: &'n Network<'c, TMTU, RESERVED, PMTU, SMTU, T, F>,
+    receiver: MessageReceiverLock<'n, 'c, PMTU>,
+    /// Error the receive task completed with, it may not be polled anymore afterwards
+    transport_error: Option<TransportError>,
+}
+
+impl<
+        'c,
+        const TMTU: usize,
+        const RESERVED: usize,
+        const PMTU: usize,
+        const SMTU: usize,
+        T: Transport<TMTU> + 'c,
+        F: WireFormat<PMTU> + 'c,
+    > Network<'c, TMTU, RESERVED, PMTU, SMTU, T, F>
+{
+    /// Wraps the network for use from a bare polling loop, see [`PollingNetwork`].
+    /// Like [`recv_with`](Self::recv_with), this takes over receiving messages and panics if they are already being received.
+    #[allow(clippy::type_complexity)]
+    pub fn polling<'n>(
+        &'n self,
+    ) -> PollingNetwork<
+        PollingState<'n, 'c, TMTU, RESERVED, PMTU, SMTU, T, F>,
+        impl Future<Output = TransportError> + Captures<'c> + 'n,
+        F::Message,
+        SequencedMessage<PMTU>,
+        impl PollOperation<F::Message, Output = Result<(), CofitError<F::Error>>> + Captures<'c> + 'n,
+        impl PollOperation<SequencedMessage<PMTU>, Output = ()> + Captures<'c> + 'n,
+    >
+    where
+        'c: 'n,
+    {
+        let receiver = self
+            .message_receiver
+            .try_lock()
+            .expect("unable to lock message receiver, did you call recv_with before?");
+
+        PollingNetwork {
+            state: PollingState {
+                network: self,
+                receiver,
+                transport_error: None,
+            },
+            recv_task: self.recv_task(),
+            start_send: move |message| self.send(message),
+            sending: None,
+            start_ack: move |received| self.send_ack(received),
+            acknowledging: None,
+            inputs: PhantomData,
+        }
+    }
+}
+
+impl<
+        'n,
+        'c,
+        const TMTU: usize,
+        const RESERVED: usize,
+        const PMTU: usize,
+        const SMTU: usize,
+        T,
+        F,
+        R,
+        S,
+        A,
+    >
+    PollingNetwork<
+        PollingState<'n, 'c, TMTU, RESERVED, PMTU, SMTU, T, F>,
+        R,
+        F::Message,
+        SequencedMessage<PMTU>,
+        S,
+        A,
+    >
+where
+    'c: 'n,
+    T: Transport<TMTU>,
+    F: WireFormat<PMTU>,
+    R: Future<Output = TransportError>,
+    S: PollOperation<F::Message, Output = Result<(), CofitError<F::Error>>>,
+    A: PollOperation<SequencedMessage<PMTU>, Output = ()>,
+{
+    /// Sends a reliable message, returning [`WouldBlock`](nb::Error::WouldBlock) until it has been acknowledged.
+    ///
+    /// Only the call starting the transmission consumes its message, subsequent calls continue the transmission
+    /// in progress and ignore theirs until it has completed. Pass the same message again until this returns.
+    pub fn poll_send(
+        mut self: Pin<&mut Self>,
+        message: F::Message,
+    ) -> nb::Result<(), CofitError<F::Error>> {
+        self.as_mut().drive().map_err(nb::Error::Other)?;
+
+        let this = self.project();
+        let mut sending = this.sending;
+        if sending.is_none() {
+            sending.set(Some(this.start_send.start(message)));
+        }
+
+        let result = sending
+            .as_mut()
+            .as_pin_mut()
+            .and_then(poll_once)
+            .ok_or(nb::Error::WouldBlock)?;
+
+        sending.set(None);
+        result.map_err(nb::Error::Other)
+    }
+
+    /// Receives the next incoming message, returning [`WouldBlock`](nb::Error::WouldBlock) while there is none.
+    /// Reliable messages are acknowledged automatically, see the [limitations](Self#limitations).
+    ///
+    /// Messages which can not be deserialized are not acknowledged, the sender will time out waiting for them.
+    pub fn poll_recv(mut self: Pin<&mut Self>) -> nb::Result<F::Message, CofitError<F::Error>> {
+        self.as_mut().drive().map_err(nb::Error::Other)?;
+
+        let this = self.as_mut().project();
+
+        // Hold back further messages until the acknowledgement of the previous one is out, keeping a single message in-flight
+        if this.acknowledging.is_some() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        let received = this
+            .state
+            .receiver
+            .try_recv()
+            .ok_or(nb::Error::WouldBlock)?;
+        let message = this
+            .state
+            .network
+            .format
+            .deserialize(received.message)
+            .map_err(|error| nb::Error::Other(NetworkError::FormatError(error).into()))?;
+
+        if this.state.network.format.is_reliable(received.message.id) {
+            let mut acknowledging = this.acknowledging;
+            acknowledging.set(Some(this.start_ack.start(received)));
+            // A failed transport is reported by the next call, the message has been received regardless
+            self.drive().ok();
+        }
+
+        Ok(message)
+    }
+
+    /// Polls the background work once: Processing incoming frames and transmitting a pending acknowledgement.
+    /// Fails once the transport did, as no frames can be processed anymore from then on.
+    fn drive(self: Pin<&mut Self>) -> Result<(), CofitError<F::Error>> {
+        let mut this = self.project();
+
+        if let Some(error) = this.state.transport_error {
+            return Err(NetworkError::Transport(error).into());
+        }
+
+        // The task loops until the transport fails
+        if let Some(error) = poll_once(this.recv_task) {
+            this.state.transport_error = Some(error);
+            return Err(NetworkError::Transport(error).into());
+        }
+
+        if this
+            .acknowledging
+            .as_mut()
+            .as_pin_mut()
+            .and_then(poll_once)
+            .is_some()
+        {
+            this.acknowledging.set(None);
+        }
+
+        Ok(())
+    }
+}
+
+/// Polls the future once without an executor, using a waker which does nothing
+fn poll_once<Fut: Future>(future: Pin<&mut Fut>) -> Option<Fut::Output> {
+    let mut context = Context::from_waker(noop_waker_ref());
+
+    match future.poll(&mut context) {
+        Poll::Ready(output) => Some(output),
+        Poll::Pending => None,
+    }
+}
@@ -0,0 +1,126 @@
+use crate::firmware::{executor_support::Mutex, Mutex as MutexTrait};
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    task::Poll,
+};
+use futures::{future::poll_fn, task::AtomicWaker};
+
+/// Lane a reliable message is sent in, see [`WireFormat::priority`](super::WireFormat::priority)
+///
+/// Only one message may be in-flight at a time, so urgent messages still wait for its acknowledgement.
+/// They are however sent before any normal message that is waiting as well, e.g. to abort an ongoing flash write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Priority {
+    #[default]
+    Normal,
+    Urgent,
+}
+
+/// Tracks urgent messages waiting for the acknowledgement lock, normal messages step back while there are any
+pub(super) struct UrgentLane {
+    /// Number of urgent messages waiting for the lock
+    waiting: AtomicUsize,
+    /// Woken once the last waiting urgent message took the lock or gave up
+    vacated: AtomicWaker,
+    /// Normal messages queue up here first, so only one of them at a time waits for the lane to be vacated
+    normal_queue: Mutex<()>,
+}
+
+impl UrgentLane {
+    /// Registers an urgent message as waiting until the returned ticket is dropped
+    pub(super) fn enter(&self) -> UrgentTicket<'_> {
+        self.waiting.fetch_add(1, Ordering::AcqRel);
+        UrgentTicket(self)
+    }
+
+    pub(super) fn is_occupied(&self) -> bool {
+        self.waiting.load(Ordering::Acquire) > 0
+    }
+
+    /// Queues up a normal message, only the holder of the returned guard may wait for the lane to be [vacated](Self::vacated)
+    pub(super) async fn queue_normal(&self) -> <Mutex<()> as MutexTrait>::Guard<'_> {
+        self.normal_queue.lock().await
+    }
+
+    /// Waits without being polled in between until no urgent message is waiting anymore
+    pub(super) async fn vacated(&self) {
+        poll_fn(|cx| {
+            // Register first so a ticket dropped in between can not be missed
+            self.vacated.register(cx.waker());
+
+            if self.is_occupied() {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await
+    }
+}
+
+impl Default for UrgentLane {
+    fn default() -> Self {
+        Self {
+            waiting: AtomicUsize::new(0),
+            vacated: AtomicWaker::new(),
+            normal_queue: Mutex::new(()),
+        }
+    }
+}
+
+/// Dropped once the urgent message acquired the lock, or when its send has been cancelled before that
+pub(super) struct UrgentTicket<'l>(&'l UrgentLane);
+
+impl Drop for UrgentTicket<'_> {
+    fn drop(&mut self) {
+        if self.0.waiting.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.0.vacated.wake();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod does {
+    use super::UrgentLane;
+    use core::{future::Future, task::Context};
+    use futures::pin_mut;
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::{Wake, Waker},
+    };
+
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn park_until_the_lane_is_vacated() {
+        let lane = UrgentLane::default();
+        let wakes = Arc::new(CountingWaker::default());
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let first = lane.enter();
+        let second = lane.enter();
+
+        let vacated = lane.vacated();
+        pin_mut!(vacated);
+        assert!(vacated.as_mut().poll(&mut cx).is_pending());
+        assert!(vacated.as_mut().poll(&mut cx).is_pending());
+
+        drop(first);
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 0);
+
+        drop(second);
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 1);
+        assert!(vacated.as_mut().poll(&mut cx).is_ready());
+    }
+}
@@ -0,0 +1,99 @@
+use crate::firmware::{executor_support::Mutex, DurationDriver, Mutex as MutexTrait, TimeDriver};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// What happens to messages sent while the rate limit is exhausted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitPolicy {
+    /// Wait until the budget allows sending the message
+    Block,
+    /// Discard the message, failing the send with [`NetworkError::RateLimited`](super::NetworkError::RateLimited)
+    Drop,
+}
+
+/// Token bucket limiting how many messages a [`Network`](super::Network) sends, see [`Network::with_rate_limit`](super::Network::with_rate_limit)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Sustained number of messages allowed per second, has to be non-zero
+    pub messages_per_second: u32,
+    /// Number of messages that may be sent back-to-back after the link has been idle
+    pub burst: u32,
+    pub policy: RateLimitPolicy,
+}
+
+struct Bucket<I> {
+    tokens: u32,
+    refilled_at: I,
+}
+
+pub(super) struct RateLimiter<D: TimeDriver> {
+    driver: D,
+    interval: D::Duration,
+    burst: u32,
+    policy: RateLimitPolicy,
+    bucket: Mutex<Bucket<D::Instant>>,
+    dropped: AtomicU32,
+}
+
+impl<D: TimeDriver> RateLimiter<D> {
+    pub(super) fn new(limit: RateLimit, driver: D) -> Self {
+        let burst = limit.burst.max(1);
+        let bucket = Bucket {
+            tokens: burst,
+            refilled_at: driver.now(),
+        };
+
+        Self {
+            interval: D::Duration::from_micros(1_000_000 / limit.messages_per_second.max(1) as u64),
+            burst,
+            policy: limit.policy,
+            bucket: Mutex::new(bucket),
+            dropped: AtomicU32::new(0),
+            driver,
+        }
+    }
+
+    /// Takes a token from the bucket, returns false if the message should be dropped instead
+    pub(super) async fn acquire(&self) -> bool {
+        let mut bucket = self.bucket.lock().await;
+
+        loop {
+            self.refill(&mut bucket);
+
+            if bucket.tokens > 0 {
+                bucket.tokens -= 1;
+                return true;
+            }
+
+            match self.policy {
+                RateLimitPolicy::Block => {
+                    self.driver
+                        .wait_until(bucket.refilled_at + self.interval)
+                        .await
+                }
+                RateLimitPolicy::Drop => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return false;
+                }
+            }
+        }
+    }
+
+    /// Number of messages dropped so far due to the [`RateLimitPolicy::Drop`] policy
+    pub(super) fn dropped_count(&self) -> u32 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn refill(&self, bucket: &mut Bucket<D::Instant>) {
+        let now = self.driver.now();
+
+        while bucket.tokens < self.burst && bucket.refilled_at + self.interval <= now {
+            bucket.tokens += 1;
+            bucket.refilled_at = bucket.refilled_at + self.interval;
+        }
+
+        // A full bucket does not accumulate time, otherwise the next refill would come early
+        if bucket.tokens == self.burst {
+            bucket.refilled_at = now;
+        }
+    }
+}
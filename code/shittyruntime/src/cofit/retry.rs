@@ -0,0 +1,61 @@
+use super::ACK_TIMEOUT_MS;
+
+/// How often a reliable message is retransmitted when no acknowledgement arrives, see [`Network::with_retry_policy`](super::Network::with_retry_policy)
+///
+/// The wait between two attempts starts at `initial_backoff_ms` and doubles after each attempt, up to `max_backoff_ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total number of transmissions including the first one, values below one are treated as one
+    pub max_attempts: u8,
+    /// Time to wait for an acknowledgement after each transmission
+    pub ack_timeout_ms: u32,
+    /// Pause before the first retransmission
+    pub initial_backoff_ms: u32,
+    /// Upper bound for the pause between two transmissions
+    pub max_backoff_ms: u32,
+}
+
+impl RetryPolicy {
+    pub(super) fn max_attempts(&self) -> u8 {
+        self.max_attempts.max(1)
+    }
+
+    /// Pause after the given (one-based) failed attempt before transmitting again
+    pub(super) fn backoff_ms(&self, attempt: u8) -> u32 {
+        let doublings = attempt.saturating_sub(1).min(31) as u32;
+
+        self.initial_backoff_ms
+            .saturating_mul(1 << doublings)
+            .min(self.max_backoff_ms)
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Single attempt without retransmissions
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            ack_timeout_ms: ACK_TIMEOUT_MS,
+            initial_backoff_ms: 0,
+            max_backoff_ms: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+
+    #[test]
+    fn double_backoff_up_to_limit() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            ack_timeout_ms: 100,
+            initial_backoff_ms: 10,
+            max_backoff_ms: 50,
+        };
+
+        let backoffs = (1..=5).map(|a| policy.backoff_ms(a)).collect::<Vec<_>>();
+        assert_eq!(backoffs, [10, 20, 40, 50, 50]);
+    }
+}
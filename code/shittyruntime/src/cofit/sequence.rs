@@ -0,0 +1,88 @@
+use super::SerializedMessage;
+use core::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+
+/// Sequence number reserved for the first message sent by a network.
+/// Receivers resynchronize on it, so a peer that has been restarted is not mistaken for sending stale messages.
+/// As a consequence, retransmissions of the very first message can not be told apart from the original.
+const INITIAL_SEQUENCE: u8 = 0;
+
+/// Message together with the sequence number of the frame it has been transmitted in
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct SequencedMessage<const MTU: usize> {
+    pub sequence: u8,
+    pub message: SerializedMessage<MTU>,
+}
+
+/// Hands out sequence numbers for outgoing messages of one kind.
+/// After the initial one, numbers cycle through `1..=255` and never return to [`INITIAL_SEQUENCE`].
+#[derive(Default)]
+pub(super) struct SequenceCounter(AtomicU32);
+
+impl SequenceCounter {
+    pub(super) fn next(&self) -> u8 {
+        match self.0.fetch_add(1, Ordering::Relaxed) {
+            0 => INITIAL_SEQUENCE,
+            count => ((count - 1) % 255 + 1) as u8,
+        }
+    }
+}
+
+/// Tracks the sequence number of the next message of one kind expected from the peer
+#[derive(Default)]
+pub(super) struct SequenceTracker(AtomicU8);
+
+impl SequenceTracker {
+    /// Whether a message with the given sequence number is due for delivery.
+    /// Messages behind the expected one are either duplicates caused by retransmissions or have been overtaken by later ones.
+    /// Gaps are skipped as the sender has given up on the messages in between.
+    pub(super) fn is_due(&self, sequence: u8) -> bool {
+        let expected = self.0.load(Ordering::Relaxed);
+
+        if sequence == INITIAL_SEQUENCE || expected == INITIAL_SEQUENCE {
+            return true;
+        }
+
+        // Distance within the cycle of `1..=255`, anything in the first half counts as ahead of the expected number
+        let distance = (sequence as u16 + 255 - expected as u16) % 255;
+        distance < 128
+    }
+
+    /// Records that the message with the given sequence number has been delivered
+    pub(super) fn advance(&self, sequence: u8) {
+        let next = if sequence == u8::MAX { 1 } else { sequence + 1 };
+        self.0.store(next, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+
+    #[test]
+    fn skip_initial_sequence_when_wrapping() {
+        let counter = SequenceCounter::default();
+        assert_eq!(counter.next(), 0);
+
+        for expected in (1..=255).chain(1..=255) {
+            assert_eq!(counter.next(), expected);
+        }
+    }
+
+    #[test]
+    fn reject_stale_sequences() {
+        let tracker = SequenceTracker::default();
+        assert!(tracker.is_due(42));
+        tracker.advance(42);
+
+        assert!(!tracker.is_due(42));
+        assert!(tracker.is_due(43));
+        assert!(tracker.is_due(50));
+
+        tracker.advance(255);
+        assert!(!tracker.is_due(255));
+        assert!(tracker.is_due(1));
+
+        // A restarted peer starts over with the initial sequence number
+        assert!(tracker.is_due(0));
+    }
+}
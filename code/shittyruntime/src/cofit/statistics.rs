@@ -0,0 +1,85 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+use serde::{Deserialize, Serialize};
+
+/// Snapshot of the traffic handled by a [`Network`](super::Network), see [`Network::statistics`](super::Network::statistics).
+/// Can be sent to the other side, e.g. to tell whether a stalled device is still receiving frames.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize, Debug)]
+pub struct NetworkStatistics {
+    /// Frames received from the transport, including ones that have been dropped afterwards
+    pub frames_received: u32,
+    /// Message and acknowledgement frames passed to the transport, stream packets are not included
+    pub frames_sent: u32,
+    /// Received frames whose header could not be parsed
+    pub malformed_headers: u32,
+    /// Received messages which have been passed on to the message handler
+    pub dispatched_messages: u32,
+    /// Received messages which have been dropped because the message handler lagged behind
+    pub dropped_messages: u32,
+    /// Received messages which have been rejected for carrying data beyond their [length](super::WireFormat::message_length)
+    pub malformed_payloads: u32,
+    /// Acknowledgements and rejections which did not belong to the message in-flight, if there was one at all
+    pub unexpected_acknowledgements: u32,
+    /// Stream packets which have been dropped because no stream was open
+    pub orphaned_stream_packets: u32,
+}
+
+/// Counters backing [`NetworkStatistics`], updated by the network as frames pass through
+#[derive(Default)]
+pub(super) struct NetworkCounters {
+    frames_received: AtomicU32,
+    frames_sent: AtomicU32,
+    malformed_headers: AtomicU32,
+    dispatched_messages: AtomicU32,
+    dropped_messages: AtomicU32,
+    malformed_payloads: AtomicU32,
+    unexpected_acknowledgements: AtomicU32,
+    orphaned_stream_packets: AtomicU32,
+}
+
+impl NetworkCounters {
+    pub(super) fn record_received(&self) {
+        self.frames_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_sent(&self) {
+        self.frames_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_malformed(&self) {
+        self.malformed_headers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_dispatched(&self) {
+        self.dispatched_messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_dropped(&self) {
+        self.dropped_messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_malformed_payload(&self) {
+        self.malformed_payloads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_unexpected_acks(&self, count: usize) {
+        self.unexpected_acknowledgements
+            .fetch_add(count as u32, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_orphaned_stream_packet(&self) {
+        self.orphaned_stream_packets.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn snapshot(&self) -> NetworkStatistics {
+        NetworkStatistics {
+            frames_received: self.frames_received.load(Ordering::Relaxed),
+            frames_sent: self.frames_sent.load(Ordering::Relaxed),
+            malformed_headers: self.malformed_headers.load(Ordering::Relaxed),
+            dispatched_messages: self.dispatched_messages.load(Ordering::Relaxed),
+            dropped_messages: self.dropped_messages.load(Ordering::Relaxed),
+            malformed_payloads: self.malformed_payloads.load(Ordering::Relaxed),
+            unexpected_acknowledgements: self.unexpected_acknowledgements.load(Ordering::Relaxed),
+            orphaned_stream_packets: self.orphaned_stream_packets.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -1,11 +1,25 @@
-use super::{MpscReceiver, StreamPacketHeader, Transport};
+use super::{MpscReceiver, StreamPacketHeader, Transport, TransportError};
 use serde::{Deserialize, Serialize};
 
 mod read;
 mod write;
 
 pub use read::StreamReadHandle;
-pub use write::StreamWriteHandle;
+pub use write::{StreamWriteHandle, STREAM_REPLAY_CAPACITY};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StreamError {
+    /// Checksum sent by the writer does not match the data that has been received
+    ChecksumMismatch,
+    /// Attempted to operate a stream which has already been closed
+    Closed,
+    /// A stream control packet could not be serialized, usually because the stream MTU is too small
+    Serialization,
+    /// The reader requested a retransmission of data which is no longer retained, see [`STREAM_REPLAY_CAPACITY`]
+    RevertOutOfRange,
+    /// The [`Transport`] failed to send a stream packet
+    Transport(TransportError),
+}
 
 /// 22-bit stream section identifier
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
@@ -20,10 +34,6 @@ impl StreamSequenceID {
         assert!(self.0 < Self::MAX_VALUE);
     }
 
-    fn into_offset(self, smtu: usize) -> u64 {
-        self.0 as u64 * smtu as u64
-    }
-
     fn into_bytes(self) -> [u8; 3] {
         let bytes = self.0.to_be_bytes();
         [bytes[1], bytes[2], bytes[3]]
@@ -44,6 +54,8 @@ pub struct StreamPacket<const MTU: usize> {
 struct StreamClosePacket {
     /// Last sequence ID that was part of the stream
     sequence_id: StreamSequenceID,
+    /// CRC-32 over the whole stream content, if the sending side calculated one
+    checksum: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -52,6 +64,32 @@ struct StreamRevertPacket {
     sequence_id: StreamSequenceID,
 }
 
+/// Running CRC-32 (IEEE 802.3) over the content of a stream
+#[derive(Clone, Copy)]
+struct StreamChecksum(u32);
+
+impl StreamChecksum {
+    const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+    fn new() -> Self {
+        Self(u32::MAX)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u32;
+            for _ in 0..8 {
+                let mask = (self.0 & 1).wrapping_neg();
+                self.0 = (self.0 >> 1) ^ (Self::POLYNOMIAL & mask);
+            }
+        }
+    }
+
+    fn value(&self) -> u32 {
+        !self.0
+    }
+}
+
 impl From<u32> for StreamSequenceID {
     fn from(src: u32) -> Self {
         assert!(src < Self::MAX_VALUE);
@@ -64,3 +102,125 @@ impl From<StreamSequenceID> for u32 {
         src.0
     }
 }
+
+#[cfg(test)]
+mod does {
+    use super::*;
+    use crate::{
+        cofit::LoopbackTransport,
+        firmware::{
+            executor_support::{Channel, Mutex},
+            Mpsc, MpscSender, Mutex as MutexTrait,
+        },
+    };
+
+    /// Feeds a single content packet followed by a close packet with the given checksum into a reader
+    async fn read_stream(checksum: Option<u32>) -> [Result<Option<[u8; 5]>, StreamError>; 2] {
+        let stream_channel = Channel::new();
+        let (sender, receiver) = stream_channel.split();
+        let receiver = Mutex::new(receiver);
+
+        let (reader_to_writer, writer_to_reader) = (Channel::new(), Channel::new());
+        let (transport, _writer_transport) =
+            LoopbackTransport::<9>::pair(&reader_to_writer, &writer_to_reader);
+
+        let mut reader = StreamReadHandle::<9, 7, 5, _>::new(receiver.lock().await, &transport);
+
+        sender
+            .send(StreamPacket {
+                header: StreamPacketHeader::Content(0),
+                bytes: [0, 0, 1, 2, 3, 4, 5],
+            })
+            .await;
+
+        let mut bytes = [0; 7];
+        let packet = StreamClosePacket {
+            sequence_id: 1.into(),
+            checksum,
+        };
+        postcard::to_slice(&packet, &mut bytes).unwrap();
+        sender
+            .send(StreamPacket {
+                header: StreamPacketHeader::Closed,
+                bytes,
+            })
+            .await;
+
+        [reader.recv().await, reader.recv().await]
+    }
+
+    #[tokio::test]
+    async fn accept_matching_checksum() {
+        let mut checksum = StreamChecksum::new();
+        checksum.update(&[1, 2, 3, 4, 5]);
+
+        let [content, end] = read_stream(Some(checksum.value())).await;
+        assert_eq!(content, Ok(Some([1, 2, 3, 4, 5])));
+        assert_eq!(end, Ok(None));
+
+        let [_, end] = read_stream(None).await;
+        assert_eq!(end, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn reject_mismatching_checksum() {
+        let [content, end] = read_stream(Some(0)).await;
+        assert_eq!(content, Ok(Some([1, 2, 3, 4, 5])));
+        assert_eq!(end, Err(StreamError::ChecksumMismatch));
+    }
+
+    fn revert_packet(sequence_id: u32) -> StreamPacket<7> {
+        let mut bytes = [0; 7];
+        let packet = StreamRevertPacket {
+            sequence_id: sequence_id.into(),
+        };
+        postcard::to_slice(&packet, &mut bytes).unwrap();
+
+        StreamPacket {
+            header: StreamPacketHeader::Revert,
+            bytes,
+        }
+    }
+
+    #[tokio::test]
+    async fn replay_retained_chunks_on_revert() {
+        let stream_channel = Channel::new();
+        let (sender, receiver) = stream_channel.split();
+        let receiver = Mutex::new(receiver);
+
+        let (writer_to_reader, reader_to_writer) = (Channel::new(), Channel::new());
+        let (transport, reader_transport) =
+            LoopbackTransport::<9>::pair(&writer_to_reader, &reader_to_writer);
+
+        let chunk_count = STREAM_REPLAY_CAPACITY as u8 + 2;
+        let source = futures::stream::iter((0..chunk_count).map(|index| [index; 5]));
+        let mut writer =
+            StreamWriteHandle::<9, 7, 5, _, _>::new(receiver.lock().await, &transport, source);
+
+        for index in 0..chunk_count {
+            assert_eq!(writer.send().await, Ok(false));
+            assert_eq!(reader_transport.recv().await.unwrap()[3..8], [index; 5]);
+        }
+
+        // The revert is processed first, the chunk is transmitted again by the next call
+        sender.send(revert_packet(chunk_count as u32 - 2)).await;
+        assert_eq!(writer.send().await, Ok(false));
+        assert_eq!(writer.send().await, Ok(false));
+        assert_eq!(
+            reader_transport.recv().await.unwrap()[3..8],
+            [chunk_count - 2; 5]
+        );
+
+        sender.send(revert_packet(0)).await;
+        assert_eq!(writer.send().await, Err(StreamError::RevertOutOfRange));
+        assert_eq!(writer.send().await, Err(StreamError::Closed));
+    }
+
+    #[test]
+    fn calculate_crc32() {
+        let mut checksum = StreamChecksum::new();
+        checksum.update(b"1234");
+        checksum.update(b"56789");
+        assert_eq!(checksum.value(), 0xCBF4_3926);
+    }
+}
@@ -1,8 +1,12 @@
 use super::{
-    super::{PacketHeader, StreamReceiverLock, STREAM_RECV_TIMEOUT_MS},
-    MpscReceiver, StreamClosePacket, StreamPacketHeader, StreamRevertPacket, StreamSequenceID,
-    Transport,
+    super::{
+        PacketHeader, StreamReceiverLock, FRAME_HEADER_SIZE, STREAM_HEADER_SIZE,
+        STREAM_RECV_TIMEOUT_MS,
+    },
+    MpscReceiver, StreamChecksum, StreamClosePacket, StreamError, StreamPacketHeader,
+    StreamRevertPacket, StreamSequenceID, Transport,
 };
+use futures::Stream;
 use StreamPacketHeader::*;
 
 pub struct StreamReadHandle<
@@ -16,6 +20,7 @@ pub struct StreamReadHandle<
     transport: &'t T,
 
     sequence_id: StreamSequenceID,
+    checksum: StreamChecksum,
     reached_end: bool,
 }
 
@@ -23,82 +28,114 @@ impl<'t, const TMTU: usize, const PMTU: usize, const SMTU: usize, T: Transport<T
     StreamReadHandle<'t, TMTU, PMTU, SMTU, T>
 {
     pub fn new(receiver: StreamReceiverLock<'t, PMTU>, transport: &'t T) -> Self {
-        assert_eq!(
-            TMTU - 1,
-            PMTU,
-            "protocol MTU has to match transport MTU minus one"
+        assert!(
+            PMTU + FRAME_HEADER_SIZE <= TMTU,
+            "protocol MTU has to leave room for the frame header in the transport MTU"
         );
         assert_eq!(
-            PMTU - 2,
+            PMTU - STREAM_HEADER_SIZE,
             SMTU,
-            "stream MTU has to match protocol MTU minus two / transport MTU minus three"
+            "stream MTU has to leave room for the stream header in the protocol MTU"
         );
 
         Self {
             receiver,
             transport,
             sequence_id: StreamSequenceID(0),
+            checksum: StreamChecksum::new(),
             reached_end: false,
         }
     }
 
-    pub async fn recv(&mut self) -> Option<[u8; SMTU]> {
+    /// Converts the handle into a [`Stream`] of the received chunks, for use with combinators like
+    /// [`TryStreamExt::try_collect`](futures::TryStreamExt::try_collect) or in `select!` alongside timeouts.
+    ///
+    /// Items are the results of [`recv`](Self::recv), the stream ends after the last chunk or after yielding an error.
+    pub fn into_stream(self) -> impl Stream<Item = Result<[u8; SMTU], StreamError>> + 't {
+        futures::stream::unfold(Some(self), |reader| async move {
+            let mut reader = reader?;
+
+            match reader.recv().await {
+                Ok(Some(data)) => Some((Ok(data), Some(reader))),
+                Ok(None) => None,
+                Err(error) => Some((Err(error), None)),
+            }
+        })
+    }
+
+    /// Receives the next chunk of data, returning `None` once the stream has ended.
+    ///
+    /// If the writer included a checksum when closing the stream and it does not match the received data,
+    /// [`StreamError::ChecksumMismatch`] is returned instead of the end of the stream.
+    pub async fn recv(&mut self) -> Result<Option<[u8; SMTU]>, StreamError> {
         loop {
             match self.receiver.recv_timeout(STREAM_RECV_TIMEOUT_MS).await {
                 Some(message) => match message.header {
                     Content(seq_id_byte) => {
-                        if let Some(data) = self.handle_content(seq_id_byte, message.bytes).await {
-                            return Some(data);
+                        if let Some(data) = self.handle_content(seq_id_byte, message.bytes).await? {
+                            return Ok(Some(data));
                         }
                     }
                     Closed => {
-                        self.handle_close(message.bytes).await;
+                        let result = self.handle_close(message.bytes).await;
                         if self.reached_end {
-                            return None;
+                            return result.map(|_| None);
                         }
                     }
-                    Revert => self.handle_revert(),
+                    Revert | CloseAck => self.handle_feedback(),
                 },
                 None => {
                     #[cfg(feature = "defmt")]
                     defmt::error!("timed out while waiting for stream packet");
-                    return None;
+                    return Ok(None);
                 }
             }
         }
     }
 
-    async fn handle_content(&mut self, seq_id_byte: u8, bytes: [u8; PMTU]) -> Option<[u8; SMTU]> {
+    async fn handle_content(
+        &mut self,
+        seq_id_byte: u8,
+        bytes: [u8; PMTU],
+    ) -> Result<Option<[u8; SMTU]>, StreamError> {
         let seq_id = StreamSequenceID::from_bytes([seq_id_byte, bytes[0], bytes[1]]);
 
         if seq_id > self.sequence_id {
-            self.request_revert().await;
-            return None;
+            self.request_revert().await?;
+            return Ok(None);
         } else if seq_id < self.sequence_id {
             #[cfg(feature = "defmt")]
             defmt::warn!("encountered discontinuity in sequence IDs");
-            return None;
+            return Ok(None);
         }
 
         self.sequence_id.increment();
 
         let mut data = [0; SMTU];
         data.copy_from_slice(&bytes[2..]);
-        Some(data)
+        self.checksum.update(&data);
+        Ok(Some(data))
     }
 
-    async fn handle_close(&mut self, bytes: [u8; PMTU]) {
+    async fn handle_close(&mut self, bytes: [u8; PMTU]) -> Result<(), StreamError> {
         match postcard::from_bytes::<StreamClosePacket>(&bytes) {
             Ok(packet) => {
                 if packet.sequence_id == self.sequence_id {
-                    self.acknowledge_close().await;
+                    self.acknowledge_close().await?;
                     self.reached_end = true;
+
+                    if matches!(packet.checksum, Some(checksum) if checksum != self.checksum.value())
+                    {
+                        #[cfg(feature = "defmt")]
+                        defmt::error!("stream content does not match checksum");
+                        return Err(StreamError::ChecksumMismatch);
+                    }
                 } else if packet.sequence_id < self.sequence_id {
                     #[cfg(feature = "defmt")]
                     defmt::warn!("encountered discontinuity while closing stream");
                     // TODO Notify the callee as data corruption might have occurred, maybe even notify the host somehow
                 } else {
-                    self.request_revert().await;
+                    self.request_revert().await?;
                 }
             }
             Err(_error) => {
@@ -106,14 +143,16 @@ impl<'t, const TMTU: usize, const PMTU: usize, const SMTU: usize, T: Transport<T
                 defmt::warn!("failed to deserialize stream close packet");
             }
         }
+
+        Ok(())
     }
 
-    fn handle_revert(&mut self) {
+    fn handle_feedback(&mut self) {
         #[cfg(feature = "defmt")]
-        defmt::warn!("dropping unexpected stream revert packet");
+        defmt::warn!("dropping unexpected stream feedback packet");
     }
 
-    async fn request_revert(&mut self) {
+    async fn request_revert(&mut self) -> Result<(), StreamError> {
         let header = PacketHeader::StreamPacket(Revert);
         let packet = StreamRevertPacket {
             sequence_id: self.sequence_id,
@@ -121,23 +160,30 @@ impl<'t, const TMTU: usize, const PMTU: usize, const SMTU: usize, T: Transport<T
 
         let mut data = [0; TMTU];
         data[0] = header.into();
-        postcard::to_slice(&packet, &mut data[1..])
-            .expect("failed to serialize stream revert packet");
+        postcard::to_slice(&packet, &mut data[1..1 + PMTU])
+            .map_err(|_| StreamError::Serialization)?;
 
-        self.transport.send(data).await;
+        self.transport
+            .send(data)
+            .await
+            .map_err(StreamError::Transport)
     }
 
-    async fn acknowledge_close(&mut self) {
-        let header = PacketHeader::StreamPacket(Closed);
+    async fn acknowledge_close(&mut self) -> Result<(), StreamError> {
+        let header = PacketHeader::StreamPacket(CloseAck);
         let packet = StreamClosePacket {
             sequence_id: self.sequence_id,
+            checksum: Some(self.checksum.value()),
         };
 
         let mut data = [0; TMTU];
         data[0] = header.into();
-        postcard::to_slice(&packet, &mut data[1..])
-            .expect("failed to serialize stream revert packet");
+        postcard::to_slice(&packet, &mut data[1..1 + PMTU])
+            .map_err(|_| StreamError::Serialization)?;
 
-        self.transport.send(data).await;
+        self.transport
+            .send(data)
+            .await
+            .map_err(StreamError::Transport)
     }
 }
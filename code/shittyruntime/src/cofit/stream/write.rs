@@ -1,12 +1,16 @@
 use super::{
-    super::StreamReceiverLock, MpscReceiver, StreamClosePacket, StreamPacketHeader,
-    StreamRevertPacket, StreamSequenceID, Transport,
+    super::StreamReceiverLock, MpscReceiver, StreamChecksum, StreamClosePacket, StreamError,
+    StreamPacketHeader, StreamRevertPacket, StreamSequenceID, Transport,
 };
-use crate::cofit::{PacketHeader, STREAM_RECV_TIMEOUT_MS};
-use core::future::Future;
+use crate::cofit::{PacketHeader, FRAME_HEADER_SIZE, STREAM_HEADER_SIZE, STREAM_RECV_TIMEOUT_MS};
+use futures::{Stream, StreamExt};
 use PacketHeader::*;
 use StreamPacketHeader::*;
 
+/// Number of chunks retained for retransmission after they have been pulled from the source.
+/// Reverts requested by the reader may not reach further back than this.
+pub const STREAM_REPLAY_CAPACITY: usize = 8;
+
 #[derive(PartialEq, Eq)]
 enum StreamState {
     Transmitting,
@@ -14,22 +18,33 @@ enum StreamState {
     Closed,
 }
 
+/// Transmits the chunks of a [`Stream`] to a [`StreamReadHandle`](super::StreamReadHandle) on the other side.
+///
+/// The source only has to be read once, the most recent [`STREAM_REPLAY_CAPACITY`] chunks are retained
+/// in case the reader misses some of them and requests a retransmission.
 pub struct StreamWriteHandle<
     't,
     const TMTU: usize,
     const PMTU: usize,
     const SMTU: usize,
     T: Transport<TMTU>,
-    F: Future<Output = Option<[u8; SMTU]>>,
-    D: FnMut(u64) -> F,
+    S: Stream<Item = [u8; SMTU]> + Unpin,
 > {
     receiver: StreamReceiverLock<'t, PMTU>,
     transport: &'t T,
 
-    data_source: D,
+    source: S,
+    source_exhausted: bool,
+    /// Chunks most recently pulled from the source, indexed by their sequence ID modulo the capacity
+    replay: [[u8; SMTU]; STREAM_REPLAY_CAPACITY],
 
     sequence_id: StreamSequenceID,
     state: StreamState,
+
+    /// Checksum over all data pulled from the source, which is everything up to, but excluding, `pulled_until`.
+    /// Kept separately as reverts cause data to be transmitted multiple times.
+    checksum: StreamChecksum,
+    pulled_until: StreamSequenceID,
 }
 
 impl<
@@ -38,37 +53,47 @@ impl<
         const PMTU: usize,
         const SMTU: usize,
         T: Transport<TMTU>,
-        F: Future<Output = Option<[u8; SMTU]>>,
-        D: FnMut(u64) -> F,
-    > StreamWriteHandle<'t, TMTU, PMTU, SMTU, T, F, D>
+        S: Stream<Item = [u8; SMTU]> + Unpin,
+    > StreamWriteHandle<'t, TMTU, PMTU, SMTU, T, S>
 {
-    pub fn new(receiver: StreamReceiverLock<'t, PMTU>, transport: &'t T, data_source: D) -> Self {
-        assert_eq!(
-            TMTU - 1,
-            PMTU,
-            "protocol MTU has to match transport MTU minus one"
+    pub fn new(receiver: StreamReceiverLock<'t, PMTU>, transport: &'t T, source: S) -> Self {
+        assert!(
+            PMTU + FRAME_HEADER_SIZE <= TMTU,
+            "protocol MTU has to leave room for the frame header in the transport MTU"
         );
         assert_eq!(
-            PMTU - 2,
+            PMTU - STREAM_HEADER_SIZE,
             SMTU,
-            "stream MTU has to match protocol MTU minus two / transport MTU minus three"
+            "stream MTU has to leave room for the stream header in the protocol MTU"
         );
 
         Self {
             receiver,
             transport,
-            data_source,
+            source,
+            source_exhausted: false,
+            replay: [[0; SMTU]; STREAM_REPLAY_CAPACITY],
             sequence_id: StreamSequenceID(0),
             state: StreamState::Transmitting,
+            checksum: StreamChecksum::new(),
+            pulled_until: StreamSequenceID(0),
         }
     }
 
+    /// Number of outgoing frames buffered by the transport, see [`Transport::queued_frames`].
+    /// Check this between calls to [`send`](Self::send) to adapt the rate at which data is pulled from the source.
+    pub fn queued_frames(&self) -> usize {
+        self.transport.queued_frames()
+    }
+
     /// Operates the stream and continually transmits data until everything has been transmitted at which point `true` is returned.
-    /// Calling this method after it returned true once will panic.
+    /// Calling this method after it returned true once will return [`StreamError::Closed`].
+    ///
+    /// Fails with [`StreamError::RevertOutOfRange`] and closes the stream if the reader requests data which is no longer retained.
     // TODO Fuse this method so it either returns self or nothing upon completion.
-    pub async fn send(&mut self) -> bool {
+    pub async fn send(&mut self) -> Result<bool, StreamError> {
         if self.state == StreamState::Closed {
-            panic!("attempted to send stream frame after stream was closed");
+            return Err(StreamError::Closed);
         }
 
         let message = if self.state != StreamState::ReachedEnd {
@@ -79,9 +104,9 @@ impl<
 
         if let Some(message) = message {
             match message.header {
-                Content(_) => self.handle_content(),
-                Closed => self.handle_close(message.bytes),
-                Revert => self.handle_revert(message.bytes),
+                Content(_) | Closed => self.handle_content(),
+                CloseAck => self.handle_close(message.bytes),
+                Revert => self.handle_revert(message.bytes)?,
             }
         } else if self.state == StreamState::ReachedEnd {
             #[cfg(feature = "defmt")]
@@ -89,15 +114,33 @@ impl<
             self.state = StreamState::Closed;
             // TODO Notify the callee that the stream transmission likely failed
         } else {
-            self.send_data().await;
+            self.send_data().await?;
         }
 
-        self.state == StreamState::Closed
+        Ok(self.state == StreamState::Closed)
     }
 
-    async fn send_data(&mut self) {
-        let offset = self.sequence_id.into_offset(SMTU);
-        let data = (self.data_source)(offset).await;
+    async fn send_data(&mut self) -> Result<(), StreamError> {
+        let index = u32::from(self.sequence_id) as usize % STREAM_REPLAY_CAPACITY;
+
+        let data = if self.sequence_id < self.pulled_until {
+            Some(self.replay[index])
+        } else if self.source_exhausted {
+            None
+        } else {
+            let data = self.source.next().await;
+
+            match data {
+                Some(payload) => {
+                    self.replay[index] = payload;
+                    self.checksum.update(&payload);
+                    self.pulled_until.increment();
+                }
+                None => self.source_exhausted = true,
+            }
+
+            data
+        };
 
         match data {
             Some(payload) => {
@@ -108,9 +151,12 @@ impl<
                 data[0] = header.into();
                 data[1] = seq_id_bytes[1];
                 data[2] = seq_id_bytes[2];
-                data[3..].copy_from_slice(&payload);
+                data[3..3 + SMTU].copy_from_slice(&payload);
 
-                self.transport.send(data).await;
+                self.transport
+                    .send(data)
+                    .await
+                    .map_err(StreamError::Transport)?;
                 self.sequence_id.increment();
             }
 
@@ -118,29 +164,49 @@ impl<
                 let header = StreamPacket(Closed);
                 let packet = StreamClosePacket {
                     sequence_id: self.sequence_id,
+                    checksum: Some(self.checksum.value()),
                 };
 
                 let mut data = [0; TMTU];
                 data[0] = header.into();
-                postcard::to_slice(&packet, &mut data[1..])
-                    .expect("failed to serialize stream close packet");
+                postcard::to_slice(&packet, &mut data[1..1 + PMTU])
+                    .map_err(|_| StreamError::Serialization)?;
 
-                self.transport.send(data).await;
+                self.transport
+                    .send(data)
+                    .await
+                    .map_err(StreamError::Transport)?;
                 self.state = StreamState::ReachedEnd;
             }
         }
+
+        Ok(())
     }
 
-    fn handle_revert(&mut self, bytes: [u8; PMTU]) {
+    fn handle_revert(&mut self, bytes: [u8; PMTU]) -> Result<(), StreamError> {
         match postcard::from_bytes::<StreamRevertPacket>(&bytes) {
+            Ok(packet) if packet.sequence_id > self.pulled_until => {
+                #[cfg(feature = "defmt")]
+                defmt::warn!("dropping stream revert packet ahead of the transmitted data");
+            }
             Ok(packet) => {
+                let distance = u32::from(self.pulled_until) - u32::from(packet.sequence_id);
+                if distance as usize > STREAM_REPLAY_CAPACITY {
+                    self.state = StreamState::Closed;
+                    return Err(StreamError::RevertOutOfRange);
+                }
+
+                // The reader may have missed data before the end, transmit it again before closing
                 self.sequence_id = packet.sequence_id;
+                self.state = StreamState::Transmitting;
             }
             Err(_error) => {
                 #[cfg(feature = "defmt")]
                 defmt::warn!("failed to deserialize stream revert packet");
             }
         }
+
+        Ok(())
     }
 
     fn handle_close(&mut self, bytes: [u8; PMTU]) {
@@ -169,6 +235,6 @@ impl<
 
     fn handle_content(&mut self) {
         #[cfg(feature = "defmt")]
-        defmt::warn!("dropping unexpected stream content packet");
+        defmt::warn!("dropping unexpected stream content or end packet");
     }
 }
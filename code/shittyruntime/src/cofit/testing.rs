@@ -0,0 +1,247 @@
+//! In-memory harness for end-to-end tests of the protocol, available with the `test-util` feature
+//!
+//! Two [`Network`]s are wired together using a [`LoopbackTransport`] whose channels are owned by a [`LoopbackLink`].
+//! [`deliver`] then exercises the whole cycle of sending a message, handling it on the other side, and acknowledging it:
+//!
+//! ```ignore
+//! let link = LoopbackLink::<9, 0, 7, 5>::new();
+//! let (host, peripheral) = link.connect(MyFormat, MyFormat);
+//!
+//! let received = run(deliver(&host, &peripheral, message));
+//! assert_eq!(received, message);
+//! ```
+
+use super::{
+    Acknowledgement, LoopbackTransport, MessageAcknowledger, MessageHandler, Network, Role,
+    SequencedMessage, SerializedMessage, StreamPacket, Transport, WireFormat,
+};
+use crate::firmware::{executor_support::Channel, Mpsc};
+use core::{cell::RefCell, fmt::Debug, future::Future};
+use futures::future::{select, Either};
+
+/// Network connected to another one by a [`LoopbackLink`]
+pub type LoopbackNetwork<
+    'c,
+    const TMTU: usize,
+    const RESERVED: usize,
+    const PMTU: usize,
+    const SMTU: usize,
+    F,
+> = Network<'c, TMTU, RESERVED, PMTU, SMTU, LoopbackTransport<'c, TMTU>, F>;
+
+/// Channels backing a host and a peripheral network which are connected in memory, see [`connect`](Self::connect)
+pub struct LoopbackLink<
+    const TMTU: usize,
+    const RESERVED: usize,
+    const PMTU: usize,
+    const SMTU: usize,
+> {
+    host_to_peripheral: Channel<[u8; TMTU]>,
+    peripheral_to_host: Channel<[u8; TMTU]>,
+    host: NetworkChannels<PMTU>,
+    peripheral: NetworkChannels<PMTU>,
+}
+
+struct NetworkChannels<const PMTU: usize> {
+    acks: Channel<Acknowledgement<PMTU>>,
+    streams: Channel<StreamPacket<PMTU>>,
+    messages: Channel<SequencedMessage<PMTU>>,
+}
+
+impl<const TMTU: usize, const RESERVED: usize, const PMTU: usize, const SMTU: usize>
+    LoopbackLink<TMTU, RESERVED, PMTU, SMTU>
+{
+    pub fn new() -> Self {
+        Self {
+            host_to_peripheral: Channel::new(),
+            peripheral_to_host: Channel::new(),
+            host: NetworkChannels::new(),
+            peripheral: NetworkChannels::new(),
+        }
+    }
+
+    /// Creates the host and peripheral networks, which may only be done once per link
+    pub fn connect<'a, F: WireFormat<PMTU> + 'a>(
+        &'a self,
+        host_format: F,
+        peripheral_format: F,
+    ) -> (
+        LoopbackNetwork<'a, TMTU, RESERVED, PMTU, SMTU, F>,
+        LoopbackNetwork<'a, TMTU, RESERVED, PMTU, SMTU, F>,
+    ) {
+        let (host_transport, peripheral_transport) =
+            LoopbackTransport::pair(&self.host_to_peripheral, &self.peripheral_to_host);
+
+        let host = self.host_on(host_transport, host_format);
+
+        let peripheral = Network::new(
+            peripheral_transport,
+            peripheral_format,
+            Role::Peripheral,
+            self.peripheral.acks.split(),
+            self.peripheral.streams.split(),
+            self.peripheral.messages.split(),
+        );
+
+        (host, peripheral)
+    }
+
+    /// Creates only the host network and returns the raw transport of the peripheral, so tests can play the peripheral by hand.
+    /// Like [`connect`](Self::connect), this may only be done once per link.
+    pub fn host<'a, F: WireFormat<PMTU> + 'a>(
+        &'a self,
+        format: F,
+    ) -> (
+        LoopbackNetwork<'a, TMTU, RESERVED, PMTU, SMTU, F>,
+        LoopbackTransport<'a, TMTU>,
+    ) {
+        let (host_transport, peripheral_transport) =
+            LoopbackTransport::pair(&self.host_to_peripheral, &self.peripheral_to_host);
+
+        (self.host_on(host_transport, format), peripheral_transport)
+    }
+
+    /// Creates the host network on a transport of your choice, e.g. one which fails on purpose, leaving the link unused
+    pub fn host_on<'a, T: Transport<TMTU> + 'a, F: WireFormat<PMTU> + 'a>(
+        &'a self,
+        transport: T,
+        format: F,
+    ) -> Network<'a, TMTU, RESERVED, PMTU, SMTU, T, F> {
+        Network::new(
+            transport,
+            format,
+            Role::Host,
+            self.host.acks.split(),
+            self.host.streams.split(),
+            self.host.messages.split(),
+        )
+    }
+}
+
+impl<const TMTU: usize, const RESERVED: usize, const PMTU: usize, const SMTU: usize> Default
+    for LoopbackLink<TMTU, RESERVED, PMTU, SMTU>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const PMTU: usize> NetworkChannels<PMTU> {
+    fn new() -> Self {
+        Self {
+            acks: Channel::new(),
+            streams: Channel::new(),
+            messages: Channel::new(),
+        }
+    }
+}
+
+/// Format which passes serialized messages through as-is, for tests which do not care about their contents
+pub struct RawFormat;
+
+impl<const PMTU: usize> WireFormat<PMTU> for RawFormat {
+    type Message = SerializedMessage<PMTU>;
+    type Error = ();
+
+    fn serialize(&self, message: Self::Message) -> Result<SerializedMessage<PMTU>, ()> {
+        Ok(message)
+    }
+
+    fn deserialize(&self, packet: SerializedMessage<PMTU>) -> Result<Self::Message, ()> {
+        Ok(packet)
+    }
+}
+
+/// Sends the message from one network to the other while driving both of them, returning the message the handler on the receiving side got.
+///
+/// # Panics
+///
+/// If the message has not been acknowledged or the handler has not been called with it.
+pub async fn deliver<
+    'c,
+    const TMTU: usize,
+    const RESERVED: usize,
+    const PMTU: usize,
+    const SMTU: usize,
+    F,
+>(
+    from: &LoopbackNetwork<'c, TMTU, RESERVED, PMTU, SMTU, F>,
+    to: &LoopbackNetwork<'c, TMTU, RESERVED, PMTU, SMTU, F>,
+    message: F::Message,
+) -> F::Message
+where
+    F: WireFormat<PMTU> + 'c,
+    F::Error: Debug,
+{
+    let received = RefCell::new(None);
+
+    let background = async {
+        futures::join!(
+            from.recv_task(),
+            to.recv_task(),
+            to.recv_with(RecordingHandler(&received)),
+        )
+    };
+
+    let result = match select(Box::pin(from.send(message)), Box::pin(background)).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => unreachable!("background tasks never complete"),
+    };
+
+    result.expect("message has not been acknowledged");
+    received
+        .into_inner()
+        .expect("handler has not received the message")
+}
+
+/// Runs the future to completion on a single-threaded runtime, e.g. to call [`deliver`] from a regular `#[test]`
+pub fn run<Fut: Future>(future: Fut) -> Fut::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .expect("failed to build runtime")
+        .block_on(future)
+}
+
+/// Stores the incoming message, then acknowledges it
+struct RecordingHandler<'r, M>(&'r RefCell<Option<M>>);
+
+impl<'r, F, const PMTU: usize> MessageHandler<F, PMTU> for RecordingHandler<'r, F::Message>
+where
+    F: WireFormat<PMTU>,
+{
+    type HandlerFut<'s> = impl Future<Output = ()> + 's where Self: 's, F: 's;
+
+    fn handle<'s>(
+        &'s mut self,
+        message: F::Message,
+        acknowledger: MessageAcknowledger<'s, F, PMTU>,
+    ) -> Self::HandlerFut<'s> {
+        async move {
+            *self.0.borrow_mut() = Some(message);
+            acknowledger.acknowledge().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+
+    #[test]
+    fn deliver_messages_in_both_directions() {
+        let link = LoopbackLink::<9, 0, 7, 5>::new();
+        let (host, peripheral) = link.connect(RawFormat, RawFormat);
+
+        let message = SerializedMessage {
+            id: 1.into(),
+            bytes: [1, 2, 3, 4, 5, 6, 7],
+        };
+
+        run(async {
+            assert_eq!(deliver(&host, &peripheral, message).await, message);
+            assert_eq!(deliver(&peripheral, &host, message).await, message);
+            assert_eq!(deliver(&host, &peripheral, message).await, message);
+        });
+    }
+}
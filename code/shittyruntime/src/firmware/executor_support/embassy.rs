@@ -6,18 +6,22 @@ use embassy::{
     util::Either,
 };
 use super::super::*;
+use super::CHANNEL_CAPACITY;
 use core::ops::Add;
 
+type EmbassyChannel<T> = embassy::channel::mpmc::Channel<NoopRawMutex, T, CHANNEL_CAPACITY>;
+
 pub struct EmbassyMutex<T>(embassy::mutex::Mutex<NoopRawMutex, T>);
-pub struct EmbassyMpsc<T>(embassy::channel::mpmc::Channel<NoopRawMutex, T, 1>);
-pub struct EmbassyMpscSender<'c, T>(&'c embassy::channel::mpmc::Channel<NoopRawMutex, T, 1>);
-pub struct EmbassyMpscReceiver<'c, T>(&'c embassy::channel::mpmc::Channel<NoopRawMutex, T, 1>);
+pub struct EmbassyMpsc<T>(EmbassyChannel<T>);
+pub struct EmbassyMpscSender<'c, T>(&'c EmbassyChannel<T>);
+pub struct EmbassyMpscReceiver<'c, T>(&'c EmbassyChannel<T>);
+#[derive(Default)]
 pub struct EmbassyTimeDriver;
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd)]
 pub struct EmbassyDuration(Duration);
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd)]
 pub struct EmbassyInstant(Instant);
 
 impl<T> Mutex for EmbassyMutex<T> {
@@ -72,6 +76,12 @@ impl<'c, T> MpscSender<T> for EmbassyMpscSender<'c, T> {
     fn send<'f>(&'f self, value: T) -> Self::SendFut<'f> {
         self.0.send(value)
     }
+
+    fn try_send(&self, value: T) -> Result<(), T> {
+        self.0.try_send(value).map_err(|error| match error {
+            embassy::channel::mpmc::TrySendError::Full(value) => value,
+        })
+    }
 }
 
 impl<'c, T> MpscReceiver<T> for EmbassyMpscReceiver<'c, T> where T: 'c {
@@ -111,7 +121,11 @@ impl TimeDriver for EmbassyTimeDriver {
     }
 }
 
-impl DurationDriver for EmbassyDuration {}
+impl DurationDriver for EmbassyDuration {
+    fn from_micros(micros: u64) -> Self {
+        Self(Duration::from_micros(micros))
+    }
+}
 
 impl InstantDriver for EmbassyInstant {
     type Duration = EmbassyDuration;
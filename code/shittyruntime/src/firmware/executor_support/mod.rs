@@ -4,6 +4,10 @@ mod tokio;
 #[cfg(feature = "embassy")]
 mod embassy;
 
+/// Number of values a [`Channel`] buffers before senders have to wait or [`try_send`](super::MpscSender::try_send) fails.
+/// On embedded targets channels are statically allocated, so this directly determines the memory used by each of them.
+pub const CHANNEL_CAPACITY: usize = 4;
+
 #[cfg(feature = "tokio")]
 pub type Channel<T> = self::tokio::TokioMpsc<T>;
 #[cfg(feature = "tokio")]
@@ -1,11 +1,15 @@
 //! Implementations of time & sync firmware traits for the tokio runtime on desktop
 
-use super::super::{DurationDriver, InstantDriver, Mpsc, MpscReceiver, MpscSender, TimeDriver};
+use super::{
+    super::{DurationDriver, InstantDriver, Mpsc, MpscReceiver, MpscSender, TimeDriver},
+    CHANNEL_CAPACITY,
+};
 use core::future::Future;
 use futures::future::Either;
-use std::{ops::Add, pin::Pin};
+use std::{ops::Add, pin::Pin, sync::Mutex};
 use tokio::time::{sleep, sleep_until, Duration, Instant, Sleep};
 
+#[derive(Default)]
 pub struct TokioTimeDriver;
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
@@ -16,13 +20,12 @@ pub struct TokioInstant(Instant);
 
 pub struct TokioMutex<T>(tokio::sync::Mutex<T>);
 pub struct TokioMpsc<T> {
-    tx: tokio::sync::broadcast::Sender<T>,
-    #[allow(dead_code)]
-    // This field has to be retained so tokio is happy, without it the channel will be closed once this last receiver is dropped
-    rx: tokio::sync::broadcast::Receiver<T>,
+    tx: tokio::sync::mpsc::Sender<T>,
+    // Tokio channels only support a single receiver, which is handed out on the first call to `receiver`
+    rx: Mutex<Option<tokio::sync::mpsc::Receiver<T>>>,
 }
-pub struct TokioMpscSender<T>(tokio::sync::broadcast::Sender<T>);
-pub struct TokioMpscReceiver<T>(tokio::sync::broadcast::Receiver<T>);
+pub struct TokioMpscSender<T>(tokio::sync::mpsc::Sender<T>);
+pub struct TokioMpscReceiver<T>(tokio::sync::mpsc::Receiver<T>);
 
 impl TimeDriver for TokioTimeDriver {
     type Duration = TokioDuration;
@@ -54,7 +57,11 @@ impl InstantDriver for TokioInstant {
     }
 }
 
-impl DurationDriver for TokioDuration {}
+impl DurationDriver for TokioDuration {
+    fn from_micros(micros: u64) -> Self {
+        Self(Duration::from_micros(micros))
+    }
+}
 
 impl<T> crate::firmware::Mutex for TokioMutex<T> {
     type Wrapped = T;
@@ -89,8 +96,11 @@ where
     type Receiver<'m> = TokioMpscReceiver<T> where T: 'm;
 
     fn new() -> Self {
-        let (tx, rx) = tokio::sync::broadcast::channel(16);
-        Self { tx, rx }
+        let (tx, rx) = tokio::sync::mpsc::channel(CHANNEL_CAPACITY);
+        Self {
+            tx,
+            rx: Mutex::new(Some(rx)),
+        }
     }
 
     fn sender<'m>(&'m self) -> Self::Sender<'m> {
@@ -98,7 +108,14 @@ where
     }
 
     fn receiver<'m>(&'m self) -> Self::Receiver<'m> {
-        TokioMpscReceiver(self.tx.subscribe())
+        let rx = self
+            .rx
+            .lock()
+            .expect("tokio mpsc receiver lock poisoned")
+            .take()
+            .expect("tokio mpsc only supports a single receiver");
+
+        TokioMpscReceiver(rx)
     }
 }
 
@@ -112,9 +129,20 @@ where
         async move {
             self.0
                 .send(value)
+                .await
                 .expect("failed to send message on tokio mpsc");
         }
     }
+
+    fn try_send(&self, value: T) -> Result<(), T> {
+        use tokio::sync::mpsc::error::TrySendError;
+
+        match self.0.try_send(value) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(value)) => Err(value),
+            Err(TrySendError::Closed(_)) => panic!("failed to send message on tokio mpsc"),
+        }
+    }
 }
 
 impl<T: Clone> MpscReceiver<T> for TokioMpscReceiver<T> {
@@ -131,7 +159,7 @@ impl<T: Clone> MpscReceiver<T> for TokioMpscReceiver<T> {
             let result = futures::future::select(Box::pin(recv_fut), Box::pin(timeout_fut));
 
             match result.await {
-                Either::Left((value, _)) => value.ok(),
+                Either::Left((value, _)) => value,
                 Either::Right(_) => None,
             }
         }
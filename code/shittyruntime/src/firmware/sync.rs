@@ -42,8 +42,12 @@ pub trait MpscSender<T> {
     where
         Self: 'f;
 
+    /// Waits until there is space in the channel and sends the value
     #[must_use]
     fn send<'f>(&'f self, value: T) -> Self::SendFut<'f>;
+
+    /// Sends the value if there is space in the channel, otherwise hands it back
+    fn try_send(&self, value: T) -> Result<(), T>;
 }
 
 pub trait MpscReceiver<T> {
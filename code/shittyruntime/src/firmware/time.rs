@@ -10,10 +10,12 @@ pub trait TimeDriver {
     fn wait_until(&self, instant: Self::Instant) -> Self::TimerFut;
 }
 
-pub trait InstantDriver: Add<Self::Duration, Output = Self> + Copy {
+pub trait InstantDriver: Add<Self::Duration, Output = Self> + PartialOrd + Copy {
     type Duration: DurationDriver;
 
     fn elapsed(&self) -> Self::Duration;
 }
 
-pub trait DurationDriver: PartialOrd + Copy {}
+pub trait DurationDriver: PartialOrd + Copy {
+    fn from_micros(micros: u64) -> Self;
+}
@@ -1,10 +1,11 @@
 use super::UsbNetwork;
 use crate::{
-    cofit::{MessageAcknowledger, MessageHandler, SerializedMessage, Transport, WireFormat},
+    cofit::{MessageAcknowledger, MessageHandler, SerializedMessage, Transport, WireFormat, ID},
     firmware::{AlignedArray, FlashController},
 };
 use core::future::Future;
 use embedded_storage_async::nor_flash::AsyncNorFlash;
+use futures::pin_mut;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -18,11 +19,18 @@ pub enum Message {
     WriteFlash(DataRange),
     ReadFlash(DataRange),
     EraseFlash(DataRange),
-    // TODO Add variant for sending error codes / messages over to the host
+    /// Response to a flash operation which failed for the given range
+    FlashError(DataRange),
+    // TODO Add variants for sending other error codes / messages over to the host
 }
 
 pub struct TestFormat;
 
+impl TestFormat {
+    /// Upper bound of the encoded size of a [`Message`]: Its variant followed by the two varint-encoded fields of a [`DataRange`]
+    pub const MESSAGE_LENGTH: usize = 1 + 2 * 10;
+}
+
 impl<const MTU: usize> WireFormat<MTU> for TestFormat {
     type Message = Message;
     type Error = postcard::Error;
@@ -32,19 +40,27 @@ impl<const MTU: usize> WireFormat<MTU> for TestFormat {
         postcard::to_slice(&message, &mut buf)?;
 
         Ok(SerializedMessage {
-            // For now we will just always use ID 42, in the future they will be dynamically negotiated between host and peripheral
-            id: 42.into(),
+            // For now we will just always use ID 21, in the future they will be dynamically negotiated between host and peripheral
+            id: 21.into(),
             bytes: buf,
         })
     }
 
     fn deserialize(&self, packet: SerializedMessage<MTU>) -> Result<Self::Message, Self::Error> {
-        if packet.id != 42.into() {
+        if packet.id != 21.into() {
             return Err(postcard::Error::DeserializeBadEnum);
         }
 
+        if !self.is_padded(&packet) {
+            return Err(postcard::Error::DeserializeBadEncoding);
+        }
+
         Ok(postcard::take_from_bytes(&packet.bytes)?.0)
     }
+
+    fn message_length(&self, _id: ID) -> usize {
+        Self::MESSAGE_LENGTH
+    }
 }
 
 pub struct TestMessageHandler<'d, Flash: AsyncNorFlash, T: Transport<64>> {
@@ -63,7 +79,7 @@ impl<'d, Flash: AsyncNorFlash, T: Transport<64>> TestMessageHandler<'d, Flash, T
     async fn handle_flash_write<const MTU: usize>(
         &self,
         range: DataRange,
-        acknowledger: MessageAcknowledger<'_, MTU>,
+        acknowledger: MessageAcknowledger<'_, TestFormat, MTU>,
     ) {
         #[cfg(feature = "defmt")]
         defmt::debug!("writing flash range {} + {}", range.offset, range.length);
@@ -75,7 +91,18 @@ impl<'d, Flash: AsyncNorFlash, T: Transport<64>> TestMessageHandler<'d, Flash, T
 
         acknowledger.acknowledge().await;
 
-        while let Some(data) = reader.recv().await {
+        loop {
+            let data = match reader.recv().await {
+                Ok(Some(data)) => data,
+                Ok(None) => break,
+                Err(_error) => {
+                    // TODO Send an error code so the host can retry the write
+                    #[cfg(feature = "defmt")]
+                    defmt::error!("failed to receive flash data");
+                    break;
+                }
+            };
+
             if offset >= end_offset {
                 #[cfg(feature = "defmt")]
                 defmt::error!("attempted to write data beyond the initially announced range");
@@ -95,36 +122,48 @@ impl<'d, Flash: AsyncNorFlash, T: Transport<64>> TestMessageHandler<'d, Flash, T
     async fn handle_flash_read<const MTU: usize>(
         &self,
         range: DataRange,
-        acknowledger: MessageAcknowledger<'_, MTU>,
+        acknowledger: MessageAcknowledger<'_, TestFormat, MTU>,
     ) {
         #[cfg(feature = "defmt")]
         defmt::debug!("reading flash range {} + {}", range.offset, range.length);
 
-        let mut writer = self
-            .network
-            .create_stream_writer(|read_offset| async move {
-                if read_offset >= range.offset + range.length {
-                    None
-                } else {
-                    let mut bytes = AlignedArray::<61>::new();
-                    self.flash
-                        .read_aligned((range.offset + read_offset) as u32, &mut (*bytes))
-                        .await
-                        .expect("failed to read flash");
-                    Some(bytes.into())
-                }
-            })
-            .await;
+        let chunks = futures::stream::unfold(0, |read_offset| async move {
+            if read_offset >= range.offset + range.length {
+                None
+            } else {
+                let mut bytes = AlignedArray::<60>::new();
+                self.flash
+                    .read_aligned((range.offset + read_offset) as u32, &mut (*bytes))
+                    .await
+                    .expect("failed to read flash");
+
+                let chunk: [u8; 60] = bytes.into();
+                Some((chunk, read_offset + chunk.len() as u64))
+            }
+        });
+        pin_mut!(chunks);
+
+        let mut writer = self.network.create_stream_writer(chunks).await;
 
         acknowledger.acknowledge().await;
 
-        while !writer.send().await {}
+        loop {
+            match writer.send().await {
+                Ok(true) => break,
+                Ok(false) => continue,
+                Err(_error) => {
+                    #[cfg(feature = "defmt")]
+                    defmt::error!("failed to transmit flash data");
+                    break;
+                }
+            }
+        }
     }
 
     async fn handle_flash_erase<const MTU: usize>(
         &self,
         range: DataRange,
-        acknowledger: MessageAcknowledger<'_, MTU>,
+        acknowledger: MessageAcknowledger<'_, TestFormat, MTU>,
     ) {
         #[cfg(feature = "defmt")]
         defmt::debug!("erasing flash range {} + {}", range.offset, range.length);
@@ -139,13 +178,22 @@ impl<'d, Flash: AsyncNorFlash, T: Transport<64>> TestMessageHandler<'d, Flash, T
                 range.offset,
                 range.length
             );
-        }
 
-        acknowledger.acknowledge().await;
+            if acknowledger
+                .respond(Message::FlashError(range))
+                .await
+                .is_err()
+            {
+                #[cfg(feature = "defmt")]
+                defmt::error!("failed to report flash erase error");
+            }
+        } else {
+            acknowledger.acknowledge().await;
+        }
     }
 }
 
-impl<'d, Flash: AsyncNorFlash, T: Transport<64>, const MTU: usize> MessageHandler<Message, MTU>
+impl<'d, Flash: AsyncNorFlash, T: Transport<64>, const MTU: usize> MessageHandler<TestFormat, MTU>
     for TestMessageHandler<'d, Flash, T>
 where
     Flash: 'd,
@@ -158,14 +206,49 @@ where
     fn handle<'s>(
         &'s mut self,
         message: Message,
-        acknowledger: MessageAcknowledger<'s, MTU>,
+        acknowledger: MessageAcknowledger<'s, TestFormat, MTU>,
     ) -> Self::HandlerFut<'s> {
         async move {
             match message {
                 Message::WriteFlash(range) => self.handle_flash_write(range, acknowledger).await,
                 Message::ReadFlash(range) => self.handle_flash_read(range, acknowledger).await,
                 Message::EraseFlash(range) => self.handle_flash_erase(range, acknowledger).await,
+                Message::FlashError(_) => acknowledger.reject().await,
             }
         }
     }
 }
+
+#[cfg(test)]
+mod does {
+    use super::*;
+
+    fn serialize(message: Message) -> SerializedMessage<64> {
+        TestFormat.serialize(message).unwrap()
+    }
+
+    #[test]
+    fn fit_largest_message_into_its_length() {
+        let packet = serialize(Message::WriteFlash(DataRange {
+            offset: u64::MAX,
+            length: u64::MAX,
+        }));
+
+        assert!(packet.bytes[TestFormat::MESSAGE_LENGTH - 1] != 0);
+        assert!(TestFormat.deserialize(packet).is_ok());
+    }
+
+    #[test]
+    fn reject_messages_with_data_beyond_their_length() {
+        let mut packet = serialize(Message::EraseFlash(DataRange {
+            offset: 4096,
+            length: 4096,
+        }));
+        packet.bytes[TestFormat::MESSAGE_LENGTH] = 1;
+
+        assert!(matches!(
+            TestFormat.deserialize(packet),
+            Err(postcard::Error::DeserializeBadEncoding)
+        ));
+    }
+}
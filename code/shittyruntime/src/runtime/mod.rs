@@ -1,5 +1,5 @@
 use crate::{
-    cofit::{Transport, UsbNetwork},
+    cofit::{Role, Transport, UsbNetwork},
     firmware::{executor_support::*, AsyncOutputCommand, FlashController, Mpsc as _, Peripherals},
     input::InputState,
 };
@@ -29,6 +29,7 @@ impl Runtime {
         let network = UsbNetwork::new(
             peripherals.usb_channel,
             TestFormat,
+            Role::Peripheral,
             ack_channel.split(),
             stream_channel.split(),
             message_channel.split(),
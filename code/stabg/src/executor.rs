@@ -4,6 +4,19 @@ use crate::{
     ShortID,
 };
 
+/// Stack contents and start point of a single [`ExecutionQueue`](crate::ExecutionQueue) run
+///
+/// Recorded by [`Executor::execute_sync_recorded`] and replayed with [`Executor::replay_sync`].
+#[cfg(feature = "alloc")]
+#[doc(cfg(feature = "alloc"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionRecord {
+    /// Processor from which the run started, `None` if it ran the whole queue
+    pub start_point: Option<ShortID>,
+    /// Contents of the stack right before the run
+    pub stack: crate::StackSnapshot,
+}
+
 pub struct Executor<'s> {
     stack: &'s mut dyn Stack,
 }
@@ -92,6 +105,53 @@ impl<'s> Executor<'s> {
         Ok(())
     }
 
+    /// Behaves like [`execute_sync`](Self::execute_sync) but records the stack before every run of the queue.
+    ///
+    /// Runs are appended to `recording` even if they fail, so the last record always reproduces the failing run.
+    #[cfg(feature = "alloc")]
+    pub fn execute_sync_recorded(
+        &mut self,
+        execution_queue: &mut dyn crate::ExecutionQueue,
+        recording: &mut alloc::vec::Vec<ExecutionRecord>,
+    ) -> Result<(), crate::processor::ExecutionError> {
+        // Remove any remainders from previous runs
+        self.stack.clear();
+
+        let mut start_point = None;
+
+        loop {
+            let stack = self
+                .stack
+                .snapshot()
+                .map_err(crate::ExecutionContextError::StackError)?;
+
+            recording.push(ExecutionRecord { start_point, stack });
+
+            execution_queue.run(start_point, self.stack)?;
+
+            match self.next_execution_step() {
+                Some(next) => start_point = Some(next),
+                None => break Ok(()),
+            }
+        }
+    }
+
+    /// Restores the stack from the given record and repeats the run it describes.
+    ///
+    /// Note that this only replays a single run, any branches it creates are left on the stack.
+    #[cfg(feature = "alloc")]
+    pub fn replay_sync(
+        &mut self,
+        execution_queue: &mut dyn crate::ExecutionQueue,
+        record: &ExecutionRecord,
+    ) -> Result<(), crate::processor::ExecutionError> {
+        self.stack
+            .restore(&record.stack)
+            .map_err(crate::ExecutionContextError::StackError)?;
+
+        execution_queue.run(record.start_point, self.stack)
+    }
+
     #[cfg(feature = "nightly")]
     pub async fn execute_async<Q: crate::AsyncExecutionQueue>(
         &mut self,
@@ -110,4 +170,29 @@ impl<'s> Executor<'s> {
 
         Ok(())
     }
+
+    /// Behaves like [`execute_async`](Self::execute_async) but measures the execution time of every processor invocation.
+    ///
+    /// Measurements accumulate across calls, look at [`ProcessorTimings`](crate::timing::ProcessorTimings) for how to access them.
+    #[cfg(feature = "timing")]
+    pub async fn execute_async_timed<Q: crate::timing::TimedExecutionQueue>(
+        &mut self,
+        execution_queue: &mut Q,
+        timer: &mut dyn crate::timing::ExecutionTimer,
+    ) -> Result<(), crate::processor::EmbeddedExecutionError> {
+        // Remove any remainders from previous runs
+        self.stack.clear();
+
+        // Run through it once completely
+        execution_queue.run_timed(None, self.stack, timer).await?;
+
+        // Repeat until there are no branches left
+        while let Some(start_point) = self.next_execution_step() {
+            execution_queue
+                .run_timed(Some(start_point), self.stack, timer)
+                .await?;
+        }
+
+        Ok(())
+    }
 }
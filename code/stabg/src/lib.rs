@@ -15,13 +15,21 @@ mod stack;
 
 pub mod processor;
 pub mod serialization;
+#[cfg(feature = "timing")]
+pub mod timing;
 
 pub use context::*;
 pub use executor::Executor;
+#[cfg(feature = "alloc")]
+pub use executor::ExecutionRecord;
 pub use identifier::*;
 pub use queue::*;
+pub use registry::{Registry, RegistryIter};
 pub use stack::*;
 
+#[cfg(feature = "alloc")]
+pub use registry::DynamicRegistry;
+
 #[doc(hidden)]
 pub use registry::IteratorRegistry;
 
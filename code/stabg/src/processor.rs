@@ -138,6 +138,10 @@ pub type EmbeddedExecutionError = GenericExecutionError<TransmuteError>;
 ///
 /// Note that by adding the `#[skip_phase]` attribute, you can save yourself
 /// from writing default implementations for [`load`](Self::load) and [`unload`](Self::unload) and instead have the macro generate them.
+///
+/// By default, the stack usage is based on the in-memory size of the output types. If their values are stored using the
+/// [`PostcardSerializer`](crate::serialization::PostcardSerializer) instead, add the `postcard` flag (`#[stack_usage(items = 1, postcard)]`)
+/// to base it on their [maximum serialized size](crate::serialization::MaxSize). This requires the output types to derive `MaxSize`.
 #[cfg(feature = "nightly")]
 pub trait EmbeddedProcessor {
     /// List of types that will be retrieved from the context during execution
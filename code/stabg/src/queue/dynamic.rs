@@ -40,6 +40,11 @@ impl DynamicExecutionQueue {
         }
     }
 
+    /// Registry containing the types of all scheduled processors, e.g. for printing the assignments with [`Registry::iter`](crate::Registry::iter)
+    pub fn registry(&self) -> &DynamicRegistry {
+        &self.registry
+    }
+
     /// Sets whether execution should be aborted when any single processor in the collection fails
     pub fn abort_on_error(&mut self, enabled: bool) -> &mut Self {
         self.abort_on_error = enabled;
@@ -13,6 +13,16 @@ pub trait ExecutionQueue {
     ) -> Result<(), crate::processor::ExecutionError>;
 }
 
+/// Outcome of a [pausable run](AsyncExecutionQueue::run_pausable) of an [`AsyncExecutionQueue`]
+#[cfg(feature = "nightly")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueProgress {
+    /// All processors following the start ID have been executed
+    Completed,
+    /// The run has been paused before executing the processor with the given ID, pass it as the `start_id` to resume
+    Paused(crate::ShortID),
+}
+
 #[cfg(feature = "nightly")]
 pub trait AsyncExecutionQueue {
     /// Cumulative stack usage of all contained processors.
@@ -32,4 +42,22 @@ pub trait AsyncExecutionQueue {
         start_id: Option<crate::ShortID>,
         stack: &'s mut dyn crate::Stack,
     ) -> Self::Fut<'s>;
+
+    type PausableFut<'s>: core::future::Future<
+            Output = Result<QueueProgress, crate::processor::EmbeddedExecutionError>,
+        > + 's
+    where
+        Self: 's;
+
+    /// Behaves like [`run`](Self::run) but stops early if the `pause` flag is set once a processor finished executing,
+    /// e.g. to let a higher-priority task run on a single-threaded executor.
+    ///
+    /// Pausing leaves the stack intact, so passing the returned ID as the `start_id` resumes the run without executing
+    /// any processor twice. The flag is not reset, but every run executes at least one processor before pausing.
+    fn run_pausable<'s>(
+        &'s mut self,
+        start_id: Option<crate::ShortID>,
+        stack: &'s mut dyn crate::Stack,
+        pause: &'s core::sync::atomic::AtomicBool,
+    ) -> Self::PausableFut<'s>;
 }
@@ -1,6 +1,10 @@
 use super::*;
 
-pub struct DynamicRegistry(alloc::vec::Vec<Identifier>);
+/// Growable registry which hands out short IDs in the order identifiers are registered
+///
+/// Slots of [unregistered](Self::unregister) identifiers stay empty until the next registration reuses them,
+/// so the short IDs of all other identifiers remain stable (e.g. because they are stored in serialized stacks).
+pub struct DynamicRegistry(alloc::vec::Vec<Option<Identifier>>);
 
 #[cfg(feature = "alloc")]
 impl DynamicRegistry {
@@ -10,17 +14,41 @@ impl DynamicRegistry {
 
     pub fn register(&mut self, id: Identifier) -> ShortID {
         self.lookup(id).unwrap_or_else(|| {
+            if let Some(code) = self.0.iter().position(Option::is_none) {
+                self.0[code] = Some(id);
+                return code as ShortID;
+            }
+
             let code = self.0.len() as ShortID;
 
             if code >= ShortID::MAX - RESERVED_COUNT {
                 panic!("attempted to register more types than supported");
             }
 
-            self.0.push(id);
+            self.0.push(Some(id));
 
             code
         })
     }
+
+    /// Removes the identifier, allowing its short ID to be handed out again by [`register`](Self::register).
+    /// Returns whether the identifier has been registered in the first place.
+    pub fn unregister(&mut self, id: Identifier) -> bool {
+        match self.0.iter_mut().find(|c| **c == Some(id)) {
+            Some(slot) => {
+                *slot = None;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Default for DynamicRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(feature = "alloc")]
@@ -29,7 +57,59 @@ impl Registry for DynamicRegistry {
         self.0
             .iter()
             .enumerate()
-            .find(|(_, c)| **c == id)
+            .find(|(_, c)| **c == Some(id))
             .map(|(i, _)| i as ShortID)
     }
+
+    fn resolve(&self, short_id: ShortID) -> Option<Identifier> {
+        self.0.get(short_id as usize).copied().flatten()
+    }
+
+    fn upper_bound(&self) -> ShortID {
+        self.0.len() as ShortID
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn iterate_all_assignments() {
+        let mut registry = DynamicRegistry::new();
+        registry.register("test.a");
+        registry.register("test.b");
+        registry.register("test.a");
+
+        let assignments = registry.iter().collect::<Vec<_>>();
+        assert_eq!(assignments, [(0, "test.a"), (1, "test.b")]);
+
+        let dynamic: &dyn Registry = &registry;
+        assert_eq!(RegistryIter::new(dynamic).count(), 2);
+    }
+
+    #[test]
+    fn reuse_unregistered_ids() {
+        let mut registry = DynamicRegistry::new();
+        registry.register("test.a");
+        registry.register("test.b");
+        registry.register("test.c");
+
+        assert!(registry.unregister("test.b"));
+        assert!(!registry.unregister("test.b"));
+        assert!(!registry.contains("test.b"));
+        assert_eq!(registry.resolve(1), None);
+
+        // Other assignments keep their IDs while the gap is filled by the next registration
+        assert_eq!(registry.lookup("test.c"), Some(2));
+        assert_eq!(registry.register("test.d"), 1);
+        assert_eq!(registry.register("test.e"), 3);
+
+        let assignments = registry.iter().collect::<Vec<_>>();
+        assert_eq!(
+            assignments,
+            [(0, "test.a"), (1, "test.d"), (2, "test.c"), (3, "test.e")]
+        );
+    }
 }
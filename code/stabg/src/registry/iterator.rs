@@ -1,8 +1,27 @@
 use super::*;
 
-pub struct IteratorRegistry<I>(pub I)
+pub struct IteratorRegistry<I>
 where
-    I: Iterator<Item = &'static Identifier> + Clone;
+    I: Iterator<Item = &'static Identifier> + Clone,
+{
+    identifiers: I,
+    upper_bound: ShortID,
+}
+
+impl<I> IteratorRegistry<I>
+where
+    I: Iterator<Item = &'static Identifier> + Clone,
+{
+    pub fn new(identifiers: I) -> Self {
+        // Counting requires a walk of the whole iterator, so do it once instead of on every call to `upper_bound`
+        let upper_bound = identifiers.clone().count() as ShortID;
+
+        Self {
+            identifiers,
+            upper_bound,
+        }
+    }
+}
 
 impl<I> Registry for IteratorRegistry<I>
 where
@@ -11,13 +30,27 @@ where
     fn lookup(&self, id: Identifier) -> Option<ShortID> {
         // Compress identifiers by deduplicating the iterator before calling `.find`
         // That way we don't have unused identifiers where types are present twice
-        let is_first = |(i, x): &(usize, &Identifier)| self.0.clone().take(*i).any(|x2| &x2 == x);
+        let is_first =
+            |(i, x): &(usize, &Identifier)| self.identifiers.clone().take(*i).any(|x2| &x2 == x);
 
-        self.0
+        self.identifiers
             .clone()
             .enumerate()
             .filter(is_first)
             .find(|(_, x)| *x == &id)
             .map(|(i, _)| i as ShortID)
     }
+
+    fn resolve(&self, short_id: ShortID) -> Option<Identifier> {
+        // Walk the backing iterator and only accept the identifier if it is the one `lookup` assigns this ID to
+        self.identifiers
+            .clone()
+            .nth(short_id as usize)
+            .copied()
+            .filter(|id| self.lookup(id) == Some(short_id))
+    }
+
+    fn upper_bound(&self) -> ShortID {
+        self.upper_bound
+    }
 }
@@ -18,8 +18,62 @@ pub trait Registry {
     /// Performs a forward lookup based on the provided identifier
     fn lookup(&self, id: Identifier) -> Option<ShortID>;
 
+    /// Performs a reverse lookup based on the provided short ID
+    ///
+    /// Registries which are unable to perform reverse lookups may rely on the default, which resolves nothing.
+    fn resolve(&self, _short_id: ShortID) -> Option<Identifier> {
+        None
+    }
+
+    /// Exclusive upper bound of the short IDs handed out by this registry
+    ///
+    /// Defaults to zero, which leaves [`iter`](Registry::iter) empty for registries that do not override it.
+    fn upper_bound(&self) -> ShortID {
+        0
+    }
+
     /// Returns whether or not a short ID has been assigned to the given identifier
     fn contains(&self, id: Identifier) -> bool {
         self.lookup(id).is_some()
     }
+
+    /// Iterates over all current assignments, useful for dumping the table when debugging
+    fn iter(&self) -> RegistryIter<'_, Self>
+    where
+        Self: Sized,
+    {
+        RegistryIter::new(self)
+    }
+}
+
+/// Iterator over all `(ShortID, Identifier)` assignments of a [`Registry`](Registry)
+///
+/// Usually obtained through [`Registry::iter`](Registry::iter), but can be constructed
+/// directly for trait objects.
+pub struct RegistryIter<'r, R: Registry + ?Sized> {
+    registry: &'r R,
+    next: ShortID,
+}
+
+impl<'r, R: Registry + ?Sized> RegistryIter<'r, R> {
+    pub fn new(registry: &'r R) -> Self {
+        Self { registry, next: 0 }
+    }
+}
+
+impl<'r, R: Registry + ?Sized> Iterator for RegistryIter<'r, R> {
+    type Item = (ShortID, Identifier);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.next < self.registry.upper_bound() {
+            let short_id = self.next;
+            self.next += 1;
+
+            if let Some(id) = self.registry.resolve(short_id) {
+                return Some((short_id, id));
+            }
+        }
+
+        None
+    }
 }
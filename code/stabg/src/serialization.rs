@@ -41,6 +41,40 @@ impl Serializer for JsonSerializer {
     }
 }
 
+/// Upper bound of the serialized size of a type, used by the [`EmbeddedProcessor`](crate::processor::EmbeddedProcessor)
+/// derive macro to estimate the stack usage of values serialized by the [`PostcardSerializer`]
+#[cfg(feature = "postcard")]
+pub use postcard::experimental::max_size::MaxSize;
+
+/// Turns types into a compact, portable binary format using [`postcard`](`postcard`)
+///
+/// Unlike the [`TransmuteSerializer`], values do not depend on the memory layout of the platform they were created on,
+/// so stacks produced on-device can be read by host tools regardless of their architecture or endianness.
+/// Values are serialized into a scratch buffer of `BUFFER` bytes on the stack before being handed out,
+/// types which serialize to more bytes than that fail with [`SerializeBufferFull`](postcard::Error::SerializeBufferFull).
+#[cfg(feature = "postcard")]
+#[derive(Clone, Copy)]
+pub struct PostcardSerializer<const BUFFER: usize>;
+
+#[cfg(feature = "postcard")]
+impl<const BUFFER: usize> Serializer for PostcardSerializer<BUFFER> {
+    type Error = postcard::Error;
+
+    fn serialize<R, T: serde::Serialize>(
+        &self,
+        value: &T,
+        callback: impl FnOnce(&[u8]) -> R,
+    ) -> Result<R, Self::Error> {
+        let mut buffer = [0; BUFFER];
+        let used = postcard::to_slice(value, &mut buffer)?;
+        Ok((callback)(used))
+    }
+
+    fn deserialize<T: DeserializeOwned>(&self, buf: &[u8]) -> Result<T, Self::Error> {
+        postcard::from_bytes(buf)
+    }
+}
+
 /// Errors that may occur while transmuting raw memory into types
 #[derive(Debug)]
 pub enum TransmuteError {
@@ -129,4 +163,44 @@ mod does {
             })
             .unwrap();
     }
+
+    #[cfg(feature = "postcard")]
+    #[test]
+    fn survive_postcard_roundtrip() {
+        let serializer = PostcardSerializer::<32>;
+        let value = Outer(42, Inner(133.7), -42);
+
+        serializer
+            .serialize(&value, |buf| {
+                let new_value: Outer = serializer.deserialize(buf).unwrap();
+                assert_eq!(new_value, value);
+            })
+            .unwrap();
+    }
+
+    #[cfg(feature = "postcard")]
+    #[test]
+    fn reject_values_exceeding_postcard_buffer() {
+        let serializer = PostcardSerializer::<4>;
+        let value = Outer(42, Inner(133.7), -42);
+
+        assert_eq!(
+            serializer.serialize(&value, |_| ()),
+            Err(postcard::Error::SerializeBufferFull)
+        );
+    }
+
+    #[cfg(feature = "postcard")]
+    #[test]
+    fn reject_truncated_postcard_values() {
+        let serializer = PostcardSerializer::<32>;
+        let value = Outer(42, Inner(133.7), -42);
+
+        serializer
+            .serialize(&value, |buf| {
+                let truncated = serializer.deserialize::<Outer>(&buf[..buf.len() - 1]);
+                assert!(truncated.is_err());
+            })
+            .unwrap();
+    }
 }
@@ -48,6 +48,23 @@ impl DynamicStack {
             Some((id, range))
         }
     }
+
+    /// Locates the data of the latest pushed value with the given type code
+    fn find(&self, code: ShortID) -> Option<Range<usize>> {
+        let mut offset = self.usage;
+
+        while offset > 0 {
+            let (id, range) = self.fetch_header_from(offset)?;
+
+            if id == code {
+                return Some(range);
+            }
+
+            offset = range.start;
+        }
+
+        None
+    }
 }
 
 impl Default for DynamicStack {
@@ -103,19 +120,22 @@ impl Stack for DynamicStack {
     }
 
     fn get(&self, code: ShortID) -> Option<&[u8]> {
-        let mut offset = self.usage;
-
-        while offset > 0 {
-            let (id, range) = self.fetch_header_from(offset)?;
+        self.find(code).map(|range| &self.data[range])
+    }
 
-            if id == code {
-                return Some(&self.data[range]);
-            }
+    fn update(&mut self, code: ShortID, data: &[u8]) -> Result<(), StackError> {
+        let range = self.find(code).ok_or(StackError::NotFound)?;
 
-            offset = range.start;
+        if range.len() != data.len() {
+            return Err(StackError::ValueTooLarge);
         }
 
-        None
+        self.data[range].copy_from_slice(data);
+        Ok(())
+    }
+
+    fn entries(&self) -> StackEntries<'_> {
+        StackEntries::new(&self.data[..self.usage])
     }
 
     fn iter_reset(&mut self) {
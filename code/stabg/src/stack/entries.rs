@@ -0,0 +1,50 @@
+use super::*;
+
+/// Iterator over the values of a [`Stack`](Stack) from newest to oldest, see [`Stack::entries`](Stack::entries)
+///
+/// Walks the memory layout shared by the [`FixedSizeStack`](super::FixedSizeStack) and the `DynamicStack`,
+/// where each value is followed by a header containing its length and type code.
+#[derive(Debug, Clone)]
+pub struct StackEntries<'s> {
+    data: &'s [u8],
+}
+
+impl<'s> StackEntries<'s> {
+    const HEADER_SIZE: usize = 6;
+
+    /// Iterates the values stored in the given slice, which has to end right after the header of the newest value
+    pub(super) fn new(data: &'s [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Iterator which yields no values, used by stacks that do not share the common memory layout
+    pub(super) fn empty() -> Self {
+        Self { data: &[] }
+    }
+}
+
+impl<'s> Iterator for StackEntries<'s> {
+    type Item = (ShortID, &'s [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.data.len();
+
+        if offset < Self::HEADER_SIZE {
+            return None;
+        }
+
+        let id: ShortID = u32::from_be_bytes([
+            self.data[offset - 4],
+            self.data[offset - 3],
+            self.data[offset - 2],
+            self.data[offset - 1],
+        ]);
+        let length = u16::from_be_bytes([self.data[offset - 6], self.data[offset - 5]]);
+        let start = offset - Self::HEADER_SIZE - length as usize;
+
+        let value = &self.data[start..offset - Self::HEADER_SIZE];
+        self.data = &self.data[..start];
+
+        Some((id, value))
+    }
+}
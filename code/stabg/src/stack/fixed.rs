@@ -51,6 +51,23 @@ impl<const CAPACITY: usize> FixedSizeStack<CAPACITY> {
             Some((id, range))
         }
     }
+
+    /// Locates the data of the latest pushed value with the given type code
+    fn find(&self, code: ShortID) -> Option<Range<usize>> {
+        let mut offset = self.usage;
+
+        while offset > 0 {
+            let (id, range) = self.fetch_header_from(offset)?;
+
+            if id == code {
+                return Some(range);
+            }
+
+            offset = range.start;
+        }
+
+        None
+    }
 }
 
 impl<const CAPACITY: usize> Default for FixedSizeStack<CAPACITY> {
@@ -95,19 +112,22 @@ impl<const CAPACITY: usize> Stack for FixedSizeStack<CAPACITY> {
     }
 
     fn get(&self, code: ShortID) -> Option<&[u8]> {
-        let mut offset = self.usage;
-
-        while offset > 0 {
-            let (id, range) = self.fetch_header_from(offset)?;
+        self.find(code).map(|range| &self.data[range])
+    }
 
-            if id == code {
-                return Some(&self.data[range]);
-            }
+    fn update(&mut self, code: ShortID, data: &[u8]) -> Result<(), StackError> {
+        let range = self.find(code).ok_or(StackError::NotFound)?;
 
-            offset = range.start;
+        if range.len() != data.len() {
+            return Err(StackError::ValueTooLarge);
         }
 
-        None
+        self.data[range].copy_from_slice(data);
+        Ok(())
+    }
+
+    fn entries(&self) -> StackEntries<'_> {
+        StackEntries::new(&self.data[..self.usage])
     }
 
     fn iter_reset(&mut self) {
@@ -145,6 +165,20 @@ mod does {
         assert_eq!(stack.pop(), Some((0, data)));
     }
 
+    #[test]
+    fn accept_peek() {
+        let mut stack = FixedSizeStack::<{ (OVERHEAD + 1) * 2 }>::new();
+        assert_eq!(stack.peek(), None);
+
+        stack.push(0, &[1]).unwrap();
+        stack.push(1, &[2]).unwrap();
+        assert_eq!(stack.peek(), Some((1, [2].as_slice())));
+        assert_eq!(stack.peek(), Some((1, [2].as_slice())));
+
+        stack.pop();
+        assert_eq!(stack.peek(), Some((0, [1].as_slice())));
+    }
+
     #[test]
     fn accept_get() {
         let mut stack = FixedSizeStack::<{ (OVERHEAD + 1) * 4 }>::new();
@@ -161,6 +195,41 @@ mod does {
     }
 
     #[test]
+    fn accept_update() {
+        let mut stack = FixedSizeStack::<{ (OVERHEAD + 2) * 3 }>::new();
+
+        stack.push(0, &[1, 1]).unwrap();
+        stack.push(1, &[2, 2]).unwrap();
+        stack.push(0, &[3, 3]).unwrap();
+
+        stack.update(0, &[4, 4]).unwrap();
+        assert_eq!(stack.get(0), Some([4, 4].as_slice()));
+        assert_eq!(stack.get(1), Some([2, 2].as_slice()));
+        assert_eq!(stack.free(), 0);
+
+        // Older values are left alone
+        stack.pop();
+        assert_eq!(stack.get(0), Some([1, 1].as_slice()));
+    }
+
+    #[test]
+    fn reject_invalid_update() {
+        let mut stack = FixedSizeStack::<{ OVERHEAD + 2 }>::new();
+        stack.push(0, &[1, 1]).unwrap();
+
+        assert!(matches!(
+            stack.update(0, &[1, 2, 3]),
+            Err(StackError::ValueTooLarge)
+        ));
+        assert!(matches!(
+            stack.update(1, &[1, 2]),
+            Err(StackError::NotFound)
+        ));
+        assert_eq!(stack.get(0), Some([1, 1].as_slice()));
+    }
+
+    #[test]
+    #[allow(deprecated)]
     fn accept_iter() {
         let data1: &[u8] = &[42, 69];
         let data2: &[u8] = &[5, 57];
@@ -175,4 +244,25 @@ mod does {
         assert_eq!(stack.iter_next(), Some((0, data1)));
         assert_eq!(stack.iter_next(), None);
     }
+
+    #[test]
+    fn iterate_entries() {
+        let data1: &[u8] = &[42, 69];
+        let data2: &[u8] = &[];
+
+        let mut stack = FixedSizeStack::<{ (OVERHEAD + 2) * 3 }>::new();
+
+        stack.push(0, data1).unwrap();
+        stack.push(1, data2).unwrap();
+        stack.push(2, data1).unwrap();
+        stack.pop();
+
+        // Every call starts out at the newest value, regardless of earlier iterations
+        assert_eq!(stack.entries().next(), Some((1, data2)));
+
+        let mut entries = stack.entries();
+        assert_eq!(entries.next(), Some((1, data2)));
+        assert_eq!(entries.next(), Some((0, data1)));
+        assert_eq!(entries.next(), None);
+    }
 }
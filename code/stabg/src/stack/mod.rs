@@ -2,11 +2,20 @@ use crate::ShortID;
 
 #[cfg(feature = "alloc")]
 mod dynamic;
+mod entries;
 mod fixed;
+mod scoped;
+mod serialized;
+#[cfg(feature = "alloc")]
+mod snapshot;
 
 #[cfg(feature = "alloc")]
 pub use dynamic::DynamicStack;
+pub use entries::StackEntries;
 pub use fixed::FixedSizeStack;
+pub use scoped::ScopedStack;
+#[cfg(feature = "alloc")]
+pub use snapshot::StackSnapshot;
 
 /// Memory overflow errors caused while modifying a [`Stack`](Stack)
 #[derive(Debug)]
@@ -14,7 +23,12 @@ pub enum StackError {
     /// The underlying storage medium has no space left to store the provided value
     StackOverflow,
     /// Internal constraints prevent a value of this size from being stored (usually `2^16`)
+    /// or it does not match the size of the value it should replace
     ValueTooLarge,
+    /// No value with the requested type code is present on the stack
+    NotFound,
+    /// The serialized snapshot is truncated or its length prefixes exceed the provided bytes
+    MalformedSnapshot,
 }
 
 /// LIFO storage for arbitrary binary data with a type tag
@@ -26,15 +40,83 @@ pub trait Stack {
     fn push(&mut self, code: ShortID, data: &[u8]) -> Result<(), StackError>;
     /// Removes the last pushed value from the stack permanently
     fn pop(&mut self) -> Option<(ShortID, &[u8])>;
+    /// Retrieves the last pushed value and its type code without removing it
+    fn peek(&self) -> Option<(ShortID, &[u8])> {
+        self.entries().next()
+    }
     /// Retrieves the latest pushed value with the given type code, ignores older values
     fn get(&self, code: ShortID) -> Option<&[u8]>;
+    /// Overwrites the latest pushed value with the given type code in place, ignores older values.
+    /// Since the stack does not grow, the new data has to be exactly as large as the value it replaces.
+    ///
+    /// The default implementation has no access to the underlying memory and can only replace the newest value
+    /// on the stack by popping and pushing it again, values further down are reported as [`NotFound`](StackError::NotFound).
+    fn update(&mut self, code: ShortID, data: &[u8]) -> Result<(), StackError> {
+        match self.peek() {
+            Some((id, value)) if id == code && value.len() == data.len() => {}
+            Some((id, _)) if id == code => return Err(StackError::ValueTooLarge),
+            _ => return Err(StackError::NotFound),
+        }
+
+        self.pop();
+        self.push(code, data)
+    }
+
+    /// Iterates through the stack from newest to oldest without modifying the values
+    ///
+    /// The default yields nothing, as there is no way to reach the values through `&self` with the legacy iteration methods.
+    /// Stacks should override it, otherwise they appear empty to [`peek`](Stack::peek), snapshots, and the default [`update`](Stack::update).
+    fn entries(&self) -> StackEntries<'_> {
+        StackEntries::empty()
+    }
 
     /// Resets the internal iteration pointer to the newest value, see [`iter_next`](Stack::iter_next)
+    #[deprecated(note = "use `entries` instead, which can not yield stale values")]
     fn iter_reset(&mut self);
     /// Iterates through the stack from newest to oldest without modifying the values.
-    /// Due to implementation details, this could not be solved with a true Iterator. If you have any ideas, let me know :D
-    // TODO Find a way to return one iterator type which works for all implementations (by holding a ref)
+    /// Yields stale values unless [`iter_reset`](Stack::iter_reset) has been called beforehand.
+    #[deprecated(note = "use `entries` instead, which can not yield stale values")]
     fn iter_next(&mut self) -> Option<(ShortID, &[u8])>;
+
+    /// Copies all values currently on the stack
+    ///
+    /// Fails with [`ValueTooLarge`](StackError::ValueTooLarge) if a value exceeds the length a snapshot can store (`2^16`).
+    #[cfg(feature = "alloc")]
+    fn snapshot(&self) -> Result<StackSnapshot, StackError> {
+        StackSnapshot::capture(self)
+    }
+
+    /// Replaces the contents of the stack with the values from the given snapshot
+    ///
+    /// If this fails, the stack contains a partially restored snapshot and should be cleared.
+    #[cfg(feature = "alloc")]
+    fn restore(&mut self, snapshot: &StackSnapshot) -> Result<(), StackError> {
+        self.clear();
+
+        for (code, data) in snapshot.iter() {
+            self.push(code, data)?;
+        }
+
+        Ok(())
+    }
+
+    /// Serializes all values currently on the stack into the given buffer, returning the number of bytes written.
+    /// Fails with [`StackOverflow`](StackError::StackOverflow) if the buffer is too small to hold them.
+    ///
+    /// The snapshot starts with the number of values as a big endian `u32`, followed by the values from oldest to newest.
+    /// Each value is preceded by its type code (`u32`) and length (`u16`), both big endian as well.
+    fn snapshot_into(&self, out: &mut [u8]) -> Result<usize, StackError> {
+        serialized::write(self, out)
+    }
+
+    /// Replaces the contents of the stack with the values from the given serialized snapshot, see [`snapshot_into`](Stack::snapshot_into).
+    /// Bytes following the last value are ignored, so the snapshot may be read back from a larger region.
+    ///
+    /// Truncated or otherwise malformed snapshots are rejected with [`MalformedSnapshot`](StackError::MalformedSnapshot)
+    /// before the stack is modified. If pushing the values fails, the stack contains a partially restored snapshot and should be cleared.
+    fn restore_from(&mut self, bytes: &[u8]) -> Result<(), StackError> {
+        serialized::read(self, bytes)
+    }
 }
 
 /// On embedded, we are directly transmuting Rust values into their internal memory representation.
@@ -69,7 +151,106 @@ pub const fn determine_stack_usage(output_count: usize, output_type_sizes: &[usi
 
 #[cfg(test)]
 mod does {
-    use crate::{determine_stack_usage, FixedSizeStack};
+    use super::{Stack, StackEntries, StackError};
+    use crate::{determine_stack_usage, FixedSizeStack, ShortID};
+
+    /// Forwards everything but the provided methods to a [`FixedSizeStack`]
+    struct ForwardingStack(FixedSizeStack<32>);
+
+    #[allow(deprecated)]
+    impl Stack for ForwardingStack {
+        fn clear(&mut self) {
+            self.0.clear()
+        }
+
+        fn push(&mut self, code: ShortID, data: &[u8]) -> Result<(), StackError> {
+            self.0.push(code, data)
+        }
+
+        fn pop(&mut self) -> Option<(ShortID, &[u8])> {
+            self.0.pop()
+        }
+
+        fn get(&self, code: ShortID) -> Option<&[u8]> {
+            self.0.get(code)
+        }
+
+        fn entries(&self) -> StackEntries<'_> {
+            self.0.entries()
+        }
+
+        fn iter_reset(&mut self) {
+            self.0.iter_reset()
+        }
+
+        fn iter_next(&mut self) -> Option<(ShortID, &[u8])> {
+            self.0.iter_next()
+        }
+    }
+
+    #[test]
+    fn update_newest_value_by_default() {
+        let mut stack = ForwardingStack(FixedSizeStack::new());
+        stack.push(0, &[1, 1]).unwrap();
+        stack.push(1, &[2, 2]).unwrap();
+
+        stack.update(1, &[3, 3]).unwrap();
+        assert_eq!(stack.get(1), Some([3, 3].as_slice()));
+
+        assert!(matches!(
+            stack.update(1, &[4]),
+            Err(StackError::ValueTooLarge)
+        ));
+        assert!(matches!(
+            stack.update(0, &[4, 4]),
+            Err(StackError::NotFound)
+        ));
+        assert_eq!(stack.entries().count(), 2);
+        assert_eq!(stack.get(0), Some([1, 1].as_slice()));
+    }
+
+    /// Implements only the methods which predate the iterator based API
+    struct LegacyStack(FixedSizeStack<32>);
+
+    #[allow(deprecated)]
+    impl Stack for LegacyStack {
+        fn clear(&mut self) {
+            self.0.clear()
+        }
+
+        fn push(&mut self, code: ShortID, data: &[u8]) -> Result<(), StackError> {
+            self.0.push(code, data)
+        }
+
+        fn pop(&mut self) -> Option<(ShortID, &[u8])> {
+            self.0.pop()
+        }
+
+        fn get(&self, code: ShortID) -> Option<&[u8]> {
+            self.0.get(code)
+        }
+
+        fn iter_reset(&mut self) {
+            self.0.iter_reset()
+        }
+
+        fn iter_next(&mut self) -> Option<(ShortID, &[u8])> {
+            self.0.iter_next()
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn keep_legacy_stacks_working() {
+        let mut stack = LegacyStack(FixedSizeStack::new());
+        stack.push(0, &[1, 1]).unwrap();
+
+        assert_eq!(stack.entries().next(), None);
+        assert!(stack.peek().is_none());
+
+        stack.iter_reset();
+        assert_eq!(stack.iter_next(), Some((0, [1, 1].as_slice())));
+    }
 
     #[test]
     fn determine_usage_correctly() {
@@ -0,0 +1,140 @@
+use super::*;
+
+/// Isolated namespace on top of a parent [`Stack`](Stack)
+///
+/// Values pushed into the scope are kept in a separate stack, so they neither shadow nor disturb
+/// values with the same type code in the parent and the parent's values are not visible from within the scope.
+/// This allows running a group of processors as a unit with its own scratch space. Once it is done,
+/// the scope is either [collapsed](Self::collapse), handing selected outputs up to the parent, or [discarded](Self::discard).
+pub struct ScopedStack<'p, P: Stack + ?Sized, S: Stack> {
+    parent: &'p mut P,
+    scope: S,
+}
+
+impl<'p, P: Stack + ?Sized, S: Stack> ScopedStack<'p, P, S> {
+    /// Opens a new scope on top of the parent, using the given stack to store the values pushed into it.
+    /// The scope stack is cleared beforehand so that the namespace starts out empty.
+    pub fn new(parent: &'p mut P, mut scope: S) -> Self {
+        scope.clear();
+        Self { parent, scope }
+    }
+
+    /// Closes the scope and pushes the latest value of each given type code onto the parent, in the order the codes are listed.
+    /// Codes without a value in the scope are skipped, all other values are dropped together with the scope.
+    ///
+    /// If this fails, the values of the preceding codes have already been pushed onto the parent.
+    pub fn collapse(self, codes: &[ShortID]) -> Result<(), StackError> {
+        for code in codes {
+            if let Some(data) = self.scope.get(*code) {
+                self.parent.push(*code, data)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Closes the scope without touching the parent, dropping all values pushed into it
+    pub fn discard(self) {}
+}
+
+impl<'p, P: Stack + ?Sized, S: Stack> Stack for ScopedStack<'p, P, S> {
+    fn clear(&mut self) {
+        self.scope.clear();
+    }
+
+    fn push(&mut self, code: ShortID, data: &[u8]) -> Result<(), StackError> {
+        self.scope.push(code, data)
+    }
+
+    fn pop(&mut self) -> Option<(ShortID, &[u8])> {
+        self.scope.pop()
+    }
+
+    fn get(&self, code: ShortID) -> Option<&[u8]> {
+        self.scope.get(code)
+    }
+
+    fn update(&mut self, code: ShortID, data: &[u8]) -> Result<(), StackError> {
+        self.scope.update(code, data)
+    }
+
+    fn entries(&self) -> StackEntries<'_> {
+        self.scope.entries()
+    }
+
+    #[allow(deprecated)]
+    fn iter_reset(&mut self) {
+        self.scope.iter_reset();
+    }
+
+    #[allow(deprecated)]
+    fn iter_next(&mut self) -> Option<(ShortID, &[u8])> {
+        self.scope.iter_next()
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+
+    type TestStack = FixedSizeStack<64>;
+
+    #[test]
+    fn isolate_scope_from_parent() {
+        let mut parent = TestStack::new();
+        parent.push(0, &[1]).unwrap();
+        parent.push(1, &[2]).unwrap();
+
+        let mut scope = ScopedStack::new(&mut parent, TestStack::new());
+        assert_eq!(scope.get(0), None);
+        assert_eq!(scope.pop(), None);
+
+        scope.push(0, &[3]).unwrap();
+        assert_eq!(scope.get(0), Some([3].as_slice()));
+        assert!(matches!(scope.update(1, &[4]), Err(StackError::NotFound)));
+        scope.discard();
+
+        assert_eq!(parent.get(0), Some([1].as_slice()));
+        assert_eq!(parent.pop(), Some((1, [2].as_slice())));
+        assert_eq!(parent.pop(), Some((0, [1].as_slice())));
+        assert_eq!(parent.pop(), None);
+    }
+
+    #[test]
+    fn merge_selected_values_into_parent() {
+        let mut parent = TestStack::new();
+        parent.push(0, &[1]).unwrap();
+
+        let mut scope = ScopedStack::new(&mut parent, TestStack::new());
+        scope.push(0, &[2]).unwrap();
+        scope.push(1, &[3]).unwrap();
+        scope.push(2, &[4]).unwrap();
+        scope.push(2, &[5]).unwrap();
+        scope.collapse(&[2, 0, 3]).unwrap();
+
+        assert_eq!(parent.get(1), None);
+        assert_eq!(parent.pop(), Some((0, [2].as_slice())));
+        assert_eq!(parent.pop(), Some((2, [5].as_slice())));
+        assert_eq!(parent.pop(), Some((0, [1].as_slice())));
+        assert_eq!(parent.pop(), None);
+    }
+
+    #[test]
+    fn nest_scopes() {
+        let mut parent = TestStack::new();
+
+        let mut outer = ScopedStack::new(&mut parent, TestStack::new());
+        outer.push(0, &[1]).unwrap();
+
+        let mut inner = ScopedStack::new(&mut outer, TestStack::new());
+        inner.push(0, &[2]).unwrap();
+        inner.push(1, &[3]).unwrap();
+        inner.collapse(&[1]).unwrap();
+
+        assert_eq!(outer.get(0), Some([1].as_slice()));
+        outer.collapse(&[0, 1]).unwrap();
+
+        assert_eq!(parent.pop(), Some((1, [3].as_slice())));
+        assert_eq!(parent.pop(), Some((0, [1].as_slice())));
+    }
+}
@@ -0,0 +1,167 @@
+use super::*;
+
+/// Size of the value count at the start of a serialized snapshot
+const COUNT_SIZE: usize = 4;
+/// Size of the type code and length preceding each serialized value
+const HEADER_SIZE: usize = 6;
+
+/// Writes all values of the stack into the buffer, oldest first, returning the number of bytes written.
+/// See [`Stack::snapshot_into`](Stack::snapshot_into) for the format.
+pub(super) fn write(stack: &(impl Stack + ?Sized), out: &mut [u8]) -> Result<usize, StackError> {
+    let (count, size) = stack
+        .entries()
+        .fold((0u32, COUNT_SIZE), |(count, size), (_, value)| {
+            (count + 1, size + HEADER_SIZE + value.len())
+        });
+
+    if out.len() < size {
+        return Err(StackError::StackOverflow);
+    }
+
+    out[..COUNT_SIZE].copy_from_slice(&count.to_be_bytes());
+
+    // Iteration runs newest to oldest, so the buffer is filled back to front
+    let mut offset = size;
+    for (code, value) in stack.entries() {
+        offset -= value.len();
+        out[offset..offset + value.len()].copy_from_slice(value);
+
+        offset -= HEADER_SIZE;
+        out[offset..offset + 4].copy_from_slice(&code.to_be_bytes());
+        out[offset + 4..offset + HEADER_SIZE].copy_from_slice(&(value.len() as u16).to_be_bytes());
+    }
+
+    Ok(size)
+}
+
+/// Replaces the contents of the stack with the values serialized in the buffer.
+/// The whole buffer is validated beforehand so that malformed snapshots leave the stack untouched.
+pub(super) fn read(stack: &mut (impl Stack + ?Sized), bytes: &[u8]) -> Result<(), StackError> {
+    for entry in SerializedEntries::new(bytes)? {
+        entry?;
+    }
+
+    stack.clear();
+
+    for entry in SerializedEntries::new(bytes)? {
+        let (code, value) = entry?;
+        stack.push(code, value)?;
+    }
+
+    Ok(())
+}
+
+/// Iterates the values of a serialized snapshot from oldest to newest, checking each length against the remaining bytes
+struct SerializedEntries<'b> {
+    bytes: &'b [u8],
+    remaining: u32,
+}
+
+impl<'b> SerializedEntries<'b> {
+    fn new(bytes: &'b [u8]) -> Result<Self, StackError> {
+        if bytes.len() < COUNT_SIZE {
+            return Err(StackError::MalformedSnapshot);
+        }
+
+        let remaining = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+        Ok(Self {
+            bytes: &bytes[COUNT_SIZE..],
+            remaining,
+        })
+    }
+}
+
+impl<'b> Iterator for SerializedEntries<'b> {
+    type Item = Result<(ShortID, &'b [u8]), StackError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        if self.bytes.len() < HEADER_SIZE {
+            self.remaining = 0;
+            return Some(Err(StackError::MalformedSnapshot));
+        }
+
+        let code: ShortID =
+            u32::from_be_bytes([self.bytes[0], self.bytes[1], self.bytes[2], self.bytes[3]]);
+        let length = u16::from_be_bytes([self.bytes[4], self.bytes[5]]) as usize;
+        let end = HEADER_SIZE + length;
+
+        if self.bytes.len() < end {
+            self.remaining = 0;
+            return Some(Err(StackError::MalformedSnapshot));
+        }
+
+        let value = &self.bytes[HEADER_SIZE..end];
+        self.bytes = &self.bytes[end..];
+        self.remaining -= 1;
+
+        Some(Ok((code, value)))
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+
+    #[test]
+    fn restore_identical_stack() {
+        let mut stack = FixedSizeStack::<64>::new();
+        stack.push(1, &[1, 2, 3]).unwrap();
+        stack.push(2, &[]).unwrap();
+        stack.push(1, &[4, 5]).unwrap();
+
+        let mut buffer = [0; 64];
+        let size = stack.snapshot_into(&mut buffer).unwrap();
+        assert_eq!(size, COUNT_SIZE + 3 * HEADER_SIZE + 5);
+
+        let mut restored = FixedSizeStack::<64>::new();
+        restored.push(3, &[42]).unwrap();
+        restored.restore_from(&buffer[..size]).unwrap();
+
+        assert_eq!(restored.get(3), None);
+        assert_eq!(restored.pop(), Some((1, [4, 5].as_slice())));
+        assert_eq!(restored.pop(), Some((2, [].as_slice())));
+        assert_eq!(restored.pop(), Some((1, [1, 2, 3].as_slice())));
+        assert_eq!(restored.pop(), None);
+    }
+
+    #[test]
+    fn report_small_buffer() {
+        let mut stack = FixedSizeStack::<64>::new();
+        stack.push(1, &[1, 2, 3]).unwrap();
+
+        let mut buffer = [0; COUNT_SIZE + HEADER_SIZE + 2];
+        assert!(matches!(
+            stack.snapshot_into(&mut buffer),
+            Err(StackError::StackOverflow)
+        ));
+    }
+
+    #[test]
+    fn reject_truncated_snapshot() {
+        let mut stack = FixedSizeStack::<64>::new();
+        stack.push(1, &[1, 2, 3]).unwrap();
+        stack.push(2, &[4]).unwrap();
+
+        let mut buffer = [0; 64];
+        let size = stack.snapshot_into(&mut buffer).unwrap();
+
+        let mut restored = FixedSizeStack::<64>::new();
+        restored.push(3, &[42]).unwrap();
+
+        // Truncated within a value, within a header, right between two values and within the value count
+        for truncated in [size - 1, size - 3, size - HEADER_SIZE - 1, 2] {
+            assert!(matches!(
+                restored.restore_from(&buffer[..truncated]),
+                Err(StackError::MalformedSnapshot)
+            ));
+        }
+
+        assert_eq!(restored.pop(), Some((3, [42].as_slice())));
+        assert_eq!(restored.pop(), None);
+    }
+}
@@ -0,0 +1,102 @@
+use super::*;
+use alloc::vec::Vec;
+
+/// Owned copy of all values on a [`Stack`](Stack) at a given point in time
+///
+/// Created by [`Stack::snapshot`](Stack::snapshot) and written back using [`Stack::restore`](Stack::restore).
+/// Snapshots are independent of the stack implementation they were taken from, so you can e.g. record
+/// the contents of a [`FixedSizeStack`](super::FixedSizeStack) and replay them on a [`DynamicStack`](super::DynamicStack).
+#[doc(cfg(feature = "alloc"))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StackSnapshot {
+    /// Concatenated values, oldest first
+    data: Vec<u8>,
+    /// Type code and length of each value in `data`, oldest first
+    entries: Vec<(ShortID, u16)>,
+}
+
+impl StackSnapshot {
+    /// Number of values contained in the snapshot
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterates the contained values from oldest to newest, i.e. in the order they have been pushed
+    pub fn iter(&self) -> impl Iterator<Item = (ShortID, &[u8])> + '_ {
+        self.entries.iter().scan(0, |offset, (code, length)| {
+            let start = *offset;
+            *offset += *length as usize;
+            Some((*code, &self.data[start..*offset]))
+        })
+    }
+
+    pub(super) fn capture(stack: &(impl Stack + ?Sized)) -> Result<Self, StackError> {
+        let mut entries = Vec::new();
+        let mut data = Vec::new();
+
+        // Iteration runs newest to oldest, so we collect in reverse and flip everything afterwards
+        for (code, value) in stack.entries() {
+            let length = u16::try_from(value.len()).map_err(|_| StackError::ValueTooLarge)?;
+            entries.push((code, length));
+            data.extend(value.iter().rev());
+        }
+
+        entries.reverse();
+        data.reverse();
+
+        Ok(Self { data, entries })
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+
+    #[test]
+    fn restore_identical_stack() {
+        let mut stack = DynamicStack::new();
+        stack.push(1, &[1, 2, 3]).unwrap();
+        stack.push(2, &[]).unwrap();
+        stack.push(1, &[4, 5]).unwrap();
+
+        let snapshot = stack.snapshot().unwrap();
+        assert_eq!(snapshot.len(), 3);
+
+        let mut restored = FixedSizeStack::<64>::new();
+        restored.push(3, &[42]).unwrap();
+        restored.restore(&snapshot).unwrap();
+
+        assert_eq!(restored.get(3), None);
+        assert_eq!(restored.pop(), Some((1, [4, 5].as_slice())));
+        assert_eq!(restored.pop(), Some((2, [].as_slice())));
+        assert_eq!(restored.pop(), Some((1, [1, 2, 3].as_slice())));
+        assert_eq!(restored.pop(), None);
+    }
+
+    #[test]
+    fn iterate_in_push_order() {
+        let mut stack = DynamicStack::new();
+        stack.push(1, &[1]).unwrap();
+        stack.push(2, &[2, 2]).unwrap();
+
+        let snapshot = stack.snapshot().unwrap();
+        let values = snapshot.iter().collect::<Vec<_>>();
+        assert_eq!(values, [(1, [1].as_slice()), (2, [2, 2].as_slice())]);
+    }
+
+    #[test]
+    fn report_overflow_on_restore() {
+        let mut stack = DynamicStack::new();
+        stack.push(1, &[0; 32]).unwrap();
+
+        let mut restored = FixedSizeStack::<16>::new();
+        assert!(matches!(
+            restored.restore(&stack.snapshot().unwrap()),
+            Err(StackError::StackOverflow)
+        ));
+    }
+}
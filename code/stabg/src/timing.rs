@@ -0,0 +1,172 @@
+//! Optional instrumentation for measuring how long each processor takes to execute
+
+use crate::{processor::EmbeddedExecutionError, AsyncExecutionQueue, ShortID, Stack};
+use core::{
+    cell::Cell,
+    future::Future,
+    ops::{AddAssign, Sub},
+};
+
+/// Extension of [`AsyncExecutionQueue`] for runs which report the execution of each processor to an [`ExecutionTimer`]
+///
+/// Implemented by the `AsyncExecutionQueue` derive macro when the `timing` feature is enabled.
+pub trait TimedExecutionQueue: AsyncExecutionQueue {
+    type TimedFut<'s>: Future<Output = Result<(), EmbeddedExecutionError>> + 's
+    where
+        Self: 's;
+
+    /// Behaves like [`run`](AsyncExecutionQueue::run) but reports the start and end of each processor execution to the given timer
+    fn run_timed<'s>(
+        &'s mut self,
+        start_id: Option<ShortID>,
+        stack: &'s mut dyn Stack,
+        timer: &'s mut dyn ExecutionTimer,
+    ) -> Self::TimedFut<'s>;
+}
+
+/// Source of timestamps used to measure the execution time of processors
+///
+/// Implement it for whatever time source your platform provides, e.g. `embassy_time::Instant` on embedded devices
+/// or `std::time::Instant` on the desktop.
+pub trait Clock {
+    /// Point in time as returned by [`now`](Self::now)
+    type Instant: Copy + Sub<Output = Self::Duration>;
+
+    /// Span of time between two [`Instant`](Self::Instant)s
+    type Duration: Copy + Default + AddAssign;
+
+    /// Returns the current point in time
+    fn now(&self) -> Self::Instant;
+}
+
+/// Hook which is invoked around every call to a processor during a timed run
+///
+/// Used by [`TimedExecutionQueue::run_timed`], usually you want to pass in [`ProcessorTimings`].
+pub trait ExecutionTimer {
+    /// Called right before the processor with the given ID is executed
+    fn start(&mut self, id: ShortID);
+
+    /// Called right after the processor with the given ID has finished executing, regardless of whether it succeeded
+    fn stop(&mut self, id: ShortID);
+}
+
+/// Accumulated execution times of a single processor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcessorTiming<D> {
+    /// Number of times the processor has been executed
+    pub calls: u32,
+    /// Sum of all execution times
+    pub total: D,
+    /// Duration of the most recent execution
+    pub last: D,
+}
+
+/// Execution times of up to `N` processors, keyed by their [`ShortID`]
+///
+/// Set `N` to the [`PROCESSOR_COUNT`](crate::AsyncExecutionQueue::PROCESSOR_COUNT) of your queue.
+/// Measurements of processors with IDs beyond that are discarded.
+pub struct ProcessorTimings<C: Clock, const N: usize> {
+    clock: C,
+    started: Option<C::Instant>,
+    timings: [ProcessorTiming<C::Duration>; N],
+}
+
+impl<C: Clock, const N: usize> ProcessorTimings<C, N> {
+    /// Creates an empty set of measurements which takes its timestamps from the given clock
+    pub fn new(clock: C) -> Self {
+        Self {
+            clock,
+            started: None,
+            timings: [ProcessorTiming::default(); N],
+        }
+    }
+
+    /// Measurements of the processor with the given ID, `None` if it has not been executed yet
+    pub fn get(&self, id: ShortID) -> Option<&ProcessorTiming<C::Duration>> {
+        self.timings
+            .get(id as usize)
+            .filter(|timing| timing.calls > 0)
+    }
+
+    /// Iterates over the measurements of all processors that have been executed at least once
+    pub fn iter(&self) -> impl Iterator<Item = (ShortID, &ProcessorTiming<C::Duration>)> {
+        self.timings
+            .iter()
+            .enumerate()
+            .filter(|(_, timing)| timing.calls > 0)
+            .map(|(id, timing)| (id as ShortID, timing))
+    }
+
+    /// Discards all measurements taken so far
+    pub fn reset(&mut self) {
+        self.started = None;
+        self.timings = [ProcessorTiming::default(); N];
+    }
+}
+
+impl<C: Clock, const N: usize> ExecutionTimer for ProcessorTimings<C, N> {
+    fn start(&mut self, _id: ShortID) {
+        self.started = Some(self.clock.now());
+    }
+
+    fn stop(&mut self, id: ShortID) {
+        if let Some(started) = self.started.take() {
+            let duration = self.clock.now() - started;
+
+            if let Some(timing) = self.timings.get_mut(id as usize) {
+                timing.calls += 1;
+                timing.total += duration;
+                timing.last = duration;
+            }
+        }
+    }
+}
+
+/// Deterministic clock which advances by one tick every time it is read
+///
+/// Useful for testing code that consumes timings, as every processor execution measures exactly one tick.
+#[derive(Debug, Default)]
+pub struct TickingClock(Cell<u32>);
+
+impl Clock for TickingClock {
+    type Instant = u32;
+    type Duration = u32;
+
+    fn now(&self) -> u32 {
+        let now = self.0.get();
+        self.0.set(now + 1);
+        now
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::{ExecutionTimer, ProcessorTimings, TickingClock};
+
+    #[test]
+    fn accumulate_timings_by_id() {
+        let mut timings = ProcessorTimings::<_, 3>::new(TickingClock::default());
+
+        timings.start(0);
+        timings.stop(0);
+        timings.start(2);
+        timings.stop(2);
+        timings.start(0);
+        timings.stop(0);
+
+        assert_eq!(timings.get(0).map(|t| t.calls), Some(2));
+        assert_eq!(timings.get(0).map(|t| t.total), Some(2));
+        assert!(timings.get(1).is_none());
+        assert_eq!(timings.iter().map(|(id, _)| id).sum::<u32>(), 2);
+    }
+
+    #[test]
+    fn discard_out_of_range_ids() {
+        let mut timings = ProcessorTimings::<_, 1>::new(TickingClock::default());
+
+        timings.start(5);
+        timings.stop(5);
+
+        assert_eq!(timings.iter().count(), 0);
+    }
+}
@@ -19,6 +19,7 @@ struct IdentifiableOpts {
 struct StackUsageOpts {
     #[darling(default)]
     items: usize,
+    postcard: Flag,
 }
 
 #[derive(FromDeriveInput, Default)]
@@ -69,11 +70,11 @@ pub fn derive_embedded_processor(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input);
     let mut errors = Error::accumulator();
 
-    let items = match StackUsageOpts::from_derive_input(&input) {
-        Ok(StackUsageOpts { items }) => items,
+    let (items, postcard) = match StackUsageOpts::from_derive_input(&input) {
+        Ok(StackUsageOpts { items, postcard }) => (items, postcard.is_present()),
         Err(err) => {
             errors.push(err);
-            0
+            (0, false)
         }
     };
 
@@ -122,12 +123,20 @@ pub fn derive_embedded_processor(input: TokenStream) -> TokenStream {
         ));
     }
 
+    let output_sizes = outputs.iter().map(|output| {
+        if postcard {
+            quote! { <#output as ::stabg::serialization::MaxSize>::POSTCARD_MAX_SIZE }
+        } else {
+            quote! { ::core::mem::size_of::<#output>() }
+        }
+    });
+
     let output = quote! {
         impl ::stabg::processor::EmbeddedProcessor for #ident {
             const TYPES_INPUT: &'static [::stabg::Identifier] = &[#(<#inputs>::IDENTIFIER, )*];
             const TYPES_OUTPUT: &'static [::stabg::Identifier] = &[#(<#outputs>::IDENTIFIER, )*];
             const STACK_USAGE: usize = ::stabg::determine_stack_usage(#items, &[
-                #(::core::mem::size_of::<#outputs>(), )*
+                #(#output_sizes, )*
             ]);
 
             type LoadFut<'s> = impl ::core::future::Future<Output = Result<(), &'static str>> + 's
@@ -212,6 +221,28 @@ pub fn derive_async_execution_queue(input: TokenStream) -> TokenStream {
 
     let processor_count = processor_type.len();
 
+    let run_body = queue_run_body(&processor_type, &processor_ident, false, false);
+    let pausable_run_body = queue_run_body(&processor_type, &processor_ident, false, true);
+
+    let timed_run = if cfg!(feature = "timing") {
+        let timed_run_body = queue_run_body(&processor_type, &processor_ident, true, false);
+
+        quote! {
+            #[automatically_derived]
+            impl ::stabg::timing::TimedExecutionQueue for #ident {
+                type TimedFut<'s> = impl ::core::future::Future<Output = Result<(), ::stabg::processor::EmbeddedExecutionError>> + 's
+                where
+                    Self: 's;
+
+                fn run_timed<'s>(&'s mut self, start_id: Option<ShortID>, stack: &'s mut dyn Stack, timer: &'s mut dyn ::stabg::timing::ExecutionTimer) -> Self::TimedFut<'s> {
+                    #timed_run_body
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
     let output = quote! {
         #[automatically_derived]
         impl ::stabg::AsyncExecutionQueue for #ident {
@@ -223,37 +254,85 @@ pub fn derive_async_execution_queue(input: TokenStream) -> TokenStream {
                 Self: 's;
 
             fn run<'s>(&'s mut self, start_id: Option<ShortID>, stack: &'s mut dyn Stack) -> Self::Fut<'s> {
-                async move {
-                    let types = ::core::iter::empty();
-                    #(
-                        let types = types.chain(<#processor_type>::TYPES_INPUT.iter());
-                        let types = types.chain(<#processor_type>::TYPES_OUTPUT.iter());
-                    )*
+                #run_body
+            }
 
-                    let registry = ::stabg::IteratorRegistry(types);
-                    let serializer = unsafe { ::stabg::serialization::TransmuteSerializer::new() };
+            type PausableFut<'s> = impl ::core::future::Future<Output = Result<::stabg::QueueProgress, ::stabg::processor::EmbeddedExecutionError>> + 's
+            where
+                Self: 's;
 
-                    let mut id: ShortID = 0;
-                    let mut running = start_id.is_none();
+            fn run_pausable<'s>(&'s mut self, start_id: Option<ShortID>, stack: &'s mut dyn Stack, pause: &'s ::core::sync::atomic::AtomicBool) -> Self::PausableFut<'s> {
+                #pausable_run_body
+            }
+        }
 
-                    #(
-                        if !running && Some(id) == start_id {
-                            running = true;
-                        }
+        #timed_run
+    };
 
-                        if running {
-                            let context = ::stabg::processor::EmbeddedExecutionContext::new(stack, id, &registry, serializer);
-                            self.#processor_ident.process(context).await?;
-                        }
+    output.into()
+}
 
-                        id += 1;
-                    )*
+/// Generates the future which runs all processors in order, starting at `start_id` if it is set.
+/// When `timed` is set, every processor invocation is wrapped in calls to a `timer` in scope.
+/// When `pausable` is set, the run stops early once a `pause` flag in scope is set and reports its progress.
+fn queue_run_body(
+    processor_type: &[syn::Type],
+    processor_ident: &[syn::Ident],
+    timed: bool,
+    pausable: bool,
+) -> proc_macro2::TokenStream {
+    let (start_timer, stop_timer) = if timed {
+        (quote! { timer.start(id); }, quote! { timer.stop(id); })
+    } else {
+        (quote! {}, quote! {})
+    };
 
-                    Ok(())
+    let (pause_check, completed) = if pausable {
+        (
+            quote! {
+                // There is nothing left to pause for after the last processor
+                if id + 1 < Self::PROCESSOR_COUNT as ShortID && pause.load(::core::sync::atomic::Ordering::Relaxed) {
+                    return Ok(::stabg::QueueProgress::Paused(id + 1));
                 }
-            }
-        }
+            },
+            quote! { Ok(::stabg::QueueProgress::Completed) },
+        )
+    } else {
+        (quote! {}, quote! { Ok(()) })
     };
 
-    output.into()
+    quote! {
+        async move {
+            let types = ::core::iter::empty();
+            #(
+                let types = types.chain(<#processor_type>::TYPES_INPUT.iter());
+                let types = types.chain(<#processor_type>::TYPES_OUTPUT.iter());
+            )*
+
+            let registry = ::stabg::IteratorRegistry::new(types);
+            let serializer = unsafe { ::stabg::serialization::TransmuteSerializer::new() };
+
+            let mut id: ShortID = 0;
+            let mut running = start_id.is_none();
+
+            #(
+                if !running && Some(id) == start_id {
+                    running = true;
+                }
+
+                if running {
+                    let context = ::stabg::processor::EmbeddedExecutionContext::new(stack, id, &registry, serializer);
+                    #start_timer
+                    let result = self.#processor_ident.process(context).await;
+                    #stop_timer
+                    result?;
+                    #pause_check
+                }
+
+                id += 1;
+            )*
+
+            #completed
+        }
+    }
 }
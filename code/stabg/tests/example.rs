@@ -40,6 +40,39 @@ mod alloc {
         }
     }
 
+    #[test]
+    fn replay_recorded_run() {
+        let mut queue = DynamicExecutionQueue::new();
+        queue
+            .schedule(TestProcessor1)
+            .unwrap()
+            .schedule(TestProcessor2)
+            .unwrap();
+
+        let mut recording = Vec::new();
+        let mut stack = DynamicStack::new();
+        Executor::new(&mut stack)
+            .execute_sync_recorded(&mut queue, &mut recording)
+            .unwrap();
+
+        assert_eq!(recording.len(), 1);
+        assert_eq!(recording[0].start_point, None);
+        assert!(recording[0].stack.is_empty());
+
+        let mut replay_stack = DynamicStack::new();
+        Executor::new(&mut replay_stack)
+            .replay_sync(&mut queue, &recording[0])
+            .unwrap();
+
+        let mut expected_stack = DynamicStack::new();
+        queue.run(None, &mut expected_stack).unwrap();
+
+        assert_eq!(
+            replay_stack.snapshot().unwrap(),
+            expected_stack.snapshot().unwrap()
+        );
+    }
+
     impl Processor for TestProcessor1 {
         fn identifier(&self) -> Identifier {
             "test.processor1"
@@ -53,6 +53,98 @@ mod does {
         });
     }
 
+    #[test]
+    fn pause_and_resume_at_processor_boundary() {
+        use core::sync::atomic::{AtomicBool, Ordering};
+
+        futures::executor::block_on(async move {
+            let mut queue = EmbeddedExecutionQueue::default();
+            let mut stack = FixedSizeStack::<{ EmbeddedExecutionQueue::STACK_USAGE }>::new();
+            let pause = AtomicBool::new(true);
+
+            let progress = queue.run_pausable(None, &mut stack, &pause).await.unwrap();
+            assert_eq!(progress, QueueProgress::Paused(1));
+
+            // The second processor reads the values pushed by the first one before the pause
+            pause.store(false, Ordering::Relaxed);
+            let progress = queue
+                .run_pausable(Some(1), &mut stack, &pause)
+                .await
+                .unwrap();
+            assert_eq!(progress, QueueProgress::Completed);
+        });
+    }
+
+    #[cfg(feature = "timing")]
+    #[test]
+    fn measure_processor_timings() {
+        use stabg::timing::{ProcessorTimings, TickingClock};
+
+        futures::executor::block_on(async move {
+            let mut queue = EmbeddedExecutionQueue::default();
+            let mut stack = FixedSizeStack::<{ EmbeddedExecutionQueue::STACK_USAGE }>::new();
+            let mut timings =
+                ProcessorTimings::<_, { EmbeddedExecutionQueue::PROCESSOR_COUNT }>::new(
+                    TickingClock::default(),
+                );
+            let mut executor = Executor::new(&mut stack);
+
+            executor
+                .execute_async_timed(&mut queue, &mut timings)
+                .await
+                .unwrap();
+
+            assert_eq!(timings.iter().count(), 2);
+            assert_eq!(timings.get(0).map(|t| t.calls), Some(1));
+            assert_eq!(timings.get(1).map(|t| t.last), Some(1));
+        });
+    }
+
+    #[cfg(feature = "postcard")]
+    #[test]
+    fn round_trip_through_postcard_serializer() {
+        use stabg::serialization::{MaxSize, PostcardSerializer};
+
+        #[derive(Identifiable, Serialize, Deserialize, MaxSize, PartialEq, Debug)]
+        #[identifier(name = "test.portable", version = "1")]
+        struct PortableType(u8, u8, u64);
+
+        #[derive(Default, EmbeddedProcessor)]
+        #[stack_usage(items = 1, postcard)]
+        #[type_usage(outputs(PortableType))]
+        #[skip_phase(load, unload)]
+        struct PortableProcessor;
+
+        impl PortableProcessor {
+            async fn process(&mut self, _ctx: Context<'_, '_>) -> Result<(), Error> {
+                Ok(())
+            }
+        }
+
+        // The estimate is based on the serialized size, which is smaller than the padded in-memory representation
+        assert_eq!(
+            PortableProcessor::STACK_USAGE,
+            determine_stack_usage(1, &[PortableType::POSTCARD_MAX_SIZE])
+        );
+        assert!(PortableType::POSTCARD_MAX_SIZE < core::mem::size_of::<PortableType>());
+
+        let mut registry = DynamicRegistry::new();
+        registry.register(PortableType::IDENTIFIER);
+
+        let mut stack = FixedSizeStack::<
+            { PortableProcessor::STACK_USAGE + processor::EmbeddedExecutionContext::OVERHEAD },
+        >::new();
+        let serializer = PostcardSerializer::<{ PortableType::POSTCARD_MAX_SIZE }>;
+        let mut ctx = GenericExecutionContext::new(&mut stack, 0, &registry, serializer);
+
+        let value = PortableType(4, 2, u64::MAX);
+        ctx.push(value).unwrap();
+        assert_eq!(
+            ctx.get::<PortableType>().unwrap(),
+            PortableType(4, 2, u64::MAX)
+        );
+    }
+
     impl TestProcessor1 {
         async fn process(&mut self, mut ctx: Context<'_, '_>) -> Result<(), Error> {
             ctx.push(TestType1(42))?;
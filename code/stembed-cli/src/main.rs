@@ -2,12 +2,15 @@
 #![feature(type_alias_impl_trait)]
 
 use clap::{Parser, Subcommand};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use std::{
     collections::HashMap,
     fs::File,
     future::Future,
-    io::{Read, Seek, SeekFrom},
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
     path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
 };
 use stembed::{
     compile::BinaryDictionaryCompiler,
@@ -22,7 +25,6 @@ use stembed::{
         serial::{GeminiPR, SerialPort},
         InputSource,
     },
-    io::util::HeapFile,
     output::{OSOutput, OutputSink},
 };
 
@@ -41,6 +43,9 @@ enum Commands {
         inputs: Vec<PathBuf>,
         #[clap(short, long)]
         output: PathBuf,
+        /// Includes an index for finding outlines by the text they write
+        #[clap(short, long)]
+        reverse_index: bool,
     },
 
     Translate {
@@ -52,6 +57,15 @@ enum Commands {
         #[clap(short, long = "dictionary")]
         dictionary_path: PathBuf,
     },
+
+    /// Checks a compiled dictionary for corruption, e.g. after copying it onto an SD card
+    Verify {
+        #[clap(short, long = "dictionary")]
+        dictionary_path: PathBuf,
+        /// Additionally looks up every entry to confirm that it resolves
+        #[clap(short, long)]
+        lookup: bool,
+    },
 }
 
 async fn async_main(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
@@ -59,31 +73,142 @@ async fn async_main(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
         Commands::TestLookup { dictionary_path } => {
             let mut dictionary_file = FileReader::open(dictionary_path)?;
             let dictionary = BinaryDictionary::new(&mut dictionary_file).await.unwrap();
-            let outline = [Stroke::from_str("H-L", dictionary.stroke_context()).unwrap()];
+            let outline = [Stroke::from_str("H-L", dictionary.stroke_context())
+                .unwrap()
+                .to_portable()];
             let result = dictionary.lookup(&outline).await;
             println!("{:?}", result);
         }
-        Commands::Compile { inputs, output } => {
+        Commands::Verify {
+            dictionary_path,
+            lookup,
+        } => {
+            let mut dictionary_file = FileReader::open(dictionary_path)?;
+            let dictionary = BinaryDictionary::new(&mut dictionary_file)
+                .await
+                .map_err(|error| format!("failed to open dictionary: {:?}", error))?;
+
+            match dictionary.metadata() {
+                Some(metadata) => {
+                    let (major, minor, patch) = metadata.compiler_version;
+                    println!("Format version: {}", metadata.format_version);
+                    println!("Compiler version: {}.{}.{}", major, minor, patch);
+                    println!("Compiled entries: {}", metadata.entry_count);
+                    println!("Created at: {} (unix time)", metadata.created_at);
+                }
+                None => println!("Metadata: none (compiled by an older version)"),
+            }
+
+            let hash = dictionary.hash();
+            println!("Hash: {:?} (key {:#x})", hash.algorithm, hash.key);
+
+            let tags = match dictionary.tags() {
+                Some(tags) => tags,
+                None => dictionary
+                    .scan_tags()
+                    .await
+                    .map_err(|error| format!("failed to scan tags: {:?}", error))?,
+            };
+            let tags = tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+            println!("Tags: {}", tags.join(", "));
+
+            let report = dictionary
+                .verify()
+                .await
+                .map_err(|error| format!("verification failed: {:?}", error))?;
+            println!("Entries: {}", report.entries());
+            println!(
+                "Load: {} ({}%)",
+                report.load(),
+                report.load_factor() * 100.0
+            );
+            println!("Longest bucket: {}", report.longest_bucket());
+
+            if lookup {
+                let outlines = dictionary
+                    .outlines()
+                    .await
+                    .map_err(|error| format!("failed to enumerate outlines: {:?}", error))?;
+                let mut unresolved = 0;
+
+                for outline in outlines.iter() {
+                    let entry = dictionary
+                        .lookup_entry(outline)
+                        .await
+                        .map_err(|error| format!("lookup failed: {:?}", error))?;
+
+                    if entry.is_none() {
+                        let formatted = outline
+                            .iter()
+                            .map(|stroke| stroke.to_string())
+                            .collect::<Vec<_>>()
+                            .join("/");
+                        println!("Unresolved outline: {}", formatted);
+                        unresolved += 1;
+                    }
+                }
+
+                println!(
+                    "Resolved {} of {} outlines",
+                    outlines.len() - unresolved,
+                    outlines.len()
+                );
+
+                if unresolved > 0 {
+                    return Err(format!("{} outlines did not resolve", unresolved).into());
+                }
+            }
+        }
+        Commands::Compile {
+            inputs,
+            output,
+            reverse_index,
+        } => {
             let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[])
                 .expect("default stroke context");
             let mut compiler = BinaryDictionaryCompiler::new(&context);
+            compiler.set_reverse_index(reverse_index);
 
             let mut bytes: HashMap<Stroke, usize> = HashMap::new();
 
-            for (tag, input) in inputs.into_iter().enumerate() {
-                let content = std::fs::read_to_string(input)?;
-                let entries = parse_dict(&content[..], &context).unwrap();
-                for entry in entries {
-                    let (outline, commands) = entry.unwrap();
+            // Parse all inputs, concurrently if enabled, the results are merged in input order
+            #[cfg(feature = "parallel")]
+            let parsed_inputs = inputs.par_iter();
+            #[cfg(not(feature = "parallel"))]
+            let parsed_inputs = inputs.iter();
 
-                    if let Some(stroke) = outline.first().cloned() {
-                        bytes.entry(stroke).and_modify(|x| *x += 1).or_insert(1);
-                    }
+            let entries = parsed_inputs
+                .enumerate()
+                .map(|(tag, input)| -> std::io::Result<_> {
+                    let content = std::fs::read_to_string(input)?;
+                    let entries = parse_dict(&content[..], &context)
+                        .unwrap()
+                        .map(|entry| {
+                            let (outline, commands) = entry.unwrap();
+                            (outline, commands, tag as u16)
+                        })
+                        .collect::<Vec<_>>();
 
-                    compiler.add(outline, commands, tag as u16).unwrap();
+                    Ok(entries)
+                })
+                .collect::<std::io::Result<Vec<_>>>()?
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+
+            for (outline, _, _) in entries.iter() {
+                if let Some(stroke) = outline.first().cloned() {
+                    bytes.entry(stroke).and_modify(|x| *x += 1).or_insert(1);
                 }
             }
 
+            #[cfg(feature = "parallel")]
+            compiler.add_parallel(entries.into_par_iter()).unwrap();
+            #[cfg(not(feature = "parallel"))]
+            for (outline, commands, tag) in entries {
+                compiler.add(outline, commands, tag).unwrap();
+            }
+
             println!("{}", compiler.stats());
             println!(
                 "Average nodes below first node: {}",
@@ -100,10 +225,12 @@ async fn async_main(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 println!("{} => {}", value, key);
             }
 
-            let mut dict_blob = HeapFile::new();
-            compiler.serialize(&mut dict_blob).await.unwrap();
+            let created_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            compiler.set_creation_time(created_at);
 
-            std::fs::write(output, &mut dict_blob.into_inner())?;
+            let mut dictionary_file = FileWriter::create(output)?;
+            compiler.serialize(&mut dictionary_file).await.unwrap();
+            dictionary_file.finish()?;
         }
         Commands::Translate { dictionary_path } => {
             let mut dictionary_file = FileReader::open(dictionary_path)?;
@@ -117,12 +244,16 @@ async fn async_main(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
 
             loop {
                 let input = input_source.scan()?;
-                let stroke = Stroke::from_input(
-                    input,
-                    &GeminiPR::DEFAULT_KEYMAP,
-                    dictionary.stroke_context(),
-                );
-                let delta = engine.push(stroke).await;
+                let stroke =
+                    Stroke::from_input(input, &GeminiPR::DEFAULT_KEYMAP, engine.stroke_context());
+                let delta = match engine.push(stroke.to_portable()).await {
+                    Ok(delta) => delta,
+                    Err(error) => {
+                        // Dropping the stroke keeps the engine usable, e.g. after a single bad read
+                        eprintln!("Dropped stroke {}, lookup failed: {:?}", stroke, error);
+                        continue;
+                    }
+                };
                 let output = formatter.consume(delta);
                 output_sink.send(output);
             }
@@ -132,14 +263,13 @@ async fn async_main(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() {
     let cli = Cli::parse();
 
-    smol::block_on(async move {
-        async_main(cli).await.unwrap();
-    });
-
-    Ok(())
+    if let Err(error) = smol::block_on(async_main(cli)) {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    }
 }
 
 struct FileReader {
@@ -183,3 +313,32 @@ impl stembed::io::Seek for FileReader {
         }
     }
 }
+
+struct FileWriter {
+    file: BufWriter<File>,
+}
+
+impl FileWriter {
+    fn create(path: PathBuf) -> std::io::Result<Self> {
+        Ok(Self {
+            file: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    /// Flushes the buffered data, reporting errors which would otherwise be ignored when dropping the writer
+    fn finish(mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl stembed::io::Write for FileWriter {
+    type WriteFuture<'a> = impl Future<Output = Result<(), stembed::io::Error>> + 'a where Self: 'a;
+
+    fn write(&mut self, data: u8) -> Self::WriteFuture<'_> {
+        async move {
+            self.file
+                .write_all(&[data])
+                .map_err(|_| stembed::io::Error::Unknown)
+        }
+    }
+}
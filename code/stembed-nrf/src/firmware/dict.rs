@@ -1,4 +1,4 @@
-use core::marker::PhantomData;
+use core::{convert::Infallible, marker::PhantomData};
 
 use alloc::string::ToString;
 use futures::Future;
@@ -23,12 +23,13 @@ impl<'c> DummyDictionary<'c> {
 impl<'c> Dictionary for DummyDictionary<'c> {
     type Stroke = Stroke<'c>;
     type OutputCommand = TextOutputCommand;
-    type LookupFuture<'a> = impl Future<Output = Option<stembed::core::dict::CommandList<Self::OutputCommand>>> + 'a
+    type Error = Infallible;
+    type LookupFuture<'a> = impl Future<Output = Result<Option<stembed::core::dict::DictionaryMatch<Self::OutputCommand>>, Self::Error>> + 'a
     where
         Self: 'a;
 
     fn lookup<'a>(&'a self, _outline: &'a [Self::Stroke]) -> Self::LookupFuture<'a> {
-        async move { None }
+        async move { Ok(None) }
     }
 
     fn fallback_commands(
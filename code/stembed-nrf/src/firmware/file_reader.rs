@@ -1,4 +1,4 @@
-use fat32::{Block, BlockDeviceError, BlockID, FileReader};
+use fat32::{Block, BlockDeviceError, BlockID, FileReader, FilesystemError};
 use futures::Future;
 use stembed::io::{Read, Seek, SeekFrom};
 
@@ -10,7 +10,6 @@ where
     WFn: Fn(BlockID, Block) -> WFut,
 {
     file: FileReader<'f, E, RFut, RFn, WFut, WFn>,
-    offset: u32,
 }
 
 impl<'f, E, RFut, RFn, WFut, WFn> Reader<'f, E, RFut, RFn, WFut, WFn>
@@ -21,7 +20,7 @@ where
     WFn: Fn(BlockID, Block) -> WFut,
 {
     pub fn new(file: FileReader<'f, E, RFut, RFn, WFut, WFn>) -> Self {
-        Self { file, offset: 0 }
+        Self { file }
     }
 }
 
@@ -38,9 +37,10 @@ where
 
     fn read(&mut self) -> Self::ReadFuture<'_> {
         async move {
-            let data = self.file.read(self.offset).await;
-            self.offset += 1;
-            data.map_err(|_e| stembed::io::Error::Unknown)
+            self.file.read_next().await.map_err(|e| match e {
+                FilesystemError::OutOfBounds => stembed::io::Error::EOF,
+                _ => stembed::io::Error::Unknown,
+            })
         }
     }
 }
@@ -58,15 +58,15 @@ where
 
     fn seek(&mut self, pos: SeekFrom) -> Self::SeekFuture<'_> {
         async move {
-            match pos {
-                SeekFrom::Start(offset) => self.offset = offset as u32,
-                SeekFrom::End(offset) => unimplemented!(),
-                SeekFrom::Current(offset) => self.offset = (self.offset as i64 + offset) as u32,
-            }
+            let pos = match pos {
+                SeekFrom::Start(offset) => fat32::SeekFrom::Start(offset),
+                SeekFrom::End(offset) => fat32::SeekFrom::End(offset),
+                SeekFrom::Current(offset) => fat32::SeekFrom::Current(offset),
+            };
 
-            // TODO Check for out-of-bounds
-
-            Ok(self.offset as u64)
+            self.file
+                .seek(pos)
+                .map_err(|_e| stembed::io::Error::Unknown)
         }
     }
 }
@@ -59,6 +59,10 @@ async fn test_stuff(p: Peripherals) {
     .await
     .unwrap();
 
+    if filesystem.is_dirty() {
+        defmt::warn!("SD card has not been unmounted cleanly, the dictionary might be corrupted");
+    }
+
     let file = filesystem.find_file("DICT", "BIN").await.unwrap().unwrap();
     let mut file_reader = FileReader::new(file, &filesystem);
     file_reader.cache_fat().await.unwrap();
@@ -72,12 +76,19 @@ async fn test_stuff(p: Peripherals) {
     use alloc::string::ToString;
 
     loop {
-        dictionary.reset_lookup_count();
+        dictionary.reset_stats();
         let input = input_source.scan().unwrap();
         defmt::debug!("Received input");
         let stroke = Stroke::from_input(input, &KeymatrixInput::DEFAULT_KEYMAP, &context);
         defmt::debug!("Processing stroke: {}", stroke.to_string().as_str());
-        let delta = engine.push(stroke).await;
+        let delta = match engine.push(stroke.to_portable()).await {
+            Ok(delta) => delta,
+            Err(error) => {
+                // Flaky SD card reads should not take down the whole keyboard
+                defmt::error!("Dictionary lookup failed: {}", defmt::Debug2Format(&error));
+                continue;
+            }
+        };
         let output = formatter.consume(delta);
 
         for instruction in output {
@@ -89,7 +100,13 @@ async fn test_stuff(p: Peripherals) {
                 }
             }
         }
-        defmt::debug!("Lookup count: {}\n", dictionary.lookup_count());
+        let stats = dictionary.stats();
+        defmt::debug!(
+            "Lookups: {}, reads: {}, seeks: {}\n",
+            stats.lookups(),
+            stats.reads(),
+            stats.seeks()
+        );
     }
 }
 
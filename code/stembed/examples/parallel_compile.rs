@@ -0,0 +1,92 @@
+//! Measures how long it takes to compile a synthetic dictionary with and without [`add_parallel`](stembed::compile::BinaryDictionaryCompiler::add_parallel).
+//!
+//! Run with `cargo run --release --example parallel_compile --features parallel [entry count]`.
+
+use rayon::iter::IntoParallelIterator;
+use smallvec::smallvec;
+use std::time::{Duration, Instant};
+use stembed::{
+    compile::BinaryDictionaryCompiler,
+    core::{engine::Command, processor::text_formatter::TextOutputCommand, Stroke, StrokeContext},
+    io::util::HeapFile,
+};
+
+const KEYS: &str = "#STKPWHRAO*EUFRPBLGTSDZ";
+
+fn main() {
+    let entry_count = std::env::args()
+        .nth(1)
+        .map(|count| count.parse().expect("entry count is not a number"))
+        .unwrap_or(200_000);
+
+    let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+
+    // Build outlines of one to three pseudo-random strokes, each writing a distinct word
+    let mut seed = 0x2545F4914F6CDD1Du64;
+    let mut random = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+
+    let entries = (0..entry_count)
+        .map(|index| {
+            let stroke_count = 1 + (random() % 3) as usize;
+            let outline = (0..stroke_count)
+                .map(|_| {
+                    let bits = random() | 1;
+                    let keys = KEYS
+                        .chars()
+                        .enumerate()
+                        .filter(|(i, _)| bits & (1 << i) > 0)
+                        .map(|(_, key)| key)
+                        .collect::<String>();
+                    Stroke::from_steno_string(keys, &context).unwrap()
+                })
+                .collect();
+            let command = Command::Output(TextOutputCommand::Write(format!("word{index}")));
+
+            (outline, smallvec![command], (index % 4) as u16)
+        })
+        .collect::<Vec<_>>();
+
+    let (serial_time, serial_output) = measure(|| {
+        let mut compiler = BinaryDictionaryCompiler::new(&context);
+        for (outline, commands, tag) in entries.clone() {
+            compiler.add(outline, commands, tag).unwrap();
+        }
+        compiler
+    });
+
+    let (parallel_time, parallel_output) = measure(|| {
+        let mut compiler = BinaryDictionaryCompiler::new(&context);
+        compiler
+            .add_parallel(entries.clone().into_par_iter())
+            .unwrap();
+        compiler
+    });
+
+    assert_eq!(serial_output, parallel_output, "outputs differ");
+
+    println!(
+        "{} entries on {} threads: serial {:?}, parallel {:?}",
+        entry_count,
+        rayon::current_num_threads(),
+        serial_time,
+        parallel_time
+    );
+}
+
+/// Times adding the entries, which is the only part that runs in parallel, and returns the serialized dictionary for comparison
+fn measure<'c>(
+    compile: impl FnOnce() -> BinaryDictionaryCompiler<'c, TextOutputCommand>,
+) -> (Duration, Vec<u8>) {
+    let start = Instant::now();
+    let compiler = compile();
+    let elapsed = start.elapsed();
+
+    let mut file = HeapFile::new();
+    smol::block_on(compiler.serialize(&mut file)).unwrap();
+    (elapsed, file.into_inner())
+}
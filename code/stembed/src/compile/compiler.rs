@@ -1,16 +1,21 @@
 use crate::core::dict::binary::{
-    calculate_bucket_index, BinaryDictionaryEntry, BinaryDictionaryEntryError, Outline,
+    reverse::{text_hash, translation_text},
+    BinaryDictionaryEntry, BinaryDictionaryEntryError, DictionaryHash, DictionaryMetadata, Outline,
 };
+use crate::core::dict::TagSet;
 use crate::io::{Write, WriteExt};
-use crate::serialize::BinaryDictionaryEntrySerializationError;
+use crate::serialize::{BinaryDictionaryEntrySerializationError, SerializableCommand};
 use crate::{
     constants::{BINARY_DICT_PREAMBLE, HASH_TABLE_EMPTY_BUCKET, HASH_TABLE_SIZE},
     core::{dict::CommandList, processor::text_formatter::TextOutputCommand, StrokeContext},
-    io::util::{CountingWriter, HeapFile},
+    io::util::{CountingWriter, Sink},
     serialize::StringSerializationError,
 };
 use alloc::{collections::BTreeMap, vec::Vec};
 use core::fmt::{Debug, Display};
+#[cfg(feature = "parallel")]
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+use smol_str::SmolStr;
 
 pub struct DictionaryStatistics {
     occupancy: BTreeMap<usize, usize>,
@@ -18,6 +23,7 @@ pub struct DictionaryStatistics {
     collisions: usize,
     entries: usize,
     load: usize,
+    longest_chain: usize,
 }
 
 impl DictionaryStatistics {
@@ -28,6 +34,7 @@ impl DictionaryStatistics {
             collisions: 0,
             entries: 0,
             load: 0,
+            longest_chain: 0,
         }
     }
 
@@ -50,6 +57,11 @@ impl DictionaryStatistics {
     pub fn load_factor(&self) -> f64 {
         self.load as f64 / HASH_TABLE_SIZE as f64
     }
+
+    /// Number of entries in the fullest bucket, which a lookup has to walk through in the worst case
+    pub fn longest_chain(&self) -> usize {
+        self.longest_chain
+    }
 }
 
 impl Display for DictionaryStatistics {
@@ -59,12 +71,14 @@ impl Display for DictionaryStatistics {
     entries: {}
     load: {} ({}%)
     collisions: {}
+    longest chain: {}
     occupancy:
 "#,
             self.entries,
             self.load,
             self.load_factor() * 100.0,
             self.collisions,
+            self.longest_chain,
         ))?;
 
         for (key, value) in self.occupancy.iter() {
@@ -81,40 +95,177 @@ impl Display for DictionaryStatistics {
     }
 }
 
-pub struct BinaryDictionaryCompiler<'c> {
+/// Builds a [`BinaryDictionary`](crate::core::dict::BinaryDictionary) whose entries contain output commands of type `O`
+pub struct BinaryDictionaryCompiler<'c, O = TextOutputCommand> {
     context: &'c StrokeContext,
     stats: DictionaryStatistics,
     hash_table: Vec<Option<usize>>,
-    buckets: Vec<Vec<BinaryDictionaryEntry<'c>>>,
+    buckets: Vec<Vec<BinaryDictionaryEntry<'c, O>>>,
     longest_outline_length: u8,
+    created_at: u64,
+    hash: DictionaryHash,
+    tags: TagSet,
+    chain_limit: Option<usize>,
+    overlong_chains: Vec<Outline<'c>>,
+    reverse_index: bool,
 }
 
-impl<'c> BinaryDictionaryCompiler<'c> {
+impl<'c, O> BinaryDictionaryCompiler<'c, O> {
     pub fn new(context: &'c StrokeContext) -> Self {
+        Self::with_hash(context, DictionaryHash::default())
+    }
+
+    /// Creates a compiler which builds the hash table using the given hash function instead of the default FNV one.
+    /// The choice is recorded in the dictionary so that [`BinaryDictionary`](crate::core::dict::BinaryDictionary) uses the same one.
+    pub fn with_hash(context: &'c StrokeContext, hash: DictionaryHash) -> Self {
         Self {
             stats: DictionaryStatistics::new(),
             context,
             hash_table: core::iter::repeat(None).take(HASH_TABLE_SIZE).collect(),
             buckets: Vec::new(),
             longest_outline_length: 0,
+            created_at: 0,
+            hash,
+            tags: TagSet::new(),
+            chain_limit: None,
+            overlong_chains: Vec::new(),
+            reverse_index: false,
+        }
+    }
+
+    /// Sets the creation time recorded in the [`DictionaryMetadata`], in seconds since the unix epoch.
+    /// Left at zero by default so that compiling the same entries yields identical dictionaries.
+    pub fn set_creation_time(&mut self, created_at: u64) {
+        self.created_at = created_at;
+    }
+
+    /// Sets the number of entries a bucket may hold before the outlines added to it are reported by
+    /// [`overlong_chains`](Self::overlong_chains). Lookups walk through the bucket entry by entry,
+    /// so long chains are slow on flash storage. Entries exceeding the limit are still added.
+    pub fn set_chain_limit(&mut self, limit: usize) {
+        self.chain_limit = Some(limit);
+    }
+
+    /// Includes a reverse index which allows finding outlines by the text they write, see
+    /// [`BinaryDictionary::reverse_lookup`](crate::core::dict::BinaryDictionary::reverse_lookup).
+    /// Disabled by default as it adds eight bytes for every entry writing text.
+    pub fn set_reverse_index(&mut self, enabled: bool) {
+        self.reverse_index = enabled;
+    }
+
+    /// Outlines which extended their bucket beyond the [chain limit](Self::set_chain_limit), in the order they have been added.
+    /// Empty if no limit is set.
+    pub fn overlong_chains(&self) -> &[Outline<'c>] {
+        &self.overlong_chains
+    }
+
+    /// Redistributes all entries added so far using a different hash function, e.g. with another key to break up overlong chains.
+    /// Statistics and [overlong chains](Self::overlong_chains) are recalculated accordingly.
+    pub fn rehash(&mut self, hash: DictionaryHash) {
+        let buckets = core::mem::take(&mut self.buckets);
+
+        self.hash = hash;
+        self.stats = DictionaryStatistics::new();
+        self.hash_table.fill(None);
+        self.overlong_chains.clear();
+
+        for entry in buckets.into_iter().flatten() {
+            let bucket_index = hash.bucket_index(entry.outline(), HASH_TABLE_SIZE);
+            self.insert(bucket_index, entry);
         }
     }
 
     pub fn add(
         &mut self,
         outline: Outline<'c>,
-        commands: CommandList<TextOutputCommand>,
+        commands: CommandList<O>,
+        tag: u16,
+    ) -> Result<(), BinaryDictionaryEntryError> {
+        self.add_with_meta(outline, commands, tag, "")
+    }
+
+    /// Adds an entry with free-form metadata of up to 255 bytes, like the source dictionary or a comment.
+    /// It can be retrieved with [`BinaryDictionary::lookup_entry`](crate::core::dict::BinaryDictionary::lookup_entry).
+    pub fn add_with_meta(
+        &mut self,
+        outline: Outline<'c>,
+        commands: CommandList<O>,
         tag: u16,
+        metadata: &str,
     ) -> Result<(), BinaryDictionaryEntryError> {
-        self.longest_outline_length = self.longest_outline_length.max(outline.len() as u8);
+        let (bucket_index, entry) = Self::prepare(&self.hash, outline, commands, tag, metadata)?;
+        self.insert(bucket_index, entry);
+        Ok(())
+    }
+
+    /// Adds all entries, building and hashing them on multiple threads.
+    ///
+    /// Entries are inserted in the order of the iterator afterwards, so the compiled
+    /// dictionary is identical to one built by calling [`add`](Self::add) for each entry.
+    #[cfg(feature = "parallel")]
+    pub fn add_parallel<I>(&mut self, entries: I) -> Result<(), BinaryDictionaryEntryError>
+    where
+        I: IndexedParallelIterator<Item = (Outline<'c>, CommandList<O>, u16)>,
+        O: Send,
+    {
+        self.add_parallel_with_meta(
+            entries.map(|(outline, commands, tag)| (outline, commands, tag, "")),
+        )
+    }
+
+    /// Parallel counterpart of [`add_with_meta`](Self::add_with_meta), see [`add_parallel`](Self::add_parallel).
+    #[cfg(feature = "parallel")]
+    pub fn add_parallel_with_meta<I, M>(
+        &mut self,
+        entries: I,
+    ) -> Result<(), BinaryDictionaryEntryError>
+    where
+        I: IndexedParallelIterator<Item = (Outline<'c>, CommandList<O>, u16, M)>,
+        M: AsRef<str>,
+        O: Send,
+    {
+        let hash = self.hash;
+        let prepared = entries
+            .map(|(outline, commands, tag, metadata)| {
+                Self::prepare(&hash, outline, commands, tag, metadata.as_ref())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (bucket_index, entry) in prepared {
+            self.insert(bucket_index, entry);
+        }
+
+        Ok(())
+    }
+
+    fn prepare(
+        hash: &DictionaryHash,
+        outline: Outline<'c>,
+        commands: CommandList<O>,
+        tag: u16,
+        metadata: &str,
+    ) -> Result<(usize, BinaryDictionaryEntry<'c, O>), BinaryDictionaryEntryError> {
+        let bucket_index = hash.bucket_index(&outline, HASH_TABLE_SIZE);
+        let entry = BinaryDictionaryEntry::new_with_metadata(
+            tag,
+            outline,
+            commands,
+            SmolStr::new(metadata),
+        )?;
+
+        Ok((bucket_index, entry))
+    }
+
+    fn insert(&mut self, bucket_index: usize, entry: BinaryDictionaryEntry<'c, O>) {
+        let outline_length = entry.outline().len();
+        self.tags.insert(entry.tag());
+        self.longest_outline_length = self.longest_outline_length.max(outline_length as u8);
         self.stats
             .stroke_length
-            .entry(outline.len())
+            .entry(outline_length)
             .and_modify(|x| *x += 1)
             .or_insert(1);
 
-        let bucket_index = calculate_bucket_index(&outline);
-        let entry = BinaryDictionaryEntry::new(tag, outline, commands)?;
         self.stats.entries += 1;
 
         match self.hash_table[bucket_index] {
@@ -125,8 +276,16 @@ impl<'c> BinaryDictionaryCompiler<'c> {
                     .get_mut(bucket_address)
                     .expect("attempted to fetch non-existent bucket during collision handling");
 
+                if self
+                    .chain_limit
+                    .map_or(false, |limit| bucket.len() >= limit)
+                {
+                    self.overlong_chains.push(entry.outline().clone());
+                }
+
                 bucket.push(entry);
                 self.stats.collisions += 1;
+                self.stats.longest_chain = self.stats.longest_chain.max(bucket.len());
 
                 self.stats
                     .occupancy
@@ -143,8 +302,14 @@ impl<'c> BinaryDictionaryCompiler<'c> {
                 // Allocate a new bucket and set its address
                 let bucket_address = self.buckets.len();
                 self.hash_table[bucket_index] = Some(bucket_address);
+
+                if self.chain_limit == Some(0) {
+                    self.overlong_chains.push(entry.outline().clone());
+                }
+
                 self.buckets.push(vec![entry]);
                 self.stats.load += 1;
+                self.stats.longest_chain = self.stats.longest_chain.max(1);
 
                 self.stats
                     .occupancy
@@ -153,8 +318,6 @@ impl<'c> BinaryDictionaryCompiler<'c> {
                     .or_insert(1);
             }
         }
-
-        Ok(())
     }
 
     pub fn stats(&self) -> &DictionaryStatistics {
@@ -169,7 +332,9 @@ pub enum BinaryDictionarySerializationError {
     EntryUnserializable(BinaryDictionaryEntrySerializationError),
 }
 
-impl<'c> BinaryDictionaryCompiler<'c> {
+impl<'c, O: SerializableCommand> BinaryDictionaryCompiler<'c, O> {
+    /// Writes the dictionary byte by byte in a single pass, without buffering it in memory.
+    /// This allows compiling straight onto slow storage like flash, which the writer is the only interface to.
     pub async fn serialize(
         &self,
         writer: &mut impl crate::io::Write,
@@ -177,19 +342,29 @@ impl<'c> BinaryDictionaryCompiler<'c> {
         // -- Begin by remapping some data and calculating offsets
         let mut writer = CountingWriter::new(writer);
 
-        // Write the dictionary entries sorted by hash and build a map
+        // Measure the dictionary entries sorted by hash and build a map
         // from in-memory bucket indices (index in self.buckets) to
         // on-disk bucket offsets relative to the start of the bucket data area.
-        let mut bucket_area = HeapFile::new();
+        // The entries are written after the hash table, so they have to be serialized twice.
         let mut bucket_offsets = [0u32; HASH_TABLE_SIZE];
 
+        // Pairs of translation hashes and entry offsets, sorted so that readers can binary search them
+        let mut reverse_index = Vec::new();
+
         {
-            let mut bucket_writer = CountingWriter::new(&mut bucket_area);
+            let mut sink = Sink;
+            let mut bucket_writer = CountingWriter::new(&mut sink);
             for bucket_address in self.hash_table.iter().filter_map(|b| b.as_ref()) {
                 let bucket = &self.buckets[*bucket_address];
                 bucket_offsets[*bucket_address] = bucket_writer.position() as u32;
 
                 for entry in bucket {
+                    if self.reverse_index {
+                        if let Some(text) = translation_text(entry.commands()) {
+                            reverse_index.push((text_hash(&text), bucket_writer.position() as u32));
+                        }
+                    }
+
                     (*entry)
                         .serialize(&mut bucket_writer)
                         .await
@@ -198,29 +373,39 @@ impl<'c> BinaryDictionaryCompiler<'c> {
             }
         }
 
-        // Write the hash table, translating bucket indices to bucket offsets
-        let mut hash_table = HeapFile::new();
-        for bucket in self.hash_table.iter() {
-            let bucket_pointer = match *bucket {
-                Some(bucket_index) => bucket_offsets[bucket_index],
-                None => HASH_TABLE_EMPTY_BUCKET,
-            };
-
-            hash_table
-                .write_u32(bucket_pointer)
-                .await
-                .map_err(BinaryDictionarySerializationError::IOError)?;
-        }
+        reverse_index.sort_unstable();
 
         // -- Start writing to the output file
 
         // Write the preamble
-        for byte in BINARY_DICT_PREAMBLE {
-            writer
-                .write(*byte)
-                .await
-                .map_err(BinaryDictionarySerializationError::IOError)?;
-        }
+        writer
+            .write_all(BINARY_DICT_PREAMBLE)
+            .await
+            .map_err(BinaryDictionarySerializationError::IOError)?;
+
+        // Write the metadata header
+        DictionaryMetadata::new(self.stats.entries as u32, self.created_at)
+            .serialize(&mut writer)
+            .await
+            .map_err(BinaryDictionarySerializationError::IOError)?;
+
+        // Write the hash function so the reader calculates the same bucket indices
+        self.hash
+            .serialize(&mut writer)
+            .await
+            .map_err(BinaryDictionarySerializationError::IOError)?;
+
+        // Write the tag manifest so applications can list the tags without scanning all entries
+        self.tags
+            .serialize(&mut writer)
+            .await
+            .map_err(BinaryDictionarySerializationError::IOError)?;
+
+        // Write the size of the reverse index, which is empty unless it has been enabled
+        writer
+            .write_u32(reverse_index.len() as u32)
+            .await
+            .map_err(BinaryDictionarySerializationError::IOError)?;
 
         println!("Preamble: {}", writer.position());
 
@@ -240,32 +425,184 @@ impl<'c> BinaryDictionaryCompiler<'c> {
 
         println!("StrokeCon: {}", writer.position());
 
-        // Write the hash table
-        let hash_table_data = hash_table.into_inner();
-        assert_eq!(
-            hash_table_data.len(),
-            HASH_TABLE_SIZE * u32::BITS as usize / 8
-        );
-
-        for byte in hash_table_data {
+        // Write the reverse index
+        for (hash, offset) in reverse_index {
             writer
-                .write(byte)
+                .write_u32(hash)
+                .await
+                .map_err(BinaryDictionarySerializationError::IOError)?;
+            writer
+                .write_u32(offset)
                 .await
                 .map_err(BinaryDictionarySerializationError::IOError)?;
         }
 
-        println!("HashTbl: {}", writer.position());
+        // Write the hash table, translating bucket indices to bucket offsets
+        for bucket in self.hash_table.iter() {
+            let bucket_pointer = match *bucket {
+                Some(bucket_index) => bucket_offsets[bucket_index],
+                None => HASH_TABLE_EMPTY_BUCKET,
+            };
 
-        // Write the bucket data
-        let bucket_area_data = bucket_area.into_inner();
-        for byte in bucket_area_data {
             writer
-                .write(byte)
+                .write_u32(bucket_pointer)
                 .await
                 .map_err(BinaryDictionarySerializationError::IOError)?;
         }
+
+        println!("HashTbl: {}", writer.position());
+
+        // Write the bucket data in the order it has been measured in
+        for bucket_address in self.hash_table.iter().filter_map(|b| b.as_ref()) {
+            for entry in &self.buckets[*bucket_address] {
+                entry
+                    .serialize(&mut writer)
+                    .await
+                    .map_err(BinaryDictionarySerializationError::EntryUnserializable)?;
+            }
+        }
         println!("DataBlob: {}", writer.position());
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod does {
+    use super::BinaryDictionaryCompiler;
+    use crate::{
+        core::{
+            dict::binary::{BinaryDictionary, DictionaryHash, HashAlgorithm, Outline},
+            engine::Command,
+            processor::text_formatter::TextOutputCommand,
+            Stroke, StrokeContext,
+        },
+        io::util::HeapFile,
+    };
+    #[cfg(feature = "parallel")]
+    use rayon::iter::IntoParallelIterator;
+    use smallvec::smallvec;
+
+    #[test]
+    fn report_overlong_chains() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let mut compiler = BinaryDictionaryCompiler::new(&context);
+        compiler.set_chain_limit(1);
+
+        // Identical outlines always share a bucket, regardless of the hash function
+        for (tag, outline) in ["KAT", "KAT", "WORBG"].into_iter().enumerate() {
+            let stroke = Stroke::from_str(outline, &context).unwrap();
+            let command = Command::Output(TextOutputCommand::Write(outline.to_lowercase()));
+            compiler
+                .add(smallvec![stroke], smallvec![command], tag as u16)
+                .unwrap();
+        }
+
+        let kat: Outline = smallvec![Stroke::from_str("KAT", &context).unwrap()];
+        assert_eq!(compiler.overlong_chains(), &[kat.clone()]);
+        assert_eq!(compiler.stats().longest_chain(), 2);
+
+        let hash = DictionaryHash::new(HashAlgorithm::SipHash, 42);
+        compiler.rehash(hash);
+        assert_eq!(compiler.overlong_chains(), &[kat.clone()]);
+        assert_eq!(compiler.stats().entries(), 3);
+        assert_eq!(compiler.stats().longest_chain(), 2);
+
+        let mut file = HeapFile::new();
+        smol::block_on(compiler.serialize(&mut file)).unwrap();
+        let dictionary: BinaryDictionary<_, TextOutputCommand> =
+            smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+
+        let work = Stroke::from_str("WORBG", &context).unwrap();
+        assert_eq!(dictionary.hash(), &hash);
+        assert!(smol::block_on(dictionary.lookup_entry(&kat))
+            .unwrap()
+            .is_some());
+        assert!(smol::block_on(dictionary.lookup_entry(&[work]))
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn compile_identically_in_parallel() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let entries = ["KAT", "WORBG", "KAT/HRAOG", "-S", "TP-PL", "A*", "PW*"]
+            .into_iter()
+            .enumerate()
+            .map(|(tag, outline)| {
+                let outline = outline
+                    .split('/')
+                    .map(|stroke| Stroke::from_str(stroke, &context).unwrap())
+                    .collect();
+                let command = Command::Output(TextOutputCommand::Write(format!("{tag}")));
+                (outline, smallvec![command], tag as u16)
+            })
+            .collect::<Vec<_>>();
+
+        let mut serial = BinaryDictionaryCompiler::new(&context);
+        for (outline, commands, tag) in entries.clone() {
+            serial.add(outline, commands, tag).unwrap();
+        }
+
+        let mut parallel = BinaryDictionaryCompiler::new(&context);
+        parallel.add_parallel(entries.into_par_iter()).unwrap();
+
+        let mut serial_file = HeapFile::new();
+        let mut parallel_file = HeapFile::new();
+        smol::block_on(serial.serialize(&mut serial_file)).unwrap();
+        smol::block_on(parallel.serialize(&mut parallel_file)).unwrap();
+
+        assert_eq!(serial_file.into_inner(), parallel_file.into_inner());
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn keep_metadata_when_compiling_in_parallel() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let entries = [
+            ("KAT", "main.json"),
+            ("WORBG", ""),
+            ("KAT/HRAOG", "typey.json"),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(tag, (outline, metadata))| {
+            let outline: Outline = outline
+                .split('/')
+                .map(|stroke| Stroke::from_str(stroke, &context).unwrap())
+                .collect();
+            let command = Command::Output(TextOutputCommand::Write(format!("{tag}")));
+            (outline, smallvec![command], tag as u16, metadata)
+        })
+        .collect::<Vec<_>>();
+
+        let mut serial = BinaryDictionaryCompiler::new(&context);
+        for (outline, commands, tag, metadata) in entries.clone() {
+            serial
+                .add_with_meta(outline, commands, tag, metadata)
+                .unwrap();
+        }
+
+        let mut parallel = BinaryDictionaryCompiler::new(&context);
+        parallel
+            .add_parallel_with_meta(entries.clone().into_par_iter())
+            .unwrap();
+
+        let mut serial_file = HeapFile::new();
+        let mut parallel_file = HeapFile::new();
+        smol::block_on(serial.serialize(&mut serial_file)).unwrap();
+        smol::block_on(parallel.serialize(&mut parallel_file)).unwrap();
+        assert_eq!(serial_file.as_slice(), parallel_file.as_slice());
+
+        let dictionary: BinaryDictionary<_, TextOutputCommand> =
+            smol::block_on(BinaryDictionary::new(&mut parallel_file)).unwrap();
+
+        for (outline, _, _, metadata) in entries {
+            let entry = smol::block_on(dictionary.lookup_entry(&outline))
+                .unwrap()
+                .unwrap();
+            assert_eq!(entry.metadata().unwrap_or(""), metadata);
+        }
+    }
+}
@@ -11,4 +11,20 @@ pub const HASH_TABLE_SIZE: usize = 125_000;
 pub const HASH_TABLE_BUCKET_SIZE: usize = (u32::BITS / u8::BITS) as usize;
 pub const HASH_TABLE_EMPTY_BUCKET: u32 = u32::MAX;
 
-pub const BINARY_DICT_PREAMBLE: &[u8] = b"stembedDict1";
+/// Marks dictionaries which start with a [`DictionaryMetadata`](crate::core::dict::DictionaryMetadata) header
+/// followed by the [`DictionaryHash`](crate::core::dict::DictionaryHash) used for the hash table
+/// and the [`TagSet`](crate::core::dict::TagSet) of all tags used by its entries, optionally including a reverse index
+pub const BINARY_DICT_PREAMBLE: &[u8] = b"stembedDict6";
+/// Marks dictionaries compiled before the reverse index has been introduced, they are otherwise identical
+pub const BINARY_DICT_UNINDEXED_PREAMBLE: &[u8] = b"stembedDict5";
+/// Marks dictionaries compiled before the tag manifest has been recorded, its tags can only be found by scanning all entries
+pub const BINARY_DICT_UNTAGGED_PREAMBLE: &[u8] = b"stembedDict4";
+/// Marks dictionaries compiled before the hash function has been recorded, they always use the default FNV hash
+pub const BINARY_DICT_FNV_PREAMBLE: &[u8] = b"stembedDict3";
+/// Marks dictionaries compiled before the metadata header has been introduced, they are otherwise identical
+pub const BINARY_DICT_LEGACY_PREAMBLE: &[u8] = b"stembedDict2";
+/// Marks dictionaries compiled before entries carried metadata, their entries lack the metadata field
+pub const BINARY_DICT_ORIGINAL_PREAMBLE: &[u8] = b"stembedDict1";
+pub const BINARY_DICT_FORMAT_VERSION: u16 = 6;
+/// Size of a reverse index record, consisting of the hash of a text followed by the offset of an entry writing it
+pub const REVERSE_INDEX_RECORD_SIZE: usize = 2 * (u32::BITS / u8::BITS) as usize;
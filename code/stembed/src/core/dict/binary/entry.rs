@@ -4,6 +4,7 @@ use crate::{
 };
 use core::fmt::Debug;
 use smallvec::SmallVec;
+use smol_str::SmolStr;
 
 #[derive(Debug)]
 pub enum BinaryDictionaryEntryError {
@@ -13,33 +14,49 @@ pub enum BinaryDictionaryEntryError {
     TooManyStrokes,
     /// You passed more than 64 (6-bit unsigned integer) commands
     TooManyCommands,
+    /// You passed metadata longer than 255 bytes (8-bit unsigned integer)
+    MetadataTooLong,
 }
 
 pub type Outline<'c> = SmallVec<[Stroke<'c>; AVG_STROKE_COUNT]>;
 
-pub(crate) struct BinaryDictionaryEntry<'c> {
+/// Entry of a [`BinaryDictionary`](super::BinaryDictionary), containing output commands of type `O`
+pub struct BinaryDictionaryEntry<'c, O = TextOutputCommand> {
     tag: u16,
     outline: Outline<'c>,
-    commands: CommandList<TextOutputCommand>,
+    commands: CommandList<O>,
+    metadata: SmolStr,
 }
 
-impl<'c> BinaryDictionaryEntry<'c> {
+impl<'c, O> BinaryDictionaryEntry<'c, O> {
     pub(crate) fn new(
         tag: u16,
         outline: Outline<'c>,
-        commands: CommandList<TextOutputCommand>,
+        commands: CommandList<O>,
     ) -> Result<Self, BinaryDictionaryEntryError> {
-        if tag > 32 {
+        Self::new_with_metadata(tag, outline, commands, SmolStr::default())
+    }
+
+    pub(crate) fn new_with_metadata(
+        tag: u16,
+        outline: Outline<'c>,
+        commands: CommandList<O>,
+        metadata: SmolStr,
+    ) -> Result<Self, BinaryDictionaryEntryError> {
+        if tag > 31 {
             Err(BinaryDictionaryEntryError::TagTooLarge)
         } else if outline.len() > 32 {
             Err(BinaryDictionaryEntryError::TooManyStrokes)
         } else if commands.len() > 64 {
             Err(BinaryDictionaryEntryError::TooManyCommands)
+        } else if metadata.len() > u8::MAX as usize {
+            Err(BinaryDictionaryEntryError::MetadataTooLong)
         } else {
             Ok(Self {
                 tag,
                 outline,
                 commands,
+                metadata,
             })
         }
     }
@@ -52,11 +69,21 @@ impl<'c> BinaryDictionaryEntry<'c> {
         &self.outline
     }
 
-    pub fn commands(&self) -> &CommandList<TextOutputCommand> {
+    pub fn commands(&self) -> &CommandList<O> {
         &self.commands
     }
 
-    pub fn into_commands(self) -> CommandList<TextOutputCommand> {
+    /// Free-form information attached when compiling the dictionary (e.g. the source dictionary or a comment).
+    /// Always `None` for entries that have been read during regular lookups, as those skip the metadata.
+    pub fn metadata(&self) -> Option<&str> {
+        if self.metadata.is_empty() {
+            None
+        } else {
+            Some(&self.metadata)
+        }
+    }
+
+    pub fn into_commands(self) -> CommandList<O> {
         self.commands
     }
 }
@@ -0,0 +1,101 @@
+use super::fnv::FnvHasher;
+use crate::{constants::FNV_HASH_KEY, core::Stroke};
+use core::hash::{Hash, Hasher};
+
+/// Hash function which can be used to distribute outlines across the buckets of the hash table
+pub trait OutlineHasher: Hasher {
+    fn with_key(key: u64) -> Self;
+}
+
+impl OutlineHasher for FnvHasher {
+    fn with_key(key: u64) -> Self {
+        FnvHasher::with_key(key)
+    }
+}
+
+/// SipHash-2-4, slower than FNV but distributes similar outlines more evenly
+#[allow(deprecated)]
+pub struct SipHasher(core::hash::SipHasher);
+
+#[allow(deprecated)]
+impl OutlineHasher for SipHasher {
+    fn with_key(key: u64) -> Self {
+        SipHasher(core::hash::SipHasher::new_with_keys(key, 0))
+    }
+}
+
+impl Hasher for SipHasher {
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes)
+    }
+}
+
+/// Hash function families supported by the binary dictionary format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Fnv,
+    SipHash,
+}
+
+impl HashAlgorithm {
+    /// Identifier stored in the dictionary header
+    pub fn id(&self) -> u8 {
+        match self {
+            HashAlgorithm::Fnv => 0,
+            HashAlgorithm::SipHash => 1,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(HashAlgorithm::Fnv),
+            1 => Some(HashAlgorithm::SipHash),
+            _ => None,
+        }
+    }
+}
+
+/// Hash function and key used to build the hash table of a dictionary.
+/// The compiler records it in the dictionary header so that the reader calculates matching bucket indices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DictionaryHash {
+    pub algorithm: HashAlgorithm,
+    /// Initial state or seed of the hash function, changing it yields a different distribution of outlines
+    pub key: u64,
+}
+
+impl DictionaryHash {
+    pub fn new(algorithm: HashAlgorithm, key: u64) -> Self {
+        Self { algorithm, key }
+    }
+
+    pub fn hash(&self, outline: &[Stroke]) -> u64 {
+        match self.algorithm {
+            HashAlgorithm::Fnv => hash_with::<FnvHasher>(self.key, outline),
+            HashAlgorithm::SipHash => hash_with::<SipHasher>(self.key, outline),
+        }
+    }
+
+    pub fn bucket_index(&self, outline: &[Stroke], table_size: usize) -> usize {
+        (self.hash(outline) % table_size as u64) as usize
+    }
+}
+
+impl Default for DictionaryHash {
+    /// FNV with its regular offset basis, which all dictionaries compiled before the hash became configurable use
+    fn default() -> Self {
+        Self::new(HashAlgorithm::Fnv, FNV_HASH_KEY)
+    }
+}
+
+fn hash_with<H: OutlineHasher>(key: u64, outline: &[Stroke]) -> u64 {
+    let mut state = H::with_key(key);
+    for stroke in outline {
+        stroke.hash(&mut state);
+    }
+    state.finish()
+}
@@ -0,0 +1,31 @@
+use crate::constants::BINARY_DICT_FORMAT_VERSION;
+
+/// Information on how a dictionary has been built, stored in the header following the preamble.
+/// Intended for identifying a dictionary in the field, e.g. to check whether it is the expected build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DictionaryMetadata {
+    pub format_version: u16,
+    /// Semantic version (major, minor, patch) of the compiler that produced the dictionary
+    pub compiler_version: (u16, u16, u16),
+    pub entry_count: u32,
+    /// Seconds since the unix epoch, zero if unknown
+    pub created_at: u64,
+}
+
+impl DictionaryMetadata {
+    /// Metadata for a dictionary built by this version of the crate
+    pub fn new(entry_count: u32, created_at: u64) -> Self {
+        let version_component = |component: &str| component.parse().unwrap_or(0);
+
+        Self {
+            format_version: BINARY_DICT_FORMAT_VERSION,
+            compiler_version: (
+                version_component(env!("CARGO_PKG_VERSION_MAJOR")),
+                version_component(env!("CARGO_PKG_VERSION_MINOR")),
+                version_component(env!("CARGO_PKG_VERSION_PATCH")),
+            ),
+            entry_count,
+            created_at,
+        }
+    }
+}
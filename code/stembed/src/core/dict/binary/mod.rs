@@ -1,55 +1,185 @@
-use super::{CommandList, Dictionary};
+use super::{CommandList, Dictionary, DictionaryHandler, DictionaryMatch, TagSet};
 use crate::{
-    constants::{BINARY_DICT_PREAMBLE, FNV_HASH_KEY, HASH_TABLE_BUCKET_SIZE, HASH_TABLE_SIZE},
-    core::{engine::Command, processor::text_formatter::TextOutputCommand, Stroke, StrokeContext},
+    constants::{
+        AVG_STROKE_COUNT, BINARY_DICT_FNV_PREAMBLE, BINARY_DICT_LEGACY_PREAMBLE,
+        BINARY_DICT_ORIGINAL_PREAMBLE, BINARY_DICT_PREAMBLE, BINARY_DICT_UNINDEXED_PREAMBLE,
+        BINARY_DICT_UNTAGGED_PREAMBLE, HASH_TABLE_BUCKET_SIZE, HASH_TABLE_EMPTY_BUCKET,
+        HASH_TABLE_SIZE, REVERSE_INDEX_RECORD_SIZE,
+    },
+    core::{
+        engine::Command, processor::text_formatter::TextOutputCommand, PortableStroke, Stroke,
+        StrokeContext,
+    },
     io::{self, Read, ReadExt, Seek, SeekExt, SeekFrom},
-    serialize::StringSerializationError,
+    serialize::{
+        BinaryDictionaryEntrySerializationError, DictionaryHashSerializationError,
+        SerializableCommand, StringSerializationError,
+    },
+};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
 };
-use alloc::string::ToString;
 use core::{
     cell::{Cell, RefCell},
     future::Future,
-    hash::{Hash, Hasher},
+    marker::PhantomData,
 };
-use smallvec::smallvec;
+use smallvec::{smallvec, SmallVec};
 
 mod entry;
 pub use entry::*;
 
-mod fnv;
-use fnv::FnvHasher;
+mod metadata;
+pub use metadata::*;
+
+mod hash;
+pub use hash::*;
+
+pub(crate) mod fnv;
+pub(crate) mod reverse;
+
+mod stats;
+use stats::CountingReader;
+pub use stats::DictionaryStats;
+
+#[cfg(feature = "std")]
+mod sync;
+#[cfg(feature = "std")]
+pub use sync::SyncBinaryDictionary;
 
 #[derive(Debug)]
 pub enum BinaryDictionaryError {
     IOError(io::Error),
     InvalidPreamble,
     CorruptedStrokeContext(StringSerializationError),
+    CorruptedHash(DictionaryHashSerializationError),
+    CorruptedEntry(BinaryDictionaryEntrySerializationError),
+    /// Another lookup is still in progress on the same dictionary
+    ConcurrentLookup,
+    /// The hash table does not match the entries in the data section
+    InconsistentHashTable,
+    /// The dictionary has been compiled without a reverse index
+    MissingReverseIndex,
+}
+
+/// Results of a successful [`BinaryDictionary::verify`] run
+#[derive(Debug, Default)]
+pub struct VerificationReport {
+    entries: usize,
+    load: usize,
+    longest_bucket: usize,
+}
+
+impl VerificationReport {
+    /// Total number of entries in the dictionary
+    pub fn entries(&self) -> usize {
+        self.entries
+    }
+
+    /// Number of buckets in the hash table that contain at least one entry
+    pub fn load(&self) -> usize {
+        self.load
+    }
+
+    /// Ratio of filled bucket count over total bucket count
+    pub fn load_factor(&self) -> f64 {
+        self.load as f64 / HASH_TABLE_SIZE as f64
+    }
+
+    /// Number of entries in the bucket with the most collisions
+    pub fn longest_bucket(&self) -> usize {
+        self.longest_bucket
+    }
 }
 
-pub struct BinaryDictionary<'d, D: Read + Seek> {
+/// Dictionary stored in the binary format produced by [`BinaryDictionaryCompiler`](crate::compile::BinaryDictionaryCompiler).
+/// Its entries contain output commands of type `O`, which has to match the type they have been compiled with.
+pub struct BinaryDictionary<'d, D: Read + Seek, O = TextOutputCommand> {
     data: RefCell<&'d mut D>,
     context: StrokeContext,
+    metadata: Option<DictionaryMetadata>,
+    hash: DictionaryHash,
+    tags: Option<TagSet>,
+    /// Whether entries contain the metadata field, which dictionaries compiled by the first versions lack
+    entry_metadata: bool,
+    reverse_offset: u64,
+    reverse_index_length: u32,
     table_offset: u64,
     data_offset: u64,
     longest_outline_length: u8,
-    lookup_counter: Cell<u32>,
+    stats: Cell<DictionaryStats>,
+    commands: PhantomData<O>,
 }
 
 impl<'d, D: Read + Seek> BinaryDictionary<'d, D> {
-    pub async fn new(data: &'d mut D) -> Result<BinaryDictionary<'d, D>, BinaryDictionaryError> {
+    /// Reads a dictionary containing text output commands, use [`open`](Self::open) for other command types
+    pub async fn new(data: &'d mut D) -> Result<Self, BinaryDictionaryError> {
+        Self::open(data).await
+    }
+}
+
+impl<'d, D: Read + Seek, O: SerializableCommand> BinaryDictionary<'d, D, O> {
+    /// Reads a dictionary containing output commands of type `O`, e.g. `BinaryDictionary::<_, MidiCommand>::open(&mut file)`
+    pub async fn open(data: &'d mut D) -> Result<Self, BinaryDictionaryError> {
         // Go to the beginning, just in case we are not there already
         data.seek(SeekFrom::Start(0))
             .await
             .map_err(BinaryDictionaryError::IOError)?;
 
         // Check the magic number and version
-        for expected in BINARY_DICT_PREAMBLE {
-            let read = data.read().await.map_err(BinaryDictionaryError::IOError)?;
-            if read != *expected {
-                return Err(BinaryDictionaryError::InvalidPreamble);
-            }
+        let mut preamble = [0; BINARY_DICT_PREAMBLE.len()];
+        for byte in preamble.iter_mut() {
+            *byte = data.read().await.map_err(BinaryDictionaryError::IOError)?;
         }
 
+        // Dictionaries compiled by older versions lack some of the headers but are otherwise identical
+        let records_hash = preamble == BINARY_DICT_PREAMBLE
+            || preamble == BINARY_DICT_UNINDEXED_PREAMBLE
+            || preamble == BINARY_DICT_UNTAGGED_PREAMBLE;
+
+        let (metadata, hash, tags) = if records_hash {
+            let metadata = DictionaryMetadata::deserialize(data)
+                .await
+                .map_err(BinaryDictionaryError::IOError)?;
+            let hash = DictionaryHash::deserialize(data)
+                .await
+                .map_err(BinaryDictionaryError::CorruptedHash)?;
+
+            let tags = if preamble != BINARY_DICT_UNTAGGED_PREAMBLE {
+                let tags = TagSet::deserialize(data)
+                    .await
+                    .map_err(BinaryDictionaryError::IOError)?;
+                Some(tags)
+            } else {
+                None
+            };
+
+            (Some(metadata), hash, tags)
+        } else if preamble == BINARY_DICT_FNV_PREAMBLE {
+            let metadata = DictionaryMetadata::deserialize(data)
+                .await
+                .map_err(BinaryDictionaryError::IOError)?;
+
+            (Some(metadata), DictionaryHash::default(), None)
+        } else if preamble == BINARY_DICT_LEGACY_PREAMBLE
+            || preamble == BINARY_DICT_ORIGINAL_PREAMBLE
+        {
+            (None, DictionaryHash::default(), None)
+        } else {
+            return Err(BinaryDictionaryError::InvalidPreamble);
+        };
+
+        let entry_metadata = preamble != BINARY_DICT_ORIGINAL_PREAMBLE;
+
+        let reverse_index_length = if preamble == BINARY_DICT_PREAMBLE {
+            data.read_u32()
+                .await
+                .map_err(BinaryDictionaryError::IOError)?
+        } else {
+            0
+        };
+
         // Read the longest outline length
         let longest_outline_length = data.read().await.map_err(BinaryDictionaryError::IOError)?;
 
@@ -60,20 +190,29 @@ impl<'d, D: Read + Seek> BinaryDictionary<'d, D> {
 
         // Calculate the location of the data section
         // (Hash table contains a 32-bit number for each bucket so we have to multiply to get a size in bytes)
-        let table_offset = data
+        let reverse_offset = data
             .stream_position()
             .await
             .map_err(BinaryDictionaryError::IOError)?;
+        let table_offset =
+            reverse_offset + reverse_index_length as u64 * REVERSE_INDEX_RECORD_SIZE as u64;
         let hash_table_size = (HASH_TABLE_SIZE * HASH_TABLE_BUCKET_SIZE) as u64;
         let data_offset = table_offset + hash_table_size;
 
         Ok(Self {
             data: RefCell::new(data),
             context,
+            metadata,
+            hash,
+            tags,
+            entry_metadata,
+            reverse_offset,
+            reverse_index_length,
             table_offset,
             data_offset,
             longest_outline_length,
-            lookup_counter: Cell::new(0),
+            stats: Cell::new(DictionaryStats::default()),
+            commands: PhantomData,
         })
     }
 
@@ -81,41 +220,348 @@ impl<'d, D: Read + Seek> BinaryDictionary<'d, D> {
         &self.context
     }
 
+    /// Information on how the dictionary has been built, `None` for dictionaries compiled before it was recorded
+    pub fn metadata(&self) -> Option<&DictionaryMetadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Hash function used to locate entries in the hash table
+    pub fn hash(&self) -> &DictionaryHash {
+        &self.hash
+    }
+
+    /// Tags used by the entries as recorded by the compiler, `None` for dictionaries compiled before it was recorded.
+    /// Use [`scan_tags`](Self::scan_tags) to find the tags of those.
+    pub fn tags(&self) -> Option<TagSet> {
+        self.tags
+    }
+
+    /// Whether the dictionary contains a reverse index, which is required for [`reverse_lookup`](Self::reverse_lookup).
+    /// Also `false` if the index has been enabled but none of the entries writes any text.
+    pub fn has_reverse_index(&self) -> bool {
+        self.reverse_index_length > 0
+    }
+
+    /// Collects the tags of all entries by reading the whole data section.
+    /// Only needed for older dictionaries, newer ones record their tags in the header, see [`tags`](Self::tags).
+    pub async fn scan_tags(&self) -> Result<TagSet, BinaryDictionaryError> {
+        let mut data = self.borrow_data()?;
+
+        data.seek(SeekFrom::Start(self.data_offset))
+            .await
+            .map_err(BinaryDictionaryError::IOError)?;
+
+        let mut tags = TagSet::new();
+
+        loop {
+            match self.read_entry(&mut data, false).await {
+                Ok(entry) => tags.insert(entry.tag()),
+                Err(BinaryDictionaryEntrySerializationError::IOError(io::Error::EOF)) => break,
+                Err(error) => return Err(BinaryDictionaryError::CorruptedEntry(error)),
+            }
+        }
+
+        Ok(tags)
+    }
+
+    fn bucket_index(&self, outline: &[Stroke]) -> usize {
+        self.hash.bucket_index(outline, HASH_TABLE_SIZE)
+    }
+
     pub fn lookup_count(&self) -> u32 {
-        self.lookup_counter.get()
+        self.stats.get().lookups()
     }
 
     pub fn reset_lookup_count(&self) {
-        self.lookup_counter.set(0);
+        DictionaryStats::reset_lookups(&self.stats);
+    }
+
+    /// Lookups performed and operations on the underlying data since the dictionary has been opened or the stats have been reset
+    pub fn stats(&self) -> DictionaryStats {
+        self.stats.get()
     }
 
-    async fn lookup(&self, outline: &[Stroke<'d>]) -> Option<CommandList<TextOutputCommand>> {
-        let lookup_count = self.lookup_counter.get();
-        self.lookup_counter.set(lookup_count + 1);
+    pub fn reset_stats(&self) {
+        self.stats.set(DictionaryStats::default());
+    }
+
+    fn borrow_data(&self) -> Result<CountingReader<'_, 'd, D>, BinaryDictionaryError> {
+        let data = self
+            .data
+            .try_borrow_mut()
+            .map_err(|_| BinaryDictionaryError::ConcurrentLookup)?;
 
-        let mut data = self.data.borrow_mut();
+        Ok(CountingReader::new(data, &self.stats))
+    }
+
+    /// Looks up the dictionary entry for the given outline including its metadata.
+    /// Intended for inspection purposes, regular lookups skip reading the metadata.
+    pub async fn lookup_entry(
+        &self,
+        outline: &[Stroke<'_>],
+    ) -> Result<Option<BinaryDictionaryEntry<'_, O>>, BinaryDictionaryError> {
+        self.find_entry(outline, true).await
+    }
+
+    async fn lookup(
+        &self,
+        outline: &[Stroke<'_>],
+    ) -> Result<Option<DictionaryMatch<O>>, BinaryDictionaryError> {
+        Ok(self
+            .find_entry(outline, false)
+            .await?
+            .map(|entry| DictionaryMatch {
+                tag: entry.tag(),
+                commands: entry.into_commands(),
+            }))
+    }
+
+    /// Finds the longest prefix of the given outline which has an entry in the dictionary, e.g. to suggest writing
+    /// something in fewer strokes. Returns the length of the prefix in strokes together with the commands of its entry.
+    pub async fn longest_matching_prefix(
+        &self,
+        outline: &[Stroke<'_>],
+    ) -> Result<Option<(usize, CommandList<O>)>, BinaryDictionaryError>
+    where
+        O: From<String>,
+    {
+        let outline: SmallVec<[PortableStroke; AVG_STROKE_COUNT]> =
+            outline.iter().map(Stroke::to_portable).collect();
+
+        Ok(DictionaryHandler::new(self)
+            .longest_matching_prefix(&outline)
+            .await?
+            .map(|(length, found)| (length, found.commands)))
+    }
+
+    /// Finds the outlines of all entries whose output commands write exactly the given text, in the order they are stored in.
+    /// Requires the dictionary to be compiled with a [reverse index](crate::compile::BinaryDictionaryCompiler::set_reverse_index).
+    pub async fn reverse_lookup(
+        &self,
+        text: &str,
+    ) -> Result<Vec<Outline<'_>>, BinaryDictionaryError> {
+        if !self.has_reverse_index() {
+            return Err(BinaryDictionaryError::MissingReverseIndex);
+        }
+
+        let mut data = self.borrow_data()?;
+        let hash = reverse::text_hash(text);
+
+        // Binary search for the first record with a matching hash
+        let (mut low, mut high) = (0, self.reverse_index_length);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let (record_hash, _) = self.read_reverse_record(&mut data, middle).await?;
+
+            if record_hash < hash {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+
+        // Collect the entries of all records sharing the hash, there is one for every outline writing the text
+        let mut entry_offsets = Vec::new();
+        for index in low..self.reverse_index_length {
+            match self.read_reverse_record(&mut data, index).await? {
+                (record_hash, offset) if record_hash == hash => entry_offsets.push(offset),
+                _ => break,
+            }
+        }
+
+        let mut outlines = Vec::new();
+
+        for offset in entry_offsets {
+            data.seek(SeekFrom::Start(self.data_offset + offset as u64))
+                .await
+                .map_err(BinaryDictionaryError::IOError)?;
+
+            let entry = self
+                .read_entry(&mut data, false)
+                .await
+                .map_err(BinaryDictionaryError::CorruptedEntry)?;
+
+            // Different texts may share a hash
+            if reverse::translation_text(entry.commands()).as_deref() == Some(text) {
+                outlines.push(entry.outline().clone());
+            }
+        }
+
+        Ok(outlines)
+    }
+
+    /// Reads the translation hash and entry offset of a record in the reverse index
+    async fn read_reverse_record(
+        &self,
+        data: &mut CountingReader<'_, 'd, D>,
+        index: u32,
+    ) -> Result<(u32, u32), BinaryDictionaryError> {
+        let record_offset = self.reverse_offset + index as u64 * REVERSE_INDEX_RECORD_SIZE as u64;
+
+        data.seek(SeekFrom::Start(record_offset))
+            .await
+            .map_err(BinaryDictionaryError::IOError)?;
+
+        let hash = data
+            .read_u32()
+            .await
+            .map_err(BinaryDictionaryError::IOError)?;
+        let offset = data
+            .read_u32()
+            .await
+            .map_err(BinaryDictionaryError::IOError)?;
+
+        Ok((hash, offset))
+    }
+
+    /// Checks the integrity of the whole dictionary by reading every entry and comparing its location against the hash table.
+    /// Intended to be run after transferring a dictionary, e.g. onto an SD card. Note that this loads the full hash table into memory.
+    pub async fn verify(&self) -> Result<VerificationReport, BinaryDictionaryError> {
+        let mut data = self.borrow_data()?;
+
+        data.seek(SeekFrom::Start(self.table_offset))
+            .await
+            .map_err(BinaryDictionaryError::IOError)?;
+
+        let mut table = Vec::with_capacity(HASH_TABLE_SIZE);
+        for _ in 0..HASH_TABLE_SIZE {
+            let bucket_pointer = data
+                .read_u32()
+                .await
+                .map_err(BinaryDictionaryError::IOError)?;
+            table.push(bucket_pointer);
+        }
+
+        let mut report = VerificationReport {
+            load: table
+                .iter()
+                .filter(|pointer| **pointer != HASH_TABLE_EMPTY_BUCKET)
+                .count(),
+            ..Default::default()
+        };
+
+        // The data section directly follows the hash table and contains the buckets in ascending order
+        let mut current_bucket = None;
+        let mut bucket_length = 0;
+        let mut visited_buckets = 0;
+
+        loop {
+            let offset = data
+                .stream_position()
+                .await
+                .map_err(BinaryDictionaryError::IOError)?
+                - self.data_offset;
+
+            let entry = match self.read_entry(&mut data, true).await {
+                Ok(entry) => entry,
+                Err(BinaryDictionaryEntrySerializationError::IOError(io::Error::EOF)) => break,
+                Err(error) => return Err(BinaryDictionaryError::CorruptedEntry(error)),
+            };
+
+            let bucket_index = self.bucket_index(entry.outline());
+            if current_bucket != Some(bucket_index) {
+                // Each bucket has to start exactly where the hash table points to
+                if current_bucket > Some(bucket_index) || table[bucket_index] as u64 != offset {
+                    return Err(BinaryDictionaryError::InconsistentHashTable);
+                }
+
+                current_bucket = Some(bucket_index);
+                visited_buckets += 1;
+                bucket_length = 0;
+            }
+
+            bucket_length += 1;
+            report.entries += 1;
+            report.longest_bucket = report.longest_bucket.max(bucket_length);
+        }
+
+        if visited_buckets != report.load {
+            return Err(BinaryDictionaryError::InconsistentHashTable);
+        }
+
+        Ok(report)
+    }
+
+    /// Reads the outlines of all entries in the order they are stored in
+    pub async fn outlines(&self) -> Result<Vec<Outline<'_>>, BinaryDictionaryError> {
+        let mut data = self.borrow_data()?;
+
+        data.seek(SeekFrom::Start(self.data_offset))
+            .await
+            .map_err(BinaryDictionaryError::IOError)?;
+
+        let mut outlines = Vec::new();
+
+        loop {
+            match self.read_entry(&mut data, false).await {
+                Ok(entry) => outlines.push(entry.outline().clone()),
+                Err(BinaryDictionaryEntrySerializationError::IOError(io::Error::EOF)) => break,
+                Err(error) => return Err(BinaryDictionaryError::CorruptedEntry(error)),
+            }
+        }
+
+        Ok(outlines)
+    }
+
+    /// Reads the entry at the current position, skipping its metadata unless requested
+    async fn read_entry(
+        &self,
+        data: &mut CountingReader<'_, 'd, D>,
+        read_metadata: bool,
+    ) -> Result<BinaryDictionaryEntry<'_, O>, BinaryDictionaryEntrySerializationError> {
+        if !self.entry_metadata {
+            BinaryDictionaryEntry::deserialize_legacy(data, &self.context).await
+        } else if read_metadata {
+            BinaryDictionaryEntry::deserialize(data, &self.context).await
+        } else {
+            BinaryDictionaryEntry::deserialize_without_metadata(data, &self.context).await
+        }
+    }
+
+    /// Lookups are cancellation-safe: The borrow of the underlying data is released when the future is dropped
+    /// and every lookup starts by seeking to an absolute position, so a lookup cancelled halfway through
+    /// does not affect subsequent ones. Running two lookups concurrently is not supported though.
+    async fn find_entry(
+        &self,
+        outline: &[Stroke<'_>],
+        read_metadata: bool,
+    ) -> Result<Option<BinaryDictionaryEntry<'_, O>>, BinaryDictionaryError> {
+        DictionaryStats::record_lookup(&self.stats);
+
+        let mut data = self.borrow_data()?;
 
         // Calculate the memory location of the bucket
-        let bucket_index = calculate_bucket_index(outline);
+        let bucket_index = self.bucket_index(outline);
         let bucket_offset = self.table_offset + (bucket_index * HASH_TABLE_BUCKET_SIZE) as u64;
 
         // Fetch the pointer into the data section
         data.seek(SeekFrom::Start(bucket_offset))
             .await
-            .expect("seek failure during lookup");
+            .map_err(BinaryDictionaryError::IOError)?;
 
-        let data_offset =
-            self.data_offset + data.read_u32().await.expect("read failure during lookup") as u64;
+        let data_offset = self.data_offset
+            + data
+                .read_u32()
+                .await
+                .map_err(BinaryDictionaryError::IOError)? as u64;
 
         // Run through the data section until we find what we are looking for
         data.seek(SeekFrom::Start(data_offset))
             .await
-            .expect("seek failure during lookup");
+            .map_err(BinaryDictionaryError::IOError)?;
 
         // Parse entries from our current position until we either reach EOF or the end of the current buckets collision list
-        while let Ok(entry) = BinaryDictionaryEntry::deserialize(*data, &self.context).await {
+        loop {
+            let entry = match self.read_entry(&mut data, read_metadata).await {
+                Ok(entry) => entry,
+                // Hitting EOF while reading the entry header means there are no more entries (or the bucket is empty).
+                // Anything else, including an EOF halfway through an entry, indicates a truncated or corrupted file.
+                Err(BinaryDictionaryEntrySerializationError::IOError(io::Error::EOF)) => break,
+                Err(error) => return Err(BinaryDictionaryError::CorruptedEntry(error)),
+            };
+
             // Check if we are still in the collision area for our initial bucket
-            let entry_bucket_index = calculate_bucket_index(entry.outline());
+            let entry_bucket_index = self.bucket_index(entry.outline());
             if entry_bucket_index != bucket_index {
                 break;
             }
@@ -123,43 +569,558 @@ impl<'d, D: Read + Seek> BinaryDictionary<'d, D> {
             // Check if we have found a matching stroke
             // TODO Add filtering by tag
             if &entry.outline()[..] == outline {
-                return Some(entry.into_commands());
+                return Ok(Some(entry));
             }
         }
 
-        None
+        Ok(None)
     }
 }
 
-impl<'d, D: Read + Seek> Dictionary for BinaryDictionary<'d, D> {
-    type Stroke = Stroke<'d>;
-    type OutputCommand = TextOutputCommand;
-    type LookupFuture<'a> = impl Future<Output = Option<CommandList<Self::OutputCommand>>> + 'a where Self: 'a;
+/// Portable strokes are mapped onto the context the dictionary has been compiled for.
+/// Strokes without a translation are output as their textual representation, converted into the command type
+impl<'d, D: Read + Seek, O: SerializableCommand + From<String>> Dictionary
+    for BinaryDictionary<'d, D, O>
+{
+    type Stroke = PortableStroke;
+    type OutputCommand = O;
+    type Error = BinaryDictionaryError;
+    type LookupFuture<'a> = impl Future<Output = Result<Option<DictionaryMatch<Self::OutputCommand>>, Self::Error>> + 'a where Self: 'a;
 
     fn lookup<'a>(&'a self, outline: &'a [Self::Stroke]) -> Self::LookupFuture<'a> {
-        self.lookup(outline)
+        async move {
+            let outline: Outline = outline
+                .iter()
+                .map(|stroke| Stroke::from_portable(*stroke, &self.context))
+                .collect();
+
+            self.lookup(&outline).await
+        }
     }
 
-    fn fallback_commands(&self, stroke: &Self::Stroke) -> super::CommandList<Self::OutputCommand> {
-        let formatted_stroke = stroke.to_string();
-        let command = Command::Output(TextOutputCommand::Write(formatted_stroke));
+    fn fallback_commands(&self, stroke: &Self::Stroke) -> CommandList<Self::OutputCommand> {
+        let formatted_stroke = Stroke::from_portable(*stroke, &self.context).to_string();
+        let command = Command::Output(O::from(formatted_stroke));
         smallvec![command]
     }
 
     fn longest_outline_length(&self) -> usize {
         self.longest_outline_length as usize
     }
-}
 
-fn hash(outline: &[Stroke]) -> u64 {
-    let mut state = FnvHasher::with_key(FNV_HASH_KEY);
-    for stroke in outline {
-        stroke.hash(&mut state);
+    fn tags(&self) -> Option<TagSet> {
+        self.tags
     }
-    state.finish()
 }
 
-pub(crate) fn calculate_bucket_index(outline: &[Stroke]) -> usize {
-    let hash = hash(outline);
-    (hash % HASH_TABLE_SIZE as u64) as usize
+#[cfg(all(test, feature = "compile"))]
+mod does {
+    use super::{
+        BinaryDictionary, BinaryDictionaryError, DictionaryHash, DictionaryStats, HashAlgorithm,
+        Outline,
+    };
+    use crate::{
+        compile::BinaryDictionaryCompiler,
+        constants::{
+            BINARY_DICT_FORMAT_VERSION, BINARY_DICT_LEGACY_PREAMBLE, BINARY_DICT_ORIGINAL_PREAMBLE,
+            BINARY_DICT_PREAMBLE, HASH_TABLE_SIZE,
+        },
+        core::{
+            dict::Dictionary, engine::Command, processor::text_formatter::TextOutputCommand,
+            Stroke, StrokeContext,
+        },
+        io::{self, util::HeapFile, Read, Seek, SeekFrom, Write},
+        serialize::SerializableCommand,
+    };
+    use core::future::Future;
+    use smallvec::smallvec;
+
+    /// File which yields to the executor once before every read
+    struct StallingFile(HeapFile);
+
+    impl Read for StallingFile {
+        type ReadFuture<'a> = impl Future<Output = Result<u8, io::Error>> + 'a where Self: 'a;
+
+        fn read(&mut self) -> Self::ReadFuture<'_> {
+            async move {
+                smol::future::yield_now().await;
+                self.0.read().await
+            }
+        }
+    }
+
+    impl Seek for StallingFile {
+        type SeekFuture<'a> = impl Future<Output = Result<u64, io::Error>> + 'a where Self: 'a;
+
+        fn seek(&mut self, pos: SeekFrom) -> Self::SeekFuture<'_> {
+            self.0.seek(pos)
+        }
+    }
+
+    /// Output command of a non-textual application, playing notes or running named macros
+    #[derive(Debug)]
+    enum MidiCommand {
+        Note(u8),
+        Macro(String),
+    }
+
+    impl From<String> for MidiCommand {
+        fn from(name: String) -> Self {
+            Self::Macro(name)
+        }
+    }
+
+    impl SerializableCommand for MidiCommand {
+        type SerializeFuture<'a, W> = impl Future<Output = Result<(), io::Error>> + 'a where Self: 'a, W: 'a + Write;
+        type DeserializeFuture<'a, R> = impl Future<Output = Result<Self, io::Error>> + 'a where R: 'a + Read;
+
+        fn serialize<'a, W: Write>(
+            &'a self,
+            header: u8,
+            writer: &'a mut W,
+        ) -> Self::SerializeFuture<'a, W> {
+            async move {
+                match self {
+                    MidiCommand::Note(note) => {
+                        writer.write(header).await?;
+                        writer.write(*note).await
+                    }
+                    MidiCommand::Macro(name) => {
+                        writer.write(header | 1).await?;
+                        writer.write(name.len() as u8).await?;
+                        for byte in name.as_bytes() {
+                            writer.write(*byte).await?;
+                        }
+                        Ok(())
+                    }
+                }
+            }
+        }
+
+        fn deserialize<'a, R: Read>(
+            header: u8,
+            reader: &'a mut R,
+        ) -> Self::DeserializeFuture<'a, R> {
+            async move {
+                if header & 1 == 0 {
+                    return Ok(MidiCommand::Note(reader.read().await?));
+                }
+
+                let mut name = Vec::new();
+                for _ in 0..reader.read().await? {
+                    name.push(reader.read().await?);
+                }
+                Ok(MidiCommand::Macro(String::from_utf8(name).unwrap()))
+            }
+        }
+    }
+
+    fn compile(context: &StrokeContext) -> Vec<u8> {
+        let mut compiler = BinaryDictionaryCompiler::new(context);
+        let stroke = Stroke::from_str("KPA*", context).unwrap();
+        let command = Command::Output(TextOutputCommand::Write("hello".into()));
+        compiler
+            .add(smallvec![stroke], smallvec![command], 0)
+            .unwrap();
+
+        let mut file = HeapFile::new();
+        smol::block_on(compiler.serialize(&mut file)).unwrap();
+        file.into_inner()
+    }
+
+    #[test]
+    fn return_none_for_missing_outline() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let mut file = HeapFile::from_raw(compile(&context));
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+        let outline = [Stroke::from_str("H-L", &context).unwrap()];
+
+        assert!(matches!(
+            smol::block_on(dictionary.lookup(&outline)),
+            Ok(None)
+        ));
+    }
+
+    #[test]
+    fn count_operations_on_underlying_data() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let mut file = HeapFile::from_raw(compile(&context));
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+        let outline = [Stroke::from_str("KPA*", &context).unwrap()];
+
+        // Opening the dictionary reads the header without counting
+        assert_eq!(dictionary.stats(), DictionaryStats::default());
+
+        assert!(smol::block_on(dictionary.lookup(&outline))
+            .unwrap()
+            .is_some());
+        let stats = dictionary.stats();
+        assert_eq!(stats.lookups(), 1);
+        assert_eq!(stats.seeks(), 2);
+        // Bucket pointer followed by at least the entry itself
+        assert!(stats.reads() > 4);
+
+        dictionary.reset_lookup_count();
+        assert_eq!(dictionary.stats().lookups(), 0);
+        assert_eq!(dictionary.stats().reads(), stats.reads());
+
+        dictionary.reset_stats();
+        assert_eq!(dictionary.stats(), DictionaryStats::default());
+    }
+
+    #[test]
+    fn find_longest_matching_prefix() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let mut compiler = BinaryDictionaryCompiler::new(&context);
+        let stroke = |stroke: &str| Stroke::from_str(stroke, &context).unwrap();
+        let write = |text: &str| Command::Output(TextOutputCommand::Write(text.into()));
+        compiler
+            .add(smallvec![stroke("KAT")], smallvec![write("cat")], 0)
+            .unwrap();
+        compiler
+            .add(
+                smallvec![stroke("KAT"), stroke("HRAOG")],
+                smallvec![write("catalog")],
+                0,
+            )
+            .unwrap();
+
+        let mut file = HeapFile::new();
+        smol::block_on(compiler.serialize(&mut file)).unwrap();
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+
+        let outline = [stroke("KAT"), stroke("HRAOG"), stroke("-S")];
+        let (length, commands) = smol::block_on(dictionary.longest_matching_prefix(&outline))
+            .unwrap()
+            .unwrap();
+        assert_eq!(length, 2);
+        assert!(matches!(
+            &commands[..],
+            [Command::Output(TextOutputCommand::Write(text))] if text == "catalog"
+        ));
+
+        let (length, _) = smol::block_on(dictionary.longest_matching_prefix(&outline[..1]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(length, 1);
+
+        assert!(matches!(
+            smol::block_on(dictionary.longest_matching_prefix(&outline[1..])),
+            Ok(None)
+        ));
+    }
+
+    #[test]
+    fn report_truncated_entry() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let mut data = compile(&context);
+        data.pop();
+
+        let mut file = HeapFile::from_raw(data);
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+        let outline = [Stroke::from_str("KPA*", &context).unwrap()];
+
+        assert!(matches!(
+            smol::block_on(dictionary.lookup(&outline)),
+            Err(BinaryDictionaryError::CorruptedEntry(_))
+        ));
+    }
+
+    #[test]
+    fn expose_entry_metadata() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let mut compiler = BinaryDictionaryCompiler::new(&context);
+        let stroke = Stroke::from_str("KPA*", &context).unwrap();
+        let command = Command::Output(TextOutputCommand::Write("hello".into()));
+        compiler
+            .add_with_meta(smallvec![stroke.clone()], smallvec![command], 0, "main.json")
+            .unwrap();
+
+        let mut file = HeapFile::new();
+        smol::block_on(compiler.serialize(&mut file)).unwrap();
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+        let outline = [stroke];
+
+        let entry = smol::block_on(dictionary.lookup_entry(&outline))
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.metadata(), Some("main.json"));
+
+        let found = smol::block_on(dictionary.lookup(&outline))
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.commands.len(), 1);
+        assert_eq!(found.tag, 0);
+    }
+
+    #[test]
+    fn expose_dictionary_metadata() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let mut file = HeapFile::from_raw(compile(&context));
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+
+        let metadata = dictionary.metadata().unwrap();
+        assert_eq!(metadata.format_version, BINARY_DICT_FORMAT_VERSION);
+        assert_eq!(metadata.entry_count, 1);
+        assert_eq!(metadata.created_at, 0);
+    }
+
+    #[test]
+    fn accept_dictionary_without_metadata() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let compiled = compile(&context);
+
+        // Replace the preamble and drop the metadata and hash headers, like older compilers did
+        let header_length = BINARY_DICT_PREAMBLE.len() + 20 + 9 + 4 + 4;
+        let mut legacy = BINARY_DICT_LEGACY_PREAMBLE.to_vec();
+        legacy.extend_from_slice(&compiled[header_length..]);
+
+        let mut file = HeapFile::from_raw(legacy);
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+        let outline = [Stroke::from_str("KPA*", &context).unwrap()];
+
+        assert!(dictionary.metadata().is_none());
+        assert!(smol::block_on(dictionary.lookup(&outline))
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn accept_dictionary_without_entry_metadata() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let compiled = compile(&context);
+
+        // Replace the preamble and drop the headers as well as the empty metadata of the only entry,
+        // like the first compilers did. The entry is located at the start of the data section, so the table stays valid.
+        let header_length = BINARY_DICT_PREAMBLE.len() + 20 + 9 + 4 + 4;
+        let mut original = BINARY_DICT_ORIGINAL_PREAMBLE.to_vec();
+        original.extend_from_slice(&compiled[header_length..compiled.len() - 1]);
+
+        let mut file = HeapFile::from_raw(original);
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+        let outline = [Stroke::from_str("KPA*", &context).unwrap()];
+
+        assert!(dictionary.metadata().is_none());
+        let found = smol::block_on(dictionary.lookup(&outline))
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            &found.commands[..],
+            [Command::Output(TextOutputCommand::Write(text))] if text == "hello"
+        ));
+
+        let entry = smol::block_on(dictionary.lookup_entry(&outline))
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.metadata(), None);
+        assert_eq!(smol::block_on(dictionary.verify()).unwrap().entries(), 1);
+    }
+
+    #[test]
+    fn use_recorded_hash_function() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let hash = DictionaryHash::new(HashAlgorithm::SipHash, 42);
+        let mut compiler = BinaryDictionaryCompiler::with_hash(&context, hash);
+        let stroke = Stroke::from_str("KPA*", &context).unwrap();
+        let command = Command::Output(TextOutputCommand::Write("hello".into()));
+        compiler
+            .add(smallvec![stroke.clone()], smallvec![command], 0)
+            .unwrap();
+
+        let mut file = HeapFile::new();
+        smol::block_on(compiler.serialize(&mut file)).unwrap();
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+
+        assert_eq!(dictionary.hash(), &hash);
+        assert!(smol::block_on(dictionary.lookup(&[stroke]))
+            .unwrap()
+            .is_some());
+        assert!(smol::block_on(dictionary.verify()).is_ok());
+    }
+
+    #[test]
+    fn list_contained_tags() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let mut compiler = BinaryDictionaryCompiler::new(&context);
+        for (outline, tag) in [("KAT", 0), ("WORBG", 2), ("TP-PL", 2)] {
+            let stroke = Stroke::from_str(outline, &context).unwrap();
+            let command = Command::Output(TextOutputCommand::Write(outline.into()));
+            compiler
+                .add(smallvec![stroke], smallvec![command], tag)
+                .unwrap();
+        }
+
+        let mut file = HeapFile::new();
+        smol::block_on(compiler.serialize(&mut file)).unwrap();
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+
+        let tags = dictionary.tags().unwrap();
+        assert_eq!(tags.iter().collect::<Vec<_>>(), [0, 2]);
+        assert_eq!(smol::block_on(dictionary.scan_tags()).unwrap(), tags);
+    }
+
+    #[test]
+    fn store_custom_output_commands() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let stroke = |outline: &str| Stroke::from_str(outline, &context).unwrap();
+
+        let mut compiler = BinaryDictionaryCompiler::new(&context);
+        let commands = smallvec![
+            Command::Output(MidiCommand::Note(60)),
+            Command::Output(MidiCommand::Macro("sustain".into())),
+        ];
+        compiler.add(smallvec![stroke("KAT")], commands, 0).unwrap();
+
+        let mut file = HeapFile::new();
+        smol::block_on(compiler.serialize(&mut file)).unwrap();
+        let dictionary =
+            smol::block_on(BinaryDictionary::<_, MidiCommand>::open(&mut file)).unwrap();
+
+        let found = smol::block_on(Dictionary::lookup(&dictionary, &[stroke("KAT").to_portable()]))
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            &found.commands[..],
+            [
+                Command::Output(MidiCommand::Note(60)),
+                Command::Output(MidiCommand::Macro(name)),
+            ] if name == "sustain"
+        ));
+
+        let fallback = dictionary.fallback_commands(&stroke("WORBG").to_portable());
+        assert!(matches!(
+            &fallback[..],
+            [Command::Output(MidiCommand::Macro(name))] if name == "WORBG"
+        ));
+    }
+
+    #[test]
+    fn recover_from_cancelled_lookup() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let mut file = StallingFile(HeapFile::from_raw(compile(&context)));
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+        let outline = [Stroke::from_str("KPA*", &context).unwrap()];
+
+        // Poll the lookup until it stalls in the middle of reading and drop it
+        let cancelled = smol::block_on(smol::future::poll_once(dictionary.lookup(&outline)));
+        assert!(cancelled.is_none());
+
+        let found = smol::block_on(dictionary.lookup(&outline))
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            &found.commands[0],
+            Command::Output(TextOutputCommand::Write(text)) if text == "hello"
+        ));
+    }
+
+    #[test]
+    fn reject_concurrent_lookup() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let mut file = StallingFile(HeapFile::from_raw(compile(&context)));
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+        let outline = [Stroke::from_str("KPA*", &context).unwrap()];
+
+        let mut pending = Box::pin(dictionary.lookup(&outline));
+        assert!(smol::block_on(smol::future::poll_once(pending.as_mut())).is_none());
+
+        assert!(matches!(
+            smol::block_on(dictionary.lookup(&outline)),
+            Err(BinaryDictionaryError::ConcurrentLookup)
+        ));
+    }
+
+    #[test]
+    fn find_all_outlines_writing_text() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let stroke = |stroke: &str| Stroke::from_str(stroke, &context).unwrap();
+        let write = |text: &str| Command::Output(TextOutputCommand::Write(text.into()));
+
+        let mut compiler = BinaryDictionaryCompiler::new(&context);
+        compiler.set_reverse_index(true);
+        for (outline, text) in [("KAT", "cat"), ("KA*T", "cat"), ("KAT/HRAOG", "catalog")] {
+            let outline = outline.split('/').map(stroke).collect();
+            compiler.add(outline, smallvec![write(text)], 0).unwrap();
+        }
+
+        let mut file = HeapFile::new();
+        smol::block_on(compiler.serialize(&mut file)).unwrap();
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+        assert!(dictionary.has_reverse_index());
+
+        let outlines = smol::block_on(dictionary.reverse_lookup("cat")).unwrap();
+        assert_eq!(outlines.len(), 2);
+        assert!(outlines.contains(&smallvec![stroke("KAT")]));
+        assert!(outlines.contains(&smallvec![stroke("KA*T")]));
+
+        let catalog: Outline = smallvec![stroke("KAT"), stroke("HRAOG")];
+        let outlines = smol::block_on(dictionary.reverse_lookup("catalog")).unwrap();
+        assert_eq!(outlines, [catalog]);
+        assert!(smol::block_on(dictionary.reverse_lookup("dog"))
+            .unwrap()
+            .is_empty());
+
+        // The index is located in front of the hash table, which regular lookups have to skip
+        assert!(smol::block_on(dictionary.lookup(&[stroke("KA*T")]))
+            .unwrap()
+            .is_some());
+        assert_eq!(smol::block_on(dictionary.verify()).unwrap().entries(), 3);
+    }
+
+    #[test]
+    fn reject_reverse_lookup_without_index() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let mut file = HeapFile::from_raw(compile(&context));
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+
+        assert!(!dictionary.has_reverse_index());
+        assert!(matches!(
+            smol::block_on(dictionary.reverse_lookup("hello")),
+            Err(BinaryDictionaryError::MissingReverseIndex)
+        ));
+    }
+
+    #[test]
+    fn verify_intact_dictionary() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let mut file = HeapFile::from_raw(compile(&context));
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+
+        let report = smol::block_on(dictionary.verify()).unwrap();
+        assert_eq!(report.entries(), 1);
+        assert_eq!(report.load(), 1);
+        assert_eq!(report.longest_bucket(), 1);
+
+        let outlines = smol::block_on(dictionary.outlines()).unwrap();
+        assert_eq!(outlines.len(), 1);
+        assert!(smol::block_on(dictionary.lookup_entry(&outlines[0]))
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn detect_inconsistent_hash_table() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let mut data = compile(&context);
+
+        let table_offset = {
+            let mut file = HeapFile::from_raw(data.clone());
+            let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+            dictionary.table_offset as usize
+        };
+
+        // Mark the bucket of the only entry as empty
+        let outline = [Stroke::from_str("KPA*", &context).unwrap()];
+        let bucket_index = DictionaryHash::default().bucket_index(&outline, HASH_TABLE_SIZE);
+        let pointer_offset = table_offset + bucket_index * 4;
+        data[pointer_offset..pointer_offset + 4].copy_from_slice(&[0xFF; 4]);
+
+        let mut file = HeapFile::from_raw(data);
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+
+        assert!(matches!(
+            smol::block_on(dictionary.verify()),
+            Err(BinaryDictionaryError::InconsistentHashTable)
+        ));
+    }
 }
@@ -0,0 +1,36 @@
+use super::fnv::FnvHasher;
+use crate::{
+    constants::FNV_HASH_KEY,
+    core::{dict::CommandList, engine::Command},
+    serialize::SerializableCommand,
+};
+use alloc::string::String;
+use core::hash::Hasher;
+
+/// Text written by the output commands of an entry in order, `None` if none of them writes any
+pub(crate) fn translation_text<O: SerializableCommand>(
+    commands: &CommandList<O>,
+) -> Option<String> {
+    let mut text: Option<String> = None;
+
+    for command in commands.iter() {
+        if let Command::Output(command) = command {
+            if let Some(written) = command.written_text() {
+                text.get_or_insert_with(String::new).push_str(written);
+            }
+        }
+    }
+
+    text
+}
+
+/// Hash under which entries writing the given text are recorded in the reverse index.
+/// Different texts may share a hash, so the text of the entries found has to be compared afterwards.
+pub(crate) fn text_hash(text: &str) -> u32 {
+    let mut hasher = FnvHasher::with_key(FNV_HASH_KEY);
+    hasher.write(text.as_bytes());
+    let hash = hasher.finish();
+
+    // Fold the upper half in so that it contributes to the truncated hash
+    (hash ^ (hash >> 32)) as u32
+}
@@ -0,0 +1,90 @@
+use crate::io::{self, Read, Seek, SeekFrom};
+use core::{
+    cell::{Cell, RefMut},
+    future::Future,
+};
+
+/// Operations performed on the underlying data of a [`BinaryDictionary`](super::BinaryDictionary), e.g. to diagnose slow flash storage.
+/// Counts saturate instead of overflowing and can be reset using [`reset_stats`](super::BinaryDictionary::reset_stats).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DictionaryStats {
+    lookups: u32,
+    reads: u32,
+    seeks: u32,
+}
+
+impl DictionaryStats {
+    /// Number of lookups, including those of outlines without an entry
+    pub fn lookups(&self) -> u32 {
+        self.lookups
+    }
+
+    /// Number of bytes read from the underlying data, each of them being a call to [`Read::read`]
+    pub fn reads(&self) -> u32 {
+        self.reads
+    }
+
+    /// Number of calls to [`Seek::seek`] on the underlying data
+    pub fn seeks(&self) -> u32 {
+        self.seeks
+    }
+
+    /// Average number of bytes read per lookup, zero if no lookup has been performed
+    pub fn reads_per_lookup(&self) -> f32 {
+        if self.lookups == 0 {
+            0.0
+        } else {
+            self.reads as f32 / self.lookups as f32
+        }
+    }
+
+    pub(super) fn record_lookup(stats: &Cell<Self>) {
+        let mut current = stats.get();
+        current.lookups = current.lookups.saturating_add(1);
+        stats.set(current);
+    }
+
+    pub(super) fn reset_lookups(stats: &Cell<Self>) {
+        stats.set(Self {
+            lookups: 0,
+            ..stats.get()
+        });
+    }
+}
+
+/// Borrow of the underlying data which counts the operations performed through it.
+/// Counting merely increments a number, so the overhead is negligible even if the stats are never read.
+pub(super) struct CountingReader<'a, 'd, D: Read + Seek> {
+    data: RefMut<'a, &'d mut D>,
+    stats: &'a Cell<DictionaryStats>,
+}
+
+impl<'a, 'd, D: Read + Seek> CountingReader<'a, 'd, D> {
+    pub(super) fn new(data: RefMut<'a, &'d mut D>, stats: &'a Cell<DictionaryStats>) -> Self {
+        Self { data, stats }
+    }
+
+    fn record(&self, record: impl FnOnce(&mut DictionaryStats)) {
+        let mut stats = self.stats.get();
+        record(&mut stats);
+        self.stats.set(stats);
+    }
+}
+
+impl<'a, 'd, D: Read + Seek> Read for CountingReader<'a, 'd, D> {
+    type ReadFuture<'r> = impl Future<Output = Result<u8, io::Error>> + 'r where Self: 'r;
+
+    fn read(&mut self) -> Self::ReadFuture<'_> {
+        self.record(|stats| stats.reads = stats.reads.saturating_add(1));
+        self.data.read()
+    }
+}
+
+impl<'a, 'd, D: Read + Seek> Seek for CountingReader<'a, 'd, D> {
+    type SeekFuture<'r> = impl Future<Output = Result<u64, io::Error>> + 'r where Self: 'r;
+
+    fn seek(&mut self, pos: SeekFrom) -> Self::SeekFuture<'_> {
+        self.record(|stats| stats.seeks = stats.seeks.saturating_add(1));
+        self.data.seek(pos)
+    }
+}
@@ -0,0 +1,154 @@
+use super::{BinaryDictionary, BinaryDictionaryError, Outline};
+use crate::{
+    core::{
+        dict::{CommandList, Dictionary, DictionaryMatch, TagSet},
+        engine::Command,
+        PortableStroke, Stroke, StrokeContext,
+    },
+    io::{Read, Seek},
+    serialize::SerializableCommand,
+};
+use alloc::string::{String, ToString};
+use async_lock::Mutex;
+use core::{
+    future::Future,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use smallvec::smallvec;
+
+/// [`BinaryDictionary`] which can be shared between tasks and threads, e.g. by multiple concurrent lookups in a desktop application.
+///
+/// The underlying reader still only supports one lookup at a time, so it is guarded by an async [`Mutex`]
+/// while the lookup counter is kept in an atomic. Lookups do not fail with [`ConcurrentLookup`](BinaryDictionaryError::ConcurrentLookup)
+/// but instead wait for the lookup in progress to finish without occupying the executor.
+///
+/// # Contention
+///
+/// Sharing the dictionary serializes all lookups at the reader. For flash-backed or otherwise slow single-reader sources,
+/// a lookup holds the lock for the whole duration of its reads, so concurrent tasks effectively queue up behind each other.
+/// If lookups frequently overlap, opening one [`BinaryDictionary`] per task on its own reader (e.g. separate file handles)
+/// avoids the contention altogether.
+pub struct SyncBinaryDictionary<'d, D: Read + Seek, O> {
+    dictionary: Mutex<BinaryDictionary<'d, D, O>>,
+    context: StrokeContext,
+    longest_outline_length: usize,
+    tags: Option<TagSet>,
+    lookup_counter: AtomicU32,
+}
+
+impl<'d, D: Read + Seek, O: SerializableCommand> SyncBinaryDictionary<'d, D, O> {
+    pub fn new(dictionary: BinaryDictionary<'d, D, O>) -> Self {
+        Self {
+            context: dictionary.stroke_context().clone(),
+            longest_outline_length: dictionary.longest_outline_length as usize,
+            tags: dictionary.tags(),
+            lookup_counter: AtomicU32::new(0),
+            dictionary: Mutex::new(dictionary),
+        }
+    }
+
+    /// Returns the wrapped dictionary, waiting for lookups in progress is not necessary as this requires ownership
+    pub fn into_inner(self) -> BinaryDictionary<'d, D, O> {
+        self.dictionary.into_inner()
+    }
+
+    pub fn stroke_context(&self) -> &StrokeContext {
+        &self.context
+    }
+
+    pub fn lookup_count(&self) -> u32 {
+        self.lookup_counter.load(Ordering::Relaxed)
+    }
+
+    pub fn reset_lookup_count(&self) {
+        self.lookup_counter.store(0, Ordering::Relaxed);
+    }
+}
+
+impl<'d, D: Read + Seek, O: SerializableCommand + From<String>> Dictionary
+    for SyncBinaryDictionary<'d, D, O>
+{
+    type Stroke = PortableStroke;
+    type OutputCommand = O;
+    type Error = BinaryDictionaryError;
+    type LookupFuture<'a> = impl Future<Output = Result<Option<DictionaryMatch<Self::OutputCommand>>, Self::Error>> + 'a where Self: 'a;
+
+    fn lookup<'a>(&'a self, outline: &'a [Self::Stroke]) -> Self::LookupFuture<'a> {
+        async move {
+            self.lookup_counter.fetch_add(1, Ordering::Relaxed);
+
+            let outline: Outline = outline
+                .iter()
+                .map(|stroke| Stroke::from_portable(*stroke, &self.context))
+                .collect();
+
+            // A lookup which was cancelled or panicked leaves the dictionary usable since every lookup starts by seeking to an absolute position
+            let dictionary = self.dictionary.lock().await;
+            dictionary.lookup(&outline).await
+        }
+    }
+
+    fn fallback_commands(&self, stroke: &Self::Stroke) -> CommandList<Self::OutputCommand> {
+        let formatted_stroke = Stroke::from_portable(*stroke, &self.context).to_string();
+        let command = Command::Output(O::from(formatted_stroke));
+        smallvec![command]
+    }
+
+    fn longest_outline_length(&self) -> usize {
+        self.longest_outline_length
+    }
+
+    fn tags(&self) -> Option<TagSet> {
+        self.tags
+    }
+}
+
+#[cfg(all(test, feature = "compile"))]
+mod does {
+    use super::SyncBinaryDictionary;
+    use crate::{
+        compile::BinaryDictionaryCompiler,
+        core::{
+            dict::{BinaryDictionary, Dictionary},
+            engine::Command,
+            processor::text_formatter::TextOutputCommand,
+            Stroke, StrokeContext,
+        },
+        io::util::HeapFile,
+    };
+    use smallvec::smallvec;
+
+    #[test]
+    fn serve_concurrent_lookups() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let outlines = ["KAT", "TKOG", "PWEURD"];
+
+        let mut compiler = BinaryDictionaryCompiler::new(&context);
+        for outline in outlines {
+            let stroke = Stroke::from_str(outline, &context).unwrap();
+            let command = Command::Output(TextOutputCommand::Write(outline.to_lowercase()));
+            compiler
+                .add(smallvec![stroke], smallvec![command], 0)
+                .unwrap();
+        }
+
+        let mut file = HeapFile::new();
+        smol::block_on(compiler.serialize(&mut file)).unwrap();
+        let mut file = HeapFile::from_raw(file.into_inner());
+
+        let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+        let dictionary = SyncBinaryDictionary::new(dictionary);
+        let [a, b, c] =
+            outlines.map(|outline| [Stroke::from_str(outline, &context).unwrap().to_portable()]);
+
+        let (a, (b, c)) = smol::block_on(smol::future::zip(
+            dictionary.lookup(&a),
+            smol::future::zip(dictionary.lookup(&b), dictionary.lookup(&c)),
+        ));
+
+        for result in [a, b, c] {
+            assert!(result.unwrap().is_some());
+        }
+        assert_eq!(dictionary.lookup_count(), 3);
+    }
+}
@@ -1,4 +1,4 @@
-use super::{super::engine::FetchedOutline, CommandList, Dictionary};
+use super::{super::engine::FetchedOutline, Dictionary, DictionaryMatch};
 use crate::constants::AVG_OUTLINE_RATIO;
 use core::ops::Deref;
 use smallvec::SmallVec;
@@ -16,16 +16,39 @@ where
         Self(dictionary)
     }
 
-    pub async fn lookup(&self, outline: &[D::Stroke]) -> Option<CommandList<D::OutputCommand>> {
+    pub fn dictionary(&self) -> &D {
+        &self.0
+    }
+
+    pub async fn lookup(
+        &self,
+        outline: &[D::Stroke],
+    ) -> Result<Option<DictionaryMatch<D::OutputCommand>>, D::Error> {
         self.0.lookup(outline).await
     }
 
+    /// Finds the longest prefix of the strokes which has an entry in the dictionary, trying decreasing lengths
+    /// starting at the longest outline contained in the dictionary. Returns the length of the prefix along with its entry.
+    pub async fn longest_matching_prefix(
+        &self,
+        strokes: &[Stroke],
+    ) -> Result<Option<(usize, DictionaryMatch<OutputCommand>)>, D::Error> {
+        let mut outline_length = strokes.len().min(self.0.longest_outline_length());
+
+        while outline_length > 0 {
+            if let Some(found) = self.lookup(&strokes[0..outline_length]).await? {
+                return Ok(Some((outline_length, found)));
+            }
+            outline_length -= 1;
+        }
+
+        Ok(None)
+    }
+
     pub async fn find_outlines<'s, 'f>(
         &self,
         strokes: &'s [Stroke],
-    ) -> OutlineList<'s, Stroke, OutputCommand> {
-        let longest_outline_length = self.0.longest_outline_length();
-
+    ) -> Result<OutlineList<'s, Stroke, OutputCommand>, D::Error> {
         // Helper function which finds one outline in the given slice
         let find_longest_matching_outline = |slice: &'s [Stroke]| async move {
             assert!(
@@ -33,24 +56,19 @@ where
                 "Attempted to find outline in empty slice!"
             );
 
-            let mut outline_length = slice.len().min(longest_outline_length);
-
-            // Try finding outlines from outline_length to 1
-            while outline_length > 0 {
-                let outline = &slice[0..outline_length];
-                if let Some(commands) = self.lookup(outline).await {
-                    return FetchedOutline {
-                        strokes: outline,
-                        commands,
-                    };
-                }
-                outline_length -= 1;
-            }
+            match self.longest_matching_prefix(slice).await? {
+                Some((outline_length, found)) => Ok(FetchedOutline {
+                    strokes: &slice[0..outline_length],
+                    commands: found.commands,
+                    tag: Some(found.tag),
+                }),
 
-            // Use the fallback if we do not find any
-            FetchedOutline {
-                strokes: &slice[0..1],
-                commands: self.0.fallback_commands(&slice[0]),
+                // Use the fallback if we do not find any
+                None => Ok(FetchedOutline {
+                    strokes: &slice[0..1],
+                    commands: self.0.fallback_commands(&slice[0]),
+                    tag: None,
+                }),
             }
         };
 
@@ -59,12 +77,12 @@ where
         let mut outlines = SmallVec::new();
 
         while offset < strokes.len() {
-            let outline = find_longest_matching_outline(&strokes[offset..]).await;
+            let outline = find_longest_matching_outline(&strokes[offset..]).await?;
             offset += outline.strokes.len();
             outlines.push(outline);
         }
 
-        outlines
+        Ok(outlines)
     }
 }
 
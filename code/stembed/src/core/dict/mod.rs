@@ -6,21 +6,47 @@ use smallvec::SmallVec;
 mod ext;
 pub(crate) use ext::*;
 
+mod stack;
+pub use stack::DictionaryStack;
+
+mod tags;
+pub use tags::TagSet;
+
 pub(crate) mod binary;
-pub use binary::BinaryDictionary;
+pub use binary::{
+    BinaryDictionary, BinaryDictionaryEntry, DictionaryHash, DictionaryMetadata, DictionaryStats,
+    HashAlgorithm, OutlineHasher, VerificationReport,
+};
+#[cfg(feature = "std")]
+pub use binary::SyncBinaryDictionary;
 
 pub type CommandList<OutputCommand> = SmallVec<[Command<OutputCommand>; AVG_CMD_COUNT]>;
 
+/// Commands of a dictionary entry together with the tag it has been compiled with
+#[derive(Debug)]
+pub struct DictionaryMatch<OutputCommand> {
+    pub commands: CommandList<OutputCommand>,
+    pub tag: u16,
+}
+
 pub trait Dictionary {
     type Stroke;
     type OutputCommand;
-    type LookupFuture<'a>: Future<Output = Option<CommandList<Self::OutputCommand>>> + 'a
+    type Error;
+    type LookupFuture<'a>: Future<Output = Result<Option<DictionaryMatch<Self::OutputCommand>>, Self::Error>>
+        + 'a
     where
         Self: 'a;
 
     fn lookup<'a>(&'a self, outline: &'a [Self::Stroke]) -> Self::LookupFuture<'_>;
     fn fallback_commands(&self, stroke: &Self::Stroke) -> CommandList<Self::OutputCommand>;
     fn longest_outline_length(&self) -> usize;
+
+    /// Tags used by the entries of this dictionary, e.g. to present them as toggleable layers.
+    /// Returns `None` if the dictionary does not know which tags it contains.
+    fn tags(&self) -> Option<TagSet> {
+        None
+    }
 }
 
 impl<D> Dictionary for &D
@@ -29,6 +55,7 @@ where
 {
     type Stroke = D::Stroke;
     type OutputCommand = D::OutputCommand;
+    type Error = D::Error;
     type LookupFuture<'a> = D::LookupFuture<'a> where Self: 'a;
 
     fn lookup<'a>(&'a self, outline: &'a [Self::Stroke]) -> Self::LookupFuture<'a> {
@@ -42,4 +69,8 @@ where
     fn longest_outline_length(&self) -> usize {
         (*self).longest_outline_length()
     }
+
+    fn tags(&self) -> Option<TagSet> {
+        (*self).tags()
+    }
 }
@@ -0,0 +1,76 @@
+use super::{CommandList, Dictionary, DictionaryMatch, TagSet};
+use core::future::Future;
+
+/// Layers one dictionary on top of another, e.g. a personal dictionary on top of the main one
+///
+/// Lookups return the entry of the `upper` dictionary and only fall through to the `lower` one if it has none.
+/// Since the engine prefers longer outlines, a longer entry of the `lower` dictionary still wins over a shorter one above it.
+/// Strokes without any entry use the fallback commands of the `lower` dictionary.
+///
+/// Further layers are added by nesting stacks, with the lowest-priority dictionary at the bottom,
+/// e.g. `DictionaryStack::new(personal, DictionaryStack::new(work, main))`.
+pub struct DictionaryStack<U, L> {
+    upper: U,
+    lower: L,
+}
+
+impl<U, L> DictionaryStack<U, L>
+where
+    U: Dictionary,
+    L: Dictionary<Stroke = U::Stroke, OutputCommand = U::OutputCommand, Error = U::Error>,
+{
+    pub fn new(upper: U, lower: L) -> Self {
+        Self { upper, lower }
+    }
+
+    /// Dictionary whose entries take precedence
+    pub fn upper(&self) -> &U {
+        &self.upper
+    }
+
+    /// Dictionary consulted if the upper one has no entry, which also provides the fallback commands
+    pub fn lower(&self) -> &L {
+        &self.lower
+    }
+}
+
+impl<U, L> Dictionary for DictionaryStack<U, L>
+where
+    U: Dictionary,
+    L: Dictionary<Stroke = U::Stroke, OutputCommand = U::OutputCommand, Error = U::Error>,
+{
+    type Stroke = U::Stroke;
+    type OutputCommand = U::OutputCommand;
+    type Error = U::Error;
+    type LookupFuture<'a> = impl Future<Output = Result<Option<DictionaryMatch<Self::OutputCommand>>, Self::Error>> + 'a where Self: 'a;
+
+    fn lookup<'a>(&'a self, outline: &'a [Self::Stroke]) -> Self::LookupFuture<'a> {
+        async move {
+            // Outlines longer than any entry of the upper dictionary can not be contained in it
+            if outline.len() <= self.upper.longest_outline_length() {
+                if let Some(found) = self.upper.lookup(outline).await? {
+                    return Ok(Some(found));
+                }
+            }
+
+            self.lower.lookup(outline).await
+        }
+    }
+
+    fn fallback_commands(&self, stroke: &Self::Stroke) -> CommandList<Self::OutputCommand> {
+        self.lower.fallback_commands(stroke)
+    }
+
+    fn longest_outline_length(&self) -> usize {
+        self.upper
+            .longest_outline_length()
+            .max(self.lower.longest_outline_length())
+    }
+
+    /// Combined tags of both dictionaries, unknown if either of them does not know its tags
+    fn tags(&self) -> Option<TagSet> {
+        let upper = self.upper.tags()?;
+        let lower = self.lower.tags()?;
+        Some(TagSet::from_bits(upper.bits() | lower.bits()))
+    }
+}
@@ -0,0 +1,68 @@
+/// Set of the tags used by the entries of a dictionary.
+/// Tags are limited to 5 bits, so the set fits into a single bitmask.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TagSet(u32);
+
+impl TagSet {
+    /// Highest tag that can be stored in a set
+    pub const MAX_TAG: u16 = 31;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    pub(crate) fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Adds the tag to the set, tags larger than [`MAX_TAG`](Self::MAX_TAG) are ignored
+    pub fn insert(&mut self, tag: u16) {
+        if tag <= Self::MAX_TAG {
+            self.0 |= 1 << tag;
+        }
+    }
+
+    pub fn contains(&self, tag: u16) -> bool {
+        tag <= Self::MAX_TAG && self.0 & (1 << tag) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Tags contained in the set in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        (0..=Self::MAX_TAG).filter(|tag| self.contains(*tag))
+    }
+}
+
+impl FromIterator<u16> for TagSet {
+    fn from_iter<I: IntoIterator<Item = u16>>(iter: I) -> Self {
+        let mut set = Self::new();
+        iter.into_iter().for_each(|tag| set.insert(tag));
+        set
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::TagSet;
+
+    #[test]
+    fn iterate_distinct_tags_in_order() {
+        let tags = [2, 0, 2, 31, 1].into_iter().collect::<TagSet>();
+
+        assert_eq!(tags.len(), 4);
+        assert!(tags.contains(31));
+        assert!(!tags.contains(3));
+        assert_eq!(tags.iter().collect::<Vec<_>>(), [0, 1, 2, 31]);
+    }
+}
@@ -74,6 +74,11 @@ impl<T, const N: usize> HistoryBuffer<T, N> {
         }
     }
 
+    /// Removes all elements from the buffer
+    pub fn clear(&mut self) {
+        while self.pop().is_some() {}
+    }
+
     pub fn back(&self) -> Option<&T> {
         if self.write_at == 0 {
             if self.filled {
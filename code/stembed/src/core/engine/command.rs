@@ -4,7 +4,8 @@ use smallvec::SmallVec;
 /// Instructions processed by the engine itself
 #[derive(Clone, Copy, Debug)]
 pub enum EngineCommand {
-    /// Removes the previous outline from the stack
+    /// Removes the previous stroke from the stack, like [`Engine::pop`](super::Engine::pop) does.
+    /// Intended to be the only command of an entry, as outputs of the same entry would be undone right away.
     UndoPrevious,
 }
 
@@ -25,8 +26,12 @@ pub struct CommandDelta<OutputCommand> {
 }
 
 impl<OutputCommand> CommandDelta<OutputCommand> {
+    /// Appends a delta which is applied after this one, so that the result has the effect of both.
+    /// Commands undone by it which this delta has yet to push are dropped instead of being undone later on.
     pub fn assimilate(&mut self, other: Self) {
-        self.to_undo += other.to_undo;
+        let retracted = other.to_undo.min(self.to_push.len());
+        self.to_push.truncate(self.to_push.len() - retracted);
+        self.to_undo += other.to_undo - retracted;
         self.to_push.extend(other.to_push);
     }
 }
@@ -39,3 +44,31 @@ impl<OutputCommand> Default for CommandDelta<OutputCommand> {
         }
     }
 }
+
+#[cfg(test)]
+mod does {
+    use super::*;
+    use smallvec::smallvec;
+
+    #[test]
+    fn retract_pending_commands_when_assimilating() {
+        let mut delta = CommandDelta {
+            to_undo: 1,
+            to_push: smallvec![1, 2],
+        };
+
+        delta.assimilate(CommandDelta {
+            to_undo: 1,
+            to_push: smallvec![3],
+        });
+        assert_eq!(delta.to_undo, 1);
+        assert_eq!(delta.to_push.as_slice(), [1, 3]);
+
+        delta.assimilate(CommandDelta {
+            to_undo: 4,
+            to_push: smallvec![],
+        });
+        assert_eq!(delta.to_undo, 3);
+        assert!(delta.to_push.is_empty());
+    }
+}
@@ -1,5 +1,13 @@
-use super::dict::{Dictionary, DictionaryHandler};
-use crate::constants::{AVG_OUTLINE_RATIO, AVG_STROKE_COUNT, HISTORY_SIZE};
+use super::{
+    dict::{BinaryDictionary, Dictionary, DictionaryHandler},
+    StrokeContext,
+};
+use crate::{
+    constants::{AVG_OUTLINE_RATIO, AVG_STROKE_COUNT, HISTORY_SIZE},
+    io::{Read, Seek},
+    serialize::SerializableCommand,
+};
+use alloc::string::String;
 use smallvec::SmallVec;
 
 mod command;
@@ -11,13 +19,38 @@ pub use outline::*;
 mod buffer;
 pub use buffer::HistoryBuffer;
 
-pub struct Engine<D>
+mod usage;
+pub use usage::*;
+
+/// Resolves strokes into outlines using a dictionary, keeping track of the history to allow re-matching and undo.
+///
+/// Strokes pushed into the engine have to be of the dictionary's stroke type. For a [`BinaryDictionary`] this is
+/// [`PortableStroke`](crate::core::PortableStroke), which does not depend on the [`StrokeContext`] the dictionary has been
+/// compiled for. Strokes may be built using any context, e.g. the one returned by [`stroke_context`](Engine::stroke_context),
+/// and converted using [`to_portable`](crate::core::Stroke::to_portable) — regardless of whether the dictionary is borrowed
+/// or has been moved into the engine using [`with_owned`](Self::with_owned).
+///
+/// Applications interested in the outlines being written, e.g. to suggest briefs, can attach a
+/// [`UsageTracker`] using [`with_usage_tracker`](Self::with_usage_tracker).
+pub struct Engine<D, T = ()>
 where
     D: Dictionary,
     D::Stroke: Clone,
 {
     history: HistoryBuffer<MatchedOutline<D::Stroke>, HISTORY_SIZE>,
     dictionary: DictionaryHandler<D>,
+    /// Number of outlines at the top of the history which may still be re-matched by following strokes.
+    /// `None` until the engine has been flushed, as every outline is eligible until then.
+    uncommitted_outlines: Option<usize>,
+    /// Number of outlines at the top of the history which can still be undone, at most `max_undo_depth`
+    undo_depth: usize,
+    max_undo_depth: usize,
+    /// Whether strokes bypass the dictionary and emit their fallback commands, e.g. for fingerspelling
+    passthrough: bool,
+    /// Number of [`UndoPrevious`](EngineCommand::UndoPrevious) commands executed while mutating the history.
+    /// They are applied once the mutation finished, as undoing requires another mutation.
+    pending_undos: usize,
+    usage_tracker: T,
 }
 
 impl<D> Engine<D>
@@ -29,26 +62,218 @@ where
         Self {
             history: HistoryBuffer::new(),
             dictionary: DictionaryHandler::new(dictionary),
+            uncommitted_outlines: None,
+            undo_depth: 0,
+            max_undo_depth: HISTORY_SIZE,
+            passthrough: false,
+            pending_undos: 0,
+            usage_tracker: (),
+        }
+    }
+
+    /// Creates an engine which takes ownership of the dictionary, e.g. to move both into a task together.
+    pub fn with_owned(dictionary: D) -> Self {
+        Self::new(dictionary)
+    }
+}
+
+impl<D, T> Engine<D, T>
+where
+    D: Dictionary,
+    D::Stroke: Clone + core::fmt::Debug,
+    T: UsageTracker<D::Stroke>,
+{
+    /// Reports all outlines written from now on to the given tracker, replacing the previous one.
+    /// Intended to be attached right after creating the engine, since outlines already in the history
+    /// are not reported retroactively but would still be retracted when they are undone.
+    pub fn with_usage_tracker<U: UsageTracker<D::Stroke>>(self, usage_tracker: U) -> Engine<D, U> {
+        Engine {
+            history: self.history,
+            dictionary: self.dictionary,
+            uncommitted_outlines: self.uncommitted_outlines,
+            undo_depth: self.undo_depth,
+            max_undo_depth: self.max_undo_depth,
+            passthrough: self.passthrough,
+            pending_undos: self.pending_undos,
+            usage_tracker,
+        }
+    }
+
+    pub fn usage_tracker(&self) -> &T {
+        &self.usage_tracker
+    }
+
+    /// Mutable access to the tracker, e.g. to periodically persist the accumulated counts
+    pub fn usage_tracker_mut(&mut self) -> &mut T {
+        &mut self.usage_tracker
+    }
+
+    pub fn dictionary(&self) -> &D {
+        self.dictionary.dictionary()
+    }
+
+    /// Number of committed outlines in the history that can still be undone, e.g. to indicate that undo is unavailable.
+    ///
+    /// Note that [`pop`](Self::pop) undoes a single stroke at a time. Popping a stroke off an outline consisting of
+    /// multiple strokes re-matches the remaining ones, so the depth only decreases once the whole outline is gone.
+    pub fn undo_depth(&self) -> usize {
+        self.undo_depth
+    }
+
+    /// Maximum number of outlines that can be undone in a row
+    pub fn max_undo_depth(&self) -> usize {
+        self.max_undo_depth
+    }
+
+    /// Limits the number of outlines that can be undone in a row. Values beyond
+    /// the `HISTORY_SIZE` are clamped as older outlines are not retained.
+    pub fn set_max_undo_depth(&mut self, depth: usize) {
+        self.max_undo_depth = depth.min(HISTORY_SIZE);
+        self.undo_depth = self.undo_depth.min(self.max_undo_depth);
+    }
+
+    /// Whether the engine is currently in passthrough mode, see [`set_passthrough`](Self::set_passthrough)
+    pub fn is_passthrough(&self) -> bool {
+        self.passthrough
+    }
+
+    /// Toggles the passthrough mode. While active, each stroke bypasses the dictionary lookup and directly emits
+    /// the dictionary's fallback commands for it, e.g. to fingerspell words which are not in the dictionary.
+    ///
+    /// Strokes written in passthrough mode are never matched together with any other strokes, neither with
+    /// previous ones nor with those following after the mode has been left again. Undoing them removes their
+    /// output like usual, undoing past them re-matches the outlines before them as if the mode had never been used.
+    pub fn set_passthrough(&mut self, passthrough: bool) {
+        self.passthrough = passthrough;
+    }
+
+    pub async fn push(
+        &mut self,
+        stroke: D::Stroke,
+    ) -> Result<CommandDelta<D::OutputCommand>, D::Error> {
+        let delta = if self.passthrough {
+            self.push_passthrough(stroke, None).await
+        } else {
+            self.mutate_stroke_history(false, None, |strokes| strokes.push(stroke))
+                .await?
+                .0
+        };
+
+        self.apply_pending_undos(delta).await
+    }
+
+    /// Variant of [`push`](Self::push) which additionally returns the outlines that produced the delta, e.g. for usage statistics.
+    /// Only newly matched outlines which emitted commands are included, in the order they have been applied.
+    pub async fn push_with_outlines(
+        &mut self,
+        stroke: D::Stroke,
+    ) -> Result<(CommandDelta<D::OutputCommand>, MatchedOutlines<D::Stroke>), D::Error> {
+        let mut outlines = MatchedOutlines::new();
+        let delta = if self.passthrough {
+            self.push_passthrough(stroke, Some(&mut outlines)).await
+        } else {
+            self.mutate_stroke_history(false, Some(&mut outlines), |strokes| strokes.push(stroke))
+                .await?
+                .0
+        };
+
+        Ok((self.apply_pending_undos(delta).await?, outlines))
+    }
+
+    /// Commits all previous strokes, so that following strokes will no longer be matched together with them.
+    /// Intended to be called once the input has been idle for a while, e.g. after the user stopped writing.
+    ///
+    /// Since the engine always outputs the best available match right away — the longest outline from the dictionary
+    /// matching a prefix of the strokes, falling back to the raw stroke — flushing does not change the current output.
+    /// It merely prevents a stroke written after the pause from retroactively turning the previous ones into a longer outline.
+    /// Undoing strokes still works across flushes.
+    pub fn flush(&mut self) {
+        self.uncommitted_outlines = Some(0);
+    }
+
+    /// Undoes the latest stroke, returning `None` once [`undo_depth`](Self::undo_depth) has been exhausted
+    pub async fn pop(
+        &mut self,
+    ) -> Result<Option<(CommandDelta<D::OutputCommand>, D::Stroke)>, D::Error> {
+        match self.pop_stroke().await? {
+            Some((delta, stroke)) => Ok(Some((self.apply_pending_undos(delta).await?, stroke))),
+            None => Ok(None),
         }
     }
 
-    pub async fn push(&mut self, stroke: D::Stroke) -> CommandDelta<D::OutputCommand> {
-        self.mutate_stroke_history(|strokes| strokes.push(stroke))
-            .await
-            .0
+    /// Undoes one stroke for every [`UndoPrevious`](EngineCommand::UndoPrevious) command executed since the last call,
+    /// combining their deltas with the given one. Re-matching the remaining strokes may request further undos,
+    /// so this loops until none are left instead of recursing from within [`execute`](Self::execute).
+    async fn apply_pending_undos(
+        &mut self,
+        mut delta: CommandDelta<D::OutputCommand>,
+    ) -> Result<CommandDelta<D::OutputCommand>, D::Error> {
+        while self.pending_undos > 0 {
+            self.pending_undos -= 1;
+
+            match self.pop_stroke().await {
+                Ok(Some((undo, _))) => delta.assimilate(undo),
+                Ok(None) => self.pending_undos = 0,
+                Err(error) => {
+                    self.pending_undos = 0;
+                    return Err(error);
+                }
+            }
+        }
+
+        Ok(delta)
     }
 
-    pub async fn pop(&mut self) -> Option<(CommandDelta<D::OutputCommand>, D::Stroke)> {
-        match self.mutate_stroke_history(|strokes| strokes.pop()).await {
-            (instructions, Some(stroke)) => Some((instructions, stroke)),
-            (_, None) => None,
+    /// Undoes the latest stroke without applying the undos requested while re-matching the remaining ones
+    async fn pop_stroke(
+        &mut self,
+    ) -> Result<Option<(CommandDelta<D::OutputCommand>, D::Stroke)>, D::Error> {
+        if self.undo_depth == 0 {
+            return Ok(None);
+        }
+
+        match self
+            .mutate_stroke_history(true, None, |strokes| strokes.pop())
+            .await?
+        {
+            (instructions, Some(stroke)) => Ok(Some((instructions, stroke))),
+            (_, None) => {
+                // Strokes which did not produce any output are not retained in the history
+                self.undo_depth = 0;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Emits the fallback commands of a stroke without consulting the dictionary.
+    /// The resulting outline is committed right away, so it acts as a boundary for re-matching in both directions.
+    async fn push_passthrough(
+        &mut self,
+        stroke: D::Stroke,
+        matched: Option<&mut MatchedOutlines<D::Stroke>>,
+    ) -> CommandDelta<D::OutputCommand> {
+        self.flush();
+
+        let strokes = [stroke];
+        let outline = FetchedOutline {
+            strokes: &strokes,
+            commands: self.dictionary.fallback_commands(&strokes[0]),
+            tag: None,
+        };
+
+        let mut output = CommandDelta::default();
+        if self.add_new_outline(outline, &mut output, matched).await {
+            self.undo_depth = (self.undo_depth + 1).min(self.max_undo_depth);
         }
+        output
     }
 
     async fn mutate_stroke_history<M, R>(
         &mut self,
+        undo: bool,
+        mut matched: Option<&mut MatchedOutlines<D::Stroke>>,
         mutator: M,
-    ) -> (CommandDelta<D::OutputCommand>, R)
+    ) -> Result<(CommandDelta<D::OutputCommand>, R), D::Error>
     where
         M: FnOnce(&mut SmallVec<[D::Stroke; AVG_OUTLINE_RATIO * AVG_STROKE_COUNT]>) -> R,
     {
@@ -58,7 +283,13 @@ where
         let mut stroke_count = 0;
         let target_count = self.dictionary.longest_outline_length();
 
-        while stroke_count < target_count {
+        // Committed outlines are left alone, except for the latest one when undoing
+        let mut outline_limit = self.uncommitted_outlines.unwrap_or(usize::MAX);
+        if undo {
+            outline_limit = outline_limit.max(1);
+        }
+
+        while stroke_count < target_count && old_outlines.len() < outline_limit {
             match self.history.pop() {
                 Some(outline) => {
                     stroke_count += outline.strokes.len();
@@ -70,6 +301,7 @@ where
 
         // Since we pushed the outlines in reverse order, we have to flip the vector around
         old_outlines.reverse();
+        let popped_outlines = old_outlines.len();
 
         // Build an array of strokes from the previous outlines and our new stroke
         let mut strokes: SmallVec<[D::Stroke; AVG_OUTLINE_RATIO * AVG_STROKE_COUNT]> =
@@ -87,12 +319,22 @@ where
         // Re-match the newly built stroke array
         let new_outlines: SmallVec<
             [FetchedOutline<'_, D::Stroke, D::OutputCommand>; AVG_OUTLINE_RATIO],
-        > = self.dictionary.find_outlines(&strokes).await;
+        > = match self.dictionary.find_outlines(&strokes).await {
+            Ok(outlines) => outlines,
+            Err(error) => {
+                // Put the old outlines back so a failed lookup leaves the history untouched
+                for outline in old_outlines {
+                    self.history.push(outline);
+                }
+                return Err(error);
+            }
+        };
 
         // Run through `old_outlines` and `new_outlines` simultaneously and compare along the way.
         // When we hit the "diversion point", undo all remaining old_outlines and apply all new outlines.
         // While iterating, we push the unchanged outlines directly onto the history stack.
         let mut output = CommandDelta::default();
+        let mut pushed_outlines = 0;
         let mut old_iter = old_outlines.into_iter().peekable();
         let mut new_iter = new_outlines.into_iter().peekable();
 
@@ -106,33 +348,53 @@ where
             if old.strokes.len() == new.strokes.len() {
                 // Both are equal, just put it back on the history stack
                 self.history.push(old);
+                pushed_outlines += 1;
             } else {
                 // They diverged! Undo the old one, apply the new one.
                 output.to_undo += old.command_count as usize;
-                self.add_new_outline(new, &mut output).await;
+                self.usage_tracker.retract(&old);
+                pushed_outlines += self
+                    .add_new_outline(new, &mut output, matched.as_deref_mut())
+                    .await as usize;
             }
         }
 
         // Handle the remaining outlines (undo old, apply new)
         for old in old_iter {
             output.to_undo += old.command_count as usize;
+            self.usage_tracker.retract(&old);
         }
 
         for new in new_iter {
-            self.add_new_outline(new, &mut output).await;
+            pushed_outlines += self
+                .add_new_outline(new, &mut output, matched.as_deref_mut())
+                .await as usize;
         }
 
+        if let Some(uncommitted_outlines) = self.uncommitted_outlines.as_mut() {
+            *uncommitted_outlines =
+                uncommitted_outlines.saturating_sub(popped_outlines) + pushed_outlines;
+        }
+
+        // Outlines without output commands are not retained, so they do not count towards the undo depth either
+        self.undo_depth = (self.undo_depth + pushed_outlines)
+            .saturating_sub(popped_outlines)
+            .min(self.max_undo_depth);
+
         // PROFIT! :D
-        (output, return_value)
+        Ok((output, return_value))
     }
 
     /// Helper function which processes a new outline, executes its EngineCommands,
-    /// collects its OutputCommands, and pushes it onto the history stack
+    /// collects its OutputCommands, and pushes it onto the history stack.
+    /// If requested, a copy of the pushed outline is appended to `matched`.
+    /// Returns whether the outline has been pushed.
     async fn add_new_outline(
         &mut self,
         new: FetchedOutline<'_, D::Stroke, D::OutputCommand>,
         output: &mut CommandDelta<D::OutputCommand>,
-    ) {
+        matched: Option<&mut MatchedOutlines<D::Stroke>>,
+    ) -> bool {
         // Execute the commands and count the number out output commands
         let mut command_count = 0;
         for command in new.commands {
@@ -141,9 +403,18 @@ where
 
         // Treat "empty" commands (mostly EngineCommands) as non-existent in terms of the stroke history
         if command_count > 0 {
-            self.history
-                .push(MatchedOutline::new(new.strokes, command_count));
+            let outline = MatchedOutline::new(new.strokes, command_count, new.tag);
+
+            if let Some(matched) = matched {
+                matched.push(outline.clone());
+            }
+
+            self.usage_tracker.record(&outline);
+
+            self.history.push(outline);
         }
+
+        command_count > 0
     }
 
     /// Helper function which executes a command and/or adds its instructions to the output.
@@ -159,12 +430,22 @@ where
                 true
             }
             Command::Engine(EngineCommand::UndoPrevious) => {
-                unimplemented!("Non-recursive implementation for undo missing");
-                // if let Some((instructions, _)) = self.pop().await {
-                //     output.assimilate(instructions);
-                // }
-                // false
+                // Undoing mutates the history again, which has to wait until the current mutation is done
+                self.pending_undos += 1;
+                false
             }
         }
     }
 }
+
+impl<'d, F, O, T> Engine<&'d BinaryDictionary<'d, F, O>, T>
+where
+    F: Read + Seek,
+    O: SerializableCommand + From<String>,
+{
+    /// Context of the borrowed dictionary, which strokes can be built with before converting them into their portable representation
+    pub fn stroke_context(&self) -> &'d StrokeContext {
+        let dictionary: &'d BinaryDictionary<'d, F, O> = *self.dictionary.dictionary();
+        dictionary.stroke_context()
+    }
+}
@@ -1,24 +1,30 @@
 use super::Command;
-use crate::constants::{AVG_CMD_COUNT, AVG_STROKE_COUNT};
+use crate::constants::{AVG_CMD_COUNT, AVG_OUTLINE_RATIO, AVG_STROKE_COUNT};
 use smallvec::SmallVec;
 
 /// Combination of strokes which have been identified as an outline.
 /// Additionally contains information about the number of commands the outline produced
 /// and whether it can be undone.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MatchedOutline<Stroke> {
     pub strokes: SmallVec<[Stroke; AVG_STROKE_COUNT]>,
     pub command_count: u16,
+    /// Tag of the dictionary entry, `None` if no entry matched and the fallback commands were used
+    pub tag: Option<u16>,
 }
 
+/// Outlines which have been matched while processing a single stroke
+pub type MatchedOutlines<Stroke> = SmallVec<[MatchedOutline<Stroke>; AVG_OUTLINE_RATIO]>;
+
 impl<Stroke> MatchedOutline<Stroke>
 where
     Stroke: Clone,
 {
-    pub(super) fn new(strokes: &[Stroke], command_count: usize) -> Self {
+    pub(super) fn new(strokes: &[Stroke], command_count: usize, tag: Option<u16>) -> Self {
         Self {
             strokes: strokes.iter().cloned().collect(),
             command_count: command_count as u16,
+            tag,
         }
     }
 }
@@ -27,4 +33,5 @@ where
 pub struct FetchedOutline<'s, Stroke, OutputCommand> {
     pub strokes: &'s [Stroke],
     pub commands: SmallVec<[Command<OutputCommand>; AVG_CMD_COUNT]>,
+    pub tag: Option<u16>,
 }
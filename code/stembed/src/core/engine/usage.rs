@@ -0,0 +1,24 @@
+use super::MatchedOutline;
+
+/// Receives the outlines written using an [`Engine`](super::Engine), e.g. to learn how often each of them is used and suggest briefs.
+///
+/// The engine does not store anything itself, accumulating the counts in memory or persisting them to flash is up to the implementation.
+/// Since the methods are called while processing a stroke, they should return quickly and defer expensive work like writing to flash.
+///
+/// Outlines are reported as soon as they produce output. Following strokes may still re-match them into a longer outline,
+/// in which case the earlier one is [retracted](Self::retract) before the new one is [recorded](Self::record).
+/// Thus, summing up both calls yields the outlines which are currently written.
+/// The unit type `()` ignores all outlines and is used by default.
+pub trait UsageTracker<Stroke> {
+    /// Called for each outline which produced output, including those which fell back to the raw stroke
+    fn record(&mut self, outline: &MatchedOutline<Stroke>);
+
+    /// Called for a previously recorded outline when it has been undone, either explicitly or by being re-matched
+    fn retract(&mut self, outline: &MatchedOutline<Stroke>);
+}
+
+impl<Stroke> UsageTracker<Stroke> for () {
+    fn record(&mut self, _outline: &MatchedOutline<Stroke>) {}
+
+    fn retract(&mut self, _outline: &MatchedOutline<Stroke>) {}
+}
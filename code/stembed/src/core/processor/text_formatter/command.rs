@@ -9,3 +9,10 @@ pub enum TextOutputCommand {
     ChangeDelimiter(char),
     ResetFormatting,
 }
+
+/// Plain text written as is, e.g. strokes which have no translation
+impl From<String> for TextOutputCommand {
+    fn from(text: String) -> Self {
+        Self::Write(text)
+    }
+}
@@ -16,16 +16,43 @@ impl UndoInfo {
 pub struct TextFormatter {
     // TODO Use the same alloc-less history buffer data type as in the Engine
     history: HistoryBuffer<(TextFormatterState, UndoInfo), COMMAND_HISTORY_SIZE>,
+    /// State that overrides the one stored in the history until the next command is processed
+    pending_state: Option<TextFormatterState>,
+    /// Whether the first output and any output after a soft reset is treated as the beginning of a sentence
+    sentence_start: bool,
 }
 
 impl TextFormatter {
+    /// Creates a formatter which outputs the first word verbatim, see [`with_sentence_start`](Self::with_sentence_start)
     pub fn new() -> Self {
+        Self::with_sentence_start(false)
+    }
+
+    /// Creates a formatter which, if `sentence_start` is set, capitalizes the first word as the beginning of a sentence.
+    /// The same applies after every [`soft_reset`](Self::soft_reset).
+    pub fn with_sentence_start(sentence_start: bool) -> Self {
         Self {
             history: HistoryBuffer::new(),
+            pending_state: None,
+            sentence_start,
         }
     }
 
+    /// Resets the formatting state to the initial one (e.g. when the focused application changes), retaining the delimiter.
+    /// Nothing is written or removed and the undo history is retained, so previous output can still be corrected.
+    /// Undoing a command discards the reset and restores the state recorded in the history.
+    pub fn soft_reset(&mut self) {
+        self.pending_state = Some(self.initial_state(self.state().delimiter));
+    }
+
+    /// Resets the formatting state and clears the undo history, forgetting everything that has been output so far
+    pub fn hard_reset(&mut self) {
+        self.history.clear();
+        self.pending_state = None;
+    }
+
     fn undo(&mut self) -> Option<TextOutputInstruction> {
+        self.pending_state = None;
         self.history
             .pop()
             .map(|(_, undo_info)| TextOutputInstruction::Backspace(undo_info.character_count))
@@ -70,16 +97,28 @@ impl TextFormatter {
         };
 
         self.history.push((state, undo_info));
+        self.pending_state = None;
 
         output
     }
 
     fn state(&self) -> TextFormatterState {
-        self.history
-            .back()
-            .map(|(s, _)| s)
+        self.pending_state
+            .as_ref()
+            .or_else(|| self.history.back().map(|(s, _)| s))
             .cloned()
-            .unwrap_or_default()
+            .unwrap_or_else(|| self.initial_state(TextFormatterState::default().delimiter))
+    }
+
+    fn initial_state(&self, delimiter: char) -> TextFormatterState {
+        if self.sentence_start {
+            TextFormatterState::sentence_start(delimiter)
+        } else {
+            TextFormatterState {
+                delimiter,
+                ..Default::default()
+            }
+        }
     }
 }
 
@@ -9,6 +9,15 @@ pub(super) struct TextFormatterState {
 }
 
 impl TextFormatterState {
+    /// State at the beginning of a new sentence, retaining the configured delimiter
+    pub(super) fn sentence_start(delimiter: char) -> Self {
+        Self {
+            delimiter,
+            attachment: AttachmentMode::Delimited,
+            capitalization: CapitalizationMode::CapitalizeNext,
+        }
+    }
+
     pub(super) fn tick(&mut self) {
         self.attachment.tick();
         self.capitalization.tick();
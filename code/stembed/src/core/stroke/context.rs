@@ -1,3 +1,5 @@
+use crate::{constants::FNV_HASH_KEY, core::dict::binary::fnv::FnvHasher};
+use core::hash::Hasher;
 use smallvec::SmallVec;
 use smol_str::SmolStr;
 
@@ -6,6 +8,11 @@ pub enum StrokeContextError {
     EmptyExtraKey,
     ReservedTokenUsed,
     DuplicateKey,
+    /// The key is part of the middle group and the left or right group, or an extra key is named like one of those,
+    /// so strokes containing it can not be parsed unambiguously
+    AmbiguousKey(SmolStr),
+    /// None of the groups contains any keys
+    NoKeys,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -23,7 +30,7 @@ pub enum Key {
 }
 
 // TODO This is not 100% unicode safe – at the moment it considers unicode scalars as keys, not grapheme clusters
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StrokeContext {
     pub(crate) left: SmolStr,
     pub(crate) middle: SmolStr,
@@ -32,43 +39,145 @@ pub struct StrokeContext {
 }
 
 impl StrokeContext {
+    /// Shorthand for building a context with the [`StrokeContextBuilder`]
     pub fn new(
         left: impl AsRef<str>,
         middle: impl AsRef<str>,
         right: impl AsRef<str>,
         extra: &[&str],
     ) -> Result<Self, StrokeContextError> {
-        let instance = Self {
-            left: SmolStr::new_inline(left.as_ref()),
-            middle: SmolStr::new_inline(middle.as_ref()),
-            right: SmolStr::new_inline(right.as_ref()),
-            extra: extra
-                .iter()
-                .map(|s| SmolStr::new_inline(s.as_ref()))
-                .collect(),
-        };
+        extra
+            .iter()
+            .fold(
+                StrokeContextBuilder::new()
+                    .with_left(left)
+                    .with_middle(middle)
+                    .with_right(right),
+                |builder, key| builder.with_extra(key),
+            )
+            .build()
+    }
+
+    pub fn key_count(&self) -> usize {
+        self.left.chars().count()
+            + self.middle.chars().count()
+            + self.right.chars().count()
+            + self.extra.len()
+    }
+
+    pub fn byte_count(&self) -> usize {
+        // Integer divide and ceil to get the minimum byte count required
+        // TODO Replace this once https://github.com/rust-lang/rust/issues/88581 lands on stable
+        (self.key_count() + 8 - 1) / 8
+    }
+
+    /// Hash of the keys, which stays the same across platforms and versions, e.g. to tell whether an
+    /// [`OwnedStroke`](super::OwnedStroke) has been built using this context
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = FnvHasher::with_key(FNV_HASH_KEY);
+
+        // Valid UTF-8 never contains 0xFF, making it an unambiguous delimiter
+        for keys in [&self.left, &self.middle, &self.right]
+            .into_iter()
+            .chain(self.extra.iter())
+        {
+            hasher.write(keys.as_bytes());
+            hasher.write(&[0xFF]);
+        }
+
+        hasher.finish()
+    }
+
+    pub(crate) fn bit_index(&self, key: &Key) -> Option<usize> {
+        match key {
+            Key::Left(expected) => find_char_index(&self.left, expected),
+            Key::Middle(expected) => {
+                find_char_index(&self.middle, expected).map(|i| i + self.left.chars().count())
+            }
+            Key::Right(expected) => find_char_index(&self.right, expected)
+                .map(|i| i + self.left.chars().count() + self.middle.chars().count()),
+            Key::Extra(expected) => {
+                self.extra
+                    .iter()
+                    .enumerate()
+                    .find(|s| s.1 == expected)
+                    .map(|(i, _)| {
+                        i + self.left.chars().count()
+                            + self.middle.chars().count()
+                            + self.right.chars().count()
+                    })
+            }
+        }
+    }
+}
+
+/// Assembles a [`StrokeContext`] group by group, validating that strokes built with it can be parsed unambiguously.
+///
+/// Keys may appear in both the left and right group (like `S` or `T` in the english steno layout), as the `-` separator
+/// or middle keys tell them apart. Keys of the middle group on the other hand must not appear in any other group.
+#[derive(Debug, Default, Clone)]
+pub struct StrokeContextBuilder {
+    left: SmolStr,
+    middle: SmolStr,
+    right: SmolStr,
+    extra: SmallVec<[SmolStr; 16]>,
+}
+
+impl StrokeContextBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the keys of the left hand in steno order, each character being one key
+    pub fn with_left(mut self, keys: impl AsRef<str>) -> Self {
+        self.left = SmolStr::new(keys);
+        self
+    }
+
+    /// Sets the keys pressed with the thumbs in steno order, each character being one key
+    pub fn with_middle(mut self, keys: impl AsRef<str>) -> Self {
+        self.middle = SmolStr::new(keys);
+        self
+    }
+
+    /// Sets the keys of the right hand in steno order, each character being one key
+    pub fn with_right(mut self, keys: impl AsRef<str>) -> Self {
+        self.right = SmolStr::new(keys);
+        self
+    }
+
+    /// Appends an extra key, which may consist of multiple characters (e.g. `FN1`)
+    pub fn with_extra(mut self, key: impl AsRef<str>) -> Self {
+        self.extra.push(SmolStr::new(key));
+        self
+    }
+
+    pub fn build(self) -> Result<StrokeContext, StrokeContextError> {
+        let Self {
+            left,
+            middle,
+            right,
+            extra,
+        } = self;
 
         // Check that no reserved tokens are used in left,middle,right
         for token in ['|', '-'] {
-            if instance.left.contains(token)
-                || instance.middle.contains(token)
-                || instance.right.contains(token)
-            {
+            if left.contains(token) || middle.contains(token) || right.contains(token) {
                 return Err(StrokeContextError::ReservedTokenUsed);
             }
         }
 
         // Ensure that extra keys are not empty or use reserved tokens
-        for key in instance.extra.iter() {
+        for key in extra.iter() {
             if key.is_empty() {
                 return Err(StrokeContextError::EmptyExtraKey);
-            } else if key.contains(',') {
+            } else if key.contains(',') || key.contains('|') {
                 return Err(StrokeContextError::ReservedTokenUsed);
             }
         }
 
         // Assert that there are no duplicate keys creating ambiguity
-        for side in [&instance.left, &instance.middle, &instance.right] {
+        for side in [&left, &middle, &right] {
             for (index, char) in side.as_str().char_indices() {
                 if let Some(remainder) = side.as_str().get(index..) {
                     for following_char in remainder.chars().skip(1) {
@@ -80,49 +189,62 @@ impl StrokeContext {
             }
         }
 
-        for index in 0..instance.extra.len() {
-            let value = &instance.extra[index];
-            let remainder = &instance.extra[(index + 1)..];
+        for index in 0..extra.len() {
+            let value = &extra[index];
+            let remainder = &extra[(index + 1)..];
             if remainder.contains(value) {
                 return Err(StrokeContextError::DuplicateKey);
             }
         }
 
-        Ok(instance)
-    }
+        // Middle keys are parsed right after the left ones, so sharing a key with either side makes it impossible to tell where it belongs
+        if let Some(key) = middle
+            .chars()
+            .find(|key| left.contains(*key) || right.contains(*key))
+        {
+            let mut buffer = [0; 4];
+            return Err(StrokeContextError::AmbiguousKey(SmolStr::new(
+                key.encode_utf8(&mut buffer),
+            )));
+        }
 
-    pub fn key_count(&self) -> usize {
-        self.left.chars().count()
-            + self.middle.chars().count()
-            + self.right.chars().count()
-            + self.extra.len()
-    }
+        // Extra keys named like regular keys can not be told apart in the steno order representation
+        let is_positional =
+            |char: char| left.contains(char) || middle.contains(char) || right.contains(char);
+        if let Some(key) = extra.iter().find(|key| {
+            let mut chars = key.chars();
+            matches!((chars.next(), chars.next()), (Some(char), None) if is_positional(char))
+        }) {
+            return Err(StrokeContextError::AmbiguousKey(key.clone()));
+        }
 
-    pub fn byte_count(&self) -> usize {
-        // Integer divide and ceil to get the minimum byte count required
-        // TODO Replace this once https://github.com/rust-lang/rust/issues/88581 lands on stable
-        (self.key_count() + 8 - 1) / 8
+        if left.is_empty() && middle.is_empty() && right.is_empty() && extra.is_empty() {
+            return Err(StrokeContextError::NoKeys);
+        }
+
+        Ok(StrokeContext {
+            left,
+            middle,
+            right,
+            extra,
+        })
     }
+}
 
-    pub(crate) fn bit_index(&self, key: &Key) -> Option<usize> {
-        match key {
-            Key::Left(expected) => find_char_index(&self.left, expected),
-            Key::Middle(expected) => {
-                find_char_index(&self.middle, expected).map(|i| i + self.left.chars().count())
+impl core::fmt::Display for StrokeContextError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StrokeContextError::EmptyExtraKey => f.write_str("extra keys must not be empty"),
+            StrokeContextError::ReservedTokenUsed => f.write_str(
+                "keys must not contain '|' or '-', extra keys must not contain '|' or ','",
+            ),
+            StrokeContextError::DuplicateKey => {
+                f.write_str("a key appears twice in the same group")
             }
-            Key::Right(expected) => find_char_index(&self.right, expected)
-                .map(|i| i + self.left.chars().count() + self.middle.chars().count()),
-            Key::Extra(expected) => {
-                self.extra
-                    .iter()
-                    .enumerate()
-                    .find(|s| s.1 == expected)
-                    .map(|(i, _)| {
-                        i + self.left.chars().count()
-                            + self.middle.chars().count()
-                            + self.right.chars().count()
-                    })
+            StrokeContextError::AmbiguousKey(key) => {
+                write!(f, "key '{}' appears in more than one group", key)
             }
+            StrokeContextError::NoKeys => f.write_str("context does not contain any keys"),
         }
     }
 }
@@ -1,11 +1,22 @@
 use crate::{constants::AVG_STROKE_BIT_COUNT, input::InputKeyState};
-use core::{fmt::Display, iter::Peekable};
+use alloc::string::String;
+use core::{
+    fmt::Display,
+    iter::Peekable,
+    ops::{BitAnd, BitOr, Sub},
+};
 use smallvec::SmallVec;
 use smol_str::SmolStr;
 
 mod context;
 pub use context::*;
 
+mod portable;
+pub use portable::*;
+
+mod owned;
+pub use owned::OwnedStroke;
+
 /// Stenography stroke implementation based on a bit vector.
 /// Because the bits themselves do not contain any information on what
 /// keys they represent, the struct holds a reference to the [`StrokeContext`]
@@ -149,7 +160,167 @@ impl<'c> Stroke<'c> {
         }
 
         // Convert the boolean vector into a stroke
-        Ok(Stroke::new(bits.into_iter(), context.clone()))
+        Ok(Stroke::new(bits.into_iter(), context))
+    }
+
+    /// Parses a stroke from its keys listed in canonical steno order, i.e. left, middle, right, and extra keys
+    /// concatenated without any delimiters (e.g. `STKPWHR`, `KPA*FN1`). This is the format some dictionaries and
+    /// tools use to store strokes.
+    ///
+    /// In contrast to [`from_str`](Self::from_str), which parses the display format produced by the [`Display`]
+    /// implementation, the `-` separator and `|` extra key delimiter carry no meaning here and are rejected.
+    /// Each character is assigned to the next key in steno order that matches it. Keys present on both sides
+    /// (like `S` or `T`) thus end up on the left side unless a later key precedes them. Likewise, extra keys
+    /// starting with a character that is also a right-hand key (e.g. `FN1`) can only follow keys positioned after it.
+    pub fn from_steno_string(
+        input: impl AsRef<str>,
+        context: &'c StrokeContext,
+    ) -> Result<Stroke<'c>, StrokeParseError> {
+        let mut remaining = input.as_ref();
+        let mut bits: SmallVec<[bool; AVG_STROKE_BIT_COUNT]> = SmallVec::new();
+
+        let mut consume = |key: &str| match remaining.strip_prefix(key) {
+            Some(rest) => {
+                remaining = rest;
+                true
+            }
+            None => false,
+        };
+
+        let mut char_buf = [0; 4];
+        for key in context
+            .left
+            .chars()
+            .chain(context.middle.chars())
+            .chain(context.right.chars())
+        {
+            bits.push(consume(key.encode_utf8(&mut char_buf)));
+        }
+
+        for key in context.extra.iter() {
+            bits.push(consume(key.as_str()));
+        }
+
+        if !remaining.is_empty() {
+            return Err(StrokeParseError::LeftoverCharacters);
+        }
+
+        Ok(Stroke::new(bits.into_iter(), context))
+    }
+
+    /// Formats the stroke in canonical steno order, the inverse of [`from_steno_string`](Self::from_steno_string).
+    ///
+    /// Since the output contains no separator, strokes whose right-hand keys could also be read as left-hand keys
+    /// (e.g. `-S` or `-TS`) will not parse back into the same stroke.
+    pub fn to_steno_string(&self) -> String {
+        let mut output = String::new();
+
+        let keys = self
+            .context
+            .left
+            .chars()
+            .chain(self.context.middle.chars())
+            .chain(self.context.right.chars());
+
+        let mut bits = self.bits();
+        for (key, bit) in keys.zip(bits.by_ref()) {
+            if bit {
+                output.push(key);
+            }
+        }
+
+        for (key, bit) in self.context.extra.iter().zip(bits) {
+            if bit {
+                output.push_str(key.as_str());
+            }
+        }
+
+        output
+    }
+
+    /// Number of keys which are pressed in only one of the two strokes, e.g. for detecting misstrokes.
+    /// Returns `None` if the strokes are based on different contexts and thus can not be compared.
+    pub fn hamming_distance(&self, other: &Stroke) -> Option<u32> {
+        if self.context != other.context {
+            return None;
+        }
+
+        Some(
+            self.bit_vec
+                .iter()
+                .zip(other.bit_vec.iter())
+                .map(|(a, b)| (a ^ b).count_ones())
+                .sum(),
+        )
+    }
+
+    /// Whether none of the keys are pressed
+    pub fn is_empty(&self) -> bool {
+        self.bit_vec.iter().all(|byte| *byte == 0)
+    }
+
+    /// Keys pressed in either of the two strokes.
+    /// Returns `None` if the strokes are based on different contexts, the `|` operator panics instead.
+    pub fn union(&self, other: &Stroke) -> Option<Stroke<'c>> {
+        self.combine(other, |a, b| a | b)
+    }
+
+    /// Keys pressed in both strokes.
+    /// Returns `None` if the strokes are based on different contexts, the `&` operator panics instead.
+    pub fn intersection(&self, other: &Stroke) -> Option<Stroke<'c>> {
+        self.combine(other, |a, b| a & b)
+    }
+
+    /// Keys pressed in this stroke but not in the other one, e.g. `*` for `KA*T` and `KAT`.
+    /// Returns `None` if the strokes are based on different contexts, the `-` operator panics instead.
+    pub fn difference(&self, other: &Stroke) -> Option<Stroke<'c>> {
+        self.combine(other, |a, b| a & !b)
+    }
+
+    fn combine(&self, other: &Stroke, operation: impl Fn(u8, u8) -> u8) -> Option<Stroke<'c>> {
+        if self.context != other.context {
+            return None;
+        }
+
+        Some(Stroke {
+            bit_vec: self
+                .bit_vec
+                .iter()
+                .zip(other.bit_vec.iter())
+                .map(|(a, b)| operation(*a, *b))
+                .collect(),
+            context: self.context,
+        })
+    }
+}
+
+/// Panics if the strokes are based on different contexts, see [`Stroke::union`] for a checked alternative
+impl<'c> BitOr for &Stroke<'c> {
+    type Output = Stroke<'c>;
+
+    fn bitor(self, other: Self) -> Stroke<'c> {
+        self.union(other)
+            .expect("attempted to combine strokes of different contexts")
+    }
+}
+
+/// Panics if the strokes are based on different contexts, see [`Stroke::intersection`] for a checked alternative
+impl<'c> BitAnd for &Stroke<'c> {
+    type Output = Stroke<'c>;
+
+    fn bitand(self, other: Self) -> Stroke<'c> {
+        self.intersection(other)
+            .expect("attempted to combine strokes of different contexts")
+    }
+}
+
+/// Panics if the strokes are based on different contexts, see [`Stroke::difference`] for a checked alternative
+impl<'c> Sub for &Stroke<'c> {
+    type Output = Stroke<'c>;
+
+    fn sub(self, other: Self) -> Stroke<'c> {
+        self.difference(other)
+            .expect("attempted to combine strokes of different contexts")
     }
 }
 
@@ -0,0 +1,111 @@
+use super::{Stroke, StrokeContext};
+use crate::constants::AVG_STROKE_BIT_COUNT;
+use smallvec::SmallVec;
+
+/// Stroke which does not borrow the [`StrokeContext`] it has been built with, e.g. to keep a stroke history in a `Vec`
+/// without tying it to the lifetime of the context.
+///
+/// Only the bit vector and the [fingerprint](StrokeContext::fingerprint) of the context are retained. To display or look up
+/// the stroke, a context has to be re-attached using [`bind`](Self::bind), which refuses contexts other than the original one.
+/// In contrast to [`PortableStroke`](super::PortableStroke), the conversion is lossless regardless of the number of keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OwnedStroke {
+    bit_vec: SmallVec<[u8; AVG_STROKE_BIT_COUNT / 8]>,
+    context_fingerprint: u64,
+}
+
+impl OwnedStroke {
+    /// Rebuilds a stroke from the parts returned by [`as_bytes`](Self::as_bytes) and [`context_fingerprint`](Self::context_fingerprint),
+    /// e.g. after transmitting or storing them
+    pub fn from_raw_parts(bytes: &[u8], context_fingerprint: u64) -> Self {
+        Self {
+            bit_vec: bytes.iter().copied().collect(),
+            context_fingerprint,
+        }
+    }
+
+    /// Bit vector of the stroke, laid out like the one of a [`Stroke`]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bit_vec
+    }
+
+    pub fn context_fingerprint(&self) -> u64 {
+        self.context_fingerprint
+    }
+
+    /// Whether the stroke has been built with the given context, or one equal to it
+    pub fn is_bound_to(&self, context: &StrokeContext) -> bool {
+        self.context_fingerprint == context.fingerprint()
+            && self.bit_vec.len() == context.byte_count()
+    }
+
+    /// Re-attaches the context, returning `None` if it differs from the one the stroke has been built with
+    pub fn bind<'c>(&self, context: &'c StrokeContext) -> Option<Stroke<'c>> {
+        if !self.is_bound_to(context) {
+            return None;
+        }
+
+        Some(Stroke {
+            bit_vec: self.bit_vec.clone(),
+            context,
+        })
+    }
+}
+
+impl<'c> Stroke<'c> {
+    /// Detaches the stroke from its context, see [`OwnedStroke`]
+    pub fn to_owned_stroke(&self) -> OwnedStroke {
+        OwnedStroke {
+            bit_vec: self.bit_vec.clone(),
+            context_fingerprint: self.context.fingerprint(),
+        }
+    }
+}
+
+impl<'c> From<&Stroke<'c>> for OwnedStroke {
+    fn from(stroke: &Stroke<'c>) -> Self {
+        stroke.to_owned_stroke()
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn round_trip_strokes() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &["FN1"]).unwrap();
+        let inputs = ["KAT", "#STKPWHRAO*EUFRPBLGTSDZ|FN1", "-Z", ""];
+
+        // Strokes can be kept around without borrowing the context
+        let history = inputs
+            .iter()
+            .map(|input| Stroke::from_str(input, &context).unwrap().to_owned_stroke())
+            .collect::<Vec<_>>();
+
+        let equal = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &["FN1"]).unwrap();
+        for (input, stroke) in inputs.iter().zip(history) {
+            let expected = Stroke::from_str(input, &equal).unwrap();
+            assert_eq!(stroke.bind(&equal), Some(expected));
+
+            let fingerprint = stroke.context_fingerprint();
+            assert_eq!(
+                OwnedStroke::from_raw_parts(stroke.as_bytes(), fingerprint),
+                stroke
+            );
+        }
+    }
+
+    #[test]
+    fn refuse_other_contexts() {
+        let english = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let extended = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &["^"]).unwrap();
+        let shifted = StrokeContext::new("#STKPWHR", "AO*E", "UFRPBLGTSDZ", &[]).unwrap();
+
+        let stroke = Stroke::from_str("KAT", &english).unwrap().to_owned_stroke();
+        assert!(stroke.is_bound_to(&english));
+        assert!(stroke.bind(&extended).is_none());
+        assert!(stroke.bind(&shifted).is_none());
+    }
+}
@@ -0,0 +1,103 @@
+use super::{Stroke, StrokeContext};
+
+/// Intermediate representation of a stroke which does not depend on any [`StrokeContext`].
+///
+/// Bit `n` is set if the `n`-th key of the stenography system is pressed, counting in steno order
+/// (left, middle, right and extra keys). Dictionaries map it to the strokes of the system they have been
+/// compiled for, which allows the engine to operate independently of the context and its lifetime.
+/// Note that only the first 64 keys of a system can be represented, any keys beyond are dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PortableStroke(u64);
+
+impl PortableStroke {
+    /// Maximum number of keys which can be represented
+    pub const KEY_LIMIT: usize = u64::BITS as usize;
+
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(&self) -> u64 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether the key at the given position in steno order is pressed
+    pub const fn is_pressed(&self, index: usize) -> bool {
+        index < Self::KEY_LIMIT && self.0 & (1 << index) != 0
+    }
+}
+
+impl<'c> Stroke<'c> {
+    /// Converts the stroke into its context independent representation.
+    /// Note that this is a potentially lossy conversion – keys beyond the [`KEY_LIMIT`](PortableStroke::KEY_LIMIT) will be dropped.
+    pub fn to_portable(&self) -> PortableStroke {
+        let bits = self
+            .bits()
+            .take(PortableStroke::KEY_LIMIT)
+            .enumerate()
+            .fold(0u64, |acc, (i, bit)| acc | ((bit as u64) << i));
+
+        PortableStroke(bits)
+    }
+
+    /// Builds the stroke for the given context from its portable representation.
+    /// Bits beyond the number of keys in the context will be ignored.
+    pub fn from_portable(stroke: PortableStroke, context: &'c StrokeContext) -> Self {
+        Self::new(
+            (0..context.key_count()).map(|index| stroke.is_pressed(index)),
+            context,
+        )
+    }
+}
+
+impl<'c> From<&Stroke<'c>> for PortableStroke {
+    fn from(stroke: &Stroke<'c>) -> Self {
+        stroke.to_portable()
+    }
+}
+
+#[cfg(test)]
+mod does {
+    use super::*;
+
+    #[test]
+    fn round_trip_strokes() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+
+        for input in ["KAT", "#STKPWHRAO*EUFRPBLGTSDZ", "-Z", "TP-PL", ""] {
+            let stroke = Stroke::from_str(input, &context).unwrap();
+            assert_eq!(
+                Stroke::from_portable(stroke.to_portable(), &context),
+                stroke
+            );
+        }
+    }
+
+    #[test]
+    fn number_keys_in_steno_order() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+
+        let stroke = Stroke::from_str("#", &context).unwrap();
+        assert_eq!(stroke.to_portable(), PortableStroke::from_bits(1));
+
+        let stroke = Stroke::from_str("-Z", &context).unwrap();
+        assert_eq!(
+            stroke.to_portable(),
+            PortableStroke::from_bits(1 << (context.key_count() - 1))
+        );
+    }
+
+    #[test]
+    fn map_between_contexts() {
+        let english = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let other = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &["^"]).unwrap();
+
+        let stroke = Stroke::from_str("KAT", &english).unwrap().to_portable();
+        assert_eq!(Stroke::from_portable(stroke, &other).to_string(), "KAT");
+        assert!(PortableStroke::default().is_empty());
+    }
+}
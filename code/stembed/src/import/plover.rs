@@ -112,13 +112,16 @@ where
     not(json_char(), |c| *c == '/')
 }
 
-fn stroke<'c, Input>(context: &'c StrokeContext) -> impl Parser<Input, Output = Stroke<'c>>
+/// Parses a stroke and reports whether it has been written using the number bar, i.e. contains digits
+fn stroke<'c, Input>(context: &'c StrokeContext) -> impl Parser<Input, Output = (Stroke<'c>, bool)>
 where
     Input: Stream<Token = char>,
     Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
 {
     many(stroke_char()).and_then(move |stroke: String| {
+        let numeric = stroke.contains(char::is_numeric);
         Stroke::from_str(preprocess(&stroke), &context)
+            .map(|parsed| (parsed, numeric))
             .map_err(
                 <Input::Error as ParseError<
                     Input::Token,
@@ -129,7 +132,10 @@ where
     })
 }
 
-fn outline<'c, Input>(context: &'c StrokeContext) -> impl Parser<Input, Output = Outline<'c>>
+/// Parses an outline and reports whether all of its strokes are numbers, see [`stroke`]
+fn outline<'c, Input>(
+    context: &'c StrokeContext,
+) -> impl Parser<Input, Output = (Outline<'c>, bool)>
 where
     Input: Stream<Token = char>,
     Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
@@ -139,6 +145,11 @@ where
         lex(char('"')),
         sep_by1(stroke(context), char('/')),
     )
+    .map(|strokes: Vec<(Stroke<'c>, bool)>| {
+        let numeric = strokes.iter().all(|(_, numeric)| *numeric);
+        let outline = strokes.into_iter().map(|(stroke, _)| stroke).collect();
+        (outline, numeric)
+    })
     .expected("outline")
 }
 
@@ -229,17 +240,28 @@ where
     Input: Stream<Token = char>,
     Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
 {
-    char('&').then(|_| {
-        translation_text()
-            .map(|c| {
-                smallvec![
-                    Command::Output(TextOutputCommand::ChangeAttachment(AttachmentMode::Glue)),
-                    Command::Output(c),
-                    Command::Output(TextOutputCommand::ChangeAttachment(AttachmentMode::Glue)),
-                ]
-            })
-            .expected("string")
-    })
+    char('&').then(|_| translation_text().map(glued).expected("string"))
+}
+
+/// Wraps the command so that it attaches to neighbouring glued commands, like fingerspelling does
+fn glued(command: TextOutputCommand) -> CommandList<TextOutputCommand> {
+    smallvec![
+        Command::Output(TextOutputCommand::ChangeAttachment(AttachmentMode::Glue)),
+        Command::Output(command),
+        Command::Output(TextOutputCommand::ChangeAttachment(AttachmentMode::Glue)),
+    ]
+}
+
+/// Plover glues numbers written using the number bar to each other and to fingerspelled letters.
+/// Entries translating such outlines to nothing but digits are converted to glued writes accordingly.
+fn glue_numbers(commands: CommandList<TextOutputCommand>) -> CommandList<TextOutputCommand> {
+    if let [Command::Output(TextOutputCommand::Write(text))] = commands.as_slice() {
+        if !text.is_empty() && text.chars().all(|c| c.is_ascii_digit()) {
+            return glued(TextOutputCommand::Write(text.clone()));
+        }
+    }
+
+    commands
 }
 
 fn meta_operator<Input>() -> impl Parser<Input, Output = CommandList<TextOutputCommand>>
@@ -287,7 +309,13 @@ where
     Input: Stream<Token = char>,
     Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
 {
-    (outline(context), lex(char(':')), translation()).map(|t| (t.0, t.2))
+    (outline(context), lex(char(':')), translation()).map(|((outline, numeric), _, commands)| {
+        if numeric {
+            (outline, glue_numbers(commands))
+        } else {
+            (outline, commands)
+        }
+    })
 }
 
 pub fn parse_dict<'c, Input>(
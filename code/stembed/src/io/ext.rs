@@ -23,8 +23,13 @@ pub trait WriteExt {
     where
         Self: 'a;
 
+    type WriteAllFuture<'a>: Future<Output = Result<()>> + 'a
+    where
+        Self: 'a;
+
     fn write_u16(&mut self, data: u16) -> Self::WriteU16Future<'_>;
     fn write_u32(&mut self, data: u32) -> Self::WriteU32Future<'_>;
+    fn write_all<'a>(&'a mut self, data: &'a [u8]) -> Self::WriteAllFuture<'a>;
 }
 
 pub trait SeekExt {
@@ -46,6 +51,7 @@ where
 {
     type WriteU16Future<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
     type WriteU32Future<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+    type WriteAllFuture<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
 
     fn write_u16(&mut self, data: u16) -> Self::WriteU16Future<'_> {
         async move {
@@ -64,6 +70,15 @@ where
             Ok(())
         }
     }
+
+    fn write_all<'a>(&'a mut self, data: &'a [u8]) -> Self::WriteAllFuture<'a> {
+        async move {
+            for byte in data.iter() {
+                self.write(*byte).await?;
+            }
+            Ok(())
+        }
+    }
 }
 
 impl<R> ReadExt for R
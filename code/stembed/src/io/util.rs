@@ -98,6 +98,17 @@ impl Seek for HeapFile {
     }
 }
 
+/// Writer which discards all data, e.g. to measure its size using a [`CountingWriter`]
+pub(crate) struct Sink;
+
+impl Write for Sink {
+    type WriteFuture<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+
+    fn write(&mut self, _data: u8) -> Self::WriteFuture<'_> {
+        async move { Ok(()) }
+    }
+}
+
 pub(crate) struct CountingWriter<'w, W: Write> {
     writer: &'w mut W,
     bytes: u64,
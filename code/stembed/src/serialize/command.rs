@@ -3,11 +3,12 @@ use crate::{
         engine::{Command, EngineCommand},
         processor::text_formatter::{AttachmentMode, CapitalizationMode, TextOutputCommand},
     },
-    io::{Read, ReadExt, Write, WriteExt},
+    io::{Error as IOError, Read, ReadExt, Write, WriteExt},
 };
 use alloc::{string::String, vec::Vec};
+use core::future::Future;
 
-// The first bit encodes the variant of the `Command` enum
+// The first bit encodes the variant of the `Command` enum, the remaining ones are up to the `SerializableCommand`
 const COMMAND_VARIANT_MASK: u8 = 0b10000000;
 const COMMAND_VARIANT_ENGINE: u8 = 0b00000000;
 const COMMAND_VARIANT_OUTPUT: u8 = 0b10000000;
@@ -37,14 +38,70 @@ const ATTACHMENT_VARIANT_ALWAYS: u8 = 0b00001100;
 
 const TEXT_OUTPUT_LENGTH_MASK: u8 = 0b00001111;
 
-impl Command<TextOutputCommand> {
-    pub async fn serialize(&self, writer: &mut impl Write) -> Result<(), crate::io::Error> {
-        use AttachmentMode::*;
-        use CapitalizationMode::*;
+/// Output command which can be stored in a [`BinaryDictionary`](crate::core::dict::BinaryDictionary).
+///
+/// Each command starts with a header byte which it shares with the [`Command`] wrapping it. The most significant bit
+/// distinguishes engine from output commands, the remaining seven bits and any data following them belong to the output command.
+pub trait SerializableCommand: Sized {
+    type SerializeFuture<'a, W>: Future<Output = Result<(), IOError>> + 'a
+    where
+        Self: 'a,
+        W: 'a + Write;
+
+    type DeserializeFuture<'a, R>: Future<Output = Result<Self, IOError>> + 'a
+    where
+        R: 'a + Read;
+
+    /// Writes the command, starting with a byte that combines the given header with the lower seven bits of its own choosing
+    fn serialize<'a, W: Write>(
+        &'a self,
+        header: u8,
+        writer: &'a mut W,
+    ) -> Self::SerializeFuture<'a, W>;
+
+    /// Reads a command given its header byte, whose most significant bit has to be ignored
+    fn deserialize<'a, R: Read>(header: u8, reader: &'a mut R) -> Self::DeserializeFuture<'a, R>;
+
+    /// Text written by the command, used to find entries by their translation in a reverse index.
+    /// Commands which do not write any text are left out of it.
+    fn written_text(&self) -> Option<&str> {
+        None
+    }
+}
 
+impl<O: SerializableCommand> Command<O> {
+    pub async fn serialize(&self, writer: &mut impl Write) -> Result<(), IOError> {
         match self {
             Command::Engine(_) => writer.write(COMMAND_VARIANT_ENGINE).await,
-            Command::Output(command) => match command {
+            Command::Output(command) => command.serialize(COMMAND_VARIANT_OUTPUT, writer).await,
+        }
+    }
+
+    pub async fn deserialize(reader: &mut impl Read) -> Result<Self, IOError> {
+        let header = reader.read().await?;
+
+        match header & COMMAND_VARIANT_MASK {
+            COMMAND_VARIANT_ENGINE => Ok(Command::Engine(EngineCommand::UndoPrevious)),
+            COMMAND_VARIANT_OUTPUT => Ok(Command::Output(O::deserialize(header, reader).await?)),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl SerializableCommand for TextOutputCommand {
+    type SerializeFuture<'a, W> = impl Future<Output = Result<(), IOError>> + 'a where Self: 'a, W: 'a + Write;
+    type DeserializeFuture<'a, R> = impl Future<Output = Result<Self, IOError>> + 'a where R: 'a + Read;
+
+    fn serialize<'a, W: Write>(
+        &'a self,
+        header: u8,
+        writer: &'a mut W,
+    ) -> Self::SerializeFuture<'a, W> {
+        use AttachmentMode::*;
+        use CapitalizationMode::*;
+
+        async move {
+            match self {
                 TextOutputCommand::Write(string) => {
                     // TODO Implement proper error handling
                     assert!(string.len() < 4096 /* 12-bit length */);
@@ -54,7 +111,7 @@ impl Command<TextOutputCommand> {
                     let length_upper = ((length & 0b111100000000) >> 8) as u8;
 
                     writer
-                        .write(COMMAND_VARIANT_OUTPUT | OUTPUT_VARIANT_TEXT | length_upper)
+                        .write(header | OUTPUT_VARIANT_TEXT | length_upper)
                         .await?;
                     writer.write(length_lower).await?;
 
@@ -65,9 +122,7 @@ impl Command<TextOutputCommand> {
                     Ok(())
                 }
                 TextOutputCommand::ChangeDelimiter(delimiter) => {
-                    writer
-                        .write(COMMAND_VARIANT_OUTPUT | OUTPUT_VARIANT_DELIMITER)
-                        .await?;
+                    writer.write(header | OUTPUT_VARIANT_DELIMITER).await?;
                     writer.write_u32(*delimiter as u32).await
                 }
                 TextOutputCommand::ChangeCapitalization(capitalization_mode) => {
@@ -83,7 +138,7 @@ impl Command<TextOutputCommand> {
                     };
 
                     writer
-                        .write(COMMAND_VARIANT_OUTPUT | OUTPUT_VARIANT_CAPITALIZATION | mode_bits)
+                        .write(header | OUTPUT_VARIANT_CAPITALIZATION | mode_bits)
                         .await
                 }
                 TextOutputCommand::ChangeAttachment(attachment_mode) => {
@@ -95,84 +150,83 @@ impl Command<TextOutputCommand> {
                     };
 
                     writer
-                        .write(COMMAND_VARIANT_OUTPUT | OUTPUT_VARIANT_ATTACHMENT | mode_bits)
+                        .write(header | OUTPUT_VARIANT_ATTACHMENT | mode_bits)
                         .await
                 }
                 TextOutputCommand::ResetFormatting => {
-                    writer
-                        .write(COMMAND_VARIANT_OUTPUT | OUTPUT_VARIANT_RESET)
-                        .await
+                    writer.write(header | OUTPUT_VARIANT_RESET).await
                 }
-            },
+            }
         }
     }
 
-    pub async fn deserialize(reader: &mut impl Read) -> Result<Self, crate::io::Error> {
+    fn deserialize<'a, R: Read>(header: u8, reader: &'a mut R) -> Self::DeserializeFuture<'a, R> {
         use AttachmentMode::*;
         use CapitalizationMode::*;
 
-        let data = reader.read().await?;
+        async move {
+            let command = match header & OUTPUT_VARIANT_MASK {
+                OUTPUT_VARIANT_TEXT => {
+                    let mut length: u16 = ((header & TEXT_OUTPUT_LENGTH_MASK) as u16) << 8;
+                    length |= reader.read().await? as u16;
 
-        match data & COMMAND_VARIANT_MASK {
-            COMMAND_VARIANT_ENGINE => Ok(Command::Engine(EngineCommand::UndoPrevious)),
-            COMMAND_VARIANT_OUTPUT => {
-                let output_command = match data & OUTPUT_VARIANT_MASK {
-                    OUTPUT_VARIANT_TEXT => {
-                        let mut length: u16 = ((data & TEXT_OUTPUT_LENGTH_MASK) as u16) << 8;
-                        length |= reader.read().await? as u16;
-
-                        // Strings allocate anyways so we can just use Vec
-                        let mut data = Vec::with_capacity(length as usize);
-
-                        for _ in 0..length {
-                            data.push(reader.read().await?);
-                        }
-
-                        // TODO Implement proper error handling/propagation when encountering invalid UTF-8 data
-                        TextOutputCommand::Write(
-                            String::from_utf8(data).expect("encountered invalid UTF-8 data"),
-                        )
-                    }
-                    OUTPUT_VARIANT_DELIMITER => {
-                        let delimiter_data = reader.read_u32().await?;
-                        // TODO Implement proper error handling/propagation when encountering invalid UTF-8 data
-                        let delimiter =
-                            char::from_u32(delimiter_data).expect("encountered invalid UTF-8 data");
-                        TextOutputCommand::ChangeDelimiter(delimiter)
-                    }
-                    OUTPUT_VARIANT_CAPITALIZATION => {
-                        let capitalization_mode = match data & CAPITALIZATION_VARIANT_MASK {
-                            CAPITALIZATION_VARIANT_NONE => None,
-                            CAPITALIZATION_VARIANT_LOWER => Lowercase,
-                            CAPITALIZATION_VARIANT_CAPIT => Capitalize,
-                            CAPITALIZATION_VARIANT_UPPER => Uppercase,
-                            CAPITALIZATION_VARIANT_LOWER_THEN_CAPIT => LowerThenCapitalize,
-                            CAPITALIZATION_VARIANT_LOWER_NEXT => LowercaseNext,
-                            CAPITALIZATION_VARIANT_CAPIT_NEXT => CapitalizeNext,
-                            CAPITALIZATION_VARIANT_UPPER_NEXT => UppercaseNext,
-                            _ => unreachable!(),
-                        };
-
-                        TextOutputCommand::ChangeCapitalization(capitalization_mode)
-                    }
-                    OUTPUT_VARIANT_ATTACHMENT => {
-                        let attachment_mode = match data & ATTACHMENT_VARIANT_MASK {
-                            ATTACHMENT_VARIANT_DELIMITED => Delimited,
-                            ATTACHMENT_VARIANT_GLUE => Glue,
-                            ATTACHMENT_VARIANT_NEXT => Next,
-                            ATTACHMENT_VARIANT_ALWAYS => Always,
-                            _ => unreachable!(),
-                        };
-
-                        TextOutputCommand::ChangeAttachment(attachment_mode)
+                    // Strings allocate anyways so we can just use Vec
+                    let mut data = Vec::with_capacity(length as usize);
+
+                    for _ in 0..length {
+                        data.push(reader.read().await?);
                     }
-                    OUTPUT_VARIANT_RESET => TextOutputCommand::ResetFormatting,
-                    _ => unreachable!(),
-                };
 
-                Ok(Command::Output(output_command))
-            }
-            _ => unreachable!(),
+                    // TODO Implement proper error handling/propagation when encountering invalid UTF-8 data
+                    TextOutputCommand::Write(
+                        String::from_utf8(data).expect("encountered invalid UTF-8 data"),
+                    )
+                }
+                OUTPUT_VARIANT_DELIMITER => {
+                    let delimiter_data = reader.read_u32().await?;
+                    // TODO Implement proper error handling/propagation when encountering invalid UTF-8 data
+                    let delimiter =
+                        char::from_u32(delimiter_data).expect("encountered invalid UTF-8 data");
+                    TextOutputCommand::ChangeDelimiter(delimiter)
+                }
+                OUTPUT_VARIANT_CAPITALIZATION => {
+                    let capitalization_mode = match header & CAPITALIZATION_VARIANT_MASK {
+                        CAPITALIZATION_VARIANT_NONE => None,
+                        CAPITALIZATION_VARIANT_LOWER => Lowercase,
+                        CAPITALIZATION_VARIANT_CAPIT => Capitalize,
+                        CAPITALIZATION_VARIANT_UPPER => Uppercase,
+                        CAPITALIZATION_VARIANT_LOWER_THEN_CAPIT => LowerThenCapitalize,
+                        CAPITALIZATION_VARIANT_LOWER_NEXT => LowercaseNext,
+                        CAPITALIZATION_VARIANT_CAPIT_NEXT => CapitalizeNext,
+                        CAPITALIZATION_VARIANT_UPPER_NEXT => UppercaseNext,
+                        _ => unreachable!(),
+                    };
+
+                    TextOutputCommand::ChangeCapitalization(capitalization_mode)
+                }
+                OUTPUT_VARIANT_ATTACHMENT => {
+                    let attachment_mode = match header & ATTACHMENT_VARIANT_MASK {
+                        ATTACHMENT_VARIANT_DELIMITED => Delimited,
+                        ATTACHMENT_VARIANT_GLUE => Glue,
+                        ATTACHMENT_VARIANT_NEXT => Next,
+                        ATTACHMENT_VARIANT_ALWAYS => Always,
+                        _ => unreachable!(),
+                    };
+
+                    TextOutputCommand::ChangeAttachment(attachment_mode)
+                }
+                OUTPUT_VARIANT_RESET => TextOutputCommand::ResetFormatting,
+                _ => unreachable!(),
+            };
+
+            Ok(command)
+        }
+    }
+
+    fn written_text(&self) -> Option<&str> {
+        match self {
+            TextOutputCommand::Write(text) => Some(text),
+            _ => None,
         }
     }
 }
@@ -1,22 +1,28 @@
+use super::{SerializableCommand, SmolStrExt, StringSerializationError};
 use crate::{
     core::{
-        dict::binary::{BinaryDictionaryEntry, BinaryDictionaryEntryError},
+        dict::{
+            binary::{BinaryDictionaryEntry, BinaryDictionaryEntryError, Outline},
+            CommandList,
+        },
         engine::Command,
         Stroke, StrokeContext,
     },
-    io::{self, Read, ReadExt, Write, WriteExt},
+    io::{self, Read, ReadExt, Seek, SeekFrom, Write, WriteExt},
 };
 use smallvec::SmallVec;
+use smol_str::SmolStr;
 
 #[derive(Debug)]
 pub enum BinaryDictionaryEntrySerializationError {
     IOError(io::Error),
     StrokeUnserializable(io::Error),
     CommandUnserializable(io::Error),
+    MetadataUnserializable(StringSerializationError),
     InvalidData(BinaryDictionaryEntryError),
 }
 
-impl<'c> BinaryDictionaryEntry<'c> {
+impl<'c, O: SerializableCommand> BinaryDictionaryEntry<'c, O> {
     pub async fn serialize(
         &self,
         writer: &mut impl Write,
@@ -44,13 +50,71 @@ impl<'c> BinaryDictionaryEntry<'c> {
                 .map_err(BinaryDictionaryEntrySerializationError::CommandUnserializable)?;
         }
 
+        SmolStr::new(self.metadata().unwrap_or_default())
+            .serialize(writer)
+            .await
+            .map_err(BinaryDictionaryEntrySerializationError::MetadataUnserializable)?;
+
         Ok(())
     }
 
     pub async fn deserialize(
         reader: &mut impl Read,
         context: &'c StrokeContext,
-    ) -> Result<BinaryDictionaryEntry<'c>, BinaryDictionaryEntrySerializationError> {
+    ) -> Result<BinaryDictionaryEntry<'c, O>, BinaryDictionaryEntrySerializationError> {
+        let (tag, outline, commands) = Self::deserialize_content(reader, context).await?;
+
+        let metadata = SmolStr::deserialize(reader)
+            .await
+            .map_err(BinaryDictionaryEntrySerializationError::MetadataUnserializable)?;
+
+        Self::new_with_metadata(tag, outline, commands, metadata)
+            .map_err(BinaryDictionaryEntrySerializationError::InvalidData)
+    }
+
+    /// Variant of [`deserialize`](Self::deserialize) which seeks past the metadata instead of reading it
+    pub async fn deserialize_without_metadata(
+        reader: &mut (impl Read + Seek),
+        context: &'c StrokeContext,
+    ) -> Result<BinaryDictionaryEntry<'c, O>, BinaryDictionaryEntrySerializationError> {
+        let (tag, outline, commands) = Self::deserialize_content(reader, context).await?;
+
+        // Errors are reported the same way as when reading the metadata so a truncated entry is not mistaken for the end of the file
+        let metadata_error = |e| {
+            BinaryDictionaryEntrySerializationError::MetadataUnserializable(
+                StringSerializationError::IOError(e),
+            )
+        };
+
+        let metadata_length = reader.read().await.map_err(metadata_error)?;
+
+        if metadata_length > 0 {
+            reader
+                .seek(SeekFrom::Current(metadata_length as i64))
+                .await
+                .map_err(metadata_error)?;
+        }
+
+        Self::new(tag, outline, commands)
+            .map_err(BinaryDictionaryEntrySerializationError::InvalidData)
+    }
+
+    /// Variant of [`deserialize`](Self::deserialize) for entries of dictionaries compiled before metadata
+    /// has been introduced, which do not contain the metadata field at all
+    pub async fn deserialize_legacy(
+        reader: &mut impl Read,
+        context: &'c StrokeContext,
+    ) -> Result<BinaryDictionaryEntry<'c, O>, BinaryDictionaryEntrySerializationError> {
+        let (tag, outline, commands) = Self::deserialize_content(reader, context).await?;
+
+        Self::new(tag, outline, commands)
+            .map_err(BinaryDictionaryEntrySerializationError::InvalidData)
+    }
+
+    async fn deserialize_content(
+        reader: &mut impl Read,
+        context: &'c StrokeContext,
+    ) -> Result<(u16, Outline<'c>, CommandList<O>), BinaryDictionaryEntrySerializationError> {
         let info = reader
             .read_u16()
             .await
@@ -64,7 +128,7 @@ impl<'c> BinaryDictionaryEntry<'c> {
             outline.push(
                 Stroke::deserialize(reader, context)
                     .await
-                    .map_err(BinaryDictionaryEntrySerializationError::IOError)?,
+                    .map_err(BinaryDictionaryEntrySerializationError::StrokeUnserializable)?,
             );
         }
 
@@ -73,12 +137,11 @@ impl<'c> BinaryDictionaryEntry<'c> {
             commands.push(
                 Command::deserialize(reader)
                     .await
-                    .map_err(BinaryDictionaryEntrySerializationError::IOError)?,
+                    .map_err(BinaryDictionaryEntrySerializationError::CommandUnserializable)?,
             );
         }
 
-        Self::new(tag, outline, commands)
-            .map_err(BinaryDictionaryEntrySerializationError::InvalidData)
+        Ok((tag, outline, commands))
     }
 }
 
@@ -95,14 +158,92 @@ mod does {
         let context =
             StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &["FN1", "FN2"]).unwrap();
         let stroke = Stroke::from_str("KPA*", &context).unwrap();
-        let entry = BinaryDictionaryEntry::new(16, smallvec![stroke], smallvec![]).unwrap();
+        let entry: BinaryDictionaryEntry =
+            BinaryDictionaryEntry::new(16, smallvec![stroke], smallvec![]).unwrap();
 
         let mut buf = HeapFile::new();
         smol::block_on(entry.serialize(&mut buf)).unwrap();
         smol::block_on(buf.seek(SeekFrom::Start(0))).unwrap();
-        let deserialized =
+        let deserialized: BinaryDictionaryEntry =
             smol::block_on(BinaryDictionaryEntry::deserialize(&mut buf, &context)).unwrap();
 
         assert_eq!(entry.outline(), deserialized.outline());
     }
+
+    #[test]
+    fn survive_roundtrip_with_metadata() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let stroke = Stroke::from_str("KPA*", &context).unwrap();
+        let entry: BinaryDictionaryEntry = BinaryDictionaryEntry::new_with_metadata(
+            0,
+            smallvec![stroke],
+            smallvec![],
+            "main.json".into(),
+        )
+        .unwrap();
+
+        let mut buf = HeapFile::new();
+        smol::block_on(entry.serialize(&mut buf)).unwrap();
+        smol::block_on(buf.seek(SeekFrom::Start(0))).unwrap();
+        let deserialized: BinaryDictionaryEntry =
+            smol::block_on(BinaryDictionaryEntry::deserialize(&mut buf, &context)).unwrap();
+
+        assert_eq!(deserialized.metadata(), Some("main.json"));
+    }
+
+    #[test]
+    fn skip_metadata() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let stroke = Stroke::from_str("KPA*", &context).unwrap();
+        let entry: BinaryDictionaryEntry = BinaryDictionaryEntry::new_with_metadata(
+            0,
+            smallvec![stroke],
+            smallvec![],
+            "main.json".into(),
+        )
+        .unwrap();
+
+        let mut buf = HeapFile::new();
+        smol::block_on(entry.serialize(&mut buf)).unwrap();
+        smol::block_on(entry.serialize(&mut buf)).unwrap();
+        smol::block_on(buf.seek(SeekFrom::Start(0))).unwrap();
+
+        for _ in 0..2 {
+            let deserialized: BinaryDictionaryEntry = smol::block_on(
+                BinaryDictionaryEntry::deserialize_without_metadata(&mut buf, &context),
+            )
+            .unwrap();
+
+            assert_eq!(entry.outline(), deserialized.outline());
+            assert_eq!(deserialized.metadata(), None);
+        }
+    }
+
+    #[test]
+    fn read_entries_without_metadata_field() {
+        let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+        let stroke = Stroke::from_str("KPA*", &context).unwrap();
+        let entry: BinaryDictionaryEntry =
+            BinaryDictionaryEntry::new(3, smallvec![stroke], smallvec![]).unwrap();
+
+        // Strip the length of the empty metadata string, which older compilers did not write
+        let mut buf = HeapFile::new();
+        smol::block_on(entry.serialize(&mut buf)).unwrap();
+        smol::block_on(entry.serialize(&mut buf)).unwrap();
+        let mut data = buf.into_inner();
+        let entry_length = data.len() / 2;
+        data.remove(entry_length * 2 - 1);
+        data.remove(entry_length - 1);
+
+        let mut buf = HeapFile::from_raw(data);
+        for _ in 0..2 {
+            let deserialized: BinaryDictionaryEntry = smol::block_on(
+                BinaryDictionaryEntry::deserialize_legacy(&mut buf, &context),
+            )
+            .unwrap();
+
+            assert_eq!(entry.outline(), deserialized.outline());
+            assert_eq!(deserialized.tag(), 3);
+        }
+    }
 }
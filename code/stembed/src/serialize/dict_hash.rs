@@ -0,0 +1,41 @@
+use crate::{
+    core::dict::{DictionaryHash, HashAlgorithm},
+    io::{self, Read, ReadExt, Write, WriteExt},
+};
+
+#[derive(Debug)]
+pub enum DictionaryHashSerializationError {
+    UnknownAlgorithm(u8),
+    IOError(io::Error),
+}
+
+impl DictionaryHash {
+    pub async fn serialize(&self, writer: &mut impl Write) -> Result<(), io::Error> {
+        writer.write(self.algorithm.id()).await?;
+        writer.write_u32((self.key >> 32) as u32).await?;
+        writer.write_u32(self.key as u32).await?;
+        Ok(())
+    }
+
+    pub async fn deserialize(
+        reader: &mut impl Read,
+    ) -> Result<Self, DictionaryHashSerializationError> {
+        let id = reader
+            .read()
+            .await
+            .map_err(DictionaryHashSerializationError::IOError)?;
+        let algorithm = HashAlgorithm::from_id(id)
+            .ok_or(DictionaryHashSerializationError::UnknownAlgorithm(id))?;
+
+        let high = reader
+            .read_u32()
+            .await
+            .map_err(DictionaryHashSerializationError::IOError)?;
+        let low = reader
+            .read_u32()
+            .await
+            .map_err(DictionaryHashSerializationError::IOError)?;
+
+        Ok(Self::new(algorithm, (high as u64) << 32 | low as u64))
+    }
+}
@@ -0,0 +1,35 @@
+use crate::{
+    core::dict::DictionaryMetadata,
+    io::{self, Read, ReadExt, Write, WriteExt},
+};
+
+impl DictionaryMetadata {
+    pub async fn serialize(&self, writer: &mut impl Write) -> Result<(), io::Error> {
+        writer.write_u16(self.format_version).await?;
+        writer.write_u16(self.compiler_version.0).await?;
+        writer.write_u16(self.compiler_version.1).await?;
+        writer.write_u16(self.compiler_version.2).await?;
+        writer.write_u32(self.entry_count).await?;
+        writer.write_u32((self.created_at >> 32) as u32).await?;
+        writer.write_u32(self.created_at as u32).await?;
+        Ok(())
+    }
+
+    pub async fn deserialize(reader: &mut impl Read) -> Result<Self, io::Error> {
+        let format_version = reader.read_u16().await?;
+        let compiler_version = (
+            reader.read_u16().await?,
+            reader.read_u16().await?,
+            reader.read_u16().await?,
+        );
+        let entry_count = reader.read_u32().await?;
+        let created_at = (reader.read_u32().await? as u64) << 32 | reader.read_u32().await? as u64;
+
+        Ok(Self {
+            format_version,
+            compiler_version,
+            entry_count,
+            created_at,
+        })
+    }
+}
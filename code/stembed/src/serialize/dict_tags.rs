@@ -0,0 +1,14 @@
+use crate::{
+    core::dict::TagSet,
+    io::{self, Read, ReadExt, Write, WriteExt},
+};
+
+impl TagSet {
+    pub async fn serialize(&self, writer: &mut impl Write) -> Result<(), io::Error> {
+        writer.write_u32(self.bits()).await
+    }
+
+    pub async fn deserialize(reader: &mut impl Read) -> Result<Self, io::Error> {
+        Ok(Self::from_bits(reader.read_u32().await?))
+    }
+}
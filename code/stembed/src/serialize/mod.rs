@@ -4,6 +4,13 @@ pub use strings::*;
 mod dict_entry;
 pub use dict_entry::*;
 
+mod dict_hash;
+pub use dict_hash::*;
+
 mod command;
+pub use command::*;
+
+mod dict_metadata;
+mod dict_tags;
 mod stroke;
 mod stroke_context;
@@ -1,7 +1,9 @@
-use smallvec::{smallvec, SmallVec};
+#![feature(generic_associated_types)]
+
+use smallvec::smallvec;
 use std::collections::HashMap;
 use stembed::core::{
-    dict::Dictionary,
+    dict::{CommandList, Dictionary, DictionaryMatch, DictionaryStack},
     engine::{Command, CommandDelta, Engine, EngineCommand},
 };
 
@@ -29,23 +31,19 @@ impl TestDict {
 impl Dictionary for TestDict {
     type Stroke = TestStroke;
     type OutputCommand = TestCommand;
-
-    fn lookup(
-        &self,
-        outline: &[Self::Stroke],
-    ) -> Option<SmallVec<[Command<Self::OutputCommand>; 2]>> {
-        self.0.get(outline).map(|v| {
-            let mut output = SmallVec::new();
-            output.extend_from_slice(&v);
-            output
-        })
+    type Error = ();
+    type LookupFuture<'a> =
+        core::future::Ready<Result<Option<DictionaryMatch<Self::OutputCommand>>, Self::Error>>;
+
+    fn lookup<'a>(&'a self, outline: &'a [Self::Stroke]) -> Self::LookupFuture<'_> {
+        core::future::ready(Ok(self.0.get(outline).map(|commands| DictionaryMatch {
+            commands: commands.iter().copied().collect(),
+            tag: 0,
+        })))
     }
 
-    fn fallback_commands(
-        &self,
-        stroke: &Self::Stroke,
-    ) -> SmallVec<[Command<Self::OutputCommand>; 2]> {
-        smallvec![Command::Output(TestCommand::Fallback(stroke.clone()))]
+    fn fallback_commands(&self, stroke: &Self::Stroke) -> CommandList<Self::OutputCommand> {
+        smallvec![Command::Output(TestCommand::Fallback(*stroke))]
     }
 
     fn longest_outline_length(&self) -> usize {
@@ -61,8 +59,12 @@ const COMMAND_0: TestCommand = TestCommand::Indexed(0);
 const COMMAND_1: TestCommand = TestCommand::Indexed(1);
 const COMMAND_2: TestCommand = TestCommand::Indexed(2);
 
+fn push(engine: &mut Engine<&TestDict>, stroke: TestStroke) -> CommandDelta<TestCommand> {
+    smol::block_on(engine.push(stroke)).unwrap()
+}
+
 #[test]
-fn miep() {
+fn undo_previous_stroke() {
     let mut dict = TestDict::new();
     dict.add(vec![STROKE_A], vec![Command::Output(COMMAND_0)]);
     dict.add(vec![STROKE_B], vec![Command::Output(COMMAND_1)]);
@@ -75,7 +77,7 @@ fn miep() {
     let mut engine = Engine::new(&dict);
 
     assert_eq!(
-        engine.push(STROKE_A),
+        push(&mut engine, STROKE_A),
         CommandDelta {
             to_undo: 0,
             to_push: smallvec![COMMAND_0]
@@ -83,7 +85,7 @@ fn miep() {
     );
 
     assert_eq!(
-        engine.push(STROKE_B),
+        push(&mut engine, STROKE_B),
         CommandDelta {
             to_undo: 1,
             to_push: smallvec![COMMAND_2]
@@ -91,10 +93,99 @@ fn miep() {
     );
 
     assert_eq!(
-        engine.push(STROKE_C),
+        push(&mut engine, STROKE_C),
         CommandDelta {
             to_undo: 1,
             to_push: smallvec![COMMAND_0]
         }
     );
+    assert_eq!(engine.undo_depth(), 1);
+
+    assert_eq!(
+        push(&mut engine, STROKE_C),
+        CommandDelta {
+            to_undo: 1,
+            to_push: smallvec![]
+        }
+    );
+    assert_eq!(engine.undo_depth(), 0);
+
+    // Nothing left to undo
+    assert_eq!(push(&mut engine, STROKE_C), CommandDelta::default());
+}
+
+#[test]
+fn undo_outlines_with_multiple_commands() {
+    let mut dict = TestDict::new();
+    dict.add(
+        vec![STROKE_A],
+        vec![Command::Output(COMMAND_0), Command::Output(COMMAND_1)],
+    );
+    dict.add(vec![STROKE_B], vec![Command::Output(COMMAND_2)]);
+    dict.add(
+        vec![STROKE_C],
+        vec![Command::Engine(EngineCommand::UndoPrevious)],
+    );
+
+    let mut engine = Engine::new(&dict);
+
+    push(&mut engine, STROKE_A);
+    push(&mut engine, STROKE_B);
+
+    assert_eq!(
+        push(&mut engine, STROKE_C),
+        CommandDelta {
+            to_undo: 1,
+            to_push: smallvec![]
+        }
+    );
+
+    // Both commands of the outline before are undone
+    assert_eq!(
+        push(&mut engine, STROKE_C),
+        CommandDelta {
+            to_undo: 2,
+            to_push: smallvec![]
+        }
+    );
+}
+
+#[test]
+fn prefer_entries_of_upper_dictionary() {
+    let mut main = TestDict::new();
+    main.add(vec![STROKE_A], vec![Command::Output(COMMAND_0)]);
+    main.add(vec![STROKE_A, STROKE_B], vec![Command::Output(COMMAND_1)]);
+
+    let mut personal = TestDict::new();
+    personal.add(vec![STROKE_A], vec![Command::Output(COMMAND_2)]);
+
+    let dictionary = DictionaryStack::new(&personal, &main);
+    assert_eq!(dictionary.longest_outline_length(), 2);
+
+    let mut engine = Engine::new(&dictionary);
+
+    assert_eq!(
+        smol::block_on(engine.push(STROKE_A)).unwrap(),
+        CommandDelta {
+            to_undo: 0,
+            to_push: smallvec![COMMAND_2]
+        }
+    );
+
+    // Longer outlines of the lower dictionary still take precedence
+    assert_eq!(
+        smol::block_on(engine.push(STROKE_B)).unwrap(),
+        CommandDelta {
+            to_undo: 1,
+            to_push: smallvec![COMMAND_1]
+        }
+    );
+
+    assert_eq!(
+        smol::block_on(engine.push(STROKE_C)).unwrap(),
+        CommandDelta {
+            to_undo: 0,
+            to_push: smallvec![TestCommand::Fallback(STROKE_C)]
+        }
+    );
 }
@@ -41,7 +41,9 @@ fn plover() {
     println!("{:?}", dictionary.longest_outline_length());
     println!(
         "{:?}",
-        dictionary.lookup(&[Stroke::from_str("KPA*", &dictionary.stroke_context()).unwrap()])
+        dictionary.lookup(&[Stroke::from_str("KPA*", &dictionary.stroke_context())
+            .unwrap()
+            .to_portable()])
     );
     let mut engine = Engine::new(&dictionary);
     let mut processor = TextFormatter::new();
@@ -52,7 +54,7 @@ fn plover() {
 
     for stroke in strokes {
         println!("Stroke: {}", stroke);
-        let delta = engine.push(stroke);
+        let delta = engine.push(stroke.to_portable());
         println!("\t{:?}", delta);
         let output = processor.consume(delta);
         for instruction in output {
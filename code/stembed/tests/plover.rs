@@ -0,0 +1,324 @@
+#![cfg(all(feature = "compile", feature = "import"))]
+
+use std::collections::HashMap;
+use stembed::{
+    compile::BinaryDictionaryCompiler,
+    core::{
+        dict::BinaryDictionary,
+        engine::{Engine, MatchedOutline, UsageTracker},
+        processor::{
+            text_formatter::{TextFormatter, TextOutputCommand, TextOutputInstruction},
+            CommandProcessor,
+        },
+        PortableStroke, Stroke, StrokeContext,
+    },
+    import::plover::parse_dict,
+    io::util::HeapFile,
+};
+
+const DICTIONARY: &str = r#"{
+"KAT": "cat",
+"KAT/HRAOG": "catalog",
+"WORBG": "work",
+"-S": "{^s}",
+"-G": "{^ing}",
+"TP-PL": "{.}",
+"A*": "{&a}",
+"PW*": "{&b}",
+"KR*": "{&c}",
+"1": "1",
+"2": "2",
+"1-9": "19"
+}"#;
+
+/// Compiles the test dictionary, tagging every entry with zero
+fn compile(context: &StrokeContext) -> HeapFile {
+    let mut compiler = BinaryDictionaryCompiler::new(context);
+
+    for entry in parse_dict(DICTIONARY, context).unwrap() {
+        let (outline, commands) = entry.unwrap();
+        compiler.add(outline, commands, 0).unwrap();
+    }
+
+    let mut file = HeapFile::new();
+    smol::block_on(compiler.serialize(&mut file)).unwrap();
+    file
+}
+
+/// Runs the `/` separated strokes through the engine and formatter, returning the resulting text.
+/// Like in Plover, a lone `*` undoes the previous stroke. A `~` flushes the engine, as if the input had been idle.
+/// A `|` toggles the passthrough mode of the engine.
+fn write(strokes: &str) -> String {
+    let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+    let mut file = compile(&context);
+
+    let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+    let mut engine = Engine::new(&dictionary);
+    let mut formatter = TextFormatter::new();
+    let mut output = String::new();
+
+    for stroke in strokes.split('/') {
+        if stroke == "~" {
+            engine.flush();
+            continue;
+        }
+
+        if stroke == "|" {
+            engine.set_passthrough(!engine.is_passthrough());
+            continue;
+        }
+
+        let delta = if stroke == "*" {
+            smol::block_on(engine.pop())
+                .unwrap()
+                .map(|(delta, _)| delta)
+                .unwrap_or_default()
+        } else {
+            let stroke = Stroke::from_str(stroke, &context).unwrap();
+            smol::block_on(engine.push(stroke.to_portable())).unwrap()
+        };
+
+        for instruction in formatter.consume(delta) {
+            match instruction {
+                TextOutputInstruction::Write(text) => output.push_str(&text),
+                TextOutputInstruction::Backspace(count) => output.truncate(output.len() - count),
+            }
+        }
+    }
+
+    output
+}
+
+#[test]
+fn write_single_stroke_words() {
+    assert_eq!(write("KAT"), " cat");
+    assert_eq!(write("KAT/WORBG"), " cat work");
+}
+
+#[test]
+fn fall_back_to_raw_steno() {
+    assert_eq!(write("STPH"), " STPH");
+}
+
+#[test]
+fn attach_suffixes() {
+    assert_eq!(write("KAT/-S"), " cats");
+    assert_eq!(write("WORBG/-G"), " working");
+    assert_eq!(write("WORBG/-G/KAT"), " working cat");
+}
+
+#[test]
+fn replace_output_with_multi_stroke_outline() {
+    assert_eq!(write("KAT/HRAOG"), " catalog");
+    assert_eq!(write("KAT/HRAOG/-S"), " catalogs");
+    assert_eq!(write("KAT/HRAOG/KAT"), " catalog cat");
+}
+
+#[test]
+fn undo_previous_stroke() {
+    assert_eq!(write("KAT/-S/*"), " cat");
+    assert_eq!(write("KAT/HRAOG/*"), " cat");
+    assert_eq!(write("KAT/WORBG/*/*"), "");
+    assert_eq!(write("KAT/*/*"), "");
+}
+
+#[test]
+fn capitalize_after_punctuation() {
+    assert_eq!(write("KAT/TP-PL/KAT"), " cat. Cat");
+    assert_eq!(write("KAT/TP-PL/*/KAT"), " cat cat");
+}
+
+#[test]
+fn glue_fingerspelling() {
+    assert_eq!(write("A*/PW*"), " ab");
+    assert_eq!(write("A*/PW*/KAT"), " ab cat");
+    assert_eq!(write("KAT/KR*/A*/PW*/WORBG"), " cat cab work");
+}
+
+#[test]
+fn glue_numbers() {
+    // The engine receives strokes written using the number bar, e.g. `#S` for `1`
+    assert_eq!(write("#S/#T/#S-T"), " 1219");
+    assert_eq!(write("KAT/#S/#T/KAT"), " cat 12 cat");
+    assert_eq!(write("A*/#S"), " a1");
+}
+
+#[test]
+fn commit_strokes_on_flush() {
+    assert_eq!(write("KAT/~"), " cat");
+    assert_eq!(write("KAT/~/HRAOG"), " cat HRAOG");
+    assert_eq!(write("KAT/~/-S"), " cats");
+    assert_eq!(write("KAT/~/WORBG/~/KAT/HRAOG"), " cat work catalog");
+}
+
+#[test]
+fn undo_across_flush() {
+    assert_eq!(write("KAT/~/*"), "");
+    assert_eq!(write("KAT/HRAOG/~/*"), " cat");
+    assert_eq!(write("KAT/~/HRAOG/*/*"), "");
+}
+
+#[test]
+fn pass_strokes_through_without_translation() {
+    assert_eq!(write("KAT/|/A*/PW*/|/WORBG"), " cat A* PW* work");
+    assert_eq!(write("KAT/|/HRAOG/|/KAT"), " cat HRAOG cat");
+    assert_eq!(write("|/KAT/HRAOG/|/-S"), " KAT HRAOGs");
+    assert_eq!(write("KAT/|/HRAOG"), " cat HRAOG");
+}
+
+#[test]
+fn undo_into_passthrough_strokes() {
+    assert_eq!(write("KAT/|/KAT/|/WORBG/*"), " cat KAT");
+    assert_eq!(write("KAT/|/KAT/|/WORBG/*/HRAOG"), " cat KAT HRAOG");
+    assert_eq!(write("KAT/HRAOG/|/KAT/*/*"), " cat");
+    assert_eq!(write("|/KAT/|/*/KAT/HRAOG"), " catalog");
+}
+
+#[test]
+fn report_matched_outlines() {
+    let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+    let mut file = compile(&context);
+    let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+    let mut engine = Engine::new(&dictionary);
+    let stroke = |stroke: &str| Stroke::from_str(stroke, &context).unwrap().to_portable();
+
+    let (_, outlines) = smol::block_on(engine.push_with_outlines(stroke("KAT"))).unwrap();
+    assert_eq!(outlines.len(), 1);
+    assert_eq!(outlines[0].strokes.len(), 1);
+    assert_eq!(outlines[0].tag, Some(0));
+
+    let (delta, outlines) = smol::block_on(engine.push_with_outlines(stroke("HRAOG"))).unwrap();
+    assert_eq!(delta.to_undo, 1);
+    assert_eq!(outlines.len(), 1);
+    assert_eq!(outlines[0].strokes.len(), 2);
+
+    let (_, outlines) = smol::block_on(engine.push_with_outlines(stroke("STPH"))).unwrap();
+    assert_eq!(outlines.len(), 1);
+    assert_eq!(outlines[0].tag, None);
+}
+
+/// Counts how often each outline is currently written
+#[derive(Default)]
+struct Frequencies(HashMap<Vec<PortableStroke>, isize>);
+
+impl Frequencies {
+    fn get(&self, outline: &[PortableStroke]) -> isize {
+        self.0.get(outline).copied().unwrap_or_default()
+    }
+}
+
+impl UsageTracker<PortableStroke> for Frequencies {
+    fn record(&mut self, outline: &MatchedOutline<PortableStroke>) {
+        *self.0.entry(outline.strokes.to_vec()).or_default() += 1;
+    }
+
+    fn retract(&mut self, outline: &MatchedOutline<PortableStroke>) {
+        *self.0.entry(outline.strokes.to_vec()).or_default() -= 1;
+    }
+}
+
+#[test]
+fn track_outline_usage() {
+    let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+    let mut file = compile(&context);
+    let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+    let mut engine = Engine::new(&dictionary).with_usage_tracker(Frequencies::default());
+    let stroke = |stroke: &str| Stroke::from_str(stroke, &context).unwrap().to_portable();
+    let (kat, hraog, work) = (stroke("KAT"), stroke("HRAOG"), stroke("WORBG"));
+
+    for stroke in [kat, hraog, work, kat] {
+        smol::block_on(engine.push(stroke)).unwrap();
+    }
+
+    let frequencies = engine.usage_tracker();
+    assert_eq!(frequencies.get(&[kat]), 1);
+    assert_eq!(frequencies.get(&[kat, hraog]), 1);
+    assert_eq!(frequencies.get(&[work]), 1);
+
+    // Undoing retracts the outlines, splitting up the multi-stroke one records its first stroke again
+    smol::block_on(engine.pop()).unwrap();
+    smol::block_on(engine.pop()).unwrap();
+    smol::block_on(engine.pop()).unwrap();
+
+    let frequencies = engine.usage_tracker();
+    assert_eq!(frequencies.get(&[kat]), 1);
+    assert_eq!(frequencies.get(&[kat, hraog]), 0);
+    assert_eq!(frequencies.get(&[work]), 0);
+}
+
+#[test]
+fn limit_undo_depth() {
+    let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+    let mut file = compile(&context);
+    let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+    let mut engine = Engine::new(&dictionary);
+    engine.set_max_undo_depth(2);
+
+    for stroke in ["KAT", "WORBG", "KAT", "HRAOG"] {
+        let stroke = Stroke::from_str(stroke, &context).unwrap();
+        smol::block_on(engine.push(stroke.to_portable())).unwrap();
+    }
+
+    // Three outlines have been written: "cat", "work" and "catalog"
+    assert_eq!(engine.max_undo_depth(), 2);
+    assert_eq!(engine.undo_depth(), 2);
+
+    // Popping a stroke off "catalog" leaves "cat" in its place
+    assert!(smol::block_on(engine.pop()).unwrap().is_some());
+    assert_eq!(engine.undo_depth(), 2);
+
+    assert!(smol::block_on(engine.pop()).unwrap().is_some());
+    assert_eq!(engine.undo_depth(), 1);
+    assert!(smol::block_on(engine.pop()).unwrap().is_some());
+    assert_eq!(engine.undo_depth(), 0);
+    assert!(smol::block_on(engine.pop()).unwrap().is_none());
+}
+
+#[test]
+fn build_strokes_for_borrowed_and_owned_dictionaries() {
+    let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+
+    // A borrowed dictionary lends its own context to build strokes with
+    let mut file = compile(&context);
+    let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+    let mut engine = Engine::new(&dictionary);
+    let stroke = Stroke::from_str("KAT", engine.stroke_context()).unwrap();
+    assert_eq!(
+        smol::block_on(engine.push(stroke.to_portable()))
+            .unwrap()
+            .to_push
+            .len(),
+        1
+    );
+
+    // An owned dictionary accepts strokes built with a separate context
+    let mut file = compile(&context);
+    let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+    let mut engine = Engine::with_owned(dictionary);
+    let stroke = Stroke::from_str("KAT", &context).unwrap();
+    assert_eq!(
+        smol::block_on(engine.push(stroke.to_portable()))
+            .unwrap()
+            .to_push
+            .len(),
+        1
+    );
+    assert_eq!(engine.dictionary().stroke_context(), &context);
+}
+
+#[test]
+fn accept_strokes_built_for_other_systems() {
+    let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+    let mut file = compile(&context);
+    let dictionary = smol::block_on(BinaryDictionary::new(&mut file)).unwrap();
+    let mut engine = Engine::new(&dictionary);
+
+    // Additional keys at the end of steno order do not shift the position of the existing ones
+    let extended = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &["FN1"]).unwrap();
+    let stroke = Stroke::from_str("KAT", &extended).unwrap();
+    let delta = smol::block_on(engine.push(stroke.to_portable())).unwrap();
+    assert!(matches!(
+        &delta.to_push[..],
+        [TextOutputCommand::Write(text)] if text == "cat"
+    ));
+}
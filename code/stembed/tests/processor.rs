@@ -34,3 +34,82 @@ fn something() {
         ]
     );
 }
+
+#[test]
+fn capitalize_after_soft_reset() {
+    let mut processor = TextFormatter::with_sentence_start(true);
+    let delta = CommandDelta {
+        to_undo: 0,
+        to_push: smallvec![TextOutputCommand::Write("hello".into())],
+    };
+    assert_eq!(
+        processor.consume(delta).as_slice(),
+        vec![TextOutputInstruction::Write(" Hello".into())]
+    );
+
+    processor.soft_reset();
+
+    let delta = CommandDelta {
+        to_undo: 0,
+        to_push: smallvec![TextOutputCommand::Write("world".into())],
+    };
+    assert_eq!(
+        processor.consume(delta).as_slice(),
+        vec![TextOutputInstruction::Write(" World".into())]
+    );
+
+    // The history from before the reset is still available for corrections
+    let delta = CommandDelta {
+        to_undo: 2,
+        to_push: smallvec![],
+    };
+    assert_eq!(
+        processor.consume(delta).as_slice(),
+        vec![
+            TextOutputInstruction::Backspace(6),
+            TextOutputInstruction::Backspace(6)
+        ]
+    );
+}
+
+#[test]
+fn write_first_word_verbatim_by_default() {
+    let mut processor = TextFormatter::new();
+    let delta = CommandDelta {
+        to_undo: 0,
+        to_push: smallvec![TextOutputCommand::Write("hello".into())],
+    };
+    assert_eq!(
+        processor.consume(delta).as_slice(),
+        vec![TextOutputInstruction::Write(" hello".into())]
+    );
+
+    processor.soft_reset();
+
+    let delta = CommandDelta {
+        to_undo: 0,
+        to_push: smallvec![TextOutputCommand::Write("world".into())],
+    };
+    assert_eq!(
+        processor.consume(delta).as_slice(),
+        vec![TextOutputInstruction::Write(" world".into())]
+    );
+}
+
+#[test]
+fn forget_history_after_hard_reset() {
+    let mut processor = TextFormatter::new();
+    let delta = CommandDelta {
+        to_undo: 0,
+        to_push: smallvec![TextOutputCommand::Write("hello".into())],
+    };
+    processor.consume(delta);
+
+    processor.hard_reset();
+
+    let delta = CommandDelta {
+        to_undo: 1,
+        to_push: smallvec![],
+    };
+    assert!(processor.consume(delta).is_empty());
+}
@@ -1,7 +1,6 @@
 use stembed::{
-    core::{Stroke, StrokeContext, StrokeContextError},
-    io::{HeapFile, Seek, SeekFrom},
-    serialize::{Deserialize, Serialize},
+    core::{Stroke, StrokeContext, StrokeContextBuilder, StrokeContextError, StrokeParseError},
+    io::{util::HeapFile, Seek, SeekFrom},
 };
 
 #[test]
@@ -18,9 +17,9 @@ fn survives_serialization_roundtrip() {
     let stroke = Stroke::from_str("KH-PD|FN1,FN2", &context).unwrap();
 
     let mut output = HeapFile::new();
-    stroke.serialize(&mut output).unwrap();
-    output.seek(SeekFrom::Start(0)).unwrap();
-    let deserialized = Stroke::deserialize(&mut output, &context).unwrap();
+    smol::block_on(stroke.serialize(&mut output)).unwrap();
+    smol::block_on(output.seek(SeekFrom::Start(0))).unwrap();
+    let deserialized = smol::block_on(Stroke::deserialize(&mut output, &context)).unwrap();
     assert_eq!(stroke, deserialized);
 }
 
@@ -44,3 +43,137 @@ fn fails_on_duplicate_keys() {
         Some(StrokeContextError::DuplicateKey)
     );
 }
+
+#[test]
+fn fails_on_ambiguous_keys() {
+    // Keys shared by the left and right hand are told apart by the separator
+    assert_eq!(
+        StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).err(),
+        None
+    );
+    assert_eq!(
+        StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &["*"]).err(),
+        Some(StrokeContextError::AmbiguousKey("*".into()))
+    );
+    assert_eq!(
+        StrokeContext::new("#STKPWHR", "AO*EUS", "FRPBLGTDZ", &[]).err(),
+        Some(StrokeContextError::AmbiguousKey("S".into()))
+    );
+    assert_eq!(
+        StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &["FN|1"]).err(),
+        Some(StrokeContextError::ReservedTokenUsed)
+    );
+    assert_eq!(
+        StrokeContext::new("", "", "", &[]).err(),
+        Some(StrokeContextError::NoKeys)
+    );
+}
+
+#[test]
+fn builds_context_group_by_group() {
+    let context = StrokeContextBuilder::new()
+        .with_left("#STKPWHR")
+        .with_middle("AO*EU")
+        .with_right("FRPBLGTSDZ")
+        .with_extra("FN1")
+        .with_extra("FN2")
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        context,
+        StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &["FN1", "FN2"]).unwrap()
+    );
+    assert_eq!(context.key_count(), 25);
+}
+
+#[test]
+fn parses_steno_order() {
+    let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &["FN1", "FN2"]).unwrap();
+
+    let left = Stroke::from_steno_string("STKPWHR", &context).unwrap();
+    assert_eq!(left, Stroke::from_str("STKPWHR", &context).unwrap());
+
+    let both = Stroke::from_steno_string("KHPDFN1FN2", &context).unwrap();
+    assert_eq!(both, Stroke::from_str("KH-PD|FN1,FN2", &context).unwrap());
+
+    let full = Stroke::from_steno_string("#STKPWHRAO*EUFRPBLGTSDZ", &context).unwrap();
+    assert_eq!(full.to_string(), "#STKPWHRAO*EUFRPBLGTSDZ");
+}
+
+#[test]
+fn rejects_display_conventions_in_steno_order() {
+    let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &["FN1", "FN2"]).unwrap();
+
+    assert_eq!(
+        Stroke::from_steno_string("KH-PD", &context).err(),
+        Some(StrokeParseError::LeftoverCharacters)
+    );
+    assert_eq!(
+        Stroke::from_steno_string("KPA*|FN1", &context).err(),
+        Some(StrokeParseError::LeftoverCharacters)
+    );
+}
+
+#[test]
+fn survives_steno_order_roundtrip() {
+    let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &["FN1", "FN2"]).unwrap();
+
+    for input in ["STKPWHR", "KPA*", "HEL", "TKPWOD", "KHPDFN1FN2", "WORLDZ"] {
+        let stroke = Stroke::from_steno_string(input, &context).unwrap();
+        assert_eq!(stroke.to_steno_string(), input);
+
+        let displayed = Stroke::from_str(stroke.to_string(), &context).unwrap();
+        assert_eq!(displayed, stroke);
+    }
+}
+
+#[test]
+fn measures_hamming_distance() {
+    let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &["FN1", "FN2"]).unwrap();
+    let stroke = Stroke::from_str("KAT", &context).unwrap();
+
+    assert_eq!(stroke.hamming_distance(&stroke), Some(0));
+    assert_eq!(
+        stroke.hamming_distance(&Stroke::from_str("KAOT", &context).unwrap()),
+        Some(1)
+    );
+    assert_eq!(
+        stroke.hamming_distance(&Stroke::from_str("TKOG", &context).unwrap()),
+        Some(5)
+    );
+
+    let other_context = StrokeContext::new("STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+    let other_stroke = Stroke::from_str("KAT", &other_context).unwrap();
+    assert_eq!(stroke.hamming_distance(&other_stroke), None);
+}
+
+#[test]
+fn combines_keys_of_strokes() {
+    let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &["FN1", "FN2"]).unwrap();
+    let stroke = |stroke: &str| Stroke::from_str(stroke, &context).unwrap();
+
+    assert_eq!(&stroke("KAT") | &stroke("-S|FN1"), stroke("KATS|FN1"));
+    assert_eq!(&stroke("KAT") & &stroke("KOT"), stroke("K-T"));
+    assert_eq!(&stroke("KA*T") - &stroke("KAT"), stroke("*"));
+    assert_eq!(stroke("KAT").difference(&stroke("KAT")), Some(stroke("")));
+
+    assert!(stroke("").is_empty());
+    assert!((&stroke("KAT") - &stroke("KAT")).is_empty());
+    assert!(!stroke("|FN2").is_empty());
+
+    let other_context = StrokeContext::new("STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+    let other_stroke = Stroke::from_str("KAT", &other_context).unwrap();
+    assert_eq!(stroke("KAT").union(&other_stroke), None);
+    assert_eq!(stroke("KAT").intersection(&other_stroke), None);
+}
+
+#[test]
+#[should_panic(expected = "different contexts")]
+fn refuses_to_combine_strokes_of_different_contexts() {
+    let context = StrokeContext::new("#STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+    let other_context = StrokeContext::new("STKPWHR", "AO*EU", "FRPBLGTSDZ", &[]).unwrap();
+
+    let _ = &Stroke::from_str("KAT", &context).unwrap()
+        | &Stroke::from_str("KAT", &other_context).unwrap();
+}